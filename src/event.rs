@@ -1,11 +1,69 @@
+#[cfg(windows)]
 pub(super) mod details;
+#[cfg(windows)]
+pub(super) mod message;
+pub(super) mod names;
 
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
 /// Main event sent by the hook to the client thread.
-#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+/// 
+/// # Ordering
+/// 
+/// Events are received in the order they were captured, also when both keyboard and mouse hooks are active.
+/// Windows calls the low-level hooks one input event at a time and waits for the hook procedure to return,
+/// before the next input event is processed. The hook procedures of this crate send the event before returning,
+/// hence the events from both hook threads are queued in the OS order.
+/// 
+/// Each [KeyboardEvent] and [MouseEvent] carries also the OS timestamp (`time` field), that can be used to sort
+/// events merged from different sources. Keep in mind that this timestamp has the resolution of the system timer
+/// (typically 10-16 milliseconds), so consecutive events often share the same timestamp.
+/// 
+/// The events compare by their capture time, so `sort` puts a merged recording in the chronological order:
+/// first by the timestamp, then by the sequence number (`seq` field) for the events sharing it, whichever device they came from.
+/// Only the events equal in both are compared by their contents, so that the order is consistent with the equality.
+/// 
+/// The [InputEvent::Text], [InputEvent::Reset], [InputEvent::MouseDoubleClick] and [InputEvent::Other] carry no timestamp,
+/// so they come before all the timed ones: sorting a recording moves them to its front, away from the events they were received with.
+/// 
+/// The ordering uses the raw tick value (the `u32` milliseconds), it is not aware of the wrap around every 49.7 days,
+/// unlike [InputEvent::elapsed_since] and the `replay`. A recording spanning the wrap sorts the events after it first.
+/// 
+/// ```rust
+/// # use willhook::event::*;
+/// let mouse = |time, seq| InputEvent::Mouse(MouseEvent {
+///     event: MouseEventType::Move(MouseMoveEvent { point: None, delta: None }),
+///     is_injected: None,
+///     time: Some(time),
+///     seq,
+///     foreground_pid: None,
+///     extra_info: 0,
+///     blocked: false,
+///     raw: None,
+/// });
+/// let mut merged = vec![mouse(20, 1), mouse(10, 7), mouse(10, 5), InputEvent::Reset];
+/// merged.sort();
+/// assert_eq!(merged, vec![InputEvent::Reset, mouse(10, 5), mouse(10, 7), mouse(20, 1)]);
+/// ```
+/// 
+/// # Matching
+/// 
+/// This enum, as well as [KeyboardKey], [MouseEventType], [MouseWheel] and [MouseButton], is `#[non_exhaustive]`.
+/// New variants may be added in minor releases, so a `match` on them must include a wildcard arm:
+/// 
+/// ```rust
+/// # use willhook::event::InputEvent;
+/// # let event = InputEvent::Other(0);
+/// match event {
+///     InputEvent::Keyboard(ke) => println!("{:?}", ke),
+///     InputEvent::Mouse(me) => println!("{:?}", me),
+///     _ => (),
+/// }
+/// ```
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum InputEvent {
     /// It is keyboard event, the inner value contains the details. See [KeyboardEvent].
     Keyboard(KeyboardEvent),
@@ -13,6 +71,123 @@ pub enum InputEvent {
     Mouse(MouseEvent),
     /// Unexpected data was received by the hook, the event type is stored for reference as inner value.
     Other(u32),
+    /// The input stream was interrupted (by a desktop switch, or the focus of an elevated window) and any tracked key or button state should be cleared.
+    /// Sent only when requested with [crate::hook::HookBuilder::emit_reset_on_focus_loss].
+    Reset,
+    /// A word typed by the user, sent after the keyboard event that finished it.
+    /// Sent only when requested with [crate::hook::HookBuilder::accumulate_text].
+    Text(String),
+    /// The button was clicked twice, quickly enough and close enough to make a double-click.
+    /// Sent after the second button down, which is delivered as usual. The click of the button is [MouseClick::DoubleClick].
+    /// Sent only when requested with [crate::hook::HookBuilder::with_double_click_detection].
+    MouseDoubleClick(MouseButton),
+}
+
+impl InputEvent {
+    /// Returns the OS timestamp of the keyboard or mouse event (its `time` field), `None` for the other events.
+    pub fn time(&self) -> Option<u32> {
+        match self {
+            InputEvent::Keyboard(ke) => ke.time,
+            InputEvent::Mouse(me) => me.time,
+            _ => None,
+        }
+    }
+
+    /// Returns the sequence number of the keyboard or mouse event (its `seq` field), `None` for the other events.
+    /// See [KeyboardEvent::seq] how to find the lost events with it.
+    pub fn seq(&self) -> Option<u64> {
+        match self {
+            InputEvent::Keyboard(ke) => Some(ke.seq),
+            InputEvent::Mouse(me) => Some(me.seq),
+            _ => None,
+        }
+    }
+
+    /// Returns the device the event came from, so that the events of one [crate::Hook] can be routed in a single loop.
+    /// The events composed on the consumer thread come from the device of their events:
+    /// the [InputEvent::Text] from the keyboard, the [InputEvent::MouseDoubleClick] from the mouse.
+    /// Returns `None` for the events that don't come from any device, like [InputEvent::Reset].
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// assert_eq!(InputEvent::Text(String::from("hi")).source(), Some(InputSource::Keyboard));
+    /// assert_eq!(InputEvent::Reset.source(), None);
+    /// ```
+    pub fn source(&self) -> Option<InputSource> {
+        match self {
+            InputEvent::Keyboard(_) | InputEvent::Text(_) => Some(InputSource::Keyboard),
+            InputEvent::Mouse(_) | InputEvent::MouseDoubleClick(_) => Some(InputSource::Mouse),
+            _ => None,
+        }
+    }
+
+    /// Returns true if Windows flagged the keyboard or mouse event as injected by the software (e.g. with `SendInput`).
+    /// Returns false for the input from the real hardware, and for the events that are not input at all (like [InputEvent::Reset]).
+    /// To drop the injected events before they are even queued, see [crate::hook::HookBuilder::physical_only].
+    pub fn is_injected(&self) -> bool {
+        let injected = match self {
+            InputEvent::Keyboard(ke) => ke.is_injected,
+            InputEvent::Mouse(me) => me.is_injected,
+            _ => None,
+        };
+        injected == Some(IsEventInjected::Injected)
+    }
+
+    /// Returns the time elapsed between the `earlier` event and this one, according to their OS timestamps.
+    /// The wrap around of the timestamps is handled, as long as the events are less than 49.7 days apart.
+    /// Returns `None` if any of the events has no timestamp.
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// let at = |time| InputEvent::Mouse(MouseEvent {
+    ///     event: MouseEventType::Move(MouseMoveEvent { point: None, delta: None }),
+    ///     is_injected: None,
+    ///     time: Some(time),
+    ///     seq: 0,
+    ///     foreground_pid: None,
+    ///     extra_info: 0,
+    ///     blocked: false,
+    ///     raw: None,
+    /// });
+    /// assert_eq!(at(1500).elapsed_since(&at(1000)), Some(std::time::Duration::from_millis(500)));
+    /// assert_eq!(at(5).elapsed_since(&at(u32::MAX)), Some(std::time::Duration::from_millis(6)));
+    /// assert_eq!(InputEvent::Reset.elapsed_since(&at(1000)), None);
+    /// ```
+    pub fn elapsed_since(&self, earlier: &InputEvent) -> Option<std::time::Duration> {
+        let elapsed = self.time()?.wrapping_sub(earlier.time()?);
+        Some(std::time::Duration::from_millis(elapsed as u64))
+    }
+
+    /// The position of the variant, to order the different events without the timestamp.
+    fn variant_index(&self) -> u8 {
+        match self {
+            InputEvent::Keyboard(_) => 0,
+            InputEvent::Mouse(_) => 1,
+            InputEvent::Other(_) => 2,
+            InputEvent::Reset => 3,
+            InputEvent::Text(_) => 4,
+            InputEvent::MouseDoubleClick(_) => 5,
+        }
+    }
+}
+
+impl Ord for InputEvent {
+    fn cmp(&self, other: &InputEvent) -> std::cmp::Ordering {
+        (self.time(), self.seq()).cmp(&(other.time(), other.seq())).then_with(|| match (self, other) {
+            (InputEvent::Keyboard(ke), InputEvent::Keyboard(other)) => ke.cmp(other),
+            (InputEvent::Mouse(me), InputEvent::Mouse(other)) => me.cmp(other),
+            (InputEvent::Other(message), InputEvent::Other(other)) => message.cmp(other),
+            (InputEvent::Text(text), InputEvent::Text(other)) => text.cmp(other),
+            (InputEvent::MouseDoubleClick(button), InputEvent::MouseDoubleClick(other)) => button.cmp(other),
+            _ => self.variant_index().cmp(&other.variant_index()),
+        })
+    }
+}
+
+impl PartialOrd for InputEvent {
+    fn partial_cmp(&self, other: &InputEvent) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 /// Indicates if the keyboard event was injected by the software, see this crate integration tests for example.
@@ -26,7 +201,8 @@ pub enum IsEventInjected {
 }
 
 /// Keyboard event with data if key was pressed down or up, what key was pressed, and if event was injected. 
-#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+/// The events compare by their raw timestamp (not aware of its wrap around), see the "Ordering" section of the [InputEvent].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KeyboardEvent {
     /// Indicates if this is a press or release
@@ -35,9 +211,106 @@ pub struct KeyboardEvent {
     pub key: Option<KeyboardKey>,
     /// If the event was injected by the software
     pub is_injected: Option<IsEventInjected>,
+    /// The time stamp of the event in milliseconds, as returned by `GetMessageTime`.
+    /// It wraps around to zero after roughly 49.7 days.
+    pub time: Option<u32>,
+    /// Position of the key on the keyboard, independent of the layout. See [PhysicalKey].
+    pub physical_key: Option<PhysicalKey>,
+    /// The modifier keys held down, including the change made by this event. See [Modifiers].
+    pub modifiers: Modifiers,
+    /// True for the key down generated by the auto-repeat of the held key, i.e. the key was already down without a key up in between.
+    /// Like the [Modifiers], it is tracked by the hook from the events it sees: the first key down after the hook is built is never a repeat.
+    pub is_repeat: bool,
+    /// For the key up, how long the key was held down: the time since its first key down, according to the OS timestamps.
+    /// It is tracked like the [KeyboardEvent::is_repeat], so it is `None` if the hook did not see the key down
+    /// (e.g. the key was already held when the hook was built). Always `None` for the key down.
+    pub held_for: Option<std::time::Duration>,
+    /// True for the key down that came too soon after the previous press of the same key, likely a bounce of the worn switch (the "chatter"),
+    /// only with [crate::hook::HookBuilder::with_chatter_detection]. It is tracked like the [KeyboardEvent::is_repeat], from the OS timestamps.
+    /// The event is delivered as usual, it is up to the caller to ignore it.
+    pub chatter: bool,
+    /// The position of the event among all the events delivered by the hook, keyboard and mouse alike, assigned when it is captured.
+    /// It increases by one with each queued event, so a gap means the events were lost in between: the ones counted by [crate::Hook::dropped_count],
+    /// e.g. because the [crate::hook::HookBuilder::capacity] was exceeded. The events filtered out by the settings are not numbered.
+    /// 0 if the event was not captured by the hook.
+    pub seq: u64,
+    /// The process owning the foreground window, only with [crate::hook::HookBuilder::with_foreground_info]. See there why it may be stale.
+    pub foreground_pid: Option<u32>,
+    /// The raw `HKL` value of the keyboard layout in use, only with [crate::hook::HookBuilder::with_layout_info]. See there why it may be stale.
+    pub layout: Option<usize>,
+    /// The `dwExtraInfo` of the event, e.g. the value the injecting application tagged its events with. 0 if it is not known.
+    pub extra_info: usize,
+    /// True if the event was not passed on to the other hooks and applications, because the [crate::hook::HookBuilder::with_blocking] predicate
+    /// returned true for it. It is the decision of the hook procedure, the event was swallowed before the system processed it.
+    pub blocked: bool,
+    /// The original data seen by the hook procedure, only with [crate::hook::HookBuilder::with_raw]. See [RawKeyboardData].
+    pub raw: Option<RawKeyboardData>,
+}
+
+impl Ord for KeyboardEvent {
+    fn cmp(&self, other: &KeyboardEvent) -> std::cmp::Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq)).then_with(|| {
+            // The tuples are comparable up to 12 elements
+            ((&self.pressed, &self.key, &self.is_injected, &self.physical_key, &self.modifiers, &self.is_repeat),
+                (&self.held_for, &self.chatter, &self.foreground_pid, &self.layout, &self.extra_info, &self.blocked, &self.raw))
+                .cmp(&((&other.pressed, &other.key, &other.is_injected, &other.physical_key, &other.modifiers, &other.is_repeat),
+                    (&other.held_for, &other.chatter, &other.foreground_pid, &other.layout, &other.extra_info, &other.blocked, &other.raw)))
+        })
+    }
+}
+
+impl PartialOrd for KeyboardEvent {
+    fn partial_cmp(&self, other: &KeyboardEvent) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The parameters of the low-level keyboard hook procedure, as received from the OS, see [crate::hook::HookBuilder::with_raw].
+/// The `KBDLLHOOKSTRUCT` is valid only during the call, so its fields are copied here: they can be inspected at any time later.
+/// All the fields of the structure are copied, none is left out.
+#[doc(alias = "KBDLLHOOKSTRUCT")]
+#[doc(alias = "KeyboardHookData")]
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RawKeyboardData {
+    /// The `wParam`, i.e. the `WM_KEYDOWN`, `WM_KEYUP`, `WM_SYSKEYDOWN` or `WM_SYSKEYUP` message
+    pub message: usize,
+    pub vk_code: u32,
+    pub scan_code: u32,
+    /// The `LLKHF_*` flags
+    pub flags: u32,
+    pub time: u32,
+    pub extra_info: usize,
 }
 
-/// Enum to distinguish system key press from normal key press.
+/// Identifies the physical position of the key on the keyboard by its hardware scan code, regardless of the keyboard layout.
+/// 
+/// Games usually bind the actions to the key positions, so that the "WASD" keys stay in the same place on e.g. the AZERTY layout
+/// (where they are labeled "ZQSD"). The [KeyboardKey] on the other hand follows the layout, as it is derived from the virtual key code.
+/// 
+/// ```rust
+/// # use willhook::event::*;
+/// // The key in the "W" position of the US layout, regardless of the label on it
+/// let forward = PhysicalKey { scan_code: 0x11, extended: false };
+/// # let event = KeyboardEvent { pressed: KeyPress::Down(IsSystemKeyPress::Normal), key: None, is_injected: None, time: None, physical_key: None, modifiers: Modifiers::empty(), is_repeat: false, held_for: None, chatter: false, seq: 0, foreground_pid: None, layout: None, extra_info: 0, blocked: false, raw: None };
+/// if event.physical_key == Some(forward) {
+///     println!("Move forward");
+/// }
+/// ```
+/// 
+/// Keep in mind that the software may inject keys without the scan code (e.g. `SendInput` with only the virtual key code),
+/// in which case the scan code is 0, see [KeyboardEvent::has_scan_code].
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PhysicalKey {
+    /// The hardware scan code, without the `0xE0` extended prefix. 0 if the key did not come from the keyboard, see [KeyboardEvent::has_scan_code]
+    pub scan_code: u16,
+    /// True for the keys reported with the `0xE0` prefix, e.g. the right control or the arrow keys (as opposed to the numpad ones)
+    pub extended: bool,
+}
+
+/// Enum to distinguish system key press from normal key press, i.e. the `WM_SYSKEYDOWN`/`WM_SYSKEYUP` from the `WM_KEYDOWN`/`WM_KEYUP`.
+/// See [KeyPress::is_system_key].
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum IsSystemKeyPress {
@@ -58,9 +331,60 @@ pub enum KeyPress {
     Other(usize),
 }
 
+impl KeyPress {
+    /// Returns true for the key pressed down (or repeated), whether it is a system key press or not.
+    /// Neither [KeyPress::is_pressed] nor [KeyPress::is_released] is true for [KeyPress::Other].
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// assert!(KeyPress::Down(IsSystemKeyPress::System).is_pressed());
+    /// assert!(KeyPress::Up(IsSystemKeyPress::Normal).is_released());
+    /// assert!(!KeyPress::Other(0).is_pressed() && !KeyPress::Other(0).is_released());
+    /// assert_eq!(bool::try_from(KeyPress::Down(IsSystemKeyPress::Normal)), Ok(true));
+    /// assert_eq!(bool::try_from(KeyPress::Other(7)), Err(KeyPress::Other(7)));
+    /// ```
+    pub fn is_pressed(&self) -> bool {
+        matches!(self, KeyPress::Down(_))
+    }
+
+    /// Returns true for the key released, whether it is a system key press or not.
+    pub fn is_released(&self) -> bool {
+        matches!(self, KeyPress::Up(_))
+    }
+
+    /// Returns true if Windows sent the `WM_SYSKEYDOWN` or `WM_SYSKEYUP`, i.e. the key was pressed or released while Alt was held
+    /// (or it is the Alt or F10 key itself), see [IsSystemKeyPress]. Same as matching the `KeyPress::Down(IsSystemKeyPress::System)`
+    /// and `KeyPress::Up(IsSystemKeyPress::System)`.
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// assert!(KeyPress::Down(IsSystemKeyPress::System).is_system_key());
+    /// assert!(!KeyPress::Up(IsSystemKeyPress::Normal).is_system_key());
+    /// assert!(!KeyPress::Other(0).is_system_key());
+    /// ```
+    pub fn is_system_key(&self) -> bool {
+        matches!(self, KeyPress::Down(IsSystemKeyPress::System) | KeyPress::Up(IsSystemKeyPress::System))
+    }
+}
+
+/// The pressed state after the transition: `true` for [KeyPress::Down], `false` for [KeyPress::Up].
+/// [KeyPress::Other] is returned back as the error, as it says nothing about the key state.
+impl TryFrom<KeyPress> for bool {
+    type Error = KeyPress;
+
+    fn try_from(press: KeyPress) -> Result<bool, KeyPress> {
+        match press {
+            KeyPress::Down(_) => Ok(true),
+            KeyPress::Up(_) => Ok(false),
+            other => Err(other),
+        }
+    }
+}
+
 /// Indicates key on the keyboard.
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum KeyboardKey {
     A,
     B,
@@ -112,6 +436,7 @@ pub enum KeyboardKey {
     PageUp,
     PageDown,
     Home,
+    End,
     ArrowLeft,
     ArrowUp,
     ArrowRight,
@@ -120,8 +445,14 @@ pub enum KeyboardKey {
     PrintScreen,
     Insert,
     Delete,
+    /// Pause (with control Break)
+    Pause,
+    /// Clear, e.g. the numpad 5 with the num lock off
+    Clear,
     LeftWindows,
     RightWindows,
+    /// The context menu key, next to the right windows key
+    ContextMenu,
     /// , (with shift <)
     Comma,         
     /// . (with shift >)
@@ -140,6 +471,12 @@ pub enum KeyboardKey {
     RightBrace,    
     /// ` (with shift ~)
     Grave,         
+    /// - (with shift _)
+    Minus,
+    /// = (with shift +)
+    Equal,
+    /// The extra key of the 102-key keyboards, next to the left shift (e.g. `\` or `<>`)
+    Oem102,
     F1,
     F2,
     F3,
@@ -183,62 +520,137 @@ pub enum KeyboardKey {
     Subtract,
     Decimal,
     Divide,
+    /// The browser, volume, media and launch keys of the multimedia keyboards. They are reported by the keyboard hook
+    /// like any other key, even when the OS turns them into `WM_APPCOMMAND` for the focused window.
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNextTrack,
+    MediaPreviousTrack,
+    MediaStop,
+    MediaPlayPause,
+    LaunchMail,
+    LaunchMediaSelect,
+    LaunchApp1,
+    LaunchApp2,
     Other(u32),
     /// Invalid input received from the OS
     InvalidKeyCodeReceived,
 }
 
-// those dont have defines.
-const VK_0: i32 = 0x30;
-const VK_1: i32 = 0x31;
-const VK_2: i32 = 0x32;
-const VK_3: i32 = 0x33;
-const VK_4: i32 = 0x34;
-const VK_5: i32 = 0x35;
-const VK_6: i32 = 0x36;
-const VK_7: i32 = 0x37;
-const VK_8: i32 = 0x38;
-const VK_9: i32 = 0x39;
-const VK_A: i32 = 0x41;
-const VK_B: i32 = 0x42;
-const VK_C: i32 = 0x43;
-const VK_D: i32 = 0x44;
-const VK_E: i32 = 0x45;
-const VK_F: i32 = 0x46;
-const VK_G: i32 = 0x47;
-const VK_H: i32 = 0x48;
-const VK_I: i32 = 0x49;
-const VK_J: i32 = 0x4A;
-const VK_K: i32 = 0x4B;
-const VK_L: i32 = 0x4C;
-const VK_M: i32 = 0x4D;
-const VK_N: i32 = 0x4E;
-const VK_O: i32 = 0x4F;
-const VK_P: i32 = 0x50;
-const VK_Q: i32 = 0x51;
-const VK_R: i32 = 0x52;
-const VK_S: i32 = 0x53;
-const VK_T: i32 = 0x54;
-const VK_U: i32 = 0x55;
-const VK_V: i32 = 0x56;
-const VK_W: i32 = 0x57;
-const VK_X: i32 = 0x58;
-const VK_Y: i32 = 0x59;
-const VK_Z: i32 = 0x5A;
-
 /// Main mouse event that can be one of [MouseEventType] and also stores if event was injected.
-#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+/// The events compare by their raw timestamp (not aware of its wrap around), see the "Ordering" section of the [InputEvent].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MouseEvent {
     /// The enum also stores the particular event data, like position or button
     pub event: MouseEventType,
     /// Indicates if event was injected by software
     pub is_injected: Option<IsEventInjected>,
+    /// The time stamp of the event in milliseconds, as returned by `GetMessageTime`.
+    /// It wraps around to zero after roughly 49.7 days.
+    pub time: Option<u32>,
+    /// See [KeyboardEvent::seq], the sequence is shared by the keyboard and the mouse events.
+    pub seq: u64,
+    /// The process owning the foreground window, only with [crate::hook::HookBuilder::with_foreground_info]. See there why it may be stale.
+    pub foreground_pid: Option<u32>,
+    /// The `dwExtraInfo` of the event, e.g. the value the injecting application tagged its events with. 0 if it is not known.
+    pub extra_info: usize,
+    /// True if the event was not passed on to the other hooks and applications, because the [crate::hook::HookBuilder::with_blocking] predicate
+    /// returned true for it. It is the decision of the hook procedure, the event was swallowed before the system processed it.
+    pub blocked: bool,
+    /// The original data seen by the hook procedure, only with [crate::hook::HookBuilder::with_raw]. See [RawMouseData].
+    pub raw: Option<RawMouseData>,
+}
+
+impl Ord for MouseEvent {
+    fn cmp(&self, other: &MouseEvent) -> std::cmp::Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq)).then_with(|| {
+            (&self.event, &self.is_injected, &self.foreground_pid, &self.extra_info, &self.blocked, &self.raw)
+                .cmp(&(&other.event, &other.is_injected, &other.foreground_pid, &other.extra_info, &other.blocked, &other.raw))
+        })
+    }
+}
+
+impl PartialOrd for MouseEvent {
+    fn partial_cmp(&self, other: &MouseEvent) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+impl KeyboardEvent {
+    /// The event as if it was not captured by the hook: only the `pressed` and the `key` are set, the other fields are empty.
+    /// The tests set the fields they care about with the struct update syntax.
+    pub(crate) fn for_test(pressed: KeyPress, key: Option<KeyboardKey>) -> KeyboardEvent {
+        KeyboardEvent {
+            pressed,
+            key,
+            is_injected: None,
+            time: None,
+            physical_key: None,
+            modifiers: Modifiers::empty(),
+            is_repeat: false,
+            held_for: None,
+            chatter: false,
+            seq: 0,
+            foreground_pid: None,
+            layout: None,
+            extra_info: 0,
+            blocked: false,
+            raw: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl MouseEvent {
+    /// The `event` as if it was not captured by the hook, see [KeyboardEvent::for_test].
+    pub(crate) fn for_test(event: MouseEventType) -> MouseEvent {
+        MouseEvent {
+            event,
+            is_injected: None,
+            time: None,
+            seq: 0,
+            foreground_pid: None,
+            extra_info: 0,
+            blocked: false,
+            raw: None,
+        }
+    }
+}
+
+/// The parameters of the low-level mouse hook procedure, as received from the OS, see [crate::hook::HookBuilder::with_raw].
+/// The `MSLLHOOKSTRUCT` is valid only during the call, so its fields are copied here: they can be inspected at any time later.
+/// All the fields of the structure are copied, see [RawKeyboardData].
+#[doc(alias = "MSLLHOOKSTRUCT")]
+#[doc(alias = "MouseHookData")]
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RawMouseData {
+    /// The `wParam`, i.e. the `WM_*` mouse message
+    pub message: usize,
+    /// The `pt`, in the per-monitor aware screen coordinates
+    pub point: Point,
+    /// The `mouseData`, with the wheel delta or the X button in the high word
+    pub mouse_data: u32,
+    /// The `LLMHF_*` flags
+    pub flags: u32,
+    pub time: u32,
+    pub extra_info: usize,
 }
 
 /// The type of the mouse event with it's specific data
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum MouseEventType {
     /// Button on the mouse was pressed
     Press(MousePressEvent),
@@ -261,28 +673,43 @@ pub struct MousePressEvent {
 /// Holds information which mouse wheel triggered the event
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum MouseWheel {
+    /// `WM_MOUSEHWHEEL`, the tilt wheel or the horizontal scroll of the touchpad: the positive delta scrolls to the right.
     Horizontal,
+    /// `WM_MOUSEWHEEL`: the positive delta scrolls up, away from the user. Note that it is the opposite of the screen
+    /// coordinates (where `y` grows downwards), while the horizontal positive delta follows them.
     Vertical,
+    /// Unexpected message, stored for reference
     Unknown(usize),
 }
 
-/// Indicates the direction of the mouse wheel spin
+/// Indicates the direction of the mouse wheel spin, derived from the sign of the wheel delta (as in `GET_WHEEL_DELTA_WPARAM`).
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MouseWheelDirection {
+    /// Positive delta: the vertical wheel rotated away from the user (scroll up), the horizontal wheel tilted to the right
     Forward,
+    /// Negative delta: the vertical wheel rotated toward the user (scroll down), the horizontal wheel tilted to the left
     Backward,
+    /// Zero delta, the raw `mouseData` is stored for reference
     Unknown(u32),
 }
 
-/// The mouse wheel event with information which wheel triggered an event and the direction of the spin
+/// The mouse wheel event with information which wheel triggered an event and the direction of the spin.
+/// 
+/// Wheel events are captured globally, before Windows routes them to any window,
+/// so they are delivered regardless of the focused window or the window under the cursor (e.g. over the desktop).
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MouseWheelEvent {
     pub wheel: MouseWheel,
-    pub direction: Option<MouseWheelDirection>
-
+    pub direction: Option<MouseWheelDirection>,
+    /// The signed distance the wheel rotated, in multiples of `WHEEL_DELTA` (120) for the classic wheels.
+    /// The high-resolution wheels report smaller values, so accumulate the deltas rather than counting the events.
+    /// Positive values are [MouseWheelDirection::Forward], negative ones [MouseWheelDirection::Backward],
+    /// see the [MouseWheel] for what they mean on each wheel. Both wheels decode the high word of the `mouseData` the same way.
+    pub delta: Option<i16>,
 }
 
 /// Point in per-monitor aware coordinates, see [MSDN](https://learn.microsoft.com/en-us/windows/desktop/api/shellscalingapi/ne-shellscalingapi-process_dpi_awareness)
@@ -298,6 +725,17 @@ pub struct Point {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MouseMoveEvent {
     pub point: Option<Point>,
+    /// The movement since the previous move, filled in only with [crate::hook::HookBuilder::with_move_deltas].
+    /// It is `None` for the first move received and for the moves without the position.
+    pub delta: Option<MoveDelta>,
+}
+
+/// The relative movement of the cursor, in pixels, see [MouseMoveEvent::delta]
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MoveDelta {
+    pub dx: i32,
+    pub dy: i32,
 }
 
 /// Indicates if button was pressed or released
@@ -309,6 +747,39 @@ pub enum MouseButtonPress {
     Other(usize),
 }
 
+impl MouseButtonPress {
+    /// Returns true for the button pressed down. Neither this nor [MouseButtonPress::is_released] is true for [MouseButtonPress::Other].
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// assert!(MouseButtonPress::Down.is_pressed());
+    /// assert!(MouseButtonPress::Up.is_released());
+    /// assert_eq!(bool::try_from(MouseButtonPress::Up), Ok(false));
+    /// assert_eq!(bool::try_from(MouseButtonPress::Other(7)), Err(MouseButtonPress::Other(7)));
+    /// ```
+    pub fn is_pressed(&self) -> bool {
+        matches!(self, MouseButtonPress::Down)
+    }
+
+    /// Returns true for the button released.
+    pub fn is_released(&self) -> bool {
+        matches!(self, MouseButtonPress::Up)
+    }
+}
+
+/// The pressed state after the transition, see the `TryFrom<KeyPress>` for the keys.
+impl TryFrom<MouseButtonPress> for bool {
+    type Error = MouseButtonPress;
+
+    fn try_from(press: MouseButtonPress) -> Result<bool, MouseButtonPress> {
+        match press {
+            MouseButtonPress::Down => Ok(true),
+            MouseButtonPress::Up => Ok(false),
+            other => Err(other),
+        }
+    }
+}
+
 /// Indicates if mouse button press is single or double click
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -318,19 +789,280 @@ pub enum MouseClick {
     Other(u32),
 }
 
-/// Identifies which mouse button triggered an event
+/// Identifies which mouse button triggered an event.
+/// The left and right buttons are the logical ones by default, i.e. swapped if the user swapped the primary and secondary buttons,
+/// see [crate::hook::HookBuilder::physical_mouse_buttons].
 #[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum MouseButton {
     Left(MouseClick),
     Right(MouseClick),
     Middle(MouseClick),
-    /// XBUTTON1
+    /// XBUTTON1, typically the "back" thumb button
     X1(MouseClick), 
-    /// XBUTTON2
+    /// XBUTTON2, typically the "forward" thumb button
     X2(MouseClick), 
-    /// Either XBUTTON1 or XBUTTON2
+    /// Either XBUTTON1 or XBUTTON2, but it could not be decoded which one.
+    /// Happens when hook data is missing or when the message reports both (or none) of the X buttons at once.
     UnkownX(MouseClick),  
     /// Unexpected mouse button. Raw code stored for reference, see MSDN documentation about low-level hooks.
     Other(usize),
 }
+
+/// Set of the mouse buttons held down at the same time, see [crate::Hook::pressed_buttons].
+/// 
+/// ```rust
+/// # use willhook::event::MouseButtons;
+/// let chord = MouseButtons::LEFT | MouseButtons::RIGHT;
+/// assert!(chord.contains(MouseButtons::LEFT));
+/// assert!(!chord.contains(MouseButtons::LEFT | MouseButtons::MIDDLE));
+/// assert!(MouseButtons::empty().is_empty());
+/// ```
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MouseButtons(u8);
+
+impl MouseButtons {
+    pub const LEFT: MouseButtons = MouseButtons(1 << 0);
+    pub const RIGHT: MouseButtons = MouseButtons(1 << 1);
+    pub const MIDDLE: MouseButtons = MouseButtons(1 << 2);
+    pub const X1: MouseButtons = MouseButtons(1 << 3);
+    pub const X2: MouseButtons = MouseButtons(1 << 4);
+
+    /// No buttons pressed
+    pub const fn empty() -> MouseButtons {
+        MouseButtons(0)
+    }
+
+    pub const fn from_bits(bits: u8) -> MouseButtons {
+        MouseButtons(bits & 0b11111)
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// True if all the `other` buttons are in this set
+    pub const fn contains(&self, other: MouseButtons) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the flag of the button, or `None` if it is not known which button it is.
+    pub fn from_button(button: MouseButton) -> Option<MouseButtons> {
+        match button {
+            MouseButton::Left(_) => Some(MouseButtons::LEFT),
+            MouseButton::Right(_) => Some(MouseButtons::RIGHT),
+            MouseButton::Middle(_) => Some(MouseButtons::MIDDLE),
+            MouseButton::X1(_) => Some(MouseButtons::X1),
+            MouseButton::X2(_) => Some(MouseButtons::X2),
+            MouseButton::UnkownX(_) | MouseButton::Other(_) => None,
+        }
+    }
+}
+
+impl std::ops::BitOr for MouseButtons {
+    type Output = MouseButtons;
+
+    fn bitor(self, rhs: MouseButtons) -> MouseButtons {
+        MouseButtons(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for MouseButtons {
+    fn bitor_assign(&mut self, rhs: MouseButtons) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Set of the modifier keys held down, reported with each [KeyboardEvent].
+/// 
+/// The state is tracked by the keyboard hook from the events it sees, so it starts empty when the hook is built
+/// (modifiers held down at that time are not reported until pressed again), and a release without the press is just ignored.
+/// Like [MouseButtons], the state is cleared on the desktop switch if [crate::hook::HookBuilder::emit_reset_on_focus_loss] is enabled.
+/// 
+/// ```rust
+/// # use willhook::event::Modifiers;
+/// let held = Modifiers::LEFT_CONTROL | Modifiers::RIGHT_SHIFT;
+/// assert!(held.contains(Modifiers::LEFT_CONTROL));
+/// assert!(held.control() && held.shift());
+/// assert!(!held.alt() && !held.windows());
+/// ```
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const LEFT_SHIFT: Modifiers = Modifiers(1 << 0);
+    pub const RIGHT_SHIFT: Modifiers = Modifiers(1 << 1);
+    pub const LEFT_CONTROL: Modifiers = Modifiers(1 << 2);
+    pub const RIGHT_CONTROL: Modifiers = Modifiers(1 << 3);
+    pub const LEFT_ALT: Modifiers = Modifiers(1 << 4);
+    pub const RIGHT_ALT: Modifiers = Modifiers(1 << 5);
+    pub const LEFT_WINDOWS: Modifiers = Modifiers(1 << 6);
+    pub const RIGHT_WINDOWS: Modifiers = Modifiers(1 << 7);
+
+    /// No modifiers held down
+    pub const fn empty() -> Modifiers {
+        Modifiers(0)
+    }
+
+    pub const fn from_bits(bits: u8) -> Modifiers {
+        Modifiers(bits)
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// True if all the `other` modifiers are in this set
+    pub const fn contains(&self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// True if any of this set is in the `other` set
+    pub const fn intersects(&self, other: Modifiers) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// True if either shift is held down
+    pub const fn shift(&self) -> bool {
+        self.intersects(Modifiers(Modifiers::LEFT_SHIFT.0 | Modifiers::RIGHT_SHIFT.0))
+    }
+
+    /// True if either control is held down
+    pub const fn control(&self) -> bool {
+        self.intersects(Modifiers(Modifiers::LEFT_CONTROL.0 | Modifiers::RIGHT_CONTROL.0))
+    }
+
+    /// True if either alt is held down
+    pub const fn alt(&self) -> bool {
+        self.intersects(Modifiers(Modifiers::LEFT_ALT.0 | Modifiers::RIGHT_ALT.0))
+    }
+
+    /// True if either windows key is held down
+    pub const fn windows(&self) -> bool {
+        self.intersects(Modifiers(Modifiers::LEFT_WINDOWS.0 | Modifiers::RIGHT_WINDOWS.0))
+    }
+
+    /// Returns the flag of the key, or `None` if it is not a modifier.
+    pub fn from_key(key: KeyboardKey) -> Option<Modifiers> {
+        match key {
+            KeyboardKey::LeftShift => Some(Modifiers::LEFT_SHIFT),
+            KeyboardKey::RightShift => Some(Modifiers::RIGHT_SHIFT),
+            KeyboardKey::LeftControl => Some(Modifiers::LEFT_CONTROL),
+            KeyboardKey::RightControl => Some(Modifiers::RIGHT_CONTROL),
+            KeyboardKey::LeftAlt => Some(Modifiers::LEFT_ALT),
+            KeyboardKey::RightAlt => Some(Modifiers::RIGHT_ALT),
+            KeyboardKey::LeftWindows => Some(Modifiers::LEFT_WINDOWS),
+            KeyboardKey::RightWindows => Some(Modifiers::RIGHT_WINDOWS),
+            _ => None,
+        }
+    }
+}
+
+impl std::ops::BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, rhs: Modifiers) -> Modifiers {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, rhs: Modifiers) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The device an [InputEvent] came from, see [InputEvent::source].
+#[derive(Copy, Clone, Ord, PartialOrd, Hash, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InputSource {
+    Keyboard,
+    Mouse,
+}
+
+/// Toggle state of the lock keys, see [crate::Hook::lock_states].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockStates {
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+/// The name given to `parse` does not match any key or button, see e.g. [KeyboardKey::as_str].
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+pub struct ParseNameError {
+    name: String,
+}
+
+impl ParseNameError {
+    pub(crate) fn new(name: &str) -> ParseNameError {
+        ParseNameError { name: name.to_owned() }
+    }
+}
+
+impl std::fmt::Display for ParseNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown name \"{}\"", self.name)
+    }
+}
+
+impl std::error::Error for ParseNameError {}
+
+#[cfg(test)]
+mod ordering_tests {
+    use super::*;
+
+    fn key(time: Option<u32>, seq: u64, key: KeyboardKey) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent { time, seq, ..KeyboardEvent::for_test(KeyPress::Down(IsSystemKeyPress::Normal), Some(key)) })
+    }
+
+    fn click(time: Option<u32>, seq: u64) -> InputEvent {
+        let press = MousePressEvent { pressed: MouseButtonPress::Down, button: MouseButton::Left(MouseClick::SingleClick) };
+        InputEvent::Mouse(MouseEvent { time, seq, ..MouseEvent::for_test(MouseEventType::Press(press)) })
+    }
+
+    #[test]
+    fn events_are_ordered_by_the_time_then_the_sequence() {
+        let mut events = vec![
+            click(Some(30), 1),
+            key(Some(10), 9, KeyboardKey::B),
+            click(Some(10), 4),
+            InputEvent::Text(String::from("ab")),
+            key(Some(20), 2, KeyboardKey::A),
+            InputEvent::Reset,
+        ];
+        events.sort();
+        assert_eq!(events, vec![
+            InputEvent::Reset,
+            InputEvent::Text(String::from("ab")),
+            click(Some(10), 4),
+            key(Some(10), 9, KeyboardKey::B),
+            key(Some(20), 2, KeyboardKey::A),
+            click(Some(30), 1),
+        ]);
+    }
+
+    #[test]
+    fn order_is_consistent_with_the_equality() {
+        use std::cmp::Ordering;
+        assert_eq!(key(Some(10), 1, KeyboardKey::A).cmp(&key(Some(10), 1, KeyboardKey::A)), Ordering::Equal);
+        assert_ne!(key(Some(10), 1, KeyboardKey::A).cmp(&key(Some(10), 1, KeyboardKey::B)), Ordering::Equal);
+        assert_ne!(key(Some(10), 1, KeyboardKey::A).cmp(&click(Some(10), 1)), Ordering::Equal);
+        assert_eq!(InputEvent::Reset.cmp(&InputEvent::Reset), Ordering::Equal);
+        // The later timestamp wins over the lower sequence number and over the contents
+        assert!(key(Some(11), 0, KeyboardKey::A) > key(Some(10), 5, KeyboardKey::Z));
+        assert!(key(None, 5, KeyboardKey::Z) < key(Some(0), 0, KeyboardKey::A));
+    }
+}
@@ -6,6 +6,45 @@ use winapi::shared::minwindef::*;
 use winapi::um::winuser::*;
 
 use crate::event::*;
+use crate::event::message::WindowsInputMessage as Message;
+
+// those dont have defines.
+const VK_0: i32 = 0x30;
+const VK_1: i32 = 0x31;
+const VK_2: i32 = 0x32;
+const VK_3: i32 = 0x33;
+const VK_4: i32 = 0x34;
+const VK_5: i32 = 0x35;
+const VK_6: i32 = 0x36;
+const VK_7: i32 = 0x37;
+const VK_8: i32 = 0x38;
+const VK_9: i32 = 0x39;
+const VK_A: i32 = 0x41;
+const VK_B: i32 = 0x42;
+const VK_C: i32 = 0x43;
+const VK_D: i32 = 0x44;
+const VK_E: i32 = 0x45;
+const VK_F: i32 = 0x46;
+const VK_G: i32 = 0x47;
+const VK_H: i32 = 0x48;
+const VK_I: i32 = 0x49;
+const VK_J: i32 = 0x4A;
+const VK_K: i32 = 0x4B;
+const VK_L: i32 = 0x4C;
+const VK_M: i32 = 0x4D;
+const VK_N: i32 = 0x4E;
+const VK_O: i32 = 0x4F;
+const VK_P: i32 = 0x50;
+const VK_Q: i32 = 0x51;
+const VK_R: i32 = 0x52;
+const VK_S: i32 = 0x53;
+const VK_T: i32 = 0x54;
+const VK_U: i32 = 0x55;
+const VK_V: i32 = 0x56;
+const VK_W: i32 = 0x57;
+const VK_X: i32 = 0x58;
+const VK_Y: i32 = 0x59;
+const VK_Z: i32 = 0x5A;
 
 impl KeyboardEvent {
     pub unsafe fn new(wm_key_code: WPARAM, kbd_hook_struct: *const KBDLLHOOKSTRUCT) -> Self {
@@ -13,6 +52,235 @@ impl KeyboardEvent {
             pressed: KeyPress::from(wm_key_code),
             key: KeyboardKey::optionally_from(kbd_hook_struct),
             is_injected: IsEventInjected::optionally_from_keyboard(kbd_hook_struct),
+            time: if kbd_hook_struct.is_null() { None } else { Some((*kbd_hook_struct).time) },
+            physical_key: if kbd_hook_struct.is_null() { None } else { Some(PhysicalKey::from(&*kbd_hook_struct)) },
+            // The hook structure knows only this one key, the state is tracked by the hook procedure
+            modifiers: Modifiers::empty(),
+            is_repeat: false,
+            held_for: None,
+            chatter: false,
+            seq: 0,
+            foreground_pid: None,
+            layout: None,
+            extra_info: if kbd_hook_struct.is_null() { 0 } else { (*kbd_hook_struct).dwExtraInfo },
+            blocked: false,
+            raw: None,
+        }
+    }
+
+    /// Parses the parameters of the low-level keyboard hook procedure, the same way the hook does:
+    /// the `wparam` is the message (e.g. `WM_KEYDOWN`), the `kbd` is the structure pointed to by the `lParam`.
+    /// It is meant for the code calling or implementing the hook procedure on its own, or for testing with the synthetic structures.
+    /// 
+    /// Only what the parameters say is filled in: the [KeyboardEvent::modifiers], [KeyboardEvent::is_repeat], [KeyboardEvent::held_for],
+    /// [KeyboardEvent::chatter] and [KeyboardEvent::seq] are tracked by the hook across the events, so they are left empty, and so is the [KeyboardEvent::raw].
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// use winapi::um::winuser::{KBDLLHOOKSTRUCT, WM_KEYDOWN};
+    /// let mut kbd: KBDLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+    /// kbd.vkCode = 0x41;
+    /// kbd.scanCode = 0x1E;
+    /// let event = KeyboardEvent::from_raw(WM_KEYDOWN as usize, &kbd);
+    /// assert_eq!(event.key, Some(KeyboardKey::A));
+    /// assert_eq!(event.pressed, KeyPress::Down(IsSystemKeyPress::Normal));
+    /// ```
+    pub fn from_raw(wparam: usize, kbd: &KBDLLHOOKSTRUCT) -> KeyboardEvent {
+        unsafe { KeyboardEvent::new(wparam as WPARAM, kbd) }
+    }
+}
+
+impl RawKeyboardData {
+    /// Returns the parameters of the hook procedure this data was copied from, see [KeyboardEvent::from_raw].
+    pub fn as_raw(&self) -> (usize, KBDLLHOOKSTRUCT) {
+        let kbd = KBDLLHOOKSTRUCT {
+            vkCode: self.vk_code,
+            scanCode: self.scan_code,
+            flags: self.flags,
+            time: self.time,
+            dwExtraInfo: self.extra_info,
+        };
+        (self.message, kbd)
+    }
+
+    pub fn new(wm_key_code: WPARAM, value: &KBDLLHOOKSTRUCT) -> RawKeyboardData {
+        RawKeyboardData {
+            message: wm_key_code,
+            vk_code: value.vkCode,
+            scan_code: value.scanCode,
+            flags: value.flags,
+            time: value.time,
+            extra_info: value.dwExtraInfo,
+        }
+    }
+}
+
+impl RawMouseData {
+    /// Returns the parameters of the hook procedure this data was copied from, see [MouseEvent::from_raw].
+    pub fn as_raw(&self) -> (usize, MSLLHOOKSTRUCT) {
+        let msll = MSLLHOOKSTRUCT {
+            pt: POINT { x: self.point.x, y: self.point.y },
+            mouseData: self.mouse_data,
+            flags: self.flags,
+            time: self.time,
+            dwExtraInfo: self.extra_info,
+        };
+        (self.message, msll)
+    }
+
+    pub fn new(wm_mouse_param: WPARAM, value: &MSLLHOOKSTRUCT) -> RawMouseData {
+        RawMouseData {
+            message: wm_mouse_param,
+            point: value.pt.into(),
+            mouse_data: value.mouseData,
+            flags: value.flags,
+            time: value.time,
+            extra_info: value.dwExtraInfo,
+        }
+    }
+}
+
+impl From<&KBDLLHOOKSTRUCT> for PhysicalKey {
+    fn from(value: &KBDLLHOOKSTRUCT) -> PhysicalKey {
+        PhysicalKey {
+            scan_code: value.scanCode as u16,
+            extended: value.flags & LLKHF_EXTENDED != 0,
+        }
+    }
+}
+
+impl PhysicalKey {
+    /// Returns the physical key that produces the `key` in the given keyboard `layout` (the raw `HKL` value, see [crate::layout]).
+    /// Returns `None` if the layout does not have such key.
+    pub fn from_key(key: KeyboardKey, layout: usize) -> Option<PhysicalKey> {
+        let vk = key.virtual_key_code()?;
+        let scan = unsafe { MapVirtualKeyExW(vk, MAPVK_VK_TO_VSC_EX, layout as HKL) };
+        match scan & 0xFF {
+            0 => None,
+            code => Some(PhysicalKey { scan_code: code as u16, extended: scan & 0xFF00 == 0xE000 }),
+        }
+    }
+
+    /// Returns the key produced by this physical key in the given keyboard `layout` (the raw `HKL` value, see [crate::layout]).
+    /// Returns `None` if the layout does not map this position to any key.
+    pub fn to_key(&self, layout: usize) -> Option<KeyboardKey> {
+        let scan = self.scan_code as UINT | if self.extended { 0xE000 } else { 0 };
+        match unsafe { MapVirtualKeyExW(scan, MAPVK_VSC_TO_VK_EX, layout as HKL) } {
+            0 => None,
+            vk => Some(KeyboardKey::from(vk)),
+        }
+    }
+}
+
+impl KeyboardEvent {
+    /// Returns false if the key came without the hardware scan code (i.e. it is 0), or the scan code is not known.
+    /// 
+    /// The keyboards always send the scan code, so the key without it did not come from the keyboard: usually it was injected
+    /// with `SendInput` by the virtual key code only, or it was synthesized by the system or the driver (e.g. some media keys).
+    /// Together with the [KeyboardEvent::is_injected] it tells where the key came from, but neither is the proof:
+    /// the injecting software may set the scan code, and the injected flag can be cleared by the drivers.
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// use winapi::um::winuser::{KBDLLHOOKSTRUCT, WM_KEYDOWN};
+    /// let mut kbd: KBDLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+    /// kbd.vkCode = 0x41;
+    /// assert!(!KeyboardEvent::from_raw(WM_KEYDOWN as usize, &kbd).has_scan_code());
+    /// kbd.scanCode = 0x1E;
+    /// assert!(KeyboardEvent::from_raw(WM_KEYDOWN as usize, &kbd).has_scan_code());
+    /// ```
+    pub fn has_scan_code(&self) -> bool {
+        matches!(self.physical_key, Some(PhysicalKey { scan_code, .. }) if scan_code != 0)
+    }
+
+    /// Returns the language identifier (`LANGID`, e.g. 0x0409 for English - United States) of the [KeyboardEvent::layout],
+    /// i.e. its low word. The primary language (`PRIMARYLANGID`, e.g. 0x09 for English) is in its lowest 10 bits.
+    /// `None` if the layout was not captured, see [crate::hook::HookBuilder::with_layout_info].
+    pub fn language_id(&self) -> Option<u16> {
+        self.layout.map(|hkl| (hkl & 0xFFFF) as u16)
+    }
+
+    /// Returns the text typed by this key press in the given keyboard `layout` (the raw `HKL` value, see [crate::layout]),
+    /// taking into account the [KeyboardEvent::modifiers] and the caps lock state of the calling thread.
+    /// Usually it is one character, but it may be more (e.g. a surrogate pair, or a ligature of the layout).
+    /// 
+    /// Returns `None` for the releases, for the keys that do not type anything (like the arrows), and for the dead keys.
+    /// The keyboard state of the calling thread is not changed, so the dead keys are not composed with the next key:
+    /// for that see [crate::hook::HookBuilder::accumulate_text].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// if let Ok(InputEvent::Keyboard(ke)) = hook.try_recv() {
+    ///     if let Some(text) = ke.to_text(hook.keyboard_layout()) {
+    ///         print!("{}", text);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn to_text(&self, layout: usize) -> Option<String> {
+        if !matches!(self.pressed, KeyPress::Down(_)) {
+            return None
+        }
+        let vk = self.key?.virtual_key_code()?;
+
+        let mut key_state: [BYTE; 256] = [0; 256];
+        let pressed = |on: bool| if on { 0x80 } else { 0 };
+        let sided = [
+            (VK_LSHIFT, Modifiers::LEFT_SHIFT), (VK_RSHIFT, Modifiers::RIGHT_SHIFT),
+            (VK_LCONTROL, Modifiers::LEFT_CONTROL), (VK_RCONTROL, Modifiers::RIGHT_CONTROL),
+            (VK_LMENU, Modifiers::LEFT_ALT), (VK_RMENU, Modifiers::RIGHT_ALT),
+        ];
+        for (vk, modifier) in sided {
+            key_state[vk as usize] = pressed(self.modifiers.contains(modifier));
+        }
+        key_state[VK_SHIFT as usize] = pressed(self.modifiers.shift());
+        key_state[VK_CONTROL as usize] = pressed(self.modifiers.control());
+        key_state[VK_MENU as usize] = pressed(self.modifiers.alt());
+        // The toggle state is the lowest bit
+        key_state[VK_CAPITAL as usize] = (unsafe { GetKeyState(VK_CAPITAL) } & 1) as BYTE;
+
+        // Bit 2 keeps the keyboard state (including the pending dead key) untouched, since Windows 10 1607
+        let text: String = to_unicode(vk, &key_state, layout, 1 << 2).into_iter().collect();
+        if text.is_empty() || text.chars().all(char::is_control) { None } else { Some(text) }
+    }
+}
+
+/// Translates the virtual key with the given keyboard state by `ToUnicodeEx`, see its documentation for the `flags`.
+/// Returns no characters if the key does not type anything, or if it is a dead key (to be composed with the next key).
+pub(crate) fn to_unicode(vk: DWORD, key_state: &[BYTE; 256], layout: usize, flags: UINT) -> Vec<char> {
+    let mut output = [0u16; 8];
+    let written = unsafe {
+        let hkl = layout as HKL;
+        ToUnicodeEx(
+            vk as UINT,
+            MapVirtualKeyExW(vk as UINT, MAPVK_VK_TO_VSC, hkl),
+            key_state.as_ptr(),
+            output.as_mut_ptr(),
+            output.len() as INT,
+            flags,
+            hkl,
+        )
+    };
+    // Negative value means that a dead key was pressed
+    if written <= 0 {
+        return Vec::new();
+    }
+    char::decode_utf16(output[..written as usize].iter().copied())
+        .filter_map(|c| c.ok())
+        .collect()
+}
+
+impl LockStates {
+    /// Queries the toggle state of the lock keys with `GetKeyState`, see [crate::Hook::lock_states].
+    pub fn current() -> LockStates {
+        // The toggle state is the lowest bit
+        let is_on = |vk| unsafe { GetKeyState(vk) } & 1 != 0;
+        LockStates {
+            caps_lock: is_on(VK_CAPITAL),
+            num_lock: is_on(VK_NUMLOCK),
+            scroll_lock: is_on(VK_SCROLL),
         }
     }
 }
@@ -59,14 +327,14 @@ impl From<WPARAM> for KeyPress {
     fn from(code: WPARAM) -> Self {
         use KeyPress::*;
         use IsSystemKeyPress::*;
-        match code.try_into() {
-            Ok(WM_KEYDOWN) => Down(Normal),
-            Ok(WM_KEYUP) => Up(Normal),
-            Ok(WM_SYSKEYDOWN) => Down(System),
-            Ok(WM_SYSKEYUP) => Up(System),
+        match Message::try_from(code) {
+            Ok(Message::KeyDown) => Down(Normal),
+            Ok(Message::KeyUp) => Up(Normal),
+            Ok(Message::SysKeyDown) => Down(System),
+            Ok(Message::SysKeyUp) => Up(System),
             // Either we failed to convert or 
             // it is not one of our supported values
-            _ => Other(code),
+            Ok(_) | Err(_) => Other(code),
         }
     }
 }
@@ -106,6 +374,7 @@ impl From<DWORD> for KeyboardKey {
                     VK_PRIOR => PageUp,
                     VK_NEXT => PageDown,
                     VK_HOME => Home,
+                    VK_END => End,
                     VK_LEFT => ArrowLeft,
                     VK_UP => ArrowUp,
                     VK_RIGHT => ArrowRight,
@@ -114,6 +383,8 @@ impl From<DWORD> for KeyboardKey {
                     VK_SNAPSHOT => PrintScreen,
                     VK_INSERT => Insert,
                     VK_DELETE => Delete,
+                    VK_PAUSE => Pause,
+                    VK_CLEAR => Clear,
                     VK_0 => Number0,
                     VK_1 => Number1,
                     VK_2 => Number2,
@@ -152,6 +423,7 @@ impl From<DWORD> for KeyboardKey {
                     VK_Z => Z,
                     VK_LWIN => LeftWindows,
                     VK_RWIN => RightWindows,
+                    VK_APPS => ContextMenu,
                     VK_NUMPAD0 => Numpad0,
                     VK_NUMPAD1 => Numpad1,
                     VK_NUMPAD2 => Numpad2,
@@ -210,6 +482,27 @@ impl From<DWORD> for KeyboardKey {
                     VK_OEM_5 => BackwardSlash,
                     VK_OEM_6 => RightBrace,
                     VK_OEM_7 => Apostrophe,
+                    VK_OEM_MINUS => Minus,
+                    VK_OEM_PLUS => Equal,
+                    VK_OEM_102 => Oem102,
+                    VK_BROWSER_BACK => BrowserBack,
+                    VK_BROWSER_FORWARD => BrowserForward,
+                    VK_BROWSER_REFRESH => BrowserRefresh,
+                    VK_BROWSER_STOP => BrowserStop,
+                    VK_BROWSER_SEARCH => BrowserSearch,
+                    VK_BROWSER_FAVORITES => BrowserFavorites,
+                    VK_BROWSER_HOME => BrowserHome,
+                    VK_VOLUME_MUTE => VolumeMute,
+                    VK_VOLUME_DOWN => VolumeDown,
+                    VK_VOLUME_UP => VolumeUp,
+                    VK_MEDIA_NEXT_TRACK => MediaNextTrack,
+                    VK_MEDIA_PREV_TRACK => MediaPreviousTrack,
+                    VK_MEDIA_STOP => MediaStop,
+                    VK_MEDIA_PLAY_PAUSE => MediaPlayPause,
+                    VK_LAUNCH_MAIL => LaunchMail,
+                    VK_LAUNCH_MEDIA_SELECT => LaunchMediaSelect,
+                    VK_LAUNCH_APP1 => LaunchApp1,
+                    VK_LAUNCH_APP2 => LaunchApp2,
                     _ => Other(code),
                 }
             }
@@ -219,11 +512,179 @@ impl From<DWORD> for KeyboardKey {
 }
 
 impl KeyboardKey {
+    /// Returns the virtual-key code for the key, unless the key is [KeyboardKey::InvalidKeyCodeReceived].
+    pub fn virtual_key_code(&self) -> Option<DWORD> {
+        use KeyboardKey::*;
+        let vk = match *self {
+            BackSpace => VK_BACK,
+            Tab => VK_TAB,
+            Enter => VK_RETURN,
+            Escape => VK_ESCAPE,
+            Space => VK_SPACE,
+            PageUp => VK_PRIOR,
+            PageDown => VK_NEXT,
+            Home => VK_HOME,
+            End => VK_END,
+            ArrowLeft => VK_LEFT,
+            ArrowUp => VK_UP,
+            ArrowRight => VK_RIGHT,
+            ArrowDown => VK_DOWN,
+            Print => VK_PRINT,
+            PrintScreen => VK_SNAPSHOT,
+            Insert => VK_INSERT,
+            Delete => VK_DELETE,
+            Pause => VK_PAUSE,
+            Clear => VK_CLEAR,
+            Number0 => VK_0,
+            Number1 => VK_1,
+            Number2 => VK_2,
+            Number3 => VK_3,
+            Number4 => VK_4,
+            Number5 => VK_5,
+            Number6 => VK_6,
+            Number7 => VK_7,
+            Number8 => VK_8,
+            Number9 => VK_9,
+            A => VK_A,
+            B => VK_B,
+            C => VK_C,
+            D => VK_D,
+            E => VK_E,
+            F => VK_F,
+            G => VK_G,
+            H => VK_H,
+            I => VK_I,
+            J => VK_J,
+            K => VK_K,
+            L => VK_L,
+            M => VK_M,
+            N => VK_N,
+            O => VK_O,
+            P => VK_P,
+            Q => VK_Q,
+            R => VK_R,
+            S => VK_S,
+            T => VK_T,
+            U => VK_U,
+            V => VK_V,
+            W => VK_W,
+            X => VK_X,
+            Y => VK_Y,
+            Z => VK_Z,
+            LeftWindows => VK_LWIN,
+            RightWindows => VK_RWIN,
+            ContextMenu => VK_APPS,
+            Numpad0 => VK_NUMPAD0,
+            Numpad1 => VK_NUMPAD1,
+            Numpad2 => VK_NUMPAD2,
+            Numpad3 => VK_NUMPAD3,
+            Numpad4 => VK_NUMPAD4,
+            Numpad5 => VK_NUMPAD5,
+            Numpad6 => VK_NUMPAD6,
+            Numpad7 => VK_NUMPAD7,
+            Numpad8 => VK_NUMPAD8,
+            Numpad9 => VK_NUMPAD9,
+            Multiply => VK_MULTIPLY,
+            Add => VK_ADD,
+            Separator => VK_SEPARATOR,
+            Subtract => VK_SUBTRACT,
+            Decimal => VK_DECIMAL,
+            Divide => VK_DIVIDE,
+            F1 => VK_F1,
+            F2 => VK_F2,
+            F3 => VK_F3,
+            F4 => VK_F4,
+            F5 => VK_F5,
+            F6 => VK_F6,
+            F7 => VK_F7,
+            F8 => VK_F8,
+            F9 => VK_F9,
+            F10 => VK_F10,
+            F11 => VK_F11,
+            F12 => VK_F12,
+            F13 => VK_F13,
+            F14 => VK_F14,
+            F15 => VK_F15,
+            F16 => VK_F16,
+            F17 => VK_F17,
+            F18 => VK_F18,
+            F19 => VK_F19,
+            F20 => VK_F20,
+            F21 => VK_F21,
+            F22 => VK_F22,
+            F23 => VK_F23,
+            F24 => VK_F24,
+            NumLock => VK_NUMLOCK,
+            ScrollLock => VK_SCROLL,
+            CapsLock => VK_CAPITAL,
+            LeftShift => VK_LSHIFT,
+            RightShift => VK_RSHIFT,
+            LeftControl => VK_LCONTROL,
+            RightControl => VK_RCONTROL,
+            LeftAlt => VK_LMENU,
+            RightAlt => VK_RMENU,
+            Period => VK_OEM_PERIOD,
+            Comma => VK_OEM_COMMA,
+            SemiColon => VK_OEM_1,
+            Slash => VK_OEM_2,
+            Grave => VK_OEM_3,
+            LeftBrace => VK_OEM_4,
+            BackwardSlash => VK_OEM_5,
+            RightBrace => VK_OEM_6,
+            Apostrophe => VK_OEM_7,
+            Minus => VK_OEM_MINUS,
+            Equal => VK_OEM_PLUS,
+            Oem102 => VK_OEM_102,
+            BrowserBack => VK_BROWSER_BACK,
+            BrowserForward => VK_BROWSER_FORWARD,
+            BrowserRefresh => VK_BROWSER_REFRESH,
+            BrowserStop => VK_BROWSER_STOP,
+            BrowserSearch => VK_BROWSER_SEARCH,
+            BrowserFavorites => VK_BROWSER_FAVORITES,
+            BrowserHome => VK_BROWSER_HOME,
+            VolumeMute => VK_VOLUME_MUTE,
+            VolumeDown => VK_VOLUME_DOWN,
+            VolumeUp => VK_VOLUME_UP,
+            MediaNextTrack => VK_MEDIA_NEXT_TRACK,
+            MediaPreviousTrack => VK_MEDIA_PREV_TRACK,
+            MediaStop => VK_MEDIA_STOP,
+            MediaPlayPause => VK_MEDIA_PLAY_PAUSE,
+            LaunchMail => VK_LAUNCH_MAIL,
+            LaunchMediaSelect => VK_LAUNCH_MEDIA_SELECT,
+            LaunchApp1 => VK_LAUNCH_APP1,
+            LaunchApp2 => VK_LAUNCH_APP2,
+            Other(code) => return Some(code),
+            InvalidKeyCodeReceived => return None,
+        };
+        Some(vk as DWORD)
+    }
+
     pub unsafe fn optionally_from(value: *const KBDLLHOOKSTRUCT) -> Option<Self> {
         if value.is_null() {
             None
         } else {
-            Some(KeyboardKey::from((*value).vkCode))
+            Some(KeyboardKey::resolve_sided((*value).vkCode, PhysicalKey::from(&*value)))
+        }
+    }
+
+    /// Returns the key for the virtual-key code, resolving the generic `VK_SHIFT`, `VK_CONTROL` and `VK_MENU`
+    /// to the left or right key with the physical key that sent it. Windows reports the generic codes e.g. for the input
+    /// injected with them, and the raw input reports only those. The right Shift has its own scan code,
+    /// the right Control and Alt are the extended versions of the left ones. The other codes are converted as they are.
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// let right_control = PhysicalKey { scan_code: 0x1D, extended: true };
+    /// assert_eq!(KeyboardKey::resolve_sided(0x11, right_control), KeyboardKey::RightControl);
+    /// assert_eq!(KeyboardKey::resolve_sided(0x10, PhysicalKey { scan_code: 0x2A, extended: false }), KeyboardKey::LeftShift);
+    /// assert_eq!(KeyboardKey::resolve_sided(0xA3, right_control), KeyboardKey::RightControl);
+    /// ```
+    pub fn resolve_sided(vk: DWORD, physical: PhysicalKey) -> KeyboardKey {
+        match vk as i32 {
+            VK_SHIFT => if physical.scan_code == 0x36 { KeyboardKey::RightShift } else { KeyboardKey::LeftShift },
+            VK_CONTROL => if physical.extended { KeyboardKey::RightControl } else { KeyboardKey::LeftControl },
+            VK_MENU => if physical.extended { KeyboardKey::RightAlt } else { KeyboardKey::LeftAlt },
+            _ => KeyboardKey::from(vk),
         }
     }
 }
@@ -233,23 +694,46 @@ impl MouseEvent {
         use MouseEventType::*;
         MouseEvent{
             is_injected: IsEventInjected::optionally_from_mouse(ms_ll_hook_struct),
-            event: match wm_mouse_param as u32 {
+            time: if ms_ll_hook_struct.is_null() { None } else { Some((*ms_ll_hook_struct).time) },
+            seq: 0,
+            foreground_pid: None,
+            extra_info: if ms_ll_hook_struct.is_null() { 0 } else { (*ms_ll_hook_struct).dwExtraInfo },
+            blocked: false,
+            raw: None,
+            event: match Message::try_from(wm_mouse_param) {
                 // Mouse press
-                WM_LBUTTONDOWN | WM_LBUTTONUP | WM_LBUTTONDBLCLK => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
-                WM_RBUTTONDOWN | WM_RBUTTONUP | WM_RBUTTONDBLCLK => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
-                WM_MBUTTONDOWN | WM_MBUTTONUP | WM_MBUTTONDBLCLK => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
-                WM_XBUTTONDOWN | WM_XBUTTONUP | WM_XBUTTONDBLCLK => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
+                Ok(Message::LButtonDown | Message::LButtonUp | Message::LButtonDblClk) => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
+                Ok(Message::RButtonDown | Message::RButtonUp | Message::RButtonDblClk) => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
+                Ok(Message::MButtonDown | Message::MButtonUp | Message::MButtonDblClk) => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
+                Ok(Message::XButtonDown | Message::XButtonUp | Message::XButtonDblClk) => Press(MousePressEvent::new(wm_mouse_param, ms_ll_hook_struct)),
                 
                 // Mouse move
-                WM_MOUSEMOVE => Move(MouseMoveEvent::new(ms_ll_hook_struct)),
+                Ok(Message::MouseMove) => Move(MouseMoveEvent::new(ms_ll_hook_struct)),
 
                 // Wheel move
-                WM_MOUSEWHEEL | WM_MOUSEHWHEEL => Wheel(MouseWheelEvent::new(wm_mouse_param, ms_ll_hook_struct)),
+                Ok(Message::MouseWheel | Message::MouseHWheel) => Wheel(MouseWheelEvent::new(wm_mouse_param, ms_ll_hook_struct)),
 
-                _ => Other(wm_mouse_param),
+                // Keyboard messages are not expected by the mouse hook
+                Ok(Message::KeyDown | Message::KeyUp | Message::SysKeyDown | Message::SysKeyUp) => Other(wm_mouse_param),
+                Err(_) => Other(wm_mouse_param),
             }
         }
     }
+
+    /// Parses the parameters of the low-level mouse hook procedure, the same way the hook does, see [KeyboardEvent::from_raw].
+    /// The button swap of [crate::hook::HookBuilder::physical_mouse_buttons] is not applied, the buttons are the logical ones.
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// use winapi::um::winuser::{MSLLHOOKSTRUCT, WM_MOUSEWHEEL, WHEEL_DELTA};
+    /// let mut msll: MSLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+    /// msll.mouseData = (WHEEL_DELTA as u32) << 16;
+    /// let event = MouseEvent::from_raw(WM_MOUSEWHEEL as usize, &msll);
+    /// assert!(matches!(event.event, MouseEventType::Wheel(MouseWheelEvent { wheel: MouseWheel::Vertical, direction: Some(MouseWheelDirection::Forward), .. })));
+    /// ```
+    pub fn from_raw(wparam: usize, msll: &MSLLHOOKSTRUCT) -> MouseEvent {
+        unsafe { MouseEvent::new(wparam as WPARAM, msll) }
+    }
 }
 
 impl MousePressEvent {
@@ -270,15 +754,10 @@ impl From<POINT> for Point {
 impl MouseWheel {
     pub fn new(wm_mouse_param: WPARAM) -> MouseWheel {
         use MouseWheel::*;
-        match wm_mouse_param.try_into() {
-            Ok(param_u32) => {
-                match param_u32 {
-                    WM_MOUSEWHEEL => Vertical,
-                    WM_MOUSEHWHEEL => Horizontal,
-                    _ => Unknown(wm_mouse_param),
-                }
-            },
-            _ => Unknown(wm_mouse_param),
+        match Message::try_from(wm_mouse_param) {
+            Ok(Message::MouseWheel) => Vertical,
+            Ok(Message::MouseHWheel) => Horizontal,
+            Ok(_) | Err(_) => Unknown(wm_mouse_param),
         }
     }
 }
@@ -287,7 +766,9 @@ impl MouseWheelEvent {
     pub unsafe fn new(wm_mouse_param: WPARAM, ms_ll_hook_struct: *const MSLLHOOKSTRUCT) -> MouseWheelEvent {
         MouseWheelEvent { 
             wheel: MouseWheel::new(wm_mouse_param), 
-            direction: MouseWheelDirection::optionally_from(ms_ll_hook_struct)
+            direction: MouseWheelDirection::optionally_from(ms_ll_hook_struct),
+            // The delta is the signed high word, it must not be widened as unsigned
+            delta: if ms_ll_hook_struct.is_null() { None } else { Some(GET_WHEEL_DELTA_WPARAM((*ms_ll_hook_struct).mouseData as WPARAM)) },
         }
         
     }
@@ -316,11 +797,11 @@ impl MouseWheelDirection {
 impl MouseMoveEvent {
     pub unsafe fn new(ms_ll_hook_struct: *const MSLLHOOKSTRUCT) -> MouseMoveEvent {
         if ms_ll_hook_struct.is_null() {
-            MouseMoveEvent{ point: None }
+            MouseMoveEvent{ point: None, delta: None }
         } else {
             let msll = &*ms_ll_hook_struct;
             let pt = msll.pt;
-            MouseMoveEvent{ point: Some(pt.into()) }
+            MouseMoveEvent{ point: Some(pt.into()), delta: None }
         }
     }
 }
@@ -328,15 +809,10 @@ impl MouseMoveEvent {
 impl From<WPARAM> for MouseButtonPress {
     fn from(value: WPARAM) -> Self {
         use MouseButtonPress::*;
-        match value.try_into() {
-            Ok(uv) => {
-                match uv {
-                    WM_LBUTTONDOWN| WM_RBUTTONDOWN| WM_MBUTTONDOWN | WM_XBUTTONDOWN => Down,
-                    WM_RBUTTONUP | WM_LBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP => Up,
-                    _ => Other(value),
-                }
-            }
-            Err(_) => Other(value),
+        match Message::try_from(value) {
+            Ok(Message::LButtonDown | Message::RButtonDown | Message::MButtonDown | Message::XButtonDown) => Down,
+            Ok(Message::LButtonUp | Message::RButtonUp | Message::MButtonUp | Message::XButtonUp) => Up,
+            Ok(_) | Err(_) => Other(value),
         }
     }
 }
@@ -344,18 +820,12 @@ impl From<WPARAM> for MouseButtonPress {
 impl From<WPARAM> for MouseClick {
     fn from(value: WPARAM) -> Self {
         use MouseClick::*;
-        match value.try_into() {
-            Ok (uv) => {
-                match uv {
-                    WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN => SingleClick,
-                    WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP => SingleClick,
-                    WM_LBUTTONDBLCLK | WM_RBUTTONDBLCLK | WM_MBUTTONDBLCLK | WM_XBUTTONDBLCLK => DoubleClick,
-                    _ => Other(value as u32),
-
-                }
-            },
-            Err(_) => Other(value as u32),
-        }    
+        match Message::try_from(value) {
+            Ok(Message::LButtonDown | Message::RButtonDown | Message::MButtonDown | Message::XButtonDown) => SingleClick,
+            Ok(Message::LButtonUp | Message::RButtonUp | Message::MButtonUp | Message::XButtonUp) => SingleClick,
+            Ok(Message::LButtonDblClk | Message::RButtonDblClk | Message::MButtonDblClk | Message::XButtonDblClk) => DoubleClick,
+            Ok(_) | Err(_) => Other(value as u32),
+        }
     }
 }
 
@@ -364,31 +834,34 @@ impl MouseButton {
         let click = MouseClick::from(wm_mouse_param);
 
         use MouseButton::*;
-        match wm_mouse_param.try_into() {
-            Ok(param) => {
-                match param {
-                    WM_LBUTTONDOWN | WM_LBUTTONUP | WM_LBUTTONDBLCLK => Left(click),
-                    WM_RBUTTONDOWN | WM_RBUTTONUP | WM_RBUTTONDBLCLK => Right(click),
-                    WM_MBUTTONDOWN | WM_MBUTTONUP | WM_MBUTTONDBLCLK=> Middle(click),
-                    WM_XBUTTONDOWN | WM_XBUTTONUP | WM_XBUTTONDBLCLK => {
-                        if ms_ll_hook_struct.is_null() {
-                            UnkownX(click)
-                        } else {
-                            Self::into_extra(click, &*ms_ll_hook_struct)
-                        }
-                    },
-                    // Value out of expected set
-                    _ => Other(wm_mouse_param)
+        match Message::try_from(wm_mouse_param) {
+            Ok(Message::LButtonDown | Message::LButtonUp | Message::LButtonDblClk) => Left(click),
+            Ok(Message::RButtonDown | Message::RButtonUp | Message::RButtonDblClk) => Right(click),
+            Ok(Message::MButtonDown | Message::MButtonUp | Message::MButtonDblClk) => Middle(click),
+            Ok(Message::XButtonDown | Message::XButtonUp | Message::XButtonDblClk) => {
+                if ms_ll_hook_struct.is_null() {
+                    UnkownX(click)
+                } else {
+                    Self::into_extra(click, &*ms_ll_hook_struct)
                 }
             },
+            // Value out of expected set
+            Ok(_) => Other(wm_mouse_param),
             // Conversion error
             Err(_) => Other(wm_mouse_param),
         }
     }
 
     fn into_extra(click: MouseClick, ms_ll_hook_struct: &MSLLHOOKSTRUCT) -> Self {
+        Self::from_x_button_data(click, ms_ll_hook_struct.mouseData)
+    }
+
+    /// The high-order word of `mouseData` indicates which X button was pressed or released.
+    /// Windows reports single X button per message, so a value with both XBUTTON1 and XBUTTON2 bits set
+    /// (or none of them) is not split into two events, but reported as single [MouseButton::UnkownX].
+    fn from_x_button_data(click: MouseClick, mouse_data: DWORD) -> Self {
         use MouseButton::*;
-        match GET_XBUTTON_WPARAM(ms_ll_hook_struct.mouseData.try_into().expect("")) {
+        match HIWORD(mouse_data) {
             XBUTTON1 => X1(click),
             XBUTTON2 => X2(click),
             _ => UnkownX(click),
@@ -396,5 +869,143 @@ impl MouseButton {
     }
 }
 
+#[cfg(test)]
+mod keyboard_key_tests {
+    use super::*;
+
+    #[test]
+    fn virtual_key_code_round_trip() {
+        for vk in 0..=0x1FF as DWORD {
+            assert_eq!(KeyboardKey::from(vk).virtual_key_code(), Some(vk));
+        }
+        assert_eq!(KeyboardKey::InvalidKeyCodeReceived.virtual_key_code(), None);
+    }
+
+    #[test]
+    fn documented_keys_are_named() {
+        assert_eq!(KeyboardKey::from(VK_END as DWORD), KeyboardKey::End);
+        assert_eq!(KeyboardKey::from(VK_PAUSE as DWORD), KeyboardKey::Pause);
+        assert_eq!(KeyboardKey::from(VK_APPS as DWORD), KeyboardKey::ContextMenu);
+        assert_eq!(KeyboardKey::from(VK_OEM_MINUS as DWORD), KeyboardKey::Minus);
+        assert_eq!(KeyboardKey::from(VK_OEM_PLUS as DWORD), KeyboardKey::Equal);
+        assert_eq!(KeyboardKey::from(VK_OEM_102 as DWORD), KeyboardKey::Oem102);
+        assert_eq!(KeyboardKey::from(VK_MEDIA_PLAY_PAUSE as DWORD), KeyboardKey::MediaPlayPause);
+        assert_eq!(KeyboardKey::from(VK_VOLUME_UP as DWORD), KeyboardKey::VolumeUp);
+        assert_eq!(KeyboardKey::from(VK_BROWSER_BACK as DWORD), KeyboardKey::BrowserBack);
+    }
+}
 
+#[cfg(test)]
+mod mouse_button_tests {
+    use super::*;
 
+    #[test]
+    fn x_button_from_high_word() {
+        use MouseButton::*;
+        use MouseClick::*;
+        let high = |word: WORD| (word as DWORD) << 16;
+        assert_eq!(MouseButton::from_x_button_data(SingleClick, high(XBUTTON1)), X1(SingleClick));
+        assert_eq!(MouseButton::from_x_button_data(SingleClick, high(XBUTTON2)), X2(SingleClick));
+        assert_eq!(MouseButton::from_x_button_data(DoubleClick, high(XBUTTON2)), X2(DoubleClick));
+        // Low-order word is reserved and must not affect the decoding
+        assert_eq!(MouseButton::from_x_button_data(SingleClick, high(XBUTTON1) | XBUTTON2 as DWORD), X1(SingleClick));
+        assert_eq!(MouseButton::from_x_button_data(SingleClick, high(XBUTTON2) | 0xFFFF), X2(SingleClick));
+        // Both or none of the buttons
+        assert_eq!(MouseButton::from_x_button_data(SingleClick, high(XBUTTON1 | XBUTTON2)), UnkownX(SingleClick));
+        assert_eq!(MouseButton::from_x_button_data(SingleClick, 0), UnkownX(SingleClick));
+    }
+
+    #[test]
+    fn x_button_from_hook_struct() {
+        let mut msll: MSLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+        msll.mouseData = (XBUTTON2 as DWORD) << 16;
+        let button = unsafe { MouseButton::from(WM_XBUTTONDOWN as WPARAM, &msll) };
+        assert_eq!(button, MouseButton::X2(MouseClick::SingleClick));
+        let button = unsafe { MouseButton::from(WM_XBUTTONUP as WPARAM, std::ptr::null()) };
+        assert_eq!(button, MouseButton::UnkownX(MouseClick::SingleClick));
+    }
+}
+
+#[cfg(test)]
+mod physical_key_tests {
+    use super::*;
+
+    #[test]
+    fn physical_key_from_hook_struct() {
+        let mut kbd: KBDLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+        kbd.scanCode = 0x1D;
+        assert_eq!(PhysicalKey::from(&kbd), PhysicalKey { scan_code: 0x1D, extended: false });
+        kbd.flags = LLKHF_EXTENDED | LLKHF_INJECTED;
+        assert_eq!(PhysicalKey::from(&kbd), PhysicalKey { scan_code: 0x1D, extended: true });
+    }
+
+    #[test]
+    fn keyboard_event_from_null_struct() {
+        let event = unsafe { KeyboardEvent::new(WM_KEYDOWN as WPARAM, std::ptr::null()) };
+        assert_eq!(event.physical_key, None);
+        assert!(!event.has_scan_code());
+    }
+
+    #[test]
+    fn zero_scan_code_is_not_a_scan_code() {
+        let mut kbd: KBDLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+        kbd.vkCode = VK_MEDIA_PLAY_PAUSE as DWORD;
+        kbd.flags = LLKHF_EXTENDED;
+        let event = KeyboardEvent::from_raw(WM_KEYDOWN as usize, &kbd);
+        assert_eq!(event.physical_key, Some(PhysicalKey { scan_code: 0, extended: true }));
+        assert!(!event.has_scan_code());
+        kbd.scanCode = 0x22;
+        assert!(KeyboardEvent::from_raw(WM_KEYDOWN as usize, &kbd).has_scan_code());
+    }
+
+    #[test]
+    fn raw_parameters_are_restored() {
+        let mut kbd: KBDLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+        kbd.vkCode = VK_RCONTROL as DWORD;
+        kbd.scanCode = 0x1D;
+        kbd.flags = LLKHF_EXTENDED;
+        kbd.time = 1234;
+        kbd.dwExtraInfo = 42;
+        let raw = RawKeyboardData::new(WM_KEYUP as WPARAM, &kbd);
+        let (wparam, restored) = raw.as_raw();
+        assert_eq!(KeyboardEvent::from_raw(wparam, &restored), KeyboardEvent::from_raw(WM_KEYUP as usize, &kbd));
+        assert_eq!(RawKeyboardData::new(wparam, &restored), raw);
+
+        let mut msll: MSLLHOOKSTRUCT = unsafe { std::mem::zeroed() };
+        msll.pt = POINT { x: -5, y: 7 };
+        msll.mouseData = (XBUTTON1 as DWORD) << 16;
+        msll.time = 1234;
+        let raw = RawMouseData::new(WM_XBUTTONDOWN as WPARAM, &msll);
+        let (wparam, restored) = raw.as_raw();
+        assert_eq!(MouseEvent::from_raw(wparam, &restored), MouseEvent::from_raw(WM_XBUTTONDOWN as usize, &msll));
+        assert_eq!(RawMouseData::new(wparam, &restored), raw);
+    }
+}
+
+#[cfg(test)]
+mod keyboard_text_tests {
+    use super::*;
+
+    fn key(key: Option<KeyboardKey>, pressed: KeyPress) -> KeyboardEvent {
+        KeyboardEvent::for_test(pressed, key)
+    }
+
+    #[test]
+    fn language_is_the_low_word_of_the_layout() {
+        let mut event = key(Some(KeyboardKey::A), KeyPress::Down(IsSystemKeyPress::Normal));
+        assert_eq!(event.language_id(), None);
+        event.layout = Some(0xF002_0409);
+        assert_eq!(event.language_id(), Some(0x0409));
+        assert_eq!(event.language_id().map(|language| language & 0x3FF), Some(0x09));
+    }
+
+    #[test]
+    fn only_typing_presses_have_text() {
+        use IsSystemKeyPress::Normal;
+        let layout = unsafe { GetKeyboardLayout(0) } as usize;
+        assert_eq!(key(Some(KeyboardKey::A), KeyPress::Up(Normal)).to_text(layout), None);
+        assert_eq!(key(None, KeyPress::Down(Normal)).to_text(layout), None);
+        assert_eq!(key(Some(KeyboardKey::Escape), KeyPress::Down(Normal)).to_text(layout), None);
+        assert_eq!(key(Some(KeyboardKey::ArrowLeft), KeyPress::Down(Normal)).to_text(layout), None);
+    }
+}
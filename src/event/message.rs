@@ -0,0 +1,108 @@
+use winapi::shared::minwindef::WPARAM;
+use winapi::um::winuser::*;
+
+/// Window messages that are delivered to the low-level hook procedures as WPARAM.
+/// Every message supported by this crate is listed here, so that the parsing can be done with exhaustive matches.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub(crate) enum WindowsInputMessage {
+    KeyDown,
+    KeyUp,
+    SysKeyDown,
+    SysKeyUp,
+    LButtonDown,
+    LButtonUp,
+    LButtonDblClk,
+    RButtonDown,
+    RButtonUp,
+    RButtonDblClk,
+    MButtonDown,
+    MButtonUp,
+    MButtonDblClk,
+    XButtonDown,
+    XButtonUp,
+    XButtonDblClk,
+    MouseMove,
+    MouseWheel,
+    MouseHWheel,
+}
+
+impl TryFrom<u32> for WindowsInputMessage {
+    /// The unrecognized message code
+    type Error = u32;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        use WindowsInputMessage::*;
+        match code {
+            WM_KEYDOWN => Ok(KeyDown),
+            WM_KEYUP => Ok(KeyUp),
+            WM_SYSKEYDOWN => Ok(SysKeyDown),
+            WM_SYSKEYUP => Ok(SysKeyUp),
+            WM_LBUTTONDOWN => Ok(LButtonDown),
+            WM_LBUTTONUP => Ok(LButtonUp),
+            WM_LBUTTONDBLCLK => Ok(LButtonDblClk),
+            WM_RBUTTONDOWN => Ok(RButtonDown),
+            WM_RBUTTONUP => Ok(RButtonUp),
+            WM_RBUTTONDBLCLK => Ok(RButtonDblClk),
+            WM_MBUTTONDOWN => Ok(MButtonDown),
+            WM_MBUTTONUP => Ok(MButtonUp),
+            WM_MBUTTONDBLCLK => Ok(MButtonDblClk),
+            WM_XBUTTONDOWN => Ok(XButtonDown),
+            WM_XBUTTONUP => Ok(XButtonUp),
+            WM_XBUTTONDBLCLK => Ok(XButtonDblClk),
+            WM_MOUSEMOVE => Ok(MouseMove),
+            WM_MOUSEWHEEL => Ok(MouseWheel),
+            WM_MOUSEHWHEEL => Ok(MouseHWheel),
+            _ => Err(code),
+        }
+    }
+}
+
+impl TryFrom<WPARAM> for WindowsInputMessage {
+    /// The unrecognized WPARAM value, including values that do not fit into message code
+    type Error = WPARAM;
+
+    fn try_from(value: WPARAM) -> Result<Self, Self::Error> {
+        match u32::try_from(value) {
+            Ok(code) => WindowsInputMessage::try_from(code).map_err(|_| value),
+            Err(_) => Err(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod windows_input_message_tests {
+    use super::WindowsInputMessage;
+    use winapi::shared::minwindef::WPARAM;
+    use winapi::um::winuser::*;
+
+    use quickcheck::*;
+
+    const KNOWN: [u32; 19] = [
+        WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+        WM_LBUTTONDOWN, WM_LBUTTONUP, WM_LBUTTONDBLCLK,
+        WM_RBUTTONDOWN, WM_RBUTTONUP, WM_RBUTTONDBLCLK,
+        WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MBUTTONDBLCLK,
+        WM_XBUTTONDOWN, WM_XBUTTONUP, WM_XBUTTONDBLCLK,
+        WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOUSEHWHEEL,
+    ];
+
+    #[test]
+    fn known_messages_are_distinct() {
+        let parsed = KNOWN.iter()
+            .map(|code| WindowsInputMessage::try_from(*code).unwrap())
+            .collect::<std::collections::HashSet<WindowsInputMessage>>();
+        assert_eq!(KNOWN.len(), parsed.len());
+    }
+
+    quickcheck! {
+        fn unknown_message_is_returned_as_error(code: u32) -> TestResult {
+            if KNOWN.contains(&code) {
+                return TestResult::discard()
+            }
+
+            TestResult::from_bool(
+                WindowsInputMessage::try_from(code) == Err(code)
+                && WindowsInputMessage::try_from(code as WPARAM) == Err(code as WPARAM))
+        }
+    }
+}
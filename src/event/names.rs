@@ -0,0 +1,289 @@
+//! Plain text names of the keys and buttons, e.g. for the configuration files or command line arguments,
+//! and the human-readable display of the events built from them, e.g. for the logs.
+
+use crate::event::*;
+
+use std::str::FromStr;
+
+macro_rules! named_keyboard_keys {
+    ($($key:ident),* $(,)?) => {
+        impl KeyboardKey {
+            /// All the keys that have the name, see [KeyboardKey::as_str]
+            const NAMED: &'static [KeyboardKey] = &[$(KeyboardKey::$key),*];
+
+            /// Returns the name of the key, which is the same as the variant name (e.g. `"LeftShift"`).
+            /// The [KeyboardKey::Other] keys don't have the name, they are displayed as their virtual key code in hex (e.g. `"0xE8"`).
+            pub fn as_str(&self) -> Option<&'static str> {
+                match self {
+                    $(KeyboardKey::$key => Some(stringify!($key)),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+named_keyboard_keys!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Number0, Number1,
+    Number2, Number3, Number4, Number5, Number6, Number7, Number8, Number9, LeftAlt, RightAlt, LeftShift,
+    RightShift, LeftControl, RightControl, BackSpace, Tab, Enter, Escape, Space, PageUp, PageDown,
+    Home, End, ArrowLeft, ArrowUp, ArrowRight, ArrowDown, Print, PrintScreen, Insert, Delete, Pause, Clear,
+    LeftWindows, RightWindows, ContextMenu, Comma, Period, Slash, SemiColon, Apostrophe, LeftBrace,
+    BackwardSlash, RightBrace, Grave, Minus, Equal, Oem102, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19,
+    F20, F21, F22, F23, F24, NumLock, ScrollLock, CapsLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
+    Numpad5, Numpad6, Numpad7, Numpad8, Numpad9, Multiply, Add, Separator, Subtract, Decimal, Divide,
+    BrowserBack, BrowserForward, BrowserRefresh, BrowserStop, BrowserSearch, BrowserFavorites, BrowserHome,
+    VolumeMute, VolumeDown, VolumeUp, MediaNextTrack, MediaPreviousTrack, MediaStop, MediaPlayPause,
+    LaunchMail, LaunchMediaSelect, LaunchApp1, LaunchApp2, InvalidKeyCodeReceived,
+);
+
+/// Parses the hex number with the `0x` prefix, used for the values without the name.
+fn parse_code(s: &str) -> Option<u32> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// The virtual key codes are mapped only on Windows, elsewhere (see the "stub-non-windows" feature) the unnamed keys have no code.
+#[cfg(windows)]
+fn key_to_code(key: &KeyboardKey) -> Option<u32> {
+    key.virtual_key_code()
+}
+
+#[cfg(not(windows))]
+fn key_to_code(_key: &KeyboardKey) -> Option<u32> {
+    None
+}
+
+#[cfg(windows)]
+fn code_to_key(code: u32) -> Option<KeyboardKey> {
+    Some(KeyboardKey::from(code))
+}
+
+#[cfg(not(windows))]
+fn code_to_key(_code: u32) -> Option<KeyboardKey> {
+    None
+}
+
+impl std::fmt::Display for KeyboardKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.as_str(), key_to_code(self)) {
+            (Some(name), _) => f.write_str(name),
+            (None, Some(code)) => write!(f, "{:#04X}", code),
+            (None, None) => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl FromStr for KeyboardKey {
+    type Err = ParseNameError;
+
+    /// Parses the name of the key (case insensitive), or the virtual key code in hex (e.g. `"0x41"` is [KeyboardKey::A]).
+    fn from_str(s: &str) -> Result<KeyboardKey, ParseNameError> {
+        if let Some(key) = KeyboardKey::NAMED.iter().find(|key| key.as_str().is_some_and(|name| name.eq_ignore_ascii_case(s))) {
+            return Ok(*key)
+        }
+        parse_code(s).and_then(code_to_key).ok_or_else(|| ParseNameError::new(s))
+    }
+}
+
+impl MouseButton {
+    /// Returns the name of the button (e.g. `"Left"`), the [MouseClick] is not part of the name.
+    /// The [MouseButton::Other] buttons don't have the name, they are displayed as the raw code in hex.
+    pub fn as_str(&self) -> Option<&'static str> {
+        match self {
+            MouseButton::Left(_) => Some("Left"),
+            MouseButton::Right(_) => Some("Right"),
+            MouseButton::Middle(_) => Some("Middle"),
+            MouseButton::X1(_) => Some("X1"),
+            MouseButton::X2(_) => Some("X2"),
+            MouseButton::UnkownX(_) => Some("UnkownX"),
+            MouseButton::Other(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MouseButton::Other(code) => write!(f, "{:#X}", code),
+            _ => f.write_str(self.as_str().unwrap_or_default()),
+        }
+    }
+}
+
+impl FromStr for MouseButton {
+    type Err = ParseNameError;
+
+    /// Parses the name of the button (case insensitive) as a [MouseClick::SingleClick], or the raw code in hex as [MouseButton::Other].
+    fn from_str(s: &str) -> Result<MouseButton, ParseNameError> {
+        let click = MouseClick::SingleClick;
+        [MouseButton::Left(click), MouseButton::Right(click), MouseButton::Middle(click),
+         MouseButton::X1(click), MouseButton::X2(click), MouseButton::UnkownX(click)]
+            .into_iter()
+            .find(|button| button.as_str().is_some_and(|name| name.eq_ignore_ascii_case(s)))
+            .or_else(|| parse_code(s).map(|code| MouseButton::Other(code as usize)))
+            .ok_or_else(|| ParseNameError::new(s))
+    }
+}
+
+impl MouseButtonPress {
+    /// Returns `"Down"` or `"Up"`, the [MouseButtonPress::Other] is displayed as the raw code in hex.
+    pub fn as_str(&self) -> Option<&'static str> {
+        match self {
+            MouseButtonPress::Down => Some("Down"),
+            MouseButtonPress::Up => Some("Up"),
+            MouseButtonPress::Other(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MouseButtonPress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MouseButtonPress::Other(code) => write!(f, "{:#X}", code),
+            _ => f.write_str(self.as_str().unwrap_or_default()),
+        }
+    }
+}
+
+impl FromStr for MouseButtonPress {
+    type Err = ParseNameError;
+
+    fn from_str(s: &str) -> Result<MouseButtonPress, ParseNameError> {
+        if s.eq_ignore_ascii_case("Down") {
+            Ok(MouseButtonPress::Down)
+        } else if s.eq_ignore_ascii_case("Up") {
+            Ok(MouseButtonPress::Up)
+        } else {
+            parse_code(s).map(|code| MouseButtonPress::Other(code as usize)).ok_or_else(|| ParseNameError::new(s))
+        }
+    }
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// Displays e.g. `KeyDown(A)`, the keys without the name are displayed as their virtual key code (e.g. `KeyUp(0xE8)`).
+impl std::fmt::Display for KeyboardEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pressed {
+            KeyPress::Down(_) => f.write_str("KeyDown")?,
+            KeyPress::Up(_) => f.write_str("KeyUp")?,
+            KeyPress::Other(code) => write!(f, "Key{:#X}", code)?,
+        }
+        match self.key {
+            Some(key) => write!(f, "({})", key),
+            None => f.write_str("(?)"),
+        }
+    }
+}
+
+/// Displays e.g. `MouseDown(Left)`, `MouseMove @ (10, 20)` or `MouseWheel(Vertical, Forward)`.
+impl std::fmt::Display for MouseEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.event {
+            MouseEventType::Press(MousePressEvent{ pressed, button }) => write!(f, "Mouse{}({})", pressed, button),
+            MouseEventType::Move(MouseMoveEvent{ point: Some(point), .. }) => write!(f, "MouseMove @ {}", point),
+            MouseEventType::Move(MouseMoveEvent{ point: None, .. }) => f.write_str("MouseMove"),
+            MouseEventType::Wheel(MouseWheelEvent{ wheel, direction, .. }) => {
+                match wheel {
+                    MouseWheel::Horizontal => f.write_str("MouseWheel(Horizontal")?,
+                    MouseWheel::Vertical => f.write_str("MouseWheel(Vertical")?,
+                    MouseWheel::Unknown(code) => write!(f, "MouseWheel({:#X}", code)?,
+                }
+                match direction {
+                    Some(MouseWheelDirection::Forward) => f.write_str(", Forward)"),
+                    Some(MouseWheelDirection::Backward) => f.write_str(", Backward)"),
+                    Some(MouseWheelDirection::Unknown(code)) => write!(f, ", {:#X})", code),
+                    None => f.write_str(")"),
+                }
+            },
+            MouseEventType::Other(code) => write!(f, "Mouse({:#X})", code),
+        }
+    }
+}
+
+/// Displays the keyboard and mouse events as [KeyboardEvent] and [MouseEvent] do, the others e.g. as `Reset` or `Text("hello")`.
+/// The details like the timestamps are left out, they are available with the `Debug` formatting.
+impl std::fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputEvent::Keyboard(ke) => ke.fmt(f),
+            InputEvent::Mouse(me) => me.fmt(f),
+            InputEvent::Other(code) => write!(f, "Other({:#X})", code),
+            InputEvent::Reset => f.write_str("Reset"),
+            InputEvent::Text(text) => write!(f, "Text({:?})", text),
+            InputEvent::MouseDoubleClick(button) => write!(f, "MouseDoubleClick({})", button),
+        }
+    }
+}
+
+#[cfg(test)]
+mod names_tests {
+    use super::*;
+
+    #[test]
+    fn every_keyboard_key_round_trips() {
+        for code in 0..=0xFF {
+            let key = KeyboardKey::from(code);
+            assert_eq!(key.to_string().parse::<KeyboardKey>(), Ok(key), "{}", key);
+        }
+        assert_eq!(KeyboardKey::InvalidKeyCodeReceived.to_string().parse(), Ok(KeyboardKey::InvalidKeyCodeReceived));
+        for key in KeyboardKey::NAMED {
+            assert_eq!(key.to_string().parse::<KeyboardKey>(), Ok(*key));
+        }
+    }
+
+    #[test]
+    fn keyboard_key_parsing_is_case_insensitive() {
+        assert_eq!("leftshift".parse(), Ok(KeyboardKey::LeftShift));
+        assert_eq!("ESCAPE".parse(), Ok(KeyboardKey::Escape));
+        assert_eq!("0x41".parse(), Ok(KeyboardKey::A));
+        assert_eq!("0xE8".parse(), Ok(KeyboardKey::Other(0xE8)));
+        assert_eq!("Foo".parse::<KeyboardKey>(), Err(ParseNameError::new("Foo")));
+        assert_eq!("0xZZ".parse::<KeyboardKey>(), Err(ParseNameError::new("0xZZ")));
+    }
+
+    #[test]
+    fn every_mouse_button_round_trips() {
+        let click = MouseClick::SingleClick;
+        for button in [MouseButton::Left(click), MouseButton::Right(click), MouseButton::Middle(click),
+                       MouseButton::X1(click), MouseButton::X2(click), MouseButton::UnkownX(click), MouseButton::Other(0x20E)] {
+            assert_eq!(button.to_string().parse::<MouseButton>(), Ok(button));
+            assert_eq!(button.to_string().to_lowercase().parse::<MouseButton>(), Ok(button));
+        }
+        assert_eq!("Thumb".parse::<MouseButton>(), Err(ParseNameError::new("Thumb")));
+    }
+
+    #[test]
+    fn every_mouse_button_press_round_trips() {
+        for press in [MouseButtonPress::Down, MouseButtonPress::Up, MouseButtonPress::Other(0x201)] {
+            assert_eq!(press.to_string().parse::<MouseButtonPress>(), Ok(press));
+        }
+        assert_eq!("down".parse(), Ok(MouseButtonPress::Down));
+        assert_eq!("Pressed".parse::<MouseButtonPress>(), Err(ParseNameError::new("Pressed")));
+    }
+
+    #[test]
+    fn events_are_displayed_readably() {
+        let key = |pressed, key| InputEvent::Keyboard(KeyboardEvent { time: Some(1), ..KeyboardEvent::for_test(pressed, key) });
+        let mouse = |event| InputEvent::Mouse(MouseEvent { time: Some(1), ..MouseEvent::for_test(event) });
+
+        assert_eq!(key(KeyPress::Down(IsSystemKeyPress::Normal), Some(KeyboardKey::A)).to_string(), "KeyDown(A)");
+        assert_eq!(key(KeyPress::Up(IsSystemKeyPress::System), Some(KeyboardKey::Other(0xE8))).to_string(), "KeyUp(0xE8)");
+        assert_eq!(key(KeyPress::Other(0x999), None).to_string(), "Key0x999(?)");
+
+        let left = MouseButton::Left(MouseClick::SingleClick);
+        assert_eq!(mouse(MouseEventType::Press(MousePressEvent{ pressed: MouseButtonPress::Down, button: left })).to_string(), "MouseDown(Left)");
+        assert_eq!(mouse(MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x: 10, y: -20 }), delta: None })).to_string(), "MouseMove @ (10, -20)");
+        let wheel = MouseWheelEvent{ wheel: MouseWheel::Vertical, direction: Some(MouseWheelDirection::Backward), delta: Some(-120) };
+        assert_eq!(mouse(MouseEventType::Wheel(wheel)).to_string(), "MouseWheel(Vertical, Backward)");
+
+        assert_eq!(InputEvent::Reset.to_string(), "Reset");
+        assert_eq!(InputEvent::Text(String::from("hi")).to_string(), "Text(\"hi\")");
+        assert_eq!(InputEvent::MouseDoubleClick(MouseButton::Left(MouseClick::DoubleClick)).to_string(), "MouseDoubleClick(Left)");
+    }
+}
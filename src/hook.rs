@@ -1,7 +1,37 @@
 pub(super) mod inner;
+mod text;
+mod coalesce;
+mod clicks;
+mod foreground;
+mod watchdog;
+#[cfg(feature = "serde")]
+mod config;
 
 use crate::hook::inner::InnerHook;
-use crate::event::InputEvent;
+use crate::hook::inner::channels::Subscription;
+use crate::hook::text::TextAccumulator;
+use crate::hook::coalesce::MoveCoalescer;
+use crate::hook::clicks::DoubleClickDetector;
+use crate::hook::foreground::{ForegroundFilter, ForegroundTarget};
+use crate::hook::watchdog::Watchdog;
+use crate::event::{InputEvent, InputSource, LockStates, MouseButtons, MouseEvent, MouseEventType, MoveDelta, Point};
+use crate::layout::LayoutSource;
+use crate::sink::{EventSink, SinkThread};
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// Transforms the events on the consumer thread, see [HookBuilder::map_events].
+type Mapper<T> = Box<dyn FnMut(InputEvent) -> Option<T> + Send + 'static>;
+
+/// Decides in the hook procedures which events are queued (see [HookBuilder::filter]) or blocked (see [HookBuilder::with_blocking]).
+pub(crate) type EventFilter = Box<dyn Fn(&InputEvent) -> bool + Send + Sync + 'static>;
 
 /// Handle to a low-level Windows hook for keyboard and/or mouse events, regardless of application focus.
 /// For more details see the [HookBuilder]. When the handle goes out of scope, then the low-level hook is removed.
@@ -18,9 +48,60 @@ use crate::event::InputEvent;
 /// // underlying low-level hook(s) are unhooked from Windows
 /// # }
 /// ```
-pub struct Hook {}
+/// 
+/// By default the hook delivers [InputEvent]s, with [HookBuilder::map_events] it delivers the user's type `T` instead.
+/// 
+/// # Threads
+/// 
+/// The [Hook] is `Send` and `Sync` (for any `T`): it can be built on one thread and moved to another, or shared
+/// (e.g. in an `Arc`) by several threads receiving from it. The hooks themselves run in the background threads of this crate,
+/// which don't depend on the thread that built the hook, so it may even be dropped on another thread.
+/// The received events go to one of the receiving threads each, see [Hook::subscribe] to give every thread all of them.
+/// The [EventReceiver] and the [CancellationHandle] are `Send` and `Sync` too, the [HookBuilder] is only `Send`.
+/// 
+/// ```rust
+/// # fn main() {
+/// let hook = willhook::willhook().unwrap();
+/// std::thread::spawn(move || {
+///     while let Ok(event) = hook.recv() {
+///         println!("{}", event);
+///     }
+/// });
+/// # }
+/// ```
+pub struct Hook<T = InputEvent> {
+    layout: LayoutSource,
+    mapper: Mutex<Mapper<T>>,
+    text: Option<Mutex<TextAccumulator>>,
+    /// See [CancellationHandle]
+    generation: u64,
+    /// Events generated on the consumer thread (like [InputEvent::Text]), delivered before the next event from the hooks
+    pending: Mutex<VecDeque<InputEvent>>,
+    /// See [HookBuilder::with_sink]
+    sink: Mutex<Option<SinkThread>>,
+    /// See [HookBuilder::with_auto_reinstall]
+    watchdog: Mutex<Option<Watchdog>>,
+    /// See [Hook::shutdown]
+    shut_down: AtomicBool,
+    /// See [HookBuilder::with_foreground_info]
+    foreground_info: bool,
+    /// See [HookBuilder::with_layout_info]
+    layout_info: bool,
+    /// See [HookBuilder::coalesce_moves]
+    moves: Option<Mutex<MoveCoalescer>>,
+    /// The moves dropped by the [HookBuilder::coalesce_moves], see [Hook::dropped_count]
+    coalesced: AtomicU64,
+    /// See [HookBuilder::with_double_click_detection]
+    clicks: Option<Mutex<DoubleClickDetector>>,
+    /// See [HookBuilder::only_foreground_process]
+    foreground: Option<Mutex<ForegroundFilter>>,
+    /// The position of the last move received, see [HookBuilder::with_move_deltas]
+    last_point: Option<Mutex<Option<Point>>>,
+    /// See [HookBuilder::with_context]
+    context: Option<Box<dyn Any + Send + Sync>>,
+}
 
-impl Hook {
+impl<T> Hook<T> {
     /// Tries to receive an event from the low-level hook(s) running in the background thread(s).
     /// If there are no events at the moment, will return Err(std::sync::mpsc::Empty):
     /// 
@@ -59,140 +140,2222 @@ impl Hook {
     /// }
     /// # }
     /// ```
-    pub fn try_recv(&self) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
-        InnerHook::try_recv()
+    /// 
+    /// If the hook stopped working (see [Hook::error]) and all the events delivered before are consumed,
+    /// then it returns Err(std::sync::mpsc::TryRecvError::Disconnected).
+    pub fn try_recv(&self) -> Result<T, std::sync::mpsc::TryRecvError> {
+        loop {
+            if let Some(mapped) = self.map(self.try_recv_event()?) {
+                return Ok(mapped)
+            }
+        }
+    }
+
+    /// Same as [Hook::try_recv], but also returns the [Instant] when the event was received by the calling thread.
+    /// Compared with the OS timestamp of the event (`time` field) it allows measuring the delivery latency.
+    pub fn try_recv_at(&self) -> Result<(T, Instant), std::sync::mpsc::TryRecvError> {
+        self.try_recv().map(|event| (event, Instant::now()))
+    }
+
+    fn try_recv_event(&self) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
+        use std::sync::mpsc::TryRecvError;
+        if self.is_shut_down() {
+            return Err(TryRecvError::Disconnected)
+        }
+        if let Some(event) = self.pop_pending() {
+            return Ok(event)
+        }
+        loop {
+            match InnerHook::try_recv() {
+                Ok(event) => if let Some(event) = self.process(event) {
+                    return Ok(event)
+                },
+                Err(TryRecvError::Empty) => return match self.take_due_move() {
+                    Some(held) => Ok(held),
+                    None if self.error().is_some() => Err(TryRecvError::Disconnected),
+                    None => Err(TryRecvError::Empty),
+                },
+                Err(error) => return Err(error),
+            }
+        }
     }
 
     /// This is blocking alternative to Hook::try_recv(). 
     /// It will block the current thread until there is an event from the low-level hook(s) running in the background thread(s), and then will return the event.
-    pub fn recv(&self) -> Result<InputEvent, std::sync::mpsc::RecvError> {
-        InnerHook::recv()
+    /// 
+    /// If the hook stopped working (see [Hook::error]), then it returns the remaining events and then the error, instead of blocking forever.
+    /// Note: the call that is already blocked when the hook fails is not woken up, use [Hook::recv_timeout] to check [Hook::error] periodically.
+    pub fn recv(&self) -> Result<T, std::sync::mpsc::RecvError> {
+        loop {
+            if let Some(mapped) = self.map(self.recv_event()?) {
+                return Ok(mapped)
+            }
+        }
     }
 
-}
+    /// Returns the iterator that blocks waiting for each next event, like [std::sync::mpsc::Receiver::iter].
+    /// It ends when [Hook::recv] fails: when the hook stopped working (see [Hook::error]) or was cancelled (see [Hook::cancellation_handle]).
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// let handle = hook.cancellation_handle();
+    /// std::thread::spawn(move || handle.cancel());
+    /// for event in hook.iter() {
+    ///     println!("{:?}", event);
+    /// }
+    /// # }
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { hook: self }
+    }
 
-impl Drop for Hook {
-    fn drop(&mut self) {
-        use crate::hook::inner::{GLOBAL_HOOK, GLOBAL_CHANNEL};
-        let mut global_hook = GLOBAL_HOOK.lock().unwrap();
-        global_hook.drop_hooks();
-        GLOBAL_CHANNEL.drain();
+    /// Returns the iterator over the events that are already queued, like [std::sync::mpsc::Receiver::try_iter].
+    /// It never blocks, and it ends as soon as [Hook::try_recv] fails.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { hook: self }
     }
-}
 
-/// The only way to build a hook is to use HookBuilder.
-/// It is possible to choose what types of hooks are active.
-/// Currently only "mouse" and "keyboard" hooks are supported (due to Windows API restrictions).
-/// 
-/// # Build hook for both mouse and keyboard:
-/// ```rust
-/// use willhook::hook::HookBuilder;
-/// fn main() {
-///     let hook = HookBuilder::new()
-///                 .with_mouse()
-///                 .with_keyboard()
-///                 .build();
-///     assert!(hook.is_some());
-/// }
-/// ```
-/// 
-/// # Limitations
-/// 
-/// At least one hook type has to be specified, otherwise build will fail:
-/// ```rust
-/// # fn main() {
-/// # use willhook::hook::HookBuilder;
-/// let bad_hook = HookBuilder::new().build();
-/// assert!(bad_hook.is_none());
-/// # }
-/// ```
-/// There can be only one hook at the moment, even if we try to create different type:
-/// 
-/// ```rust
-/// # fn main() {
-/// # use willhook::hook::HookBuilder;
-/// let hook = HookBuilder::new()
-///             .with_mouse()
-///             .build();
-/// 
-/// assert!(hook.is_some());
-/// // Building second hook while the first one is still in scope will fail.
-/// // Even if that second hook is keyboard hook:
-/// let another_hook = HookBuilder::new().with_keyboard().build();
-/// assert!(another_hook.is_none());
-/// # }
-/// ```
-/// 
-/// Only after the old hook is dropped, the new one can be created:
-/// 
-/// ```rust
-/// # fn main() {
-/// # use willhook::hook::HookBuilder;
-/// let hook = HookBuilder::new()
-///             .with_mouse()
-///             .build();
-/// 
-/// assert!(hook.is_some());
-/// // It could go out of scope as well, but let's drop it explicitly:
-/// drop(hook);
-/// // Since there is no "active" hook at the moment, now we can create another:
-/// let another_hook = HookBuilder::new().with_keyboard().build();
-/// assert!(another_hook.is_some());
-/// # }
-/// ```
-pub struct HookBuilder {
-    mouse: bool,
-    keyboard: bool,
-}
+    /// Receives all the events queued at the moment, in order, without blocking. Returns an empty `Vec` if there are none.
+    /// Handy for the loops running at fixed cadence, like the UI repainting every frame.
+    /// Unlike [Hook::flush], the events are returned rather than discarded.
+    pub fn try_recv_all(&self) -> Vec<T> {
+        self.try_iter().collect()
+    }
 
-impl HookBuilder {
-    pub fn new() -> Self {
-        Self {
-            mouse: false,
-            keyboard: false,
+    /// Receives up to `max` events in one call: waits up to the `timeout` for the first event (like [Hook::recv_timeout]),
+    /// then takes the events already queued, without waiting any longer. So it returns as soon as there is anything to process,
+    /// and never later than the `timeout`, which bounds the latency of the loop processing the events in batches.
+    /// 
+    /// - Empty: no event came within the `timeout`, or none will come (see [Hook::recv_timeout]), or the `max` is 0 (returned right away).
+    /// - Partial: fewer than `max` events were queued when the first one came, the rest are not waited for.
+    /// - Full: `max` events, the ones queued beyond them are left for the next call.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use std::time::Duration;
+    /// let hook = willhook::willhook().unwrap();
+    /// let batch = hook.try_recv_batch(64, Duration::from_millis(10));
+    /// assert!(batch.len() <= 64);
+    /// # }
+    /// ```
+    #[doc(alias = "try_recv_timeout_many")]
+    pub fn try_recv_batch(&self, max: usize, timeout: Duration) -> Vec<T> {
+        if max == 0 {
+            return Vec::new()
+        }
+        match self.recv_timeout(timeout) {
+            Ok(first) => std::iter::once(first).chain(self.try_iter().take(max - 1)).collect(),
+            Err(_) => Vec::new(),
         }
     }
 
-    /// Instructs builder to spawn a new mouse hook in background thread on HookBuilder::build().
-    pub fn with_mouse(mut self) -> Self {
-        self.mouse = true;
-        self
+    /// Returns the next event without consuming it, or `None` if there is none at the moment. Never blocks.
+    /// The next [Hook::peek] returns the same event, and so does the next receive, e.g. [Hook::try_recv].
+    /// Handy for the UI showing the next pending event, while the events are consumed elsewhere.
+    /// 
+    /// The event is the one the [HookBuilder::map_events] mapper gets, so it may still be dropped by the mapper once received.
+    /// The peeked event is taken off the hook's queue and kept aside until it is received, or discarded by the [Hook::flush].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// if let Some(next) = hook.peek() {
+    ///     println!("Next up: {}", next);
+    ///     assert_eq!(hook.try_recv(), Ok(next));
+    /// }
+    /// # }
+    /// ```
+    pub fn peek(&self) -> Option<InputEvent> {
+        if self.is_shut_down() {
+            return None
+        }
+        if let Some(event) = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).front() {
+            return Some(event.clone())
+        }
+        let event = self.try_recv_event().ok()?;
+        // In front of whatever the processing of the event generated meanwhile
+        self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_front(event.clone());
+        Some(event)
     }
 
-    /// Instructs builder to spawn a new keyboard hook in background thread on HookBuilder::build().
-    pub fn with_keyboard(mut self) -> Self {
-        self.keyboard = true;
-        self
+    /// Returns true if an event can be received right away, without consuming it. Never blocks.
+    /// The event is set aside as by the [Hook::peek], which also means that it may still be dropped by the [HookBuilder::map_events] mapper.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::willhook().unwrap();
+    /// if hook.has_pending() {
+    ///     assert!(hook.peek().is_some());
+    /// }
+    /// # }
+    /// ```
+    pub fn has_pending(&self) -> bool {
+        self.peek().is_some()
     }
 
-    /// Builds the requested hooks and returns common handle for them.
-    /// If any hooks are active, then the build fails.
-    pub fn build(self) -> Option<Hook> {
-        // No hook was requested - do not default, just return None
-        if !self.keyboard && !self.mouse {
-            return None
+    fn recv_event(&self) -> Result<InputEvent, std::sync::mpsc::RecvError> {
+        if self.is_shut_down() {
+            return Err(std::sync::mpsc::RecvError)
         }
-        
+        if self.error().is_some() {
+            return self.try_recv_event().map_err(|_| std::sync::mpsc::RecvError);
+        }
+        if let Some(event) = self.pop_pending() {
+            return Ok(event)
+        }
+        loop {
+            // The held move has to be delivered when it is due, even if nothing else comes
+            let event = match self.move_due_in() {
+                Some(due_in) => match InnerHook::recv_timeout(due_in) {
+                    Ok(event) => event,
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => match self.take_due_move() {
+                        Some(held) => return Ok(held),
+                        None => continue,
+                    },
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return Err(std::sync::mpsc::RecvError),
+                },
+                None => InnerHook::recv()?,
+            };
+            if let Some(event) = self.process(event) {
+                return Ok(event)
+            }
+        }
+    }
+
+    /// Returns the handle that cancels the blocking receives of this hook, e.g. from the thread that shuts down the program.
+    /// 
+    /// After [CancellationHandle::cancel], all the calls blocked in [Hook::recv], [Hook::recv_timeout] or [Hook::recv_deadline]
+    /// return right away with the "disconnected" error, and [Hook::wait_for] with [WaitError::Cancelled].
+    /// The cancellation is permanent for this hook: the future blocking calls return the error immediately too.
+    /// The non-blocking [Hook::try_recv] is not affected, so the remaining events can still be consumed.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// let handle = hook.cancellation_handle();
+    /// std::thread::spawn(move || handle.cancel());
+    /// // Without the cancellation this would block until a key is pressed
+    /// assert!(hook.recv().is_err());
+    /// assert!(hook.cancellation_handle().is_cancelled());
+    /// # }
+    /// ```
+    pub fn cancellation_handle(&self) -> CancellationHandle {
+        CancellationHandle { generation: self.generation }
+    }
+
+    /// Returns an independent receiver getting a copy of every event captured from now on, e.g. to feed a logger next to the UI.
+    /// The hook and each receiver consume their own events, dropping a receiver does not affect the others.
+    /// 
+    /// The receivers get the events as captured by the hook procedures (after the [HookBuilder::filter]),
+    /// but not the processing done when receiving from the hook: no [HookBuilder::map_events] mapping,
+    /// no [InputEvent::Text] or [InputEvent::MouseDoubleClick], no [HookBuilder::coalesce_moves], no [HookBuilder::only_foreground_process],
+    /// no `foreground_pid` and no `layout`.
+    /// With the [HookBuilder::capacity] set, each receiver is bounded by it (dropping its oldest events when full), so a forgotten receiver cannot grow forever.
+    /// By default the receivers are unbounded, like the hook itself: drop the receivers that are not read anymore.
+    /// Once the hook is dropped (or [Hook::shutdown]), the receivers deliver the events they already have and then report the disconnection.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::willhook().unwrap();
+    /// let logger = hook.subscribe();
+    /// std::thread::spawn(move || {
+    ///     while let Ok(event) = logger.recv() {
+    ///         println!("{:?}", event);
+    ///     }
+    /// });
+    /// // The hook itself still gets every event
+    /// assert!(hook.try_recv().is_err());
+    /// # }
+    /// ```
+    pub fn subscribe(&self) -> EventReceiver {
+        use crate::hook::inner::GLOBAL_CHANNEL;
+        use crate::hook::inner::channels::HookChannels;
+        let subscription = if self.is_shut_down() {
+            HookChannels::disconnected_subscription()
+        } else {
+            GLOBAL_CHANNEL.subscribe(None)
+        };
+        EventReceiver { subscription }
+    }
+
+    /// Discards all the events queued at the moment and returns how many were discarded.
+    /// Useful to start fresh, e.g. after a long operation on the consumer thread, when the queued events are stale.
+    /// The events are discarded before the [HookBuilder::map_events] mapping, so the count includes the events that the mapping would drop.
+    pub fn flush(&self) -> usize {
+        let pending = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).drain(..).count();
+        pending + InnerHook::flush()
+    }
+
+    /// Returns how many events were discarded since the hook was built, because the queue was full (see [HookBuilder::capacity]).
+    /// The hook procedures can't wait for the room, so the [OverflowPolicy] decides what is discarded, and this counts it.
+    /// The unbounded queue never overflows, and the replacements made by the [OverflowPolicy::KeepLatestOnly] are not counted.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::hook::HookBuilder::new().with_mouse().capacity(64).build().unwrap();
+    /// if hook.overflowed_events() > 0 {
+    ///     println!("Consuming too slowly, some events were lost");
+    /// }
+    /// # }
+    /// ```
+    pub fn overflowed_events(&self) -> u64 {
+        InnerHook::overflowed()
+    }
+
+    /// Returns how many events were lost since the hook was built, for whatever reason: discarded on the full queue
+    /// (see [Hook::overflowed_events]), replaced with the newer ones by the [OverflowPolicy::KeepLatestOnly],
+    /// or dropped by the [HookBuilder::coalesce_moves]. Zero means the received events are the complete record,
+    /// e.g. the recorded macro is not lossy. The count only grows for the life of the hook.
+    /// The events dropped on purpose, by the [HookBuilder::filter], the [HookBuilder::only_foreground_process] or the [HookBuilder::map_events], are not counted.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().capacity(1024).build().unwrap();
+    /// // ... record the keys ...
+    /// if hook.dropped_count() > 0 {
+    ///     println!("The recording is incomplete");
+    /// }
+    /// # }
+    /// ```
+    pub fn dropped_count(&self) -> u64 {
+        InnerHook::overflowed() + InnerHook::replaced() + self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// Reports the native `HHOOK` values of the installed hooks and the ids of the threads running them,
+    /// e.g. to compare with what the other hooking software or the debugger reports. They are informational only,
+    /// nothing can be done with them through this crate.
+    /// 
+    /// A hook that stopped working (see [Hook::error]) is reported as `None`, as are all of them after the [Hook::shutdown].
+    /// The [Backend::RawInput] installs no hooks, so it reports `None` as well.
+    /// With the [HookBuilder::with_auto_reinstall] the handles change with every reinstallation.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::willhook().unwrap();
+    /// let handles = hook.handles();
+    /// if let Some(keyboard) = handles.keyboard {
+    ///     println!("Keyboard hook {:#x} runs in the thread {}", keyboard.handle, keyboard.thread_id);
+    /// }
+    /// # }
+    /// ```
+    pub fn handles(&self) -> HookHandles {
         use crate::hook::inner::GLOBAL_HOOK;
-        // This lock ensures that during the time of building, no other builder is active.
-        // If different threads attempt to construct the hook handle, 
-        // they may race for underlying static globals holding the actuall inner hooks.
-        // To prevent this, simple mutex is used so that only one instance of HookBuilder::build() is running at the moment.
-        // In "normal" use case one would create a hook at the start of the program, or at least in one thread.
-        // But the goal of this crate was to be failproof, so here comes the lock:
-        let mut global_hooks = GLOBAL_HOOK.lock().unwrap();
+        if self.is_shut_down() {
+            return HookHandles::default()
+        }
+        GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).handles()
+    }
 
-        if global_hooks.is_any_hook_present() {
-            return None
+    /// Returns true while all the underlying low-level hooks are installed in Windows.
+    /// It becomes false when the hook stops working, see [Hook::error].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::willhook().unwrap();
+    /// assert!(hook.is_installed());
+    /// # }
+    /// ```
+    pub fn is_installed(&self) -> bool {
+        use crate::hook::inner::GLOBAL_HOOK;
+        if self.is_shut_down() {
+            return false
+        }
+        let global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        global_hooks.is_installed()
+    }
+
+    /// Confirms that the hook is actually called by the OS, by injecting a probe event and waiting up to the `timeout` for it.
+    /// Returns true if every installed hook (keyboard and/or mouse) observed its probe.
+    /// Useful as a self-test on startup, as e.g. a security software or a policy may prevent the low-level hooks from being called,
+    /// although they were installed successfully.
+    /// 
+    /// The probes are injected with `SendInput`: a press and release of an unassigned virtual key (`0x97`) for the keyboard hook
+    /// and a move by zero pixels for the mouse hook. The hook swallows the probes, they are not delivered to the [Hook],
+    /// nor passed to the applications. But if the hook is not live, the probes reach the focused window (which should ignore them).
+    /// The hooks installed later than this one (e.g. by other processes) see the probes, as they are called before this one.
+    /// If the [HookBuilder::physical_only] is used, the probes are still recognized.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() {
+    /// # use std::time::Duration;
+    /// let hook = willhook::willhook().unwrap();
+    /// assert!(hook.verify(Duration::from_secs(1)));
+    /// # }
+    /// ```
+    pub fn verify(&self, timeout: Duration) -> bool {
+        !self.is_shut_down() && probe_hooks(timeout).unwrap_or(false)
+    }
+
+    /// Removes the hook of the given device, while the hook of the other one keeps delivering the events, e.g. to stop capturing the mouse
+    /// once it is not needed anymore. With the [HookBuilder::thread_per_hook] the background thread of the removed hook is joined,
+    /// otherwise the shared thread keeps running the other hook. The events of the device that are already queued are still delivered.
+    /// 
+    /// Returns false if the hook of the device is not installed, if it is the only hook (see [Hook::shutdown] instead),
+    /// or with the [Backend::RawInput], which can't drop a device. The removed hook is not reinstalled by the [HookBuilder::with_auto_reinstall],
+    /// and it can't be added back: a new [Hook] has to be built. With the [HookBuilder::thread_per_hook], the desktop switches
+    /// for the [HookBuilder::emit_reset_on_focus_loss] are watched by the keyboard hook thread, so removing the keyboard hook stops the resets.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::InputSource;
+    /// let hook = willhook::willhook().unwrap();
+    /// assert!(hook.remove_hook(InputSource::Mouse));
+    /// assert!(!willhook::installed_hooks().mouse);
+    /// assert!(!hook.remove_hook(InputSource::Keyboard));
+    /// # }
+    /// ```
+    pub fn remove_hook(&self, source: InputSource) -> bool {
+        use crate::hook::inner::{GLOBAL_HOOK, HookKind};
+        if self.is_shut_down() {
+            return false
+        }
+        let kind = match source {
+            InputSource::Keyboard => HookKind::Keyboard,
+            InputSource::Mouse => HookKind::Mouse,
+        };
+        GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove_hook(kind)
+    }
+
+    /// Waits up to the `timeout` until the message loops of all the background threads are running, i.e. each retrieved a message.
+    /// Returns false on timeout, or if the hook is not installed.
+    /// The [HookBuilder::build] returns right after the hooks are installed, just before their threads enter the message loop:
+    /// the input that comes in between is delayed until the loop runs, but it is not lost. This closes even that gap,
+    /// e.g. for the tests that inject the input right after building the hook. Unlike the [Hook::verify] it injects no input,
+    /// it only posts the `WM_NULL` to the threads, so it can't tell if the OS actually calls the hooks.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use std::time::Duration;
+    /// let hook = willhook::willhook().unwrap();
+    /// assert!(hook.wait_ready(Duration::from_secs(1)));
+    /// # }
+    /// ```
+    pub fn wait_ready(&self, timeout: Duration) -> bool {
+        use crate::hook::inner::GLOBAL_HOOK;
+        if self.is_shut_down() {
+            return false
         }
+        // Collected up front, so that the other calls on the hook don't wait for the whole timeout
+        let loops = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).message_loops();
+        let deadline = Instant::now().checked_add(timeout);
+        !loops.is_empty() && loops.iter().all(|(thread_id, ready)| ready.wait(*thread_id, deadline))
+    }
 
-        if self.keyboard {
-            global_hooks.setup_keyboard_hook();
+    /// Returns the reason why the hook stopped delivering events, or `None` if the hook works fine.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// assert_eq!(hook.error(), None);
+    /// # }
+    /// ```
+    pub fn error(&self) -> Option<HookError> {
+        use crate::hook::inner::GLOBAL_HOOK;
+        if self.is_shut_down() {
+            return Some(HookError::ShutDown)
         }
-        if self.mouse {
-            global_hooks.setup_mouse_hook();
+        let global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        global_hooks.loop_error()
+    }
+
+    /// Removes the hooks and joins their background threads right away, instead of waiting for the [Hook] to be dropped.
+    /// The blocked receives are woken up, and all the receives from now on return the "disconnected" error,
+    /// the events still queued are discarded. The [Hook::error] becomes [HookError::ShutDown].
+    /// 
+    /// It can be called any number of times, from any thread - e.g. from the `SetConsoleCtrlHandler` routine,
+    /// which Windows runs in its own thread. It waits for the background threads to finish, so it must not be called
+    /// from the [HookBuilder::with_sink] or the predicates run by the hook procedures.
+    /// Once it returns, a new [Hook] can be built, even while this one is still around.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// hook.shutdown();
+    /// hook.shutdown();
+    /// assert!(hook.recv().is_err());
+    /// assert!(willhook::mouse_hook().is_some());
+    /// # }
+    /// ```
+    pub fn shutdown(&self) {
+        use crate::hook::inner::{GLOBAL_HOOK, GLOBAL_CHANNEL, GLOBAL_SETTINGS};
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            return
+        }
+        GLOBAL_CHANNEL.cancel(self.generation);
+        // The watchdog needs the global lock to reinstall the hooks, stop it before taking the lock
+        self.watchdog.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+        let mut global_hook = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        global_hook.drop_hooks();
+        // No event is captured anymore, so the sink gets the whole rest of the queue before it stops.
+        // It is stopped under the global lock, so that it does not compete with the next hook for the events.
+        self.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+        GLOBAL_CHANNEL.release();
+        // The hook procedures are not called anymore, so the user's predicates can be released as well
+        GLOBAL_SETTINGS.set_filter(None);
+        GLOBAL_SETTINGS.set_blocking(None);
+        // Only now the next hook may be built: released while the teardown was in progress, it would have its queue
+        // and settings cleared by this hook
+        drop(global_hook);
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::SeqCst)
+    }
+
+    /// Returns the mouse buttons held down at the moment, e.g. to detect chords like left+right.
+    /// The state is tracked by the mouse hook (so it is always empty without it), and it is independent from the events consumed so far.
+    /// 
+    /// The state starts empty when the hook is built, so buttons held down at that time are not reported until pressed again.
+    /// Releases that happen on the other desktop (UAC prompt, lock screen) are not seen by the hook,
+    /// so the state is cleared on every desktop switch (and the elevated window focus), but only if [HookBuilder::emit_reset_on_focus_loss] is enabled.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::MouseButtons;
+    /// let hook = willhook::mouse_hook().unwrap();
+    /// if hook.pressed_buttons().contains(MouseButtons::LEFT | MouseButtons::RIGHT) {
+    ///     println!("Left and right buttons are held together");
+    /// }
+    /// # }
+    /// ```
+    pub fn pressed_buttons(&self) -> MouseButtons {
+        use crate::hook::inner::GLOBAL_PRESSED_BUTTONS;
+        GLOBAL_PRESSED_BUTTONS.get()
+    }
+
+    /// Returns the keys held down at the moment, ordered by their virtual-key code, e.g. to show them in an overlay.
+    /// The state is tracked by the keyboard hook (so it is always empty without it), and like the [Hook::pressed_buttons]
+    /// it is independent from the events consumed so far, it starts empty when the hook is built and it is cleared on the resets.
+    /// The keys reported without the virtual-key code (like [crate::event::KeyboardKey::InvalidKeyCodeReceived]) are not tracked.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::KeyboardKey;
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// if hook.pressed_keys().contains(&KeyboardKey::W) {
+    ///     println!("Moving forward");
+    /// }
+    /// # }
+    /// ```
+    pub fn pressed_keys(&self) -> Vec<crate::event::KeyboardKey> {
+        use crate::hook::inner::GLOBAL_PRESSED_KEYS;
+        GLOBAL_PRESSED_KEYS.get()
+    }
+
+    /// Forgets the keys and buttons tracked as held down, as if they were all released.
+    /// The hook tracks them from the events it sees, for the [Hook::pressed_buttons], the [Hook::pressed_keys], the [crate::event::Modifiers]
+    /// and the [crate::event::KeyboardEvent::is_repeat]. A release it didn't see (e.g. swallowed by the lock screen)
+    /// leaves the key stuck down, until it is pressed and released again. The desktop switches clear the state on their own,
+    /// but only with [HookBuilder::emit_reset_on_focus_loss]: this is for the other occasions the application knows about,
+    /// e.g. the `WM_WTSSESSION_CHANGE` of its window. No [InputEvent::Reset] is sent.
+    /// The lock states need no reset, they are queried from the OS, see [Hook::lock_states].
+    pub fn reset_state(&self) {
+        use crate::hook::inner::{GLOBAL_PRESSED_BUTTONS, GLOBAL_PRESSED_MODIFIERS, GLOBAL_PRESSED_KEYS};
+        GLOBAL_PRESSED_BUTTONS.reset();
+        GLOBAL_PRESSED_MODIFIERS.reset();
+        GLOBAL_PRESSED_KEYS.reset();
+    }
+
+    /// Returns how long the hook procedures take to process the events, `None` unless requested with [HookBuilder::with_latency_stats].
+    /// 
+    /// Windows removes the low-level hook without any notice when its procedure takes longer than the `LowLevelHooksTimeout`
+    /// (in the `HKEY_CURRENT_USER\Control Panel\Desktop` registry key), see [Hook::is_installed]. Most of the time is spent
+    /// in the [HookBuilder::filter] and [HookBuilder::with_blocking] predicates, which run in the hook procedure:
+    /// the `max` approaching the timeout means they are too slow. The other processing happens on the receiving thread, it is not counted.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_latency_stats().build().unwrap();
+    /// let stats = hook.latency_stats().unwrap();
+    /// if stats.max > std::time::Duration::from_millis(100) {
+    ///     println!("The filter is too slow, the hook may be removed");
+    /// }
+    /// # }
+    /// ```
+    pub fn latency_stats(&self) -> Option<LatencyStats> {
+        use crate::hook::inner::GLOBAL_LATENCY;
+        GLOBAL_LATENCY.is_enabled().then(|| GLOBAL_LATENCY.stats())
+    }
+
+    /// Returns how many times the hook procedures got each message they did not recognize, as the pairs of the raw `WPARAM`
+    /// and the count, in the order the messages were first seen. `None` unless requested with [HookBuilder::count_unknown_messages].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().count_unknown_messages().build().unwrap();
+    /// for (message, count) in hook.unknown_message_count().unwrap() {
+    ///     println!("The message {:#x} was received {} times", message, count);
+    /// }
+    /// # }
+    /// ```
+    pub fn unknown_message_count(&self) -> Option<Vec<(usize, u64)>> {
+        use crate::hook::inner::GLOBAL_UNKNOWN_MESSAGES;
+        GLOBAL_UNKNOWN_MESSAGES.is_enabled().then(|| GLOBAL_UNKNOWN_MESSAGES.counts())
+    }
+
+    /// Returns the context given to [HookBuilder::with_context], `None` if there is none or it is not a `C`.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_context(String::from("editor")).build().unwrap();
+    /// assert_eq!(hook.context::<String>().map(String::as_str), Some("editor"));
+    /// assert_eq!(hook.context::<u32>(), None);
+    /// # }
+    /// ```
+    pub fn context<C: Any>(&self) -> Option<&C> {
+        self.context.as_ref()?.downcast_ref()
+    }
+
+    /// Returns the toggle state of the Caps Lock, Num Lock and Scroll Lock at the moment, e.g. to show them on the screen.
+    /// 
+    /// The state is queried with `GetKeyState` on the calling thread, not tracked by the hook, so it works with any hook.
+    /// `GetAsyncKeyState` can't be used instead, as it reports only whether the key is held, not the toggle.
+    /// A thread that processes window messages sees the state as of the last input message it received,
+    /// so it may lag behind the events of the hook.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// if hook.lock_states().caps_lock {
+    ///     println!("Caps Lock is on");
+    /// }
+    /// # }
+    /// ```
+    pub fn lock_states(&self) -> LockStates {
+        LockStates::current()
+    }
+
+    /// Enables or disables delivery of all keyboard events at runtime, without rebuilding the hook.
+    /// Disabled events are dropped in the hook procedure, they are still passed to the other hooks and applications.
+    /// All event kinds are enabled when the hook is built, except the mouse moves without [HookBuilder::with_mouse_moves].
+    pub fn set_keyboard_enabled(&self, enabled: bool) {
+        use crate::hook::inner::GLOBAL_SETTINGS;
+        GLOBAL_SETTINGS.set_keyboard_enabled(enabled);
+    }
+
+    /// Enables or disables delivery of mouse move events at runtime, see [Hook::set_keyboard_enabled].
+    pub fn set_mouse_moves_enabled(&self, enabled: bool) {
+        use crate::hook::inner::GLOBAL_SETTINGS;
+        GLOBAL_SETTINGS.set_mouse_moves_enabled(enabled);
+    }
+
+    /// Enables or disables delivery of mouse button events at runtime, see [Hook::set_keyboard_enabled].
+    /// The [Hook::pressed_buttons] state is still tracked while the buttons are disabled.
+    pub fn set_mouse_buttons_enabled(&self, enabled: bool) {
+        use crate::hook::inner::GLOBAL_SETTINGS;
+        GLOBAL_SETTINGS.set_mouse_buttons_enabled(enabled);
+    }
+
+    /// Enables or disables delivery of mouse wheel events at runtime, see [Hook::set_keyboard_enabled].
+    pub fn set_mouse_wheels_enabled(&self, enabled: bool) {
+        use crate::hook::inner::GLOBAL_SETTINGS;
+        GLOBAL_SETTINGS.set_mouse_wheels_enabled(enabled);
+    }
+
+    /// Stops the delivery of all the events, while the hooks stay installed: the hook procedures just pass the events on.
+    /// Unlike dropping the [Hook] and building a new one, pausing and resuming is instant, it only flips an atomic flag
+    /// read by the hook procedures. The events already queued are still received.
+    /// 
+    /// Nothing is tracked while paused, so the [Hook::pressed_keys], [Hook::pressed_buttons] and the modifiers are forgotten
+    /// by the [Hook::resume], and the keys held through the pause are not reported as the repeats (nor with the `held_for`).
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// hook.pause();
+    /// assert!(hook.is_paused());
+    /// // ...the user types the password...
+    /// hook.resume();
+    /// # }
+    /// ```
+    pub fn pause(&self) {
+        use crate::hook::inner::GLOBAL_SETTINGS;
+        GLOBAL_SETTINGS.set_paused(true);
+    }
+
+    /// Resumes the delivery of the events stopped by [Hook::pause]. Does nothing if the hook is not paused.
+    pub fn resume(&self) {
+        use crate::hook::inner::{GLOBAL_SETTINGS, GLOBAL_PRESSED_BUTTONS, GLOBAL_PRESSED_MODIFIERS, GLOBAL_PRESSED_KEYS};
+        if GLOBAL_SETTINGS.is_paused() {
+            GLOBAL_PRESSED_BUTTONS.reset();
+            GLOBAL_PRESSED_MODIFIERS.reset();
+            GLOBAL_PRESSED_KEYS.reset();
+            GLOBAL_SETTINGS.set_paused(false);
+        }
+    }
+
+    /// Returns true between the [Hook::pause] and the [Hook::resume].
+    pub fn is_paused(&self) -> bool {
+        use crate::hook::inner::GLOBAL_SETTINGS;
+        GLOBAL_SETTINGS.is_paused()
+    }
+
+    /// Returns the raw `HKL` value of the keyboard layout selected with [HookBuilder::layout], as it is at this moment.
+    /// See the [crate::layout] module for details.
+    pub fn keyboard_layout(&self) -> usize {
+        self.layout.resolve()
+    }
+
+    /// Blocks the current thread until there is an event from the low-level hook(s), but no longer than the `timeout`.
+    /// It works similarly to [std::sync::mpsc::Receiver::recv_timeout].
+    /// If the hook stopped working (see [Hook::error]), then the timeout is reported as Err(RecvTimeoutError::Disconnected).
+    /// 
+    /// The keyboard and the mouse events share one queue in the order the OS delivered them,
+    /// so it returns whichever comes first, there is no need to select over the devices.
+    /// Err(RecvTimeoutError::Timeout) means that no event came in time, Err(RecvTimeoutError::Disconnected) that none will come:
+    /// besides the failure, also after the [Hook::cancellation_handle] cancelled the hook or it was [Hook::shutdown].
+    /// 
+    /// This makes it easy to wake up periodically for the housekeeping:
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use std::sync::mpsc::RecvTimeoutError;
+    /// # use std::time::Duration;
+    /// let hook = willhook::willhook().unwrap();
+    /// # hook.cancellation_handle().cancel();
+    /// loop {
+    ///     match hook.recv_timeout(Duration::from_millis(100)) {
+    ///         Ok(event) => println!("{:?}", event),
+    ///         Err(RecvTimeoutError::Timeout) => println!("Nothing happened, doing the housekeeping"),
+    ///         Err(RecvTimeoutError::Disconnected) => break,
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, std::sync::mpsc::RecvTimeoutError> {
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            // Practically infinite timeout
+            None => return self.recv().map_err(|_| std::sync::mpsc::RecvTimeoutError::Disconnected),
+        };
+        loop {
+            let event = self.recv_event_timeout(deadline.saturating_duration_since(Instant::now()))?;
+            if let Some(mapped) = self.map(event) {
+                return Ok(mapped)
+            }
+        }
+    }
+
+    fn recv_event_timeout(&self, timeout: Duration) -> Result<InputEvent, std::sync::mpsc::RecvTimeoutError> {
+        use std::sync::mpsc::RecvTimeoutError;
+        if self.is_shut_down() {
+            return Err(RecvTimeoutError::Disconnected)
+        }
+        if let Some(event) = self.pop_pending() {
+            return Ok(event)
+        }
+        let started = Instant::now();
+        loop {
+            let remaining = timeout.saturating_sub(started.elapsed());
+            let received = match self.move_due_in() {
+                // See Hook::recv_event
+                Some(due_in) if due_in < remaining => match InnerHook::recv_timeout(due_in) {
+                    Err(RecvTimeoutError::Timeout) => match self.take_due_move() {
+                        Some(held) => return Ok(held),
+                        None => continue,
+                    },
+                    result => result,
+                },
+                _ => InnerHook::recv_timeout(remaining),
+            };
+            match received {
+                Ok(event) => if let Some(event) = self.process(event) {
+                    return Ok(event)
+                },
+                Err(RecvTimeoutError::Timeout) if self.error().is_some() => return Err(RecvTimeoutError::Disconnected),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Blocks the current thread until there is an event from the low-level hook(s), but no longer than until the `deadline`.
+    /// It is more convenient than [Hook::recv_timeout] for the loops running at fixed cadence, as the deadline does not drift.
+    /// If the `deadline` already passed, then it behaves like [Hook::try_recv].
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T, std::sync::mpsc::RecvTimeoutError> {
+        self.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Blocks the current thread until an event satisfying the predicate is received, and then returns that event.
+    /// All events received before the matching one are consumed and dropped.
+    /// If the `timeout` is `None`, then it waits indefinitely.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// # use willhook::hook::WaitError;
+    /// # use std::time::Duration;
+    /// let hook = willhook::keyboard_hook().unwrap();
+    /// // Wait until the user presses the Escape key
+    /// let escape = hook.wait_for(|event| match event {
+    ///         InputEvent::Keyboard(ke) => ke.key == Some(KeyboardKey::Escape),
+    ///         _ => false,
+    ///     }, Some(Duration::from_millis(10)));
+    /// // This example definitely can't receive any user input, so it will time out:
+    /// assert_eq!(escape, Err(WaitError::Timeout));
+    /// # }
+    /// ```
+    pub fn wait_for<F>(&self, predicate: F, timeout: Option<Duration>) -> Result<T, WaitError>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let event = match deadline {
+                None => self.recv().map_err(|_| self.wait_error(WaitError::Disconnected))?,
+                Some(deadline) => {
+                    self.recv_deadline(deadline).map_err(|e| match e {
+                        std::sync::mpsc::RecvTimeoutError::Timeout => WaitError::Timeout,
+                        std::sync::mpsc::RecvTimeoutError::Disconnected => self.wait_error(WaitError::Disconnected),
+                    })?
+                },
+            };
+            if predicate(&event) {
+                return Ok(event)
+            }
+        }
+    }
+
+    /// Sends the text typed so far as [InputEvent::Text], without waiting for the end of the word.
+    /// Returns false if text accumulation is not enabled (see [HookBuilder::accumulate_text]) or nothing was typed.
+    pub fn flush_text(&self) -> bool {
+        let text = match &self.text {
+            Some(text) => text.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take(),
+            None => None,
+        };
+        match text {
+            Some(text) => {
+                self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_back(InputEvent::Text(text));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Replaces the `error` with [WaitError::Cancelled] if that's the actual reason.
+    fn wait_error(&self, error: WaitError) -> WaitError {
+        if self.cancellation_handle().is_cancelled() {
+            WaitError::Cancelled
+        } else {
+            error
+        }
+    }
+
+    fn pop_pending(&self) -> Option<InputEvent> {
+        self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop_front()
+    }
+
+    /// Runs the event received from the hooks through the processing requested from the builder, returns the event to deliver now.
+    fn process(&self, event: InputEvent) -> Option<InputEvent> {
+        if let Some(foreground) = &self.foreground {
+            // Before the accumulation, so that the keys typed into the other processes don't make up the text
+            if !foreground.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).admits(&event) {
+                return None
+            }
+        }
+        self.coalesce(self.accumulate(event))
+    }
+
+    /// Passes the event through the [HookBuilder::coalesce_moves] (if enabled), returns the event to deliver now.
+    fn coalesce(&self, event: InputEvent) -> Option<InputEvent> {
+        let moves = match &self.moves {
+            Some(moves) => moves,
+            None => return Some(event),
+        };
+        let mut moves = moves.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if moves.replaces(&event) {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+        let (now, next) = moves.feed(event, Instant::now());
+        drop(moves);
+        if let Some(next) = next {
+            // Before anything the event itself queued, like the typed word
+            self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_front(next);
+        }
+        now
+    }
+
+    fn move_due_in(&self) -> Option<Duration> {
+        let moves = self.moves.as_ref()?;
+        moves.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).due_in(Instant::now())
+    }
+
+    fn take_due_move(&self) -> Option<InputEvent> {
+        let moves = self.moves.as_ref()?;
+        moves.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take_due(Instant::now())
+    }
+
+    /// Fills in the details requested from the builder, and feeds the text accumulator and the double-click detector (if enabled)
+    /// with the event, queuing what they produce.
+    fn accumulate(&self, mut event: InputEvent) -> InputEvent {
+        if let (Some(last_point), InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(me), .. })) = (&self.last_point, &mut event) {
+            if let Some(point) = me.point {
+                let mut last_point = last_point.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                me.delta = last_point.map(|last| MoveDelta{ dx: point.x - last.x, dy: point.y - last.y });
+                *last_point = Some(point);
+            }
+        }
+        if self.foreground_info {
+            match &mut event {
+                InputEvent::Keyboard(ke) => ke.foreground_pid = foreground_pid(),
+                InputEvent::Mouse(me) => me.foreground_pid = foreground_pid(),
+                _ => (),
+            }
+        }
+        if let (true, InputEvent::Keyboard(ke)) = (self.layout_info, &mut event) {
+            ke.layout = Some(LayoutSource::ForegroundThread.resolve());
+        }
+        if let Some(text) = &self.text {
+            let word = text.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).feed(&event, self.layout.resolve());
+            if let Some(word) = word {
+                self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_back(InputEvent::Text(word));
+            }
+        }
+        if let Some(clicks) = &self.clicks {
+            let max_interval = crate::system::double_click_time().as_millis() as u32;
+            let double_click = clicks.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).feed(&event, max_interval, crate::system::double_click_size());
+            if let Some(double_click) = double_click {
+                self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push_back(double_click);
+            }
+        }
+        event
+    }
+
+    fn map(&self, event: InputEvent) -> Option<T> {
+        let mut mapper = self.mapper.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        (*mapper)(event)
+    }
+}
+
+/// The reason why [Hook::wait_for] did not return the matching event.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub enum WaitError {
+    /// No matching event was received before the timeout elapsed
+    Timeout,
+    /// The underlying channel is disconnected, no more events will be received
+    Disconnected,
+    /// The wait was cancelled with the [CancellationHandle]
+    Cancelled,
+}
+
+impl std::fmt::Display for WaitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaitError::Timeout => write!(f, "timed out waiting for the matching event"),
+            WaitError::Disconnected => write!(f, "hook channel is disconnected"),
+            WaitError::Cancelled => write!(f, "waiting for the event was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
+
+/// Cancels the blocking receives of the [Hook], see [Hook::cancellation_handle].
+/// The handle can be cloned and sent to other threads. It does not keep the hook alive,
+/// and it does not affect the hooks built after this one.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct CancellationHandle {
+    generation: u64,
+}
+
+impl CancellationHandle {
+    /// Makes all the blocking receives of the hook return right away, the ones in progress and the future ones.
+    pub fn cancel(&self) {
+        use crate::hook::inner::GLOBAL_CHANNEL;
+        GLOBAL_CHANNEL.cancel(self.generation);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        use crate::hook::inner::GLOBAL_CHANNEL;
+        GLOBAL_CHANNEL.is_cancelled(self.generation)
+    }
+}
+
+/// Blocking iterator over the events of the [Hook], see [Hook::iter].
+pub struct Iter<'a, T> {
+    hook: &'a Hook<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.hook.recv().ok()
+    }
+}
+
+/// Non-blocking iterator over the queued events of the [Hook], see [Hook::try_iter].
+pub struct TryIter<'a, T> {
+    hook: &'a Hook<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.hook.try_recv().ok()
+    }
+}
+
+// Fails to compile if a change breaks the thread safety documented on the [Hook]
+#[allow(dead_code)]
+fn assert_thread_safety<T>() {
+    fn send_and_sync<S: Send + Sync>() {}
+    fn send<S: Send>() {}
+    send_and_sync::<Hook<T>>();
+    send_and_sync::<EventReceiver>();
+    send_and_sync::<CancellationHandle>();
+    send::<HookBuilder<T>>();
+}
+
+/// An independent receiver of the events captured by the [Hook], see [Hook::subscribe].
+/// 
+/// The clone continues from the same point: it gets the events this receiver has not received yet, and every event after them.
+/// Like the `mpsc` receiver, it can only receive: it can be handed to another module or thread without letting it remove the hooks,
+/// which stay installed until the [Hook] is dropped. Dropping the receiver does not affect the hook.
+pub struct EventReceiver {
+    subscription: Arc<Subscription>,
+}
+
+impl EventReceiver {
+    /// Like [Hook::try_recv], returns Err(std::sync::mpsc::TryRecvError::Disconnected) once the hook is gone and all the events are consumed.
+    pub fn try_recv(&self) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
+        self.subscription.try_recv()
+    }
+
+    /// Like [Hook::recv], blocks until the next event, or returns the error once the hook is gone and all the events are consumed.
+    pub fn recv(&self) -> Result<InputEvent, std::sync::mpsc::RecvError> {
+        self.subscription.recv()
+    }
+
+    /// Like [Hook::recv_timeout], waits at most the `timeout` for the next event.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<InputEvent, std::sync::mpsc::RecvTimeoutError> {
+        self.subscription.recv_timeout(timeout)
+    }
+
+    /// Returns an iterator over the queued events, which does not block.
+    pub fn try_iter(&self) -> impl Iterator<Item = InputEvent> + '_ {
+        std::iter::from_fn(move || self.try_recv().ok())
+    }
+
+    /// Returns an iterator that blocks for the next event, and ends once the hook is gone and all the events are consumed.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::willhook().unwrap();
+    /// let receiver = hook.subscribe();
+    /// drop(hook);
+    /// assert_eq!(receiver.iter().count(), 0);
+    /// # }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = InputEvent> + '_ {
+        std::iter::from_fn(move || self.recv().ok())
+    }
+}
+
+impl Clone for EventReceiver {
+    fn clone(&self) -> EventReceiver {
+        use crate::hook::inner::GLOBAL_CHANNEL;
+        EventReceiver { subscription: GLOBAL_CHANNEL.subscribe(Some(&self.subscription)) }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Hook<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// The low-level hooks of this crate that are live in the process at the moment, see [crate::installed_hooks].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+pub struct InstalledHooks {
+    pub keyboard: bool,
+    pub mouse: bool,
+}
+
+impl InstalledHooks {
+    /// True if any hook is live, so [HookBuilder::build] would fail.
+    pub fn any(&self) -> bool {
+        self.keyboard || self.mouse
+    }
+}
+
+/// The time the hook procedures spent on the events since the hook was built, see [Hook::latency_stats].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+pub struct LatencyStats {
+    /// The number of the events measured
+    pub events: u64,
+    /// The average time spent on one event, zero if there was none
+    pub average: Duration,
+    /// The longest time spent on one event
+    pub max: Duration,
+}
+
+/// The native `HHOOK` handles of the hooks and the threads running them, for diagnostics, see [Hook::handles].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+pub struct HookHandles {
+    /// The keyboard hook, `None` if it is not installed
+    pub keyboard: Option<NativeHook>,
+    /// The mouse hook, `None` if it is not installed
+    pub mouse: Option<NativeHook>,
+}
+
+/// A single hook installed with `SetWindowsHookExW`, see [HookHandles].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub struct NativeHook {
+    /// The `HHOOK` value
+    pub handle: usize,
+    /// The id of the thread running the message loop of the hook, also seen in the debuggers as the "willhook-*" thread
+    pub thread_id: u32,
+}
+
+/// Reports which low-level hooks of this crate are currently installed, without installing anything.
+pub(crate) fn installed_hooks() -> InstalledHooks {
+    use crate::hook::inner::{GLOBAL_HOOK, HookKind};
+    let global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    InstalledHooks {
+        keyboard: global_hooks.is_kind_installed(HookKind::Keyboard),
+        mouse: global_hooks.is_kind_installed(HookKind::Mouse),
+    }
+}
+
+/// The reason why the [Hook] could not be built (see [HookBuilder::try_build]), or stopped delivering events (see [Hook::error]).
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
+pub enum HookError {
+    /// Neither the keyboard nor the mouse hook was requested from the [HookBuilder].
+    NoHookRequested,
+    /// The hooks of this crate are already installed in the process, only one [Hook] can be live at a time.
+    /// It does not matter which kinds the live hook has: e.g. the keyboard hook can't be built next to the mouse hook either.
+    /// The live hook keeps working, the failed build does not change any of its settings.
+    AlreadyInstalled,
+    /// Windows refused to install the hook (or to register the raw input devices), the value is the `GetLastError` code.
+    WinApiError(u32),
+    /// The background thread for the hook could not be spawned, or it died or got stuck before installing the hook.
+    ThreadSpawnFailed,
+    /// `GetMessageW` failed in the hook thread, the value is the `GetLastError` code.
+    /// The low-level hook is removed, no more events will be received.
+    MessageLoopFailed(u32),
+    /// The hook was removed with [Hook::shutdown].
+    ShutDown,
+    /// The process runs on a non-interactive window station (e.g. as a service, or as a scheduled task that runs whether
+    /// the user is logged on or not), where Windows would install the hooks, but never deliver any input to them.
+    /// The hook has to be built by a process started in the user's session, see [HookBuilder::on_input_desktop].
+    NonInteractiveSession,
+}
+
+impl std::fmt::Display for HookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookError::NoHookRequested => write!(f, "no hook was requested"),
+            HookError::AlreadyInstalled => write!(f, "hooks are already installed"),
+            HookError::WinApiError(code) => write!(f, "hook could not be installed (error code {})", code),
+            HookError::ThreadSpawnFailed => write!(f, "hook thread could not be spawned"),
+            HookError::MessageLoopFailed(code) => write!(f, "hook thread message loop failed (error code {})", code),
+            HookError::ShutDown => write!(f, "hook was shut down"),
+            HookError::NonInteractiveSession => write!(f, "process does not run on an interactive window station"),
+        }
+    }
+}
+
+impl std::error::Error for HookError {}
+
+/// Returns the id of the process owning the foreground window, if there is any.
+fn foreground_pid() -> Option<u32> {
+    use winapi::um::winuser::{GetForegroundWindow, GetWindowThreadProcessId};
+    let window = unsafe { GetForegroundWindow() };
+    if window.is_null() {
+        return None
+    }
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(window, &mut pid) };
+    (pid != 0).then_some(pid)
+}
+
+/// Injects the probes of [Hook::verify] and waits up to the `timeout` for the installed hooks to observe them.
+/// Returns `None` if the probes could not be injected, so the hooks could not be checked at all.
+fn probe_hooks(timeout: Duration) -> Option<bool> {
+    use crate::hook::inner::{GLOBAL_HOOK, GLOBAL_PROBE, HookKind};
+    use crate::hook::inner::probe::Probe;
+    use winapi::um::winuser::{SendInput, INPUT};
+
+    let kinds: Vec<HookKind> = {
+        let global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        [HookKind::Keyboard, HookKind::Mouse].into_iter().filter(|&kind| global_hooks.is_kind_installed(kind)).collect()
+    };
+    if kinds.is_empty() {
+        return Some(false)
+    }
+
+    GLOBAL_PROBE.reset();
+    for &kind in &kinds {
+        let mut inputs = Probe::inputs(kind);
+        let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_mut_ptr(), std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            // Blocked by UIPI or another thread, the probe can't tell anything
+            return None
+        }
+    }
+
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        if kinds.iter().all(|&kind| GLOBAL_PROBE.is_seen(kind)) {
+            return Some(true)
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(false)
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+impl<T> Drop for Hook<T> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Which edges of the key or button press are delivered, see [HookBuilder::key_edges].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Edges {
+    /// Only the presses (key down, button down)
+    DownOnly,
+    /// Only the releases (key up, button up)
+    UpOnly,
+    /// Both presses and releases
+    #[default]
+    Both,
+}
+
+/// What happens to the new event when the queue of the [Hook] is full, see [HookBuilder::capacity].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OverflowPolicy {
+    /// The oldest queued event is discarded to make room for the new one, so the queue holds the most recent history.
+    #[default]
+    DropOldest,
+    /// The new event is discarded, so the queue holds the events from the moment it was full.
+    DropNewest,
+    /// Only the latest event of each kind is kept, regardless of the capacity: one keyboard event, one mouse move,
+    /// one mouse button, one mouse wheel and so on. The new event replaces the queued one of the same kind,
+    /// and is queued after the others. Useful for trackers that care only about the current state, like the cursor position.
+    KeepLatestOnly,
+}
+
+/// How the input is captured, see [HookBuilder::backend].
+/// Both backends deliver the same [InputEvent]s through the same [Hook], so switching between them does not change the consumer.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Backend {
+    /// The low-level hooks (`WH_KEYBOARD_LL` and `WH_MOUSE_LL`), called by the OS before the input reaches any application.
+    #[default]
+    LowLevelHook,
+    /// The raw input (`WM_INPUT`) received by a hidden message-only window, registered with `RegisterRawInputDevices`.
+    /// 
+    /// It does not slow down the input of the whole system, and it still works where the low-level hooks are blocked
+    /// (e.g. by some anti-cheat or security software). On the other hand:
+    /// - The raw input is just a copy of the input: the events are delivered to the applications regardless of this crate,
+    ///   so the [Hook::verify] probes can't be swallowed and reach the focused window.
+    /// - The mouse moves carry the cursor position at the moment the raw input is processed (the device reports only the motion),
+    ///   and the moves of the cursor without the device motion (e.g. `SetCursorPos`) are not reported at all.
+    /// - The double-clicks are not recognized, all the presses are [crate::event::MouseClick::SingleClick].
+    /// - The injected input is recognized by the missing device, the OS does not flag it as it does for the low-level hooks.
+    /// - Only one window in the process can receive the raw input of the device class, so this backend
+    ///   takes it away from any other raw input registration of the application (e.g. of a game engine).
+    RawInput,
+}
+
+/// The scheduling priority of the background threads running the hooks, see [HookBuilder::with_thread_priority].
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ThreadPriority {
+    /// `THREAD_PRIORITY_NORMAL`, the priority of any other thread
+    #[default]
+    Normal,
+    /// `THREAD_PRIORITY_ABOVE_NORMAL`
+    AboveNormal,
+    /// `THREAD_PRIORITY_HIGHEST`
+    Highest,
+    /// `THREAD_PRIORITY_TIME_CRITICAL`, scheduled before anything else of the normal priority class
+    TimeCritical,
+}
+
+/// The only way to build a hook is to use HookBuilder.
+/// It is possible to choose what types of hooks are active.
+/// Currently only "mouse" and "keyboard" hooks are supported (due to Windows API restrictions).
+/// 
+/// # Build hook for both mouse and keyboard:
+/// ```rust
+/// use willhook::hook::HookBuilder;
+/// fn main() {
+///     let hook = HookBuilder::new()
+///                 .with_mouse()
+///                 .with_keyboard()
+///                 .build();
+///     assert!(hook.is_some());
+/// }
+/// ```
+/// 
+/// # Limitations
+/// 
+/// At least one hook type has to be specified, otherwise build will fail:
+/// ```rust
+/// # fn main() {
+/// # use willhook::hook::HookBuilder;
+/// let bad_hook = HookBuilder::new().build();
+/// assert!(bad_hook.is_none());
+/// # }
+/// ```
+/// There can be only one hook at the moment, even if we try to create different type:
+/// 
+/// ```rust
+/// # fn main() {
+/// # use willhook::hook::HookBuilder;
+/// let hook = HookBuilder::new()
+///             .with_mouse()
+///             .build();
+/// 
+/// assert!(hook.is_some());
+/// // Building second hook while the first one is still in scope will fail.
+/// // Even if that second hook is keyboard hook:
+/// let another_hook = HookBuilder::new().with_keyboard().build();
+/// assert!(another_hook.is_none());
+/// # }
+/// ```
+/// 
+/// Only after the old hook is dropped, the new one can be created:
+/// 
+/// ```rust
+/// # fn main() {
+/// # use willhook::hook::HookBuilder;
+/// let hook = HookBuilder::new()
+///             .with_mouse()
+///             .build();
+/// 
+/// assert!(hook.is_some());
+/// // It could go out of scope as well, but let's drop it explicitly:
+/// drop(hook);
+/// // Since there is no "active" hook at the moment, now we can create another:
+/// let another_hook = HookBuilder::new().with_keyboard().build();
+/// assert!(another_hook.is_some());
+/// # }
+/// ```
+/// 
+/// # Persisting the configuration
+/// 
+/// With the "serde" feature the builder can be serialized, e.g. to a config file, and deserialized later.
+/// Only the plain options are persisted: the closures ([HookBuilder::map_events], [HookBuilder::with_sink]) are excluded,
+/// so the deserialized builder delivers the [InputEvent]s, without any sink. The missing options take their default values.
+/// The thread of [HookBuilder::scoped_to_thread] is excluded as well, as its id is valid only in the running process, and so are the [HookBuilder::with_context],
+/// the [HookBuilder::install_on_current_thread] and the process of [HookBuilder::only_foreground_process] (the [HookBuilder::only_foreground_exe] is kept).
+/// 
+/// ```rust
+/// # fn main() {
+/// # #[cfg(feature = "serde")] {
+/// # use willhook::hook::{HookBuilder, Edges};
+/// let config = serde_json::to_string(&HookBuilder::new().with_keyboard().key_edges(Edges::DownOnly)).unwrap();
+/// let builder: HookBuilder = serde_json::from_str(&config).unwrap();
+/// assert!(builder.build().is_some());
+/// # }
+/// # }
+/// ```
+pub struct HookBuilder<T = InputEvent> {
+    mouse: bool,
+    mouse_moves: bool,
+    keyboard: bool,
+    reset_on_focus_loss: bool,
+    layout: LayoutSource,
+    physical_only: bool,
+    skip_no_remove: bool,
+    raw: bool,
+    latency_stats: bool,
+    unknown_messages: bool,
+    key_edges: Edges,
+    button_edges: Edges,
+    accumulate_text: bool,
+    foreground_info: bool,
+    layout_info: bool,
+    foreground_target: Option<ForegroundTarget>,
+    chatter_threshold: Option<Duration>,
+    coalesce_moves: Option<Duration>,
+    double_clicks: bool,
+    move_deltas: bool,
+    capacity: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    clear_pending: bool,
+    thread_per_hook: bool,
+    thread_priority: ThreadPriority,
+    input_desktop: bool,
+    physical_buttons: bool,
+    backend: Backend,
+    scope: Option<u32>,
+    current_thread: bool,
+    auto_reinstall: Option<Duration>,
+    sink: Option<Box<dyn EventSink + Send>>,
+    filter: Option<EventFilter>,
+    blocking: Option<EventFilter>,
+    context: Option<Box<dyn Any + Send + Sync>>,
+    mapper: Mapper<T>,
+}
+
+impl HookBuilder {
+    pub fn new() -> Self {
+        Self {
+            mouse: false,
+            mouse_moves: false,
+            keyboard: false,
+            reset_on_focus_loss: false,
+            layout: LayoutSource::ForegroundThread,
+            physical_only: false,
+            skip_no_remove: false,
+            raw: false,
+            latency_stats: false,
+            unknown_messages: false,
+            key_edges: Edges::Both,
+            button_edges: Edges::Both,
+            accumulate_text: false,
+            foreground_info: false,
+            layout_info: false,
+            foreground_target: None,
+            chatter_threshold: None,
+            coalesce_moves: None,
+            double_clicks: false,
+            move_deltas: false,
+            capacity: None,
+            overflow_policy: OverflowPolicy::DropOldest,
+            clear_pending: true,
+            thread_per_hook: false,
+            thread_priority: ThreadPriority::Normal,
+            input_desktop: false,
+            physical_buttons: false,
+            backend: Backend::LowLevelHook,
+            scope: None,
+            current_thread: false,
+            auto_reinstall: None,
+            sink: None,
+            filter: None,
+            blocking: None,
+            context: None,
+            mapper: Box::new(Some),
+        }
+    }
+}
+
+impl<T> HookBuilder<T> {
+    /// Instructs builder to spawn a new mouse hook in background thread on HookBuilder::build().
+    /// 
+    /// By default the mouse hook delivers only the buttons and the wheels, the moves are dropped in the hook procedure:
+    /// there are hundreds of them per second while the mouse moves, and most of the applications care only about the clicks.
+    /// See [HookBuilder::with_mouse_moves] to get them too.
+    pub fn with_mouse(mut self) -> Self {
+        self.mouse = true;
+        self
+    }
+
+    /// Instructs builder to deliver the mouse moves as well, which the mouse hook drops by default (see [HookBuilder::with_mouse]).
+    /// It does not install the mouse hook on its own, it is still requested with [HookBuilder::with_mouse].
+    /// The moves can be enabled and disabled later with [Hook::set_mouse_moves_enabled].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_mouse().with_mouse_moves().build().unwrap();
+    /// if let Ok(InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(me), .. })) = hook.try_recv() {
+    ///     println!("The cursor is at {:?}", me.point);
+    /// }
+    /// # }
+    /// ```
+    pub fn with_mouse_moves(mut self) -> Self {
+        self.mouse_moves = true;
+        self
+    }
+
+    /// Instructs builder to spawn a new keyboard hook in background thread on HookBuilder::build().
+    pub fn with_keyboard(mut self) -> Self {
+        self.keyboard = true;
+        self
+    }
+
+    /// Instructs builder to emit [InputEvent::Reset] whenever the input desktop is switched,
+    /// or the focus moves to or from the window of an elevated process.
+    /// 
+    /// Low-level hooks do not receive the input that goes to another desktop.
+    /// When e.g. a key is pressed, then the UAC prompt shows up and the key is released, the release is never seen by the hook.
+    /// Consumers that track pressed keys or buttons should clear their state when [InputEvent::Reset] is received.
+    /// 
+    /// The only OS signal that triggers the reset is the `EVENT_SYSTEM_DESKTOPSWITCH` WinEvent,
+    /// which Windows raises both when switching to and back from the other desktop, for example:
+    /// - the UAC consent prompt (secure desktop),
+    /// - the Ctrl+Alt+Del screen,
+    /// - locking and unlocking the workstation (e.g. Win+L).
+    /// 
+    /// Low-level hooks see the input regardless of the focus, with one exception: the User Interface Privilege Isolation
+    /// may not call the hooks of this process for the input going to a process running at a higher integrity level
+    /// (e.g. "Run as administrator"), unless this process is elevated (or has `uiAccess`) too.
+    /// So the reset is sent as well when the `EVENT_SYSTEM_FOREGROUND` WinEvent reports the focus moving to or from such window.
+    /// When the integrity level of the focused process can't be read, it is assumed to be higher: a spurious reset is
+    /// cheaper than a key stuck down. Changing the focus between the other windows does NOT trigger the reset.
+    pub fn emit_reset_on_focus_loss(mut self) -> Self {
+        self.reset_on_focus_loss = true;
+        self
+    }
+
+    /// Selects the keyboard layout used to translate the keys into text, see [crate::layout] for details.
+    /// By default the layout of the foreground window's thread is used.
+    pub fn layout(mut self, source: LayoutSource) -> Self {
+        self.layout = source;
+        self
+    }
+
+    /// Instructs builder to deliver only the input coming from the real hardware.
+    /// All events flagged by Windows as injected (e.g. by `SendInput` used by automation or accessibility tools like Narrator)
+    /// are dropped inside the hook procedure and never reach the [Hook]. They are still passed to the other hooks and applications.
+    pub fn physical_only(mut self) -> Self {
+        self.physical_only = true;
+        self
+    }
+
+    /// Instructs builder to drop the mouse events for which the hook procedure is called with the `HC_NOREMOVE` code.
+    /// 
+    /// The hook procedures are called with `HC_ACTION` for the input removed from the queue, and `HC_NOREMOVE`
+    /// when the application only peeked at it (`PeekMessage` with `PM_NOREMOVE`), so the same input may be seen twice.
+    /// Windows documents only `HC_ACTION` for the low-level hooks, but it does not hurt to be defensive if doubled events are a concern.
+    /// The keyboard events are always delivered only for `HC_ACTION`, this option matters only for the mouse events.
+    /// By default the mouse events are delivered for both codes. The events for any other code are never delivered, just passed on.
+    pub fn skip_no_remove(mut self) -> Self {
+        self.skip_no_remove = true;
+        self
+    }
+
+    /// Instructs builder to copy the original parameters of the hook procedures into the events,
+    /// as [crate::event::KeyboardEvent::raw] and [crate::event::MouseEvent::raw].
+    /// It is an escape hatch for the details the typed events do not expose (yet).
+    /// 
+    /// The pointed `KBDLLHOOKSTRUCT` or `MSLLHOOKSTRUCT` is valid only while the hook procedure runs,
+    /// so its fields are copied in the procedure: the copies are safe to inspect at any time later.
+    /// Only the low-level hooks fill them in, the events of the [Backend::RawInput] and of the [HookBuilder::scoped_to_thread] hooks have no raw data.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_raw().build().unwrap();
+    /// if let Ok(InputEvent::Keyboard(KeyboardEvent{ raw: Some(raw), .. })) = hook.try_recv() {
+    ///     println!("LLKHF flags: {:#x}", raw.flags);
+    /// }
+    /// # }
+    /// ```
+    #[doc(alias = "with_raw_struct")]
+    pub fn with_raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
+    /// Instructs builder to measure the time the hook procedures spend on each event, reported by [Hook::latency_stats].
+    /// The time is read with `QueryPerformanceCounter` when the event is handed over to the processing and when it is passed on,
+    /// which costs well under a microsecond. Only the hook procedures are measured: the [Backend::RawInput] has no timeout to worry about.
+    pub fn with_latency_stats(mut self) -> Self {
+        self.latency_stats = true;
+        self
+    }
+
+    /// Instructs builder to count the messages the hook procedures did not recognize, reported by [Hook::unknown_message_count].
+    /// Such messages are delivered anyway, as the [crate::event::KeyPress::Other] or [crate::event::MouseEventType::Other],
+    /// so the count helps only to tell what an unusual keyboard, mouse or IME sends, without receiving every event.
+    /// Up to 16 different messages are counted. With the "logging" feature each of them is logged when it is first seen.
+    pub fn count_unknown_messages(mut self) -> Self {
+        self.unknown_messages = true;
+        self
+    }
+
+    /// Instructs builder to mark the key downs that came within the `threshold` after the previous press of the same key
+    /// as [crate::event::KeyboardEvent::chatter]: the worn switches of the mechanical keyboards bounce, producing the spurious presses
+    /// a few milliseconds apart. The auto-repeated downs are never marked, only a new press after the release is.
+    /// 
+    /// The events are only marked, they are all delivered, so that the caller decides how to debounce them.
+    /// The OS timestamps are in milliseconds (and the real resolution is coarser, see the "Ordering" section of the [InputEvent]),
+    /// so the `threshold` is rounded down to the milliseconds. The few tens of milliseconds separate the chatter from the fast typing.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use std::time::Duration;
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_chatter_detection(Duration::from_millis(30)).build().unwrap();
+    /// if let Ok(InputEvent::Keyboard(ke)) = hook.try_recv() {
+    ///     if ke.chatter {
+    ///         println!("The {:?} key bounced", ke.key);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[doc(alias = "debounce")]
+    pub fn with_chatter_detection(mut self, threshold: Duration) -> Self {
+        self.chatter_threshold = Some(threshold);
+        self
+    }
+
+    /// Selects which key edges (presses and/or releases) are delivered, by default [Edges::Both].
+    /// The unwanted edges are filtered out inside the hook procedure, so they never reach the [Hook].
+    /// E.g. a hotkey listener usually cares only about [Edges::DownOnly], which halves the traffic.
+    /// The mouse buttons are selected on their own with [HookBuilder::button_edges], so the two compose freely.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// use willhook::hook::{Edges, HookBuilder};
+    /// // Key presses, and all of the mouse
+    /// let hook = HookBuilder::new().with_keyboard().with_mouse().key_edges(Edges::DownOnly).build().unwrap();
+    /// # }
+    /// ```
+    pub fn key_edges(mut self, edges: Edges) -> Self {
+        self.key_edges = edges;
+        self
+    }
+
+    /// Delivers only the key presses, same as `key_edges(Edges::DownOnly)`, see [HookBuilder::key_edges].
+    pub fn keyboard_downs_only(self) -> Self {
+        self.key_edges(Edges::DownOnly)
+    }
+
+    /// Delivers only the key releases, same as `key_edges(Edges::UpOnly)`, see [HookBuilder::key_edges].
+    pub fn keyboard_ups_only(self) -> Self {
+        self.key_edges(Edges::UpOnly)
+    }
+
+    /// Instructs builder to report the mouse buttons as they are on the device, regardless of the "Switch primary and secondary buttons"
+    /// system setting (`SM_SWAPBUTTON`). By default the buttons are logical, as Windows reports them to the applications:
+    /// with the setting on, the physical right button is reported as the [crate::event::MouseButton::Left] and the other way around.
+    /// With this option it is the [crate::event::MouseButton::Right], as it is with the setting off. Only the left and the right buttons are swapped.
+    /// The setting is checked on every press, so its change applies right away (but the button held while it changed may be released as the other one).
+    /// Both the [Backend::LowLevelHook] and the [Backend::RawInput] report the buttons the same way.
+    pub fn physical_mouse_buttons(mut self) -> Self {
+        self.physical_buttons = true;
+        self
+    }
+
+    /// Same as [HookBuilder::key_edges], but for the mouse buttons. Mouse moves and wheels are not affected.
+    pub fn button_edges(mut self, edges: Edges) -> Self {
+        self.button_edges = edges;
+        self
+    }
+
+    /// Queues only the events for which the `predicate` returns true, the others are filtered out inside the hook procedures.
+    /// Unlike [HookBuilder::map_events], the unwanted events never reach the queue, which matters for the noisy mouse moves.
+    /// The predicate is applied after the other filters (like [HookBuilder::key_edges]) and it may be called from several
+    /// hook threads, so it must be `Sync`. It is called for every input event in the system, so it must return quickly.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// // Only the mouse buttons, the moves and wheels never reach the queue
+    /// let hook = willhook::hook::HookBuilder::new()
+    ///     .with_mouse()
+    ///     .filter(|event| matches!(event, InputEvent::Mouse(MouseEvent{ event: MouseEventType::Press(_), .. })))
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&InputEvent) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+
+    /// Blocks the events for which the `predicate` returns true: they are not passed to the other hooks and applications,
+    /// as if the user did not press the key or the button at all. E.g. a broken key of the laptop can be disabled this way.
+    /// The blocked events are still delivered to the [Hook] (unless filtered out) with the `blocked` flag set, so the consumer sees what was blocked.
+    /// 
+    /// **Warning:** the predicate runs inside the hook procedure, and the input of the whole system waits for it.
+    /// It has to return right away: no locks, no I/O, no allocations. If it takes longer than the "LowLevelHooksTimeout"
+    /// (a few hundred milliseconds at most), Windows passes the event on anyway, and it may even remove the hook silently.
+    /// A predicate that blocks too much (e.g. all the keys) leaves the user without the keyboard until the [Hook] is dropped.
+    /// 
+    /// Only the low-level hooks can block the input, the predicate is ignored with [Backend::RawInput].
+    /// The probes of [Hook::verify] are never passed to the predicate.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// // Disable the Insert key, that keeps being pressed by accident
+    /// let hook = willhook::hook::HookBuilder::new()
+    ///     .with_keyboard()
+    ///     .with_blocking(|event| matches!(event, InputEvent::Keyboard(ke) if ke.key == Some(KeyboardKey::Insert)))
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn with_blocking<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&InputEvent) -> bool + Send + Sync + 'static,
+    {
+        self.blocking = Some(Box::new(predicate));
+        self
+    }
+
+    /// Instructs builder to run each hook (keyboard and mouse) in its own background thread.
+    /// By default all the hooks share one background thread, as Windows calls the low-level hooks one at a time anyway.
+    /// It does not apply to the [Backend::RawInput], which always uses one thread.
+    pub fn thread_per_hook(mut self) -> Self {
+        self.thread_per_hook = true;
+        self
+    }
+
+    /// Sets the priority of the background thread(s) running the hooks, by default [ThreadPriority::Normal].
+    /// Windows removes the low-level hook silently, when its procedure does not return within the `LowLevelHooksTimeout`.
+    /// On a loaded system the hook thread may be scheduled too late for that, the higher priority makes it less likely.
+    /// 
+    /// The hook thread only waits for the input and queues it, so it takes very little of the CPU even at the high priority.
+    /// But the [HookBuilder::filter] and [HookBuilder::with_blocking] predicates run in it: a slow predicate in the thread
+    /// of the [ThreadPriority::TimeCritical] can starve the rest of the system, including the input itself.
+    /// The priority also applies to the thread of the [Backend::RawInput].
+    pub fn with_thread_priority(mut self, priority: ThreadPriority) -> Self {
+        self.thread_priority = priority;
+        self
+    }
+
+    /// Instructs builder to install the hooks on the desktop that receives the user input at the moment.
+    /// The low-level hooks (and the [Backend::RawInput] window) see only the input of the desktop their thread is assigned to,
+    /// which is the desktop of the process by default. So the background thread calls `SetThreadDesktop(OpenInputDesktop(...))`
+    /// before it installs anything, and the build fails with [HookError::WinApiError] if the desktop could not be opened.
+    /// 
+    /// It helps the processes started on a non-interactive desktop or window station of the user's session, e.g. by a scheduled task.
+    /// It can't help a Windows service in the session 0: the sessions are isolated, and the input desktop of the session 0 is not
+    /// the one the user types into. The service has to start a helper process in the user's session (`CreateProcessAsUserW`),
+    /// and build the hook there. The desktop is not followed when the input switches to another one (e.g. to the secure desktop),
+    /// see [HookBuilder::emit_reset_on_focus_loss].
+    pub fn on_input_desktop(mut self) -> Self {
+        self.input_desktop = true;
+        self
+    }
+
+    /// Selects how the input is captured, by default [Backend::LowLevelHook].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Instructs builder to install the thread hooks (`WH_KEYBOARD`, `WH_MOUSE`) for the thread with the `thread_id`,
+    /// instead of the system-wide low-level hooks. Only the input retrieved by that thread is captured, e.g. the UI thread
+    /// of the application that is interested just in the input to its own windows.
+    /// 
+    /// The differences to the low-level hooks:
+    /// - The thread must belong to this process, otherwise the build fails with [HookError::WinApiError]
+    ///   (the hook procedure would have to live in a DLL injected to the other process).
+    /// - The hook procedures are called in the hooked thread, when it retrieves the input messages - so they delay only that thread.
+    ///   A slow hook procedure is not removed by Windows, as it happens with the low-level hooks.
+    /// - The input is seen only when the thread retrieves it: the keys typed to the other applications are not captured,
+    ///   and the mouse moves are reported only over the thread's windows.
+    /// - Windows does not tell if the events were injected, so the `is_injected` is `None` and [HookBuilder::physical_only] keeps them.
+    /// - [Hook::verify] and [HookBuilder::with_auto_reinstall] don't work, as the probes are not sent to the thread.
+    /// - The events consumed by [HookBuilder::with_blocking] are discarded before they reach the thread's windows.
+    /// 
+    /// It does not apply to the [Backend::RawInput].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// use winapi::um::processthreadsapi::GetCurrentThreadId;
+    /// // Capture only the input of this (UI) thread
+    /// let hook = willhook::hook::HookBuilder::new()
+    ///     .with_keyboard()
+    ///     .scoped_to_thread(unsafe { GetCurrentThreadId() })
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn scoped_to_thread(mut self, thread_id: u32) -> Self {
+        self.scope = Some(thread_id);
+        self
+    }
+
+    /// Instructs builder to install the hooks in the thread calling [HookBuilder::build], instead of spawning the background thread for them.
+    /// It is meant for the applications that already run the message loop in a dedicated thread, e.g. the UI thread.
+    /// 
+    /// **The calling thread must keep pumping the messages** (`GetMessageW` or `PeekMessageW`) for as long as the [Hook] lives:
+    /// Windows calls the hook procedures from within the message retrieval of the thread that installed the hooks.
+    /// If the thread does not retrieve the messages, no event is captured, and the input of the whole system waits
+    /// for the hooks until the `LowLevelHooksTimeout`, after which Windows may remove them silently.
+    /// So the thread should not wait for the events of the [Hook] itself, use [Hook::try_recv] or receive them on another thread.
+    /// 
+    /// Dropping the [Hook] only removes the hooks, the thread is not told to quit (nor joined), and any thread may drop it.
+    /// The options that need the hook thread don't apply: [HookBuilder::with_auto_reinstall], [HookBuilder::emit_reset_on_focus_loss],
+    /// [HookBuilder::on_input_desktop], [HookBuilder::with_thread_priority] and [HookBuilder::thread_per_hook], and neither does the [Backend::RawInput].
+    /// [Hook::wait_ready] returns false, as the caller's message loop is not known, and [Hook::verify] can't succeed while it blocks the pumping thread.
+    /// 
+    /// ```rust,no_run
+    /// # fn main() {
+    /// use winapi::um::winuser::{GetMessageW, TranslateMessage, DispatchMessageW, MSG};
+    /// let hook = willhook::hook::HookBuilder::new()
+    ///     .with_keyboard()
+    ///     .install_on_current_thread()
+    ///     .build()
+    ///     .unwrap();
+    /// let mut msg: MSG = unsafe { std::mem::zeroed() };
+    /// while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+    ///     unsafe {
+    ///         TranslateMessage(&msg);
+    ///         DispatchMessageW(&msg);
+    ///     }
+    ///     while let Ok(event) = hook.try_recv() {
+    ///         println!("{:?}", event);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn install_on_current_thread(mut self) -> Self {
+        self.current_thread = true;
+        self
+    }
+
+    /// Instructs builder to check every `interval` that the hooks are still called, and to reinstall them if they are not.
+    /// Windows silently removes a low-level hook that does not return in time (see `LowLevelHooksTimeout` in the registry),
+    /// and the removed hook is not notified in any way - it just stops receiving the events.
+    ///
+    /// The check is the same as [Hook::verify], run in its own background thread, so each check injects the probe events.
+    /// The events that happened while the hooks were removed are lost. If the probes can't be injected
+    /// (e.g. while the secure desktop is shown), the check is skipped. It does not apply to the [Backend::RawInput].
+    ///
+    /// ```rust
+    /// # fn main() {
+    /// # use std::time::Duration;
+    /// let hook = willhook::hook::HookBuilder::new()
+    ///     .with_keyboard()
+    ///     .with_auto_reinstall(Duration::from_secs(5))
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(hook.is_installed());
+    /// # }
+    /// ```
+    pub fn with_auto_reinstall(mut self, interval: Duration) -> Self {
+        self.auto_reinstall = Some(interval);
+        self
+    }
+
+    /// Limits the number of events queued in the [Hook], until they are received. By default the queue is unbounded.
+    /// When the queue is full, the [OverflowPolicy] selected with [HookBuilder::overflow_policy] decides which event is discarded.
+    /// The hook procedures never wait for the room in the queue, as that would freeze the input of the whole system.
+    /// The capacity of 0 discards all the events, unless the policy is [OverflowPolicy::KeepLatestOnly].
+    /// The bounded queue is allocated when the hook is built, so queuing the events never allocates - this suits the
+    /// high rates of mouse moves. The unbounded queue allocates only when it grows, not for every event.
+    /// The receivers of the [Hook::subscribe] are bounded (and allocated when subscribing) the same way.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Selects what happens when the queue is full, by default [OverflowPolicy::DropOldest].
+    /// The [OverflowPolicy::KeepLatestOnly] applies also without the [HookBuilder::capacity].
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Attaches the user's value to the [Hook], returned by [Hook::context]. It is not used by the hook in any way.
+    /// 
+    /// It lets the code that gets the `&Hook` (e.g. a function routing the events of the hooks built for different purposes)
+    /// tell what the hook is for, without a closure capturing it. The [HookBuilder::with_sink] and the other callbacks
+    /// get the events only, so they have to capture the context themselves, if they need it.
+    /// The context is dropped together with the [Hook], and it is not persisted with the builder configuration.
+    pub fn with_context<C: Any + Send + Sync>(mut self, context: C) -> Self {
+        self.context = Some(Box::new(context));
+        self
+    }
+
+    /// Instructs builder to keep the events left in the queue when the hook is built, instead of discarding them.
+    /// 
+    /// The queue is a static shared by all the hooks, as the hook procedures have no place to keep their own.
+    /// Dropping a [Hook] releases the queue, but the events can still be left there by the hooks that never made it to the user,
+    /// e.g. when the build failed after one of the hooks was already installed. By default such events are discarded, so every new
+    /// [Hook] (and every test building one) starts with an empty queue. The events captured once the new hooks are installed are always kept.
+    pub fn keep_pending_on_start(mut self) -> Self {
+        self.clear_pending = false;
+        self
+    }
+
+    /// Instructs builder to hand over all the captured events to the `sink`, running in its own background thread.
+    /// The sink takes the events over: they are not received from the [Hook] anymore (the [Hook::try_recv] and others will not get them).
+    /// The sink gets the events as they were captured, before [HookBuilder::map_events] and [HookBuilder::accumulate_text].
+    /// 
+    /// The sink thread stops when the [Hook] is dropped, after handling the events left in the queue, see the [crate::sink] module.
+    /// It is stopped while the hook is torn down, so the sink must not wait for the [Hook] methods, like [Hook::is_installed].
+    pub fn with_sink<S: EventSink + Send + 'static>(mut self, sink: S) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Instructs builder to translate the keystrokes into text, and emit it word by word as [InputEvent::Text].
+    /// 
+    /// The word is emitted right after the keyboard event that typed a whitespace (e.g. Space, Tab or Enter),
+    /// or when [Hook::flush_text] is called. Backspace removes the last character, shift, caps lock and dead keys are respected.
+    /// The translation runs on the thread receiving from the [Hook], using the layout selected with [HookBuilder::layout].
+    /// 
+    /// This is a heuristic, not a real edit control: the cursor movement, selection, clipboard or the IME input are not followed,
+    /// so the text may differ from what the application received. The result also depends on the keyboard layout.
+    pub fn accumulate_text(mut self) -> Self {
+        self.accumulate_text = true;
+        self
+    }
+
+    /// Instructs builder to deliver at most one mouse move per `min_interval`, the latest one.
+    /// Useful for the applications that only want the cursor position from time to time, e.g. to repaint it.
+    /// The moves themselves have to be requested with [HookBuilder::with_mouse_moves].
+    /// 
+    /// The moves are collapsed on the thread receiving from the [Hook], the hook procedures still queue all of them
+    /// (to limit the queue itself, see [OverflowPolicy::KeepLatestOnly]). The move that comes too early is held back
+    /// and delivered when it is due, unless a newer move replaces it. The buttons, wheels and keys are never held back:
+    /// the held move is delivered right before them, so that the position is up to date e.g. when a button is pressed.
+    /// The async stream (with the "async" feature) delivers the held move only with the next event.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use std::time::Duration;
+    /// // At most 30 cursor updates per second
+    /// let hook = willhook::hook::HookBuilder::new()
+    ///     .with_mouse()
+    ///     .with_mouse_moves()
+    ///     .coalesce_moves(Duration::from_millis(33))
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn coalesce_moves(mut self, min_interval: Duration) -> Self {
+        self.coalesce_moves = Some(min_interval);
+        self
+    }
+
+    /// Instructs builder to emit [InputEvent::MouseDoubleClick] after the button down that completed a double-click.
+    /// The low-level hooks never see the double-clicks, as the windows get them only if their class asks for them.
+    /// So they are detected on the thread receiving from the [Hook], the same way as Windows does it: the second down
+    /// of the same button has to come within the [crate::system::double_click_time] from the first one,
+    /// and within the [crate::system::double_click_size] rectangle around it. Both settings are read for every click.
+    /// 
+    /// The position of the click is the one of the last mouse move received (the low-level hook does not report it with the click),
+    /// so it needs the moves to be delivered, see [HookBuilder::with_mouse_moves]: the moves dropped by the [HookBuilder::filter]
+    /// or [Hook::set_mouse_moves_enabled] are not seen. Without any move the clicks are assumed to be at the same position.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_mouse().with_double_click_detection().build().unwrap();
+    /// if let Ok(InputEvent::MouseDoubleClick(MouseButton::Left(_))) = hook.try_recv() {
+    ///     println!("Open the file");
+    /// }
+    /// # }
+    /// ```
+    pub fn with_double_click_detection(mut self) -> Self {
+        self.double_clicks = true;
+        self
+    }
+
+    /// Instructs builder to fill in the [crate::event::MouseMoveEvent::delta], the movement since the previous move received.
+    /// The first move after the hook is built has no previous position, so its delta is `None`, as for the moves without the position.
+    /// The absolute position is still reported as well. The moves themselves have to be requested with [HookBuilder::with_mouse_moves].
+    /// 
+    /// The deltas are computed from the positions reported by the OS, which are clamped to the screen:
+    /// the cursor stopped at the edge of the screen reports no movement, although the mouse moves.
+    /// With [HookBuilder::coalesce_moves] the deltas of the dropped moves are added to the delivered one.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_mouse().with_mouse_moves().with_move_deltas().build().unwrap();
+    /// if let Ok(InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(MouseMoveEvent{ delta: Some(delta), .. }), .. })) = hook.try_recv() {
+    ///     println!("Turn the camera by {}, {}", delta.dx, delta.dy);
+    /// }
+    /// # }
+    /// ```
+    pub fn with_move_deltas(mut self) -> Self {
+        self.move_deltas = true;
+        self
+    }
+
+    /// Instructs builder to fill in the `foreground_pid` of the keyboard and mouse events: the process owning the foreground window.
+    /// 
+    /// The foreground window is queried on the thread receiving from the [Hook], not in the hook procedure, so that it does
+    /// not delay the input of the whole system. So it is the foreground window when the event is received, not when it happened:
+    /// it may be stale, e.g. for the click that activated another window, or when the events were queued for a while.
+    /// It is `None` when there is no foreground window (e.g. while the focus is being switched) and for the events handed over
+    /// to the [HookBuilder::with_sink].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_foreground_info().build().unwrap();
+    /// if let Ok(InputEvent::Keyboard(ke)) = hook.try_recv() {
+    ///     println!("{:?} typed into the process {:?}", ke.key, ke.foreground_pid);
+    /// }
+    /// # }
+    /// ```
+    pub fn with_foreground_info(mut self) -> Self {
+        self.foreground_info = true;
+        self
+    }
+
+    /// Instructs builder to fill in the `layout` of the keyboard events: the keyboard layout (`HKL`) of the foreground window's thread,
+    /// i.e. the one the user is typing with. The same key types different characters in the different layouts,
+    /// so this tells e.g. which language the user typed in, see [KeyboardEvent::language_id].
+    /// 
+    /// Like [HookBuilder::with_foreground_info], the layout is queried on the thread receiving from the [Hook], not in the hook procedure,
+    /// so it is the layout when the event is received. It may be stale, e.g. for the keys typed right before switching the layout,
+    /// and it is `None` for the events handed over to the [HookBuilder::with_sink]. The [HookBuilder::layout] is not affected.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::event::*;
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_layout_info().build().unwrap();
+    /// if let Ok(InputEvent::Keyboard(ke)) = hook.try_recv() {
+    ///     println!("{:?} typed with the layout {:x?} of the language {:x?}", ke.key, ke.layout, ke.language_id());
+    /// }
+    /// # }
+    /// ```
+    pub fn with_layout_info(mut self) -> Self {
+        self.layout_info = true;
+        self
+    }
+
+    /// Instructs builder to deliver the keyboard and mouse events only while the process `pid` owns the foreground window,
+    /// e.g. to log only the input to one application. The low-level hooks are global, so the events are still captured
+    /// (and seen by the [HookBuilder::with_sink] and the [Hook::subscribe] receivers), they are dropped when received from the [Hook].
+    /// The other events (like [InputEvent::Reset]) are always delivered.
+    /// 
+    /// Like [HookBuilder::with_foreground_info], the foreground window is checked on the thread receiving from the [Hook],
+    /// when the event is received rather than when it happened. The result is reused for 50 ms, so that e.g. the burst of mouse moves
+    /// does not query the OS for every move: the events may be attributed to the previous foreground process for that long after the switch.
+    /// The process id is not persisted with the configuration. Replaces the [HookBuilder::only_foreground_exe].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// // Only the input to this process, e.g. its console window
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().only_foreground_process(std::process::id()).build().unwrap();
+    /// # }
+    /// ```
+    pub fn only_foreground_process(mut self, pid: u32) -> Self {
+        self.foreground_target = Some(ForegroundTarget::Pid(pid));
+        self
+    }
+
+    /// Same as [HookBuilder::only_foreground_process], but the process is identified by the file name of its executable, e.g. "game.exe",
+    /// compared case-insensitively. It matches any process started from that executable, and it keeps working across its restarts.
+    /// The processes that can't be queried (e.g. the protected ones) never match. Replaces the [HookBuilder::only_foreground_process].
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// let hook = willhook::hook::HookBuilder::new().with_keyboard().with_mouse().only_foreground_exe("game.exe").build().unwrap();
+    /// # }
+    /// ```
+    pub fn only_foreground_exe(mut self, exe: &str) -> Self {
+        self.foreground_target = Some(ForegroundTarget::Exe(exe.to_owned()));
+        self
+    }
+
+    /// Instructs builder to transform the events with the `mapper`, so the [Hook] delivers the mapped type `U`.
+    /// When the `mapper` returns `None`, then the event is dropped.
+    /// 
+    /// The `mapper` runs on the thread receiving from the [Hook] (not in the hook procedure), so it may be arbitrarily slow.
+    /// Calling `map_events` again replaces the previous mapper.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::hook::HookBuilder;
+    /// # use willhook::event::*;
+    /// // Deliver only the keys that were pressed
+    /// let hook = HookBuilder::new()
+    ///     .with_keyboard()
+    ///     .map_events(|event| match event {
+    ///         InputEvent::Keyboard(KeyboardEvent{ pressed: KeyPress::Down(_), key, .. }) => key,
+    ///         _ => None,
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// let key: Result<KeyboardKey, _> = hook.try_recv();
+    /// assert!(key.is_err());
+    /// # }
+    /// ```
+    pub fn map_events<U, F>(self, mapper: F) -> HookBuilder<U>
+    where
+        F: FnMut(InputEvent) -> Option<U> + Send + 'static,
+    {
+        HookBuilder {
+            mouse: self.mouse,
+            mouse_moves: self.mouse_moves,
+            keyboard: self.keyboard,
+            reset_on_focus_loss: self.reset_on_focus_loss,
+            layout: self.layout,
+            physical_only: self.physical_only,
+            skip_no_remove: self.skip_no_remove,
+            raw: self.raw,
+            latency_stats: self.latency_stats,
+            unknown_messages: self.unknown_messages,
+            key_edges: self.key_edges,
+            button_edges: self.button_edges,
+            accumulate_text: self.accumulate_text,
+            foreground_info: self.foreground_info,
+            layout_info: self.layout_info,
+            foreground_target: self.foreground_target,
+            chatter_threshold: self.chatter_threshold,
+            coalesce_moves: self.coalesce_moves,
+            double_clicks: self.double_clicks,
+            move_deltas: self.move_deltas,
+            capacity: self.capacity,
+            overflow_policy: self.overflow_policy,
+            clear_pending: self.clear_pending,
+            thread_per_hook: self.thread_per_hook,
+            thread_priority: self.thread_priority,
+            input_desktop: self.input_desktop,
+            physical_buttons: self.physical_buttons,
+            backend: self.backend,
+            scope: self.scope,
+            current_thread: self.current_thread,
+            auto_reinstall: self.auto_reinstall,
+            sink: self.sink,
+            filter: self.filter,
+            blocking: self.blocking,
+            context: self.context,
+            mapper: Box::new(mapper),
+        }
+    }
+
+    /// Builds the requested hooks and returns common handle for them.
+    /// If any hooks are active, then the build fails.
+    /// The build fails also if Windows refuses to install any of the requested hooks.
+    /// See [HookBuilder::try_build] for the reason of the failure.
+    pub fn build(self) -> Option<Hook<T>> {
+        self.try_build().ok()
+    }
+
+    /// Same as [HookBuilder::build], but returns the reason why the build failed.
+    /// 
+    /// ```rust
+    /// # fn main() {
+    /// # use willhook::hook::{HookBuilder, HookError};
+    /// let hook = HookBuilder::new().with_keyboard().try_build().unwrap();
+    /// assert_eq!(HookBuilder::new().with_mouse().try_build().err(), Some(HookError::AlreadyInstalled));
+    /// drop(hook);
+    /// assert_eq!(HookBuilder::new().try_build().err(), Some(HookError::NoHookRequested));
+    /// # }
+    /// ```
+    pub fn try_build(self) -> Result<Hook<T>, HookError> {
+        // No hook was requested - do not default, just fail
+        if !self.keyboard && !self.mouse {
+            return Err(HookError::NoHookRequested)
+        }
+        
+        use crate::hook::inner::GLOBAL_HOOK;
+        // This lock ensures that during the time of building, no other builder is active.
+        // If different threads attempt to construct the hook handle, 
+        // they may race for underlying static globals holding the actuall inner hooks.
+        // To prevent this, simple mutex is used so that only one instance of HookBuilder::build() is running at the moment.
+        // In "normal" use case one would create a hook at the start of the program, or at least in one thread.
+        // But the goal of this crate was to be failproof, so here comes the lock:
+        let mut global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if global_hooks.is_any_hook_present() {
+            return Err(HookError::AlreadyInstalled)
+        }
+
+        // Otherwise the build would succeed, but the hook would silently deliver nothing
+        if !crate::hook::inner::desktop::is_interactive_window_station() {
+            log_error!("The process does not run on an interactive window station, the hooks would get no input");
+            return Err(HookError::NonInteractiveSession)
+        }
+
+        use crate::hook::inner::{GLOBAL_SETTINGS, GLOBAL_PRESSED_BUTTONS, GLOBAL_PRESSED_MODIFIERS, GLOBAL_PRESSED_KEYS, GLOBAL_CHANNEL, GLOBAL_LATENCY, GLOBAL_UNKNOWN_MESSAGES};
+        GLOBAL_SETTINGS.set_drop_injected(self.physical_only);
+        GLOBAL_SETTINGS.set_skip_no_remove(self.skip_no_remove);
+        GLOBAL_SETTINGS.set_raw_data(self.raw);
+        GLOBAL_SETTINGS.set_chatter_threshold(self.chatter_threshold);
+        GLOBAL_LATENCY.configure(self.latency_stats);
+        GLOBAL_UNKNOWN_MESSAGES.configure(self.unknown_messages);
+        GLOBAL_SETTINGS.set_thread_priority(self.thread_priority);
+        GLOBAL_SETTINGS.set_input_desktop(self.input_desktop);
+        GLOBAL_SETTINGS.set_physical_buttons(self.physical_buttons);
+        GLOBAL_PRESSED_BUTTONS.reset();
+        GLOBAL_PRESSED_MODIFIERS.reset();
+        GLOBAL_PRESSED_KEYS.reset();
+        GLOBAL_SETTINGS.enable_all();
+        GLOBAL_SETTINGS.set_mouse_moves_enabled(self.mouse_moves);
+        GLOBAL_CHANNEL.configure(self.capacity, self.overflow_policy);
+        if self.clear_pending {
+            let stale = GLOBAL_CHANNEL.drain();
+            if stale > 0 {
+                log_info!("Discarded {} events left in the queue before the hook was built", stale);
+            }
+        }
+        GLOBAL_SETTINGS.set_key_edges(self.key_edges);
+        GLOBAL_SETTINGS.set_button_edges(self.button_edges);
+        GLOBAL_SETTINGS.set_filter(self.filter);
+        GLOBAL_SETTINGS.set_blocking(self.blocking);
+        // From now on every failure releases the predicates, as the [Hook::shutdown] does
+        let mut predicates = PredicatesGuard { armed: true };
+
+        // If any of the requested hooks fails to install, then don't hand out a hook that will never receive anything.
+        use crate::hook::inner::HookKind;
+        let kinds: Vec<HookKind> = [(self.keyboard, HookKind::Keyboard), (self.mouse, HookKind::Mouse)]
+            .into_iter()
+            .filter_map(|(requested, kind)| requested.then_some(kind))
+            .collect();
+        match self.backend {
+            Backend::LowLevelHook if self.current_thread => global_hooks.setup_hooks_on_current_thread(&kinds, self.scope)?,
+            Backend::LowLevelHook => global_hooks.setup_hooks(&kinds, self.reset_on_focus_loss, !self.thread_per_hook, self.scope)?,
+            Backend::RawInput => global_hooks.setup_raw_input(&kinds, self.reset_on_focus_loss)?,
+        }
+        // Before the watchdog, which can't be stopped while the global lock is held
+        let sink = match self.sink.map(SinkThread::new).transpose() {
+            Ok(sink) => sink,
+            Err(error) => {
+                global_hooks.drop_hooks();
+                return Err(error)
+            },
+        };
+        let watchdog = match self.auto_reinstall {
+            Some(interval) if self.backend == Backend::LowLevelHook && self.scope.is_none() && !self.current_thread => {
+                match Watchdog::new(interval, self.reset_on_focus_loss, !self.thread_per_hook) {
+                    Ok(watchdog) => Some(watchdog),
+                    Err(error) => {
+                        global_hooks.drop_hooks();
+                        return Err(error)
+                    },
+                }
+            },
+            _ => None,
+        };
+        predicates.armed = false;
+
+        Ok(Hook{
+            layout: self.layout,
+            mapper: Mutex::new(self.mapper),
+            text: if self.accumulate_text { Some(Mutex::new(TextAccumulator::new())) } else { None },
+            pending: Mutex::new(VecDeque::new()),
+            generation: GLOBAL_CHANNEL.renew_cancellation(),
+            sink: Mutex::new(sink),
+            watchdog: Mutex::new(watchdog),
+            shut_down: AtomicBool::new(false),
+            foreground_info: self.foreground_info,
+            layout_info: self.layout_info,
+            moves: self.coalesce_moves.map(|interval| Mutex::new(MoveCoalescer::new(interval))),
+            coalesced: AtomicU64::new(0),
+            clicks: if self.double_clicks { Some(Mutex::new(DoubleClickDetector::new())) } else { None },
+            foreground: self.foreground_target.map(|target| Mutex::new(ForegroundFilter::new(target))),
+            last_point: if self.move_deltas { Some(Mutex::new(None)) } else { None },
+            context: self.context,
+        })
+    }
+}
+
+/// Releases the user's predicates stored by the [HookBuilder::try_build] if the build fails, so that they (and what they capture)
+/// are not kept until the next successful build.
+struct PredicatesGuard {
+    armed: bool,
+}
+
+impl Drop for PredicatesGuard {
+    fn drop(&mut self) {
+        if self.armed {
+            use crate::hook::inner::GLOBAL_SETTINGS;
+            GLOBAL_SETTINGS.set_filter(None);
+            GLOBAL_SETTINGS.set_blocking(None);
         }
-        
-        return Some(Hook{})
     }
 }
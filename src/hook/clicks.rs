@@ -0,0 +1,125 @@
+use crate::event::*;
+
+/// Detects the double-clicks from the button downs, see [crate::hook::HookBuilder::with_double_click_detection].
+/// It runs on the consumer thread, the low-level hooks never see the double-click messages.
+pub(super) struct DoubleClickDetector {
+    /// The low-level hook reports the position only with the moves, the clicks happen at the last one
+    last_point: Option<Point>,
+    /// The first click of the possible double-click: the button, the OS timestamp and the position
+    first_click: Option<(MouseButton, u32, Option<Point>)>,
+}
+
+impl DoubleClickDetector {
+    pub fn new() -> DoubleClickDetector {
+        DoubleClickDetector { last_point: None, first_click: None }
+    }
+
+    /// Feeds the event, returns the [InputEvent::MouseDoubleClick] if the event completed one.
+    /// The second click has to come within the `max_interval` (in milliseconds) and within the rectangle of the `size`
+    /// centered on the first click, as for the double-clicks reported to the windows.
+    pub fn feed(&mut self, event: &InputEvent, max_interval: u32, size: (u32, u32)) -> Option<InputEvent> {
+        let me = match event {
+            InputEvent::Mouse(me) => me,
+            InputEvent::Reset => {
+                *self = DoubleClickDetector::new();
+                return None
+            },
+            _ => return None,
+        };
+        let (button, time) = match (me.event, me.time) {
+            (MouseEventType::Move(MouseMoveEvent{ point, .. }), _) => {
+                self.last_point = point.or(self.last_point);
+                return None
+            },
+            (MouseEventType::Press(MousePressEvent{ pressed: MouseButtonPress::Down, button }), Some(time)) => (button, time),
+            _ => return None,
+        };
+
+        let point = self.last_point;
+        let is_double = match self.first_click {
+            Some((first_button, first_time, first_point)) => {
+                std::mem::discriminant(&first_button) == std::mem::discriminant(&button)
+                    && time.wrapping_sub(first_time) <= max_interval
+                    && is_close(first_point, point, size)
+            },
+            None => false,
+        };
+        if is_double {
+            // The third click starts over, like in the windows
+            self.first_click = None;
+            Some(InputEvent::MouseDoubleClick(double_click_of(button)))
+        } else {
+            self.first_click = Some((button, time, point));
+            None
+        }
+    }
+}
+
+fn is_close(first: Option<Point>, second: Option<Point>, (width, height): (u32, u32)) -> bool {
+    match (first, second) {
+        (Some(first), Some(second)) => {
+            (first.x - second.x).unsigned_abs() <= width / 2 && (first.y - second.y).unsigned_abs() <= height / 2
+        },
+        // Without any move the position is unknown, but it most likely did not change
+        _ => true,
+    }
+}
+
+fn double_click_of(button: MouseButton) -> MouseButton {
+    use MouseButton::*;
+    match button {
+        Left(_) => Left(MouseClick::DoubleClick),
+        Right(_) => Right(MouseClick::DoubleClick),
+        Middle(_) => Middle(MouseClick::DoubleClick),
+        X1(_) => X1(MouseClick::DoubleClick),
+        X2(_) => X2(MouseClick::DoubleClick),
+        UnkownX(_) => UnkownX(MouseClick::DoubleClick),
+        Other(code) => Other(code),
+    }
+}
+
+#[cfg(test)]
+mod double_click_detector_tests {
+    use super::*;
+
+    fn mouse(event: MouseEventType, time: u32) -> InputEvent {
+        InputEvent::Mouse(MouseEvent { time: Some(time), ..MouseEvent::for_test(event) })
+    }
+
+    fn down(button: MouseButton, time: u32) -> InputEvent {
+        mouse(MouseEventType::Press(MousePressEvent{ pressed: MouseButtonPress::Down, button }), time)
+    }
+
+    fn move_to(x: i32, y: i32) -> InputEvent {
+        mouse(MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x, y }), delta: None }), 0)
+    }
+
+    const LEFT: MouseButton = MouseButton::Left(MouseClick::SingleClick);
+    const RIGHT: MouseButton = MouseButton::Right(MouseClick::SingleClick);
+
+    #[test]
+    fn second_quick_click_is_a_double_click() {
+        let mut clicks = DoubleClickDetector::new();
+        assert_eq!(clicks.feed(&down(LEFT, 1000), 500, (4, 4)), None);
+        let up = mouse(MouseEventType::Press(MousePressEvent{ pressed: MouseButtonPress::Up, button: LEFT }), 1100);
+        assert_eq!(clicks.feed(&up, 500, (4, 4)), None);
+        assert_eq!(clicks.feed(&down(LEFT, 1500), 500, (4, 4)), Some(InputEvent::MouseDoubleClick(MouseButton::Left(MouseClick::DoubleClick))));
+        // The third click is a first one again
+        assert_eq!(clicks.feed(&down(LEFT, 1600), 500, (4, 4)), None);
+    }
+
+    #[test]
+    fn slow_far_or_other_button_clicks_are_not_double_clicks() {
+        let mut clicks = DoubleClickDetector::new();
+        clicks.feed(&down(LEFT, 1000), 500, (4, 4));
+        assert_eq!(clicks.feed(&down(LEFT, 1501), 500, (4, 4)), None);
+        assert_eq!(clicks.feed(&down(RIGHT, 1600), 500, (4, 4)), None);
+
+        clicks.feed(&move_to(10, 10), 500, (4, 4));
+        clicks.feed(&down(LEFT, 2000), 500, (4, 4));
+        clicks.feed(&move_to(13, 10), 500, (4, 4));
+        assert_eq!(clicks.feed(&down(LEFT, 2100), 500, (4, 4)), None);
+        clicks.feed(&move_to(14, 11), 500, (4, 4));
+        assert_eq!(clicks.feed(&down(LEFT, 2200), 500, (4, 4)), Some(InputEvent::MouseDoubleClick(MouseButton::Left(MouseClick::DoubleClick))));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::event::*;
+
+use std::time::{Duration, Instant};
+
+/// Collapses the mouse moves received in quick succession, see [crate::hook::HookBuilder::coalesce_moves].
+/// It runs on the consumer thread, the hook procedures queue all the moves.
+pub(super) struct MoveCoalescer {
+    min_interval: Duration,
+    last_delivered: Option<Instant>,
+    /// The latest move that came too early, delivered when it is due or before the next other event
+    held: Option<InputEvent>,
+}
+
+impl MoveCoalescer {
+    pub fn new(min_interval: Duration) -> MoveCoalescer {
+        MoveCoalescer { min_interval, last_delivered: None, held: None }
+    }
+
+    /// Feeds the received event, returns the event to deliver now (if any) and the event to deliver right after it.
+    /// A held move is delivered before the other event, so that the position is up to date e.g. when a button is pressed.
+    pub fn feed(&mut self, event: InputEvent, now: Instant) -> (Option<InputEvent>, Option<InputEvent>) {
+        if !is_move(&event) {
+            return match self.held.take() {
+                Some(held) => {
+                    self.last_delivered = Some(now);
+                    (Some(held), Some(event))
+                },
+                None => (Some(event), None),
+            }
+        }
+        let event = match self.held.take() {
+            Some(held) => add_delta(&held, event),
+            None => event,
+        };
+        if self.is_due(now) {
+            self.last_delivered = Some(now);
+            (Some(event), None)
+        } else {
+            self.held = Some(event);
+            (None, None)
+        }
+    }
+
+    /// Returns true if feeding the event would drop the held move, see [crate::Hook::dropped_count].
+    pub fn replaces(&self, event: &InputEvent) -> bool {
+        self.held.is_some() && is_move(event)
+    }
+
+    /// Returns how long until the held move is due, or `None` if no move is held.
+    pub fn due_in(&self, now: Instant) -> Option<Duration> {
+        self.held.as_ref()?;
+        let due = self.last_delivered.map(|last| last + self.min_interval)?;
+        Some(due.saturating_duration_since(now))
+    }
+
+    /// Returns the held move, if it is due.
+    pub fn take_due(&mut self, now: Instant) -> Option<InputEvent> {
+        if self.due_in(now)? > Duration::ZERO {
+            return None
+        }
+        self.last_delivered = Some(now);
+        self.held.take()
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        match self.last_delivered {
+            Some(last) => now.saturating_duration_since(last) >= self.min_interval,
+            None => true,
+        }
+    }
+}
+
+fn is_move(event: &InputEvent) -> bool {
+    matches!(event, InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(_), .. }))
+}
+
+/// Adds the delta of the dropped move to the one replacing it, so that the delivered deltas still sum up to the whole movement.
+/// It stays unknown if the dropped move did not know it.
+fn add_delta(dropped: &InputEvent, mut event: InputEvent) -> InputEvent {
+    if let (InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(dropped), .. }),
+            InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(replacing), .. })) = (dropped, &mut event) {
+        replacing.delta = match (dropped.delta, replacing.delta) {
+            (Some(dropped), Some(replacing)) => Some(MoveDelta{ dx: dropped.dx + replacing.dx, dy: dropped.dy + replacing.dy }),
+            _ => None,
+        };
+    }
+    event
+}
+
+#[cfg(test)]
+mod move_coalescer_tests {
+    use super::*;
+
+    fn a_move(x: i32) -> InputEvent {
+        InputEvent::Mouse(MouseEvent::for_test(MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x, y: 0 }), delta: None })))
+    }
+
+    #[test]
+    fn moves_are_delivered_at_most_once_per_interval() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut moves = MoveCoalescer::new(Duration::from_millis(10));
+
+        assert_eq!(moves.feed(a_move(1), at(0)), (Some(a_move(1)), None));
+        assert!(!moves.replaces(&a_move(2)));
+        assert_eq!(moves.feed(a_move(2), at(3)), (None, None));
+        assert!(moves.replaces(&a_move(3)));
+        assert!(!moves.replaces(&InputEvent::Reset));
+        assert_eq!(moves.feed(a_move(3), at(6)), (None, None));
+        assert_eq!(moves.due_in(at(6)), Some(Duration::from_millis(4)));
+        assert_eq!(moves.take_due(at(8)), None);
+        // The latest position is kept
+        assert_eq!(moves.take_due(at(10)), Some(a_move(3)));
+        assert_eq!(moves.due_in(at(10)), None);
+
+        assert_eq!(moves.feed(a_move(4), at(25)), (Some(a_move(4)), None));
+    }
+
+    #[test]
+    fn deltas_of_the_dropped_moves_are_added_up() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let with_delta = |x, dx: Option<i32>| InputEvent::Mouse(MouseEvent::for_test(
+            MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x, y: 0 }), delta: dx.map(|dx| MoveDelta{ dx, dy: 0 }) }),
+        ));
+        let mut moves = MoveCoalescer::new(Duration::from_millis(10));
+
+        assert_eq!(moves.feed(with_delta(1, None), at(0)), (Some(with_delta(1, None)), None));
+        assert_eq!(moves.feed(with_delta(3, Some(2)), at(3)), (None, None));
+        assert_eq!(moves.feed(with_delta(4, Some(1)), at(6)), (None, None));
+        assert_eq!(moves.feed(with_delta(8, Some(4)), at(10)), (Some(with_delta(8, Some(7))), None));
+    }
+
+    #[test]
+    fn other_events_are_never_held() {
+        let start = Instant::now();
+        let mut moves = MoveCoalescer::new(Duration::from_secs(1));
+        assert_eq!(moves.feed(InputEvent::Reset, start), (Some(InputEvent::Reset), None));
+
+        assert_eq!(moves.feed(a_move(1), start), (Some(a_move(1)), None));
+        assert_eq!(moves.feed(a_move(2), start), (None, None));
+        // The held move goes first, so the position is right for the event
+        assert_eq!(moves.feed(InputEvent::Reset, start), (Some(a_move(2)), Some(InputEvent::Reset)));
+        assert_eq!(moves.due_in(start), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Serialization of the [HookBuilder] options, see the "Persisting the configuration" section of its documentation.
+
+use crate::event::InputEvent;
+use crate::hook::{Backend, Edges, HookBuilder, OverflowPolicy, ThreadPriority};
+use crate::hook::foreground::ForegroundTarget;
+use crate::layout::LayoutSource;
+
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The options of the [HookBuilder] that can be persisted, i.e. all but the closures, the context, the thread id and the process id.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct BuilderOptions {
+    mouse: bool,
+    mouse_moves: bool,
+    keyboard: bool,
+    reset_on_focus_loss: bool,
+    layout: LayoutSource,
+    physical_only: bool,
+    skip_no_remove: bool,
+    raw: bool,
+    latency_stats: bool,
+    unknown_messages: bool,
+    key_edges: Edges,
+    button_edges: Edges,
+    accumulate_text: bool,
+    foreground_info: bool,
+    layout_info: bool,
+    foreground_exe: Option<String>,
+    chatter_threshold: Option<Duration>,
+    coalesce_moves: Option<Duration>,
+    double_clicks: bool,
+    move_deltas: bool,
+    capacity: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    clear_pending: bool,
+    thread_per_hook: bool,
+    thread_priority: ThreadPriority,
+    input_desktop: bool,
+    physical_buttons: bool,
+    backend: Backend,
+    auto_reinstall: Option<Duration>,
+}
+
+impl<T> From<&HookBuilder<T>> for BuilderOptions {
+    fn from(builder: &HookBuilder<T>) -> BuilderOptions {
+        BuilderOptions {
+            mouse: builder.mouse,
+            mouse_moves: builder.mouse_moves,
+            keyboard: builder.keyboard,
+            reset_on_focus_loss: builder.reset_on_focus_loss,
+            layout: builder.layout,
+            physical_only: builder.physical_only,
+            skip_no_remove: builder.skip_no_remove,
+            raw: builder.raw,
+            latency_stats: builder.latency_stats,
+            unknown_messages: builder.unknown_messages,
+            key_edges: builder.key_edges,
+            button_edges: builder.button_edges,
+            accumulate_text: builder.accumulate_text,
+            foreground_info: builder.foreground_info,
+            layout_info: builder.layout_info,
+            foreground_exe: match &builder.foreground_target {
+                Some(ForegroundTarget::Exe(exe)) => Some(exe.clone()),
+                _ => None,
+            },
+            chatter_threshold: builder.chatter_threshold,
+            coalesce_moves: builder.coalesce_moves,
+            double_clicks: builder.double_clicks,
+            move_deltas: builder.move_deltas,
+            capacity: builder.capacity,
+            overflow_policy: builder.overflow_policy,
+            clear_pending: builder.clear_pending,
+            thread_per_hook: builder.thread_per_hook,
+            thread_priority: builder.thread_priority,
+            input_desktop: builder.input_desktop,
+            physical_buttons: builder.physical_buttons,
+            backend: builder.backend,
+            auto_reinstall: builder.auto_reinstall,
+        }
+    }
+}
+
+impl Default for BuilderOptions {
+    fn default() -> BuilderOptions {
+        BuilderOptions::from(&HookBuilder::new())
+    }
+}
+
+impl<T> Serialize for HookBuilder<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BuilderOptions::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for HookBuilder<InputEvent> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<HookBuilder<InputEvent>, D::Error> {
+        let options = BuilderOptions::deserialize(deserializer)?;
+        Ok(HookBuilder {
+            mouse: options.mouse,
+            mouse_moves: options.mouse_moves,
+            keyboard: options.keyboard,
+            reset_on_focus_loss: options.reset_on_focus_loss,
+            layout: options.layout,
+            physical_only: options.physical_only,
+            skip_no_remove: options.skip_no_remove,
+            raw: options.raw,
+            latency_stats: options.latency_stats,
+            unknown_messages: options.unknown_messages,
+            key_edges: options.key_edges,
+            button_edges: options.button_edges,
+            accumulate_text: options.accumulate_text,
+            foreground_info: options.foreground_info,
+            layout_info: options.layout_info,
+            foreground_target: options.foreground_exe.map(ForegroundTarget::Exe),
+            chatter_threshold: options.chatter_threshold,
+            coalesce_moves: options.coalesce_moves,
+            double_clicks: options.double_clicks,
+            move_deltas: options.move_deltas,
+            capacity: options.capacity,
+            overflow_policy: options.overflow_policy,
+            clear_pending: options.clear_pending,
+            thread_per_hook: options.thread_per_hook,
+            thread_priority: options.thread_priority,
+            input_desktop: options.input_desktop,
+            physical_buttons: options.physical_buttons,
+            backend: options.backend,
+            auto_reinstall: options.auto_reinstall,
+            ..HookBuilder::new()
+        })
+    }
+}
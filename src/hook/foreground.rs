@@ -0,0 +1,111 @@
+use crate::event::*;
+
+use std::time::{Duration, Instant};
+
+/// How long the result of the foreground lookup is reused, so that the burst of events (e.g. the mouse moves) does not query the OS for each one.
+const LOOKUP_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The process that has to be in the foreground for the events to be delivered,
+/// see [crate::hook::HookBuilder::only_foreground_process] and [crate::hook::HookBuilder::only_foreground_exe].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(super) enum ForegroundTarget {
+    Pid(u32),
+    /// The file name of the executable, e.g. "game.exe"
+    Exe(String),
+}
+
+/// Drops the keyboard and mouse events received while the target process is not in the foreground.
+/// It runs on the consumer thread, the low-level hooks are global and can't be limited to one process.
+pub(super) struct ForegroundFilter {
+    target: ForegroundTarget,
+    /// The last lookup: when it was made, and whether the target was in the foreground
+    last_lookup: Option<(Instant, bool)>,
+}
+
+impl ForegroundFilter {
+    pub fn new(target: ForegroundTarget) -> ForegroundFilter {
+        ForegroundFilter { target, last_lookup: None }
+    }
+
+    /// True if the event is delivered: the keyboard and mouse events only while the target is in the foreground, the others always.
+    pub fn admits(&mut self, event: &InputEvent) -> bool {
+        match event {
+            InputEvent::Keyboard(_) | InputEvent::Mouse(_) => self.is_target_in_foreground(Instant::now()),
+            _ => true,
+        }
+    }
+
+    fn is_target_in_foreground(&mut self, now: Instant) -> bool {
+        if let Some((looked_up, in_foreground)) = self.last_lookup {
+            if now.saturating_duration_since(looked_up) < LOOKUP_INTERVAL {
+                return in_foreground
+            }
+        }
+        let in_foreground = super::foreground_pid().is_some_and(|pid| self.is_target(pid));
+        self.last_lookup = Some((now, in_foreground));
+        in_foreground
+    }
+
+    fn is_target(&self, pid: u32) -> bool {
+        match &self.target {
+            ForegroundTarget::Pid(target) => pid == *target,
+            ForegroundTarget::Exe(exe) => process_image(pid).is_some_and(|image| is_image_of(&image, exe)),
+        }
+    }
+}
+
+/// Returns the full path of the executable of the process, `None` if the process can't be queried (e.g. the protected ones).
+fn process_image(pid: u32) -> Option<String> {
+    use winapi::shared::minwindef::{DWORD, FALSE, MAX_PATH};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winbase::QueryFullProcessImageNameW;
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+        if process.is_null() {
+            return None
+        }
+        // The paths longer than MAX_PATH are rare, and such a process is simply not matched
+        let mut buffer = [0u16; MAX_PATH];
+        let mut size = buffer.len() as DWORD;
+        let queried = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut size);
+        CloseHandle(process);
+        (queried != FALSE).then(|| String::from_utf16_lossy(&buffer[..size as usize]))
+    }
+}
+
+/// True if the executable `image` (the full path) is the `exe`, compared by the file name and case-insensitively, like the file system does.
+fn is_image_of(image: &str, exe: &str) -> bool {
+    let file_name = image.rsplit(['\\', '/']).next().unwrap_or(image);
+    file_name.to_lowercase() == exe.to_lowercase()
+}
+
+#[cfg(test)]
+mod foreground_tests {
+    use super::*;
+
+    #[test]
+    fn executable_is_matched_by_the_file_name() {
+        assert!(is_image_of("C:\\Games\\Game.EXE", "game.exe"));
+        assert!(is_image_of("game.exe", "game.exe"));
+        assert!(!is_image_of("C:\\Games\\game.exe.bak", "game.exe"));
+        assert!(!is_image_of("C:\\game.exe\\other.exe", "game.exe"));
+    }
+
+    #[test]
+    fn only_the_input_is_filtered_and_the_lookup_is_reused() {
+        // No foreground process has the id 0
+        let mut filter = ForegroundFilter::new(ForegroundTarget::Pid(0));
+        assert!(filter.admits(&InputEvent::Reset));
+        assert!(filter.admits(&InputEvent::Text(String::from("abc"))));
+        assert!(filter.last_lookup.is_none());
+
+        let now = Instant::now();
+        assert!(!filter.is_target_in_foreground(now));
+        filter.last_lookup = Some((now, true));
+        assert!(filter.is_target_in_foreground(now + LOOKUP_INTERVAL / 2));
+        assert!(!filter.is_target_in_foreground(now + LOOKUP_INTERVAL));
+    }
+}
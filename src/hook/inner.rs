@@ -1,13 +1,23 @@
 pub(super) mod raw;
 pub(super) mod channels;
+pub(super) mod desktop;
+pub(super) mod latency;
 pub(super) mod low_level;
+pub(super) mod settings;
+pub(super) mod pressed;
+pub(super) mod probe;
+pub(super) mod raw_input;
+pub(super) mod ready;
+pub(super) mod unknown;
 
-use crate::hook::inner::{raw::RawHook, channels::HookChannels};
+use crate::hook::inner::{raw::{RawHook, NextHooks}, desktop::{InputDesktop, FocusWatch}, channels::HookChannels, latency::HookLatency, settings::HookSettings, pressed::{PressedButtons, PressedKeys, PressedModifiers}, probe::Probe, raw_input::RawInputThread, ready::LoopReady, unknown::UnknownMessages};
 use crate::event::*;
+use crate::hook::{HookError, HookHandles, NativeHook};
 
 use std::{
     thread::JoinHandle,
-    sync::{Arc, Condvar, Mutex}
+    sync::{Arc, Condvar, Mutex, atomic::{AtomicU64, Ordering}},
+    time::{Duration, Instant},
 };
 
 use once_cell::sync::Lazy;
@@ -18,160 +28,690 @@ use winapi::{shared::{
     windef::*
 }};
 use winapi::um::{
-        processthreadsapi::GetCurrentThreadId,
+        errhandlingapi::GetLastError,
+        processthreadsapi::{GetCurrentThread, GetCurrentThreadId, SetThreadPriority},
+        winbase::THREAD_PRIORITY_NORMAL,
     winuser::{
     HOOKPROC, LPMSG,
-    SetWindowsHookExA, UnhookWindowsHookEx, GetMessageA, PostThreadMessageA,
+    SetWindowsHookExW, UnhookWindowsHookEx, GetMessageW, PostThreadMessageW,
     WM_QUIT,
-    WH_KEYBOARD_LL, WH_MOUSE_LL,
+    WH_KEYBOARD_LL, WH_MOUSE_LL, WH_KEYBOARD, WH_MOUSE,
     }
 };
 
+/// Kinds of the low-level hooks, in the order they are installed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HookKind {
+    Keyboard,
+    Mouse,
+}
+
+impl HookKind {
+    /// The low-level hook, or the thread hook if it is `scoped` to a thread, see [crate::hook::HookBuilder::scoped_to_thread].
+    fn hook_id(&self, scoped: bool) -> INT {
+        match (self, scoped) {
+            (HookKind::Keyboard, false) => WH_KEYBOARD_LL,
+            (HookKind::Mouse, false) => WH_MOUSE_LL,
+            (HookKind::Keyboard, true) => WH_KEYBOARD,
+            (HookKind::Mouse, true) => WH_MOUSE,
+        }
+    }
+
+    /// Names the thread running the hooks of the given kinds, so that it can be told apart in the debuggers and profilers.
+    /// The standard library passes the name to `SetThreadDescription` as well, so it shows up in the native tools.
+    fn thread_name(kinds: &[HookKind]) -> &'static str {
+        match kinds {
+            [HookKind::Keyboard] => "willhook-keyboard-ll",
+            [HookKind::Mouse] => "willhook-mouse-ll",
+            _ => "willhook-ll",
+        }
+    }
+
+    fn procedure(&self, scoped: bool) -> HOOKPROC {
+        use crate::hook::inner::low_level::{keyboard_procedure, mouse_procedure, thread_keyboard_procedure, thread_mouse_procedure};
+        match (self, scoped) {
+            (HookKind::Keyboard, false) => Some(keyboard_procedure),
+            (HookKind::Mouse, false) => Some(mouse_procedure),
+            (HookKind::Keyboard, true) => Some(thread_keyboard_procedure),
+            (HookKind::Mouse, true) => Some(thread_mouse_procedure),
+        }
+    }
+}
+
 pub struct GlobalHooks {
-    keyboard: Option<InnerHook>,
-    mouse: Option<InnerHook>,
+    threads: Vec<InnerHook>,
+    /// The kinds of the low-level hooks requested by the [crate::Hook], kept while the hooks are reinstalled (or failed to be),
+    /// so that the next attempt knows what to install, and the slot stays taken until the [crate::Hook] is shut down.
+    requested: Vec<HookKind>,
+    raw_input: Option<RawInputThread>,
 }
 
 impl GlobalHooks {
     pub fn is_any_hook_present(&self) -> bool {
-        self.keyboard.is_some() || self.mouse.is_some()
+        !self.threads.is_empty() || !self.requested.is_empty() || self.raw_input.is_some()
+    }
+
+    /// Registers the devices of the given kinds for the raw input instead of installing the low-level hooks.
+    /// Returns the error if the registration failed.
+    pub fn setup_raw_input(&mut self, kinds: &[HookKind], watch_desktop_switch: bool) -> Result<(), HookError> {
+        self.raw_input = Some(RawInputThread::new(kinds, watch_desktop_switch)?);
+        Ok(())
+    }
+
+    /// Installs the hooks of the given kinds, either all in one background thread (with one message loop),
+    /// or each in its own thread. Desktop switches are watched by only one of the threads, so that the reset is not reported twice.
+    /// Returns the error if any of the hooks could not be installed, and then none of them is installed.
+    pub fn setup_hooks(&mut self, kinds: &[HookKind], watch_desktop_switch: bool, shared_thread: bool, scope: Option<DWORD>) -> Result<(), HookError> {
+        match self.install_hooks(kinds, watch_desktop_switch, shared_thread, scope) {
+            Ok(()) => {
+                self.requested = kinds.to_vec();
+                Ok(())
+            },
+            Err(error) => {
+                self.drop_hooks();
+                Err(error)
+            },
+        }
+    }
+
+    /// Installs the hooks of the given kinds in the calling thread, instead of the background one.
+    /// Returns the error if any of the hooks could not be installed, and then none of them is installed.
+    pub fn setup_hooks_on_current_thread(&mut self, kinds: &[HookKind], scope: Option<DWORD>) -> Result<(), HookError> {
+        self.threads.push(InnerHook::on_current_thread(kinds, scope)?);
+        self.requested = kinds.to_vec();
+        Ok(())
+    }
+
+    /// Replaces the low-level hooks with the freshly installed ones of the requested kinds, see [crate::hook::HookBuilder::with_auto_reinstall].
+    /// The arguments are the same as for [GlobalHooks::setup_hooks], the raw input is left alone.
+    /// The hooks removed with [GlobalHooks::remove_hook] are not reinstalled. If it fails, then no hook is installed,
+    /// but the requested kinds are kept for the next attempt.
+    pub fn reinstall_hooks(&mut self, watch_desktop_switch: bool, shared_thread: bool) -> Result<(), HookError> {
+        self.reinstall_with(|global_hooks, kinds| global_hooks.install_hooks(kinds, watch_desktop_switch, shared_thread, None))
+    }
+
+    /// Same as [GlobalHooks::reinstall_hooks], with the hooks installed by the `install`, so that the tests don't install the real ones.
+    fn reinstall_with<F>(&mut self, install: F) -> Result<(), HookError>
+    where
+        F: FnOnce(&mut GlobalHooks, &[HookKind]) -> Result<(), HookError>,
+    {
+        if self.requested.is_empty() {
+            return Ok(())
+        }
+        let kinds = self.requested.clone();
+        self.threads.clear();
+        let result = install(self, &kinds);
+        if result.is_err() {
+            // Don't keep the hooks installed before the failure, the next attempt installs all of them again
+            self.threads.clear();
+        }
+        result
     }
 
-    pub fn setup_mouse_hook(&mut self) {
-        use crate::hook::inner::low_level::mouse_procedure;
-        self.mouse = Some(InnerHook::new(WH_MOUSE_LL, Some(mouse_procedure)));
+    /// Installs the hooks like [GlobalHooks::setup_hooks], but leaves the ones installed before the failure, if any.
+    fn install_hooks(&mut self, kinds: &[HookKind], watch_desktop_switch: bool, shared_thread: bool, scope: Option<DWORD>) -> Result<(), HookError> {
+        if shared_thread {
+            InnerHook::new(kinds, watch_desktop_switch, scope).map(|hook| self.threads.push(hook))
+        } else {
+            kinds.iter().enumerate().try_for_each(|(index, kind)| {
+                InnerHook::new(&[*kind], watch_desktop_switch && index == 0, scope).map(|hook| self.threads.push(hook))
+            })
+        }
     }
 
-    pub fn setup_keyboard_hook(&mut self) {
-        use crate::hook::inner::low_level::keyboard_procedure;
-        self.keyboard = Some(InnerHook::new(WH_KEYBOARD_LL, Some(keyboard_procedure)));
+    /// Removes the low-level hook of the given kind, leaving the other one installed, see [crate::Hook::remove_hook].
+    /// Returns false if there is no such hook, or it is the last one.
+    pub fn remove_hook(&mut self, kind: HookKind) -> bool {
+        let hook_count: usize = self.threads.iter().map(|hook| hook.kinds.len()).sum();
+        let index = match self.threads.iter().position(|hook| hook.kinds.contains(&kind)) {
+            Some(index) if hook_count > 1 => index,
+            _ => return false,
+        };
+        let removed = if self.threads[index].kinds.len() == 1 {
+            // Dropping unhooks it and joins its thread
+            self.threads.remove(index);
+            true
+        } else {
+            self.threads[index].remove_hook(kind)
+        };
+        if removed {
+            self.requested.retain(|requested| *requested != kind);
+        }
+        removed
+    }
+
+    /// True if there is at least one hook and all of them are installed.
+    pub fn is_installed(&self) -> bool {
+        self.is_any_hook_present()
+            && self.requested.iter().all(|kind| self.threads.iter().any(|hook| hook.kinds.contains(kind)))
+            && self.threads.iter().all(|hook| hook.is_installed())
+            && self.raw_input.as_ref().and_then(|raw_input| raw_input.loop_error()).is_none()
+    }
+
+    /// True if the hook of the given kind is present and installed.
+    pub fn is_kind_installed(&self, kind: HookKind) -> bool {
+        self.threads.iter().any(|hook| hook.kinds.contains(&kind) && hook.is_installed())
+            || self.raw_input.as_ref().is_some_and(|raw_input| raw_input.is_kind_registered(kind))
+    }
+
+    /// Reports the native handles of the installed hooks, see [crate::Hook::handles].
+    pub fn handles(&self) -> HookHandles {
+        let mut handles = HookHandles::default();
+        for (kind, native) in self.threads.iter().flat_map(|hook| hook.native_hooks()) {
+            match kind {
+                HookKind::Keyboard => handles.keyboard = Some(native),
+                HookKind::Mouse => handles.mouse = Some(native),
+            }
+        }
+        handles
+    }
+
+    /// Returns the message loops of all the hook threads with their thread ids, see [crate::Hook::wait_ready].
+    pub fn message_loops(&self) -> Vec<(DWORD, Arc<LoopReady>)> {
+        self.threads.iter().filter_map(|hook| hook.message_loop())
+            .chain(self.raw_input.as_ref().map(|raw_input| raw_input.message_loop()))
+            .collect()
+    }
+
+    /// Returns the error of the first hook whose message loop failed, if any.
+    pub fn loop_error(&self) -> Option<HookError> {
+        self.threads.iter().find_map(|hook| hook.loop_error())
+            .or_else(|| self.raw_input.as_ref().and_then(|raw_input| raw_input.loop_error()))
     }
 
     pub fn drop_hooks(&mut self) {
-        self.keyboard = None;
-        self.mouse = None;
+        self.threads.clear();
+        self.requested.clear();
+        self.raw_input = None;
     }
 }
 
 pub(super) static GLOBAL_CHANNEL: Lazy<HookChannels> = Lazy::new(|| HookChannels::new());
-pub(super) static GLOBAL_HOOK: Mutex<GlobalHooks> = Mutex::new(GlobalHooks{keyboard: None, mouse: None});
+pub(super) static GLOBAL_SETTINGS: HookSettings = HookSettings::new();
+pub(super) static GLOBAL_PRESSED_BUTTONS: PressedButtons = PressedButtons::new();
+pub(super) static GLOBAL_PRESSED_MODIFIERS: PressedModifiers = PressedModifiers::new();
+pub(super) static GLOBAL_PRESSED_KEYS: PressedKeys = PressedKeys::new();
+pub(super) static GLOBAL_PROBE: Probe = Probe::new();
+pub(super) static GLOBAL_LATENCY: HookLatency = HookLatency::new();
+pub(super) static GLOBAL_UNKNOWN_MESSAGES: UnknownMessages = UnknownMessages::new();
+pub(super) static GLOBAL_NEXT_HOOKS: NextHooks = NextHooks::new();
+/// The next [KeyboardEvent::seq] or [MouseEvent::seq], one counter for both, so that the order is global.
+pub(super) static GLOBAL_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+pub(super) static GLOBAL_HOOK: Mutex<GlobalHooks> = Mutex::new(GlobalHooks{threads: Vec::new(), requested: Vec::new(), raw_input: None});
+
+/// Removes the hook, also from the [GLOBAL_NEXT_HOOKS]. Returns false if `UnhookWindowsHookEx` failed.
+/// Any thread can unhook, not only the one that installed the hook.
+fn unhook(handle: HHOOK) -> bool {
+    GLOBAL_NEXT_HOOKS.forget(handle);
+    0 != unsafe { UnhookWindowsHookEx(handle) }
+}
 
+/// Installs the hooks of the given kinds in the calling thread, with the id `thread_id`. If any of them fails,
+/// the ones already installed are removed and the `GetLastError` code is returned.
+fn install_hooks(kinds: &[HookKind], scope: Option<DWORD>, thread_id: DWORD) -> Result<Vec<HHOOK>, DWORD> {
+    let mut installed = Vec::new();
+    for kind in kinds {
+        // The thread hook procedure lives in this process, so it needs no module handle - but the thread has to be of this process too
+        let hhook = unsafe { SetWindowsHookExW(kind.hook_id(scope.is_some()), kind.procedure(scope.is_some()), NULL as HINSTANCE, scope.unwrap_or(NULL as DWORD)) };
+        if hhook == NULL as HHOOK {
+            let error_code = unsafe { GetLastError() };
+            log_error!("SetWindowsHookExW failed for the {:?} hook, error code {}", kind, error_code);
+            for hhook in installed {
+                unhook(hhook);
+            }
+            return Err(error_code);
+        }
+        log_info!("Installed the {:?} hook {:#x} in the thread {}", kind, hhook as usize, thread_id);
+        GLOBAL_NEXT_HOOKS.set(*kind, hhook);
+        installed.push(hhook);
+    }
+    Ok(installed)
+}
+
+/// Numbers the event about to be queued, see [KeyboardEvent::seq].
+pub(super) fn next_seq() -> u64 {
+    GLOBAL_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The background thread running the message loop for one or more low-level hooks.
+/// Or just the hooks installed in the caller's thread, which runs the message loop on its own, see [InnerHook::on_current_thread].
 pub struct InnerHook {
-    hook_handle: Arc<Mutex<RawHook>>,
+    kinds: Vec<HookKind>,
+    hook_handles: Arc<Mutex<Vec<RawHook>>>,
     thread_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+    ready: Arc<LoopReady>,
+    /// False for the caller's thread: it must not be told to quit, nor joined
+    owns_thread: bool,
 }
 
 impl Drop for InnerHook {
     fn drop(&mut self) {
-        let (winapi_handle, thread_id) = if let Ok(inner) = self.hook_handle.lock() {
-            ((*inner).raw_handle, (*inner).thread_id)
-        } else {
-            // The hook thread panicked, apparently.
-            return;
+        // Taking the handles ensures they are unhooked only once, even if the hook thread removed them on its own.
+        // The handles stay valid even if a panic poisoned the lock, and leaving them hooked would slow down the system input.
+        let (winapi_handles, thread_id) = {
+            let mut inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let handles: Vec<HHOOK> = inner.iter_mut().filter_map(|raw| raw.take()).collect();
+            (handles, inner.first().map_or(NULL as DWORD, |raw| raw.thread_id))
         };
 
-        if winapi_handle == NULL as HHOOK || thread_id == NULL as DWORD {
-            // This handle is not associated with the valid raw hook.
+        if winapi_handles.is_empty() || thread_id == NULL as DWORD {
+            // These handles are not associated with the valid raw hooks.
+            return;
+        }
+
+        if !self.owns_thread {
+            let all_unhooked = winapi_handles.into_iter().fold(true, |all_unhooked, winapi_handle| all_unhooked & unhook(winapi_handle));
+            if all_unhooked {
+                log_info!("Uninstalled the {:?} hook(s) of the caller's thread {}", self.kinds, thread_id);
+            } else {
+                log_error!("UnhookWindowsHookEx failed for the {:?} hook(s), error code {}", self.kinds, unsafe { GetLastError() });
+            }
             return;
         }
 
         unsafe {
             // Non-null value indicates success. Something wen't wrong while unhooking.
             // This is "theoretical" scenario. Don't kill the hook thread, maybe OS won't blow up.
-            if 0 == UnhookWindowsHookEx(winapi_handle) {
+            let mut all_unhooked = true;
+            for winapi_handle in winapi_handles {
+                all_unhooked &= unhook(winapi_handle);
+            }
+            if !all_unhooked {
+                log_error!("UnhookWindowsHookEx failed for the {:?} hook(s), error code {}", self.kinds, GetLastError());
                 return;
             }
+            log_info!("Uninstalled the {:?} hook(s) of the thread {}", self.kinds, thread_id);
 
             // Again as long as OS is keeping it's side of the deal, this should never happen.
             // But just in case... we won't try to join with the thread, if anything bad DOES happen.
-            if 0 == PostThreadMessageA(thread_id, WM_QUIT, NULL as WPARAM, NULL as LPARAM) {
+            if 0 == PostThreadMessageW(thread_id, WM_QUIT, NULL as WPARAM, NULL as LPARAM) {
                 return;
             }
         }
 
         // Below ridiculous chain of calls is "necessary" to move a value out of a mutex.
         // See : https://stackoverflow.com/questions/30573188/cannot-move-data-out-of-a-mutex
-        if let Ok(mut lock) = self.thread_handle.lock() {
-            if let Some(jh) = lock.take() {
-                let _ignore_error = jh.join();
-            }
+        if let Some(jh) = self.thread_handle.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take() {
+            let _ignore_error = jh.join();
         }
     }
 }
 
+/// Sets the priority of the calling hook thread, as requested from the builder.
+/// The thread still works at the normal priority, so the failure is only logged.
+fn apply_thread_priority() {
+    let priority = GLOBAL_SETTINGS.thread_priority();
+    if priority != THREAD_PRIORITY_NORMAL as i32 && unsafe { SetThreadPriority(GetCurrentThread(), priority) } == 0 {
+        log_warn!("SetThreadPriority({}) failed, error code {}", priority, unsafe { GetLastError() });
+    }
+}
+
+/// How long [InnerHook::new] waits for its thread to install the hooks.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often [InnerHook::new] checks if its thread is still alive, while waiting for it.
+const STARTUP_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
 impl InnerHook {
-    pub fn new(hook_id: INT, handler: HOOKPROC) -> InnerHook {
+    /// Installs the hooks of the given kinds in the new thread,
+    /// or returns [HookError::WinApiError] if `SetWindowsHookExW` failed for any of them.
+    /// With the `scope`, the thread hooks are installed for that thread instead of the low-level hooks.
+    pub fn new(kinds: &[HookKind], watch_desktop_switch: bool, scope: Option<DWORD>) -> Result<InnerHook, HookError> {
+        let kinds = kinds.to_vec();
+        let installed_kinds = kinds.clone();
+
         // The raw hook data that will be set by the background thread
-        let raw_hook = Arc::new(Mutex::new(RawHook::new()));
-        let deferred_handle = raw_hook.clone();
+        let raw_hooks = Arc::new(Mutex::new(Vec::new()));
+        let deferred_handles = raw_hooks.clone();
 
-        // Used to notify the "owner" of the hook that thread started
-        let is_started = Arc::new((Mutex::new(false), Condvar::new()));
+        // Used to notify the "owner" of the hook that thread started, and whether the hooks were installed
+        let is_started = Arc::new((Mutex::new(None::<Result<(), DWORD>>), Condvar::new()));
         let set_started = is_started.clone();
 
+        let ready = LoopReady::new();
+        let set_ready = ready.clone();
+
         // Start a new thread and in that thread:
-        // - install the hook
+        // - install the hooks
         // - set the raw hook data
         // - notify the owner thread that raw hook data are available
         // - wait for the message to quit
-        let install_hook = Arc::new(Mutex::new(Some(std::thread::spawn(move || {
-            let hhook;
-            unsafe {
-                hhook = SetWindowsHookExA(hook_id, handler, NULL as HINSTANCE, NULL as DWORD);
-            }
+        let thread_name = HookKind::thread_name(&kinds).to_owned();
+        let install_hook = std::thread::Builder::new().name(thread_name).spawn(move || {
+            let thread_id = unsafe { GetCurrentThreadId() };
+            apply_thread_priority();
+            let report_failure = |error_code| {
+                let (start_lock, start_cvar) = &*set_started;
+                let mut started = start_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                *started = Some(Err(error_code));
+                start_cvar.notify_one();
+            };
+            // The hooks are bound to the desktop of the thread, so it has to be switched before they are installed
+            let _input_desktop = match InputDesktop::attach() {
+                Ok(input_desktop) => input_desktop,
+                Err(error_code) => return report_failure(error_code),
+            };
+            // Without all the hooks the caller won't get what was requested, report the failure and quit right away.
+            let installed = match install_hooks(&kinds, scope, thread_id) {
+                Ok(installed) => installed,
+                Err(error_code) => return report_failure(error_code),
+            };
 
-            // Set the HHOOK and ThreadID so that the "owner" thread can later kill hook and join with it
-            if hhook != NULL as HHOOK {
-                if let Ok(mut exclusive) = deferred_handle.lock() {
-                    exclusive.raw_handle = hhook;
-                    exclusive.thread_id = unsafe { GetCurrentThreadId() };
+            // Set the HHOOKs and ThreadID so that the "owner" thread can later kill hooks and join with it
+            {
+                let mut exclusive = deferred_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                for hhook in installed {
+                    let mut raw_hook = RawHook::new();
+                    raw_hook.set(hhook);
+                    raw_hook.thread_id = thread_id;
+                    exclusive.push(raw_hook);
                 }
             }
 
-            // Notify the "owner" thread that the hook is started
+            // Removed when the thread quits, see FocusWatch
+            let _focus_watch = watch_desktop_switch.then(FocusWatch::new);
+
+            // Notify the "owner" thread that the hooks are started
             {
                 let (start_lock, start_cvar) = &*set_started;
-                let mut started = start_lock.lock().unwrap();
-                *started = true;
+                let mut started = start_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if started.is_some() {
+                    // The owner gave up waiting, nobody would ever remove the hooks
+                    for raw_hook in deferred_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter_mut() {
+                        if let Some(handle) = raw_hook.take() {
+                            unhook(handle);
+                        }
+                    }
+                    return;
+                }
+                *started = Some(Ok(()));
                 start_cvar.notify_one();
             }
 
-            // This call keeps the hook alive until the InnerHook is dropped.
-            // GetMessageA waits for a message to this thread, blocking thread from quiting.
-            // InnerHook's Drop implementation sends the message to this thread making GetMessageA return the value.
-            // At the moment the message is received, the underlying low-level Windows hook is already "unhooked",
+            // This loop keeps the hooks alive until the InnerHook is dropped.
+            // GetMessageW waits for a message to this thread, blocking thread from quiting.
+            // InnerHook's Drop implementation sends the WM_QUIT to this thread making GetMessageW return 0.
+            // At the moment the message is received, the underlying low-level Windows hooks are already "unhooked",
             // so we simply quit and let the InnerHook's Drop implementation join with this thread.
             let mut msg = std::mem::MaybeUninit::uninit();
-            unsafe {
-                GetMessageA(
-                    msg.as_mut_ptr() as LPMSG,
-                    -1isize as HWND,  // -1 => Wait only for message to this thread specifically
-                    NULL as UINT,
-                    NULL as UINT,
-                );
+            loop {
+                let result = unsafe {
+                    GetMessageW(
+                        msg.as_mut_ptr() as LPMSG,
+                        -1isize as HWND,  // -1 => Wait only for message to this thread specifically
+                        NULL as UINT,
+                        NULL as UINT,
+                    )
+                };
+                match result {
+                    0 => break,
+                    -1 => {
+                        // The message loop is broken, so the hook procedures would not be called anymore anyway.
+                        // Remove the hooks right away (so they do not slow down the system input) and leave the error for the owner.
+                        let error_code = unsafe { GetLastError() };
+                        log_error!("GetMessageW failed in the hook thread {}, error code {}, the hooks are removed", thread_id, error_code);
+                        for raw_hook in deferred_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter_mut() {
+                            if let Some(handle) = raw_hook.take() {
+                                unhook(handle);
+                            }
+                            raw_hook.loop_error = Some(error_code);
+                        }
+                        break;
+                    },
+                    _ => set_ready.set(),
+                }
             }
-        }))));
+        }).map_err(|_| HookError::ThreadSpawnFailed)?;
 
         {
-            // Wait for the hook to start and set the value.
+            // Wait for the hooks to start and set the value.
+            // The thread may die before it gets to the notification (e.g. it panics), so the wait must not be endless.
             let (start_lock, start_cvar) = &*is_started;
-            let mut started = start_lock.lock().unwrap();
-            while !*started {
-                started = start_cvar.wait(started).unwrap();
+            let mut started = start_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let deadline = Instant::now() + STARTUP_TIMEOUT;
+            while started.is_none() {
+                let now = Instant::now();
+                let is_dead = install_hook.is_finished();
+                if is_dead || now >= deadline {
+                    log_error!("The hook thread {} before installing the hooks", if is_dead { "died" } else { "got stuck" });
+                    // Tell the thread (if it's alive) to remove the hooks it may still install
+                    *started = Some(Err(0));
+                    drop(started);
+                    if is_dead {
+                        let _ignore_error = install_hook.join();
+                    }
+                    return Err(HookError::ThreadSpawnFailed);
+                }
+                let timeout = STARTUP_CHECK_INTERVAL.min(deadline - now);
+                started = start_cvar.wait_timeout(started, timeout).unwrap_or_else(|poisoned| poisoned.into_inner()).0;
+            }
+            if let Some(Err(error_code)) = *started {
+                drop(started);
+                // The thread is already finishing, so joining won't block.
+                let _ignore_error = install_hook.join();
+                return Err(HookError::WinApiError(error_code));
             }
         }
+        let install_hook = Arc::new(Mutex::new(Some(install_hook)));
 
-        InnerHook {
-            hook_handle: raw_hook,
+        Ok(InnerHook {
+            kinds: installed_kinds,
+            hook_handles: raw_hooks,
             thread_handle: install_hook,
+            ready,
+            owns_thread: true,
+        })
+    }
+
+    /// Installs the hooks of the given kinds in the calling thread, see [crate::hook::HookBuilder::install_on_current_thread].
+    /// No thread is spawned: the caller's message loop keeps the hooks running, and dropping only unhooks them.
+    pub fn on_current_thread(kinds: &[HookKind], scope: Option<DWORD>) -> Result<InnerHook, HookError> {
+        let thread_id = unsafe { GetCurrentThreadId() };
+        let raw_hooks = install_hooks(kinds, scope, thread_id)
+            .map_err(HookError::WinApiError)?
+            .into_iter()
+            .map(|hhook| {
+                let mut raw_hook = RawHook::new();
+                raw_hook.set(hhook);
+                raw_hook.thread_id = thread_id;
+                raw_hook
+            })
+            .collect();
+        Ok(InnerHook {
+            kinds: kinds.to_vec(),
+            hook_handles: Arc::new(Mutex::new(raw_hooks)),
+            thread_handle: Arc::new(Mutex::new(None)),
+            ready: LoopReady::new(),
+            owns_thread: false,
+        })
+    }
+
+    /// Returns the hook of the given raw hooks (in the order of the `kinds`) without the thread, nothing is installed.
+    /// The handles may be fake or NULL, e.g. to test the cleanup when the unhooking fails.
+    #[cfg(test)]
+    pub fn from_raw_hooks(kinds: &[HookKind], raw_hooks: Vec<RawHook>) -> InnerHook {
+        InnerHook {
+            kinds: kinds.to_vec(),
+            hook_handles: Arc::new(Mutex::new(raw_hooks)),
+            thread_handle: Arc::new(Mutex::new(None)),
+            ready: LoopReady::new(),
+            owns_thread: true,
+        }
+    }
+
+    /// Returns the stored handles, `None` for the ones not installed or already unhooked.
+    #[cfg(test)]
+    pub fn raw_handles(&self) -> Vec<Option<HHOOK>> {
+        let inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        inner.iter().map(|raw| raw.handle()).collect()
+    }
+
+    pub fn is_installed(&self) -> bool {
+        let inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        !inner.is_empty() && inner.iter().all(|raw| raw.is_installed())
+    }
+
+    /// Unhooks the hook of the given kind, while the thread keeps running the other hooks.
+    /// The last hook of the thread must be removed by dropping the [InnerHook], so that the thread quits.
+    fn remove_hook(&mut self, kind: HookKind) -> bool {
+        let position = match self.kinds.iter().position(|installed| *installed == kind) {
+            Some(position) if self.kinds.len() > 1 => position,
+            _ => return false,
+        };
+        let mut inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if position < inner.len() {
+            if let Some(handle) = inner[position].take() {
+                if !unhook(handle) {
+                    log_error!("UnhookWindowsHookEx failed for the {:?} hook, error code {}", kind, unsafe { GetLastError() });
+                    inner[position].set(handle);
+                    GLOBAL_NEXT_HOOKS.set(kind, handle);
+                    return false;
+                }
+            }
+            inner.remove(position);
         }
+        self.kinds.remove(position);
+        log_info!("Uninstalled the {:?} hook, the {:?} hook(s) are kept", kind, self.kinds);
+        true
+    }
+
+    /// Returns the installed hooks of this thread, the raw hooks are stored in the order of the kinds.
+    fn native_hooks(&self) -> Vec<(HookKind, NativeHook)> {
+        let inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.kinds.iter().zip(inner.iter())
+            .filter_map(|(kind, raw)| raw.handle().map(|handle| (*kind, NativeHook { handle: handle as usize, thread_id: raw.thread_id })))
+            .collect()
+    }
+
+    /// Returns the id of the hook thread and its message loop, unless the thread is already gone.
+    /// The message loop of the caller's thread is not known.
+    fn message_loop(&self) -> Option<(DWORD, Arc<LoopReady>)> {
+        if !self.owns_thread {
+            return None
+        }
+        let inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        inner.first().filter(|raw| raw.is_installed()).map(|raw| (raw.thread_id, self.ready.clone()))
+    }
+
+    pub fn loop_error(&self) -> Option<HookError> {
+        let inner = self.hook_handles.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        inner.iter().find_map(|raw| raw.loop_error).map(HookError::MessageLoopFailed)
     }
 
     pub fn recv() -> Result<InputEvent, std::sync::mpsc::RecvError> {
         GLOBAL_CHANNEL.recv()
     }
 
+    pub fn recv_timeout(timeout: std::time::Duration) -> Result<InputEvent, std::sync::mpsc::RecvTimeoutError> {
+        GLOBAL_CHANNEL.recv_timeout(timeout)
+    }
+
+    #[cfg(feature = "async")]
+    pub fn register_waker(waker: &std::task::Waker) {
+        GLOBAL_CHANNEL.register_waker(waker)
+    }
+
     pub fn try_recv() -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
         GLOBAL_CHANNEL.try_recv()
     }
+
+    pub fn flush() -> usize {
+        GLOBAL_CHANNEL.drain()
+    }
+
+    pub fn overflowed() -> u64 {
+        GLOBAL_CHANNEL.overflowed()
+    }
+
+    pub fn replaced() -> u64 {
+        GLOBAL_CHANNEL.replaced()
+    }
+}
+
+#[cfg(test)]
+mod inner_hook_tests {
+    use super::*;
+
+    #[test]
+    fn hook_without_handles_is_dropped_quietly() {
+        let inner = InnerHook::from_raw_hooks(&[HookKind::Keyboard], vec![RawHook::new()]);
+        assert!(!inner.is_installed());
+        assert_eq!(inner.raw_handles(), vec![None]);
+        assert!(inner.message_loop().is_none());
+        drop(inner);
+
+        let inner = InnerHook::from_raw_hooks(&[HookKind::Keyboard], Vec::new());
+        assert!(!inner.is_installed());
+        drop(inner);
+    }
+
+    #[test]
+    fn failed_unhook_keeps_the_handle() {
+        let (keyboard, mouse) = (0x10 as HHOOK, 0x20 as HHOOK);
+        // The thread id must be set, otherwise the drop does not even try to unhook. The thread is never told to quit,
+        // as the fake handles are refused by UnhookWindowsHookEx (which just fails on them).
+        let thread_id = unsafe { GetCurrentThreadId() };
+        let mut inner = InnerHook::from_raw_hooks(
+            &[HookKind::Keyboard, HookKind::Mouse],
+            vec![RawHook::with_handle(keyboard, thread_id), RawHook::with_handle(mouse, thread_id)],
+        );
+        assert!(inner.is_installed());
+        assert_eq!(inner.native_hooks().len(), 2);
+
+        // The refused handle is stored back for the later attempt, and published again for the CallNextHookEx
+        assert!(!inner.remove_hook(HookKind::Mouse));
+        assert_eq!(inner.raw_handles(), vec![Some(keyboard), Some(mouse)]);
+        assert_eq!(GLOBAL_NEXT_HOOKS.get(HookKind::Mouse), mouse);
+
+        // The drop takes the handles and forgets them before unhooking, so even the refused ones are not passed to CallNextHookEx anymore.
+        // It gives up on the failure, without telling the thread to quit or waiting for it.
+        drop(inner);
+        assert_eq!(GLOBAL_NEXT_HOOKS.get(HookKind::Mouse), NULL as HHOOK);
+        assert_eq!(GLOBAL_NEXT_HOOKS.get(HookKind::Keyboard), NULL as HHOOK);
+
+        // The other tests expect no hook to be published
+        GLOBAL_NEXT_HOOKS.forget(keyboard);
+        GLOBAL_NEXT_HOOKS.forget(mouse);
+    }
+
+    #[test]
+    fn failed_reinstall_is_retried() {
+        // The thread id is not set, so the fake hooks are dropped without unhooking anything
+        let fake = |kinds: &[HookKind]| InnerHook::from_raw_hooks(kinds, kinds.iter().map(|_| RawHook::with_handle(0x30 as HHOOK, NULL as DWORD)).collect());
+        let mut global_hooks = GlobalHooks { threads: vec![fake(&[HookKind::Keyboard, HookKind::Mouse])], requested: vec![HookKind::Keyboard, HookKind::Mouse], raw_input: None };
+        assert!(global_hooks.is_installed());
+
+        assert_eq!(global_hooks.reinstall_with(|_, _| Err(HookError::ThreadSpawnFailed)), Err(HookError::ThreadSpawnFailed));
+        assert!(global_hooks.threads.is_empty());
+        assert!(!global_hooks.is_installed());
+        // The hook is still owned, no other may be built meanwhile
+        assert!(global_hooks.is_any_hook_present());
+
+        let mut retried = Vec::new();
+        assert_eq!(global_hooks.reinstall_with(|global_hooks, kinds| {
+            retried = kinds.to_vec();
+            global_hooks.threads.push(fake(kinds));
+            Ok(())
+        }), Ok(()));
+        assert_eq!(retried, vec![HookKind::Keyboard, HookKind::Mouse]);
+        assert!(global_hooks.is_installed());
+
+        global_hooks.drop_hooks();
+        assert!(!global_hooks.is_any_hook_present());
+    }
+
+    #[test]
+    fn poisoned_global_lock_is_recovered() {
+        let _ = std::thread::spawn(|| {
+            let _global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            panic!("poisoning the global lock");
+        }).join();
+        assert!(GLOBAL_HOOK.is_poisoned());
+
+        // The public API keeps reporting the state instead of panicking
+        let installed = crate::hook::installed_hooks();
+        assert!(!installed.keyboard && !installed.mouse);
+        // No real hook is installed here: the procedures of the lib tests call the mocked CallNextHookEx
+        assert_eq!(crate::hook::HookBuilder::new().try_build().err(), Some(HookError::NoHookRequested));
+        assert!(!GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).is_any_hook_present());
+    }
 }
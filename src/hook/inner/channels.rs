@@ -1,43 +1,578 @@
-use std::sync::Mutex;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+use std::task::Waker;
+use std::time::{Duration, Instant};
 
 use crate::event::*;
+use crate::hook::OverflowPolicy;
 
+/// The queue of events between the hook procedures and the consumer.
+/// It works like an mpsc channel, but it can be bounded with the [OverflowPolicy] applied when it is full.
+/// Unlike the mpsc channel it does not allocate per event: the ring buffer only grows when it is full,
+/// and the bounded queue is allocated up-front, so the hook procedures never allocate.
+/// The same goes for the queues of the [Subscription]s: the bounded ones are allocated when subscribing,
+/// the unbounded ones grow (and allocate) in the hook procedures when they are full.
 pub struct HookChannels {
-    keyboard_sender: Mutex<Sender<InputEvent>>,
-    mouse_sender: Mutex<Sender<InputEvent>>,
-    receiver: Mutex<Receiver<InputEvent>>,
+    queue: Mutex<EventQueue>,
+    available: Condvar,
 }
 
+struct EventQueue {
+    events: VecDeque<InputEvent>,
+    capacity: Option<usize>,
+    policy: OverflowPolicy,
+    /// The events discarded because the queue was full, see [crate::Hook::overflowed_events]
+    overflowed: u64,
+    /// The events replaced by the newer ones with the [OverflowPolicy::KeepLatestOnly], see [crate::Hook::dropped_count]
+    replaced: u64,
+    /// Blocking receives return immediately, see [crate::hook::CancellationHandle]
+    cancelled: bool,
+    /// Distinguishes the hooks built one after another, so that a stale handle does not cancel the new hook
+    generation: u64,
+    /// The task waiting for the next event, see [crate::stream::HookStream]
+    waker: Option<Waker>,
+    /// See [crate::hook::EventReceiver], the dropped ones are removed on the next event
+    subscribers: Vec<Weak<Subscription>>,
+}
+
+/// The queue of a single [crate::hook::EventReceiver], getting a copy of every event sent to the [HookChannels].
+pub struct Subscription {
+    queue: Mutex<SubscriptionQueue>,
+    available: Condvar,
+}
+
+struct SubscriptionQueue {
+    events: VecDeque<InputEvent>,
+    capacity: Option<usize>,
+    /// The hook is gone, the receives fail once the queued events are consumed
+    disconnected: bool,
+}
+
+// The rejected event is returned by value, like the mpsc channel does: boxing it would allocate in the hook procedures
+#[allow(clippy::result_large_err)]
 impl HookChannels {
     pub fn new() -> HookChannels {
-        let (s, r) = channel();
         HookChannels {
-            keyboard_sender: Mutex::new(s.clone()),
-            mouse_sender: Mutex::new(s.clone()),
-            receiver: Mutex::new(r),
+            queue: Mutex::new(EventQueue {
+                events: VecDeque::new(),
+                capacity: None,
+                policy: OverflowPolicy::DropOldest,
+                overflowed: 0,
+                replaced: 0,
+                cancelled: false,
+                generation: 0,
+                waker: None,
+                subscribers: Vec::new(),
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Sets the capacity (`None` for unbounded) and the overflow policy for the events sent from now on, and restarts the overflow and replacement counts.
+    pub fn configure(&self, capacity: Option<usize>, policy: OverflowPolicy) {
+        let mut queue = self.lock();
+        queue.capacity = capacity;
+        queue.policy = policy;
+        queue.overflowed = 0;
+        queue.replaced = 0;
+        if let Some(capacity) = capacity {
+            // The queue is drained when the previous hook is dropped, so at most `capacity` events are stored from now on
+            queue.events.shrink_to(capacity);
+            let missing = capacity.saturating_sub(queue.events.len());
+            queue.events.reserve_exact(missing);
+        }
+    }
+
+    /// Starts the new generation of receivers, that are not cancelled. Returns the generation.
+    pub fn renew_cancellation(&self) -> u64 {
+        let mut queue = self.lock();
+        queue.cancelled = false;
+        queue.generation = queue.generation.wrapping_add(1);
+        queue.generation
+    }
+
+    /// Cancels all the blocking receives of the given generation, the ones in progress and the future ones.
+    pub fn cancel(&self, generation: u64) {
+        let mut queue = self.lock();
+        if queue.generation == generation {
+            queue.cancelled = true;
+            self.available.notify_all();
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self, generation: u64) -> bool {
+        let queue = self.lock();
+        queue.generation == generation && queue.cancelled
+    }
+
+    pub fn send_keyboard_event(&self, ke: KeyboardEvent) -> Result<(), SendError<InputEvent>> {
+        self.send(InputEvent::Keyboard(ke))
+    }
+
+    pub fn send_mouse_event(&self, me: MouseEvent) -> Result<(), SendError<InputEvent>>  {
+        self.send(InputEvent::Mouse(me))
+    }
+
+    pub fn send_reset_event(&self) -> Result<(), SendError<InputEvent>>  {
+        self.send(InputEvent::Reset)
+    }
+
+    /// Queues the event, or returns it back if there was no room for it. Either way the event discarded for the lack of room is counted.
+    /// The subscribers get their copy regardless, as their queues are independent.
+    fn send(&self, event: InputEvent) -> Result<(), SendError<InputEvent>> {
+        let mut queue = self.lock();
+        if !queue.subscribers.is_empty() {
+            queue.subscribers.retain(|subscriber| match subscriber.upgrade() {
+                Some(subscriber) => {
+                    subscriber.push(event.clone());
+                    true
+                },
+                None => false,
+            });
+        }
+        match queue.policy {
+            OverflowPolicy::KeepLatestOnly => {
+                let kind = EventKind::of(&event);
+                let queued = queue.events.len();
+                queue.events.retain(|queued| EventKind::of(queued) != kind);
+                queue.replaced += (queued - queue.events.len()) as u64;
+            },
+            OverflowPolicy::DropOldest => {
+                if queue.is_full() {
+                    queue.overflow();
+                    // With zero capacity there is nothing to make room in
+                    if queue.events.pop_front().is_none() {
+                        return Err(SendError(event));
+                    }
+                }
+            },
+            OverflowPolicy::DropNewest => {
+                if queue.is_full() {
+                    queue.overflow();
+                    return Err(SendError(event));
+                }
+            },
+        }
+        queue.events.push_back(event);
+        self.available.notify_one();
+        let waker = queue.waker.take();
+        drop(queue);
+        // Waking only schedules the task, it does not run it in the hook procedure
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Wakes the `waker` with the next queued event (or the cancellation), replacing the previously registered one.
+    #[cfg(any(test, feature = "async"))]
+    pub fn register_waker(&self, waker: &Waker) {
+        let mut queue = self.lock();
+        match &queue.waker {
+            Some(registered) if registered.will_wake(waker) => (),
+            _ => queue.waker = Some(waker.clone()),
+        }
+    }
+
+    pub fn recv(&self) -> Result<InputEvent, RecvError> {
+        let mut queue = self.lock();
+        loop {
+            if queue.cancelled {
+                return Err(RecvError)
+            }
+            if let Some(event) = queue.events.pop_front() {
+                return Ok(event)
+            }
+            queue = self.available.wait(queue).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<InputEvent, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut queue = self.lock();
+        loop {
+            if queue.cancelled {
+                return Err(RecvTimeoutError::Disconnected)
+            }
+            if let Some(event) = queue.events.pop_front() {
+                return Ok(event)
+            }
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => timeout,
+            };
+            if remaining.is_zero() {
+                return Err(RecvTimeoutError::Timeout)
+            }
+            queue = self.available.wait_timeout(queue, remaining).unwrap_or_else(|poisoned| poisoned.into_inner()).0;
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<InputEvent, TryRecvError> {
+        self.lock().events.pop_front().ok_or(TryRecvError::Empty)
+    }
+
+    /// Returns how many events were discarded because the queue was full, since the last [HookChannels::configure].
+    pub fn overflowed(&self) -> u64 {
+        self.lock().overflowed
+    }
+
+    /// Returns how many events were replaced with the [OverflowPolicy::KeepLatestOnly], since the last [HookChannels::configure].
+    pub fn replaced(&self) -> u64 {
+        self.lock().replaced
+    }
+
+    /// Discards all the queued events and returns how many there were.
+    pub fn drain(&self) -> usize {
+        self.lock().events.drain(..).count()
+    }
+
+    /// Drops all the queued events together with the memory allocated for them, when the hook is dropped.
+    /// The hook procedures have no place to keep the queue of their own, so the queue itself stays for the next hook.
+    /// The subscribers are disconnected, but they keep the events they have not received yet.
+    pub fn release(&self) {
+        let mut queue = self.lock();
+        queue.events = VecDeque::new();
+        queue.waker = None;
+        for subscriber in queue.subscribers.drain(..).filter_map(|subscriber| subscriber.upgrade()) {
+            subscriber.disconnect();
+        }
+    }
+
+    /// Starts a new subscription, getting every event sent from now on.
+    /// The `copy_of` subscription (if any) gives the events it has not received yet, so that the new one continues from the same point.
+    /// A copy of a disconnected subscription is disconnected as well.
+    pub fn subscribe(&self, copy_of: Option<&Subscription>) -> Arc<Subscription> {
+        let mut queue = self.lock();
+        let (mut events, disconnected) = match copy_of {
+            Some(original) => {
+                let original = original.lock();
+                (original.events.clone(), original.disconnected)
+            },
+            None => (VecDeque::new(), false),
+        };
+        if let Some(capacity) = queue.capacity {
+            // Pushing to the full queue makes room first, so it never grows past this
+            events.reserve_exact(capacity.saturating_sub(events.len()));
+        }
+        let subscription = Arc::new(Subscription {
+            queue: Mutex::new(SubscriptionQueue { events, capacity: queue.capacity, disconnected }),
+            available: Condvar::new(),
+        });
+        if !disconnected {
+            queue.subscribers.push(Arc::downgrade(&subscription));
+        }
+        subscription
+    }
+
+    /// Returns a subscription that is disconnected right away, for the hooks that are already shut down.
+    pub fn disconnected_subscription() -> Arc<Subscription> {
+        Arc::new(Subscription {
+            queue: Mutex::new(SubscriptionQueue { events: VecDeque::new(), capacity: None, disconnected: true }),
+            available: Condvar::new(),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, EventQueue> {
+        // The queue is always consistent, even if some thread panicked while holding the lock
+        self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Subscription {
+    /// Queues the event, dropping the oldest one if the queue is full, as a slow subscriber must not block the others.
+    fn push(&self, event: InputEvent) {
+        let mut queue = self.lock();
+        if matches!(queue.capacity, Some(capacity) if queue.events.len() >= capacity) && queue.events.pop_front().is_none() {
+            return
+        }
+        queue.events.push_back(event);
+        self.available.notify_one();
+    }
+
+    fn disconnect(&self) {
+        self.lock().disconnected = true;
+        self.available.notify_all();
+    }
+
+    pub fn recv(&self) -> Result<InputEvent, RecvError> {
+        let mut queue = self.lock();
+        loop {
+            if let Some(event) = queue.events.pop_front() {
+                return Ok(event)
+            }
+            if queue.disconnected {
+                return Err(RecvError)
+            }
+            queue = self.available.wait(queue).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<InputEvent, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut queue = self.lock();
+        loop {
+            if let Some(event) = queue.events.pop_front() {
+                return Ok(event)
+            }
+            if queue.disconnected {
+                return Err(RecvTimeoutError::Disconnected)
+            }
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => timeout,
+            };
+            if remaining.is_zero() {
+                return Err(RecvTimeoutError::Timeout)
+            }
+            queue = self.available.wait_timeout(queue, remaining).unwrap_or_else(|poisoned| poisoned.into_inner()).0;
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<InputEvent, TryRecvError> {
+        let mut queue = self.lock();
+        match queue.events.pop_front() {
+            Some(event) => Ok(event),
+            None if queue.disconnected => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SubscriptionQueue> {
+        self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl EventQueue {
+    fn is_full(&self) -> bool {
+        matches!(self.capacity, Some(capacity) if self.events.len() >= capacity)
+    }
+
+    /// Counts the discarded event. Only the first one is logged, so that the slow consumer is not slowed down even more.
+    fn overflow(&mut self) {
+        self.overflowed += 1;
+        if self.overflowed == 1 {
+            log_warn!("The queue of {} events is full, discarding the events with {:?}", self.capacity.unwrap_or_default(), self.policy);
+        }
+    }
+}
+
+/// The granularity of the [OverflowPolicy::KeepLatestOnly]
+#[derive(PartialEq)]
+struct EventKind(std::mem::Discriminant<InputEvent>, Option<std::mem::Discriminant<MouseEventType>>);
+
+impl EventKind {
+    fn of(event: &InputEvent) -> EventKind {
+        let mouse_kind = match event {
+            InputEvent::Mouse(me) => Some(std::mem::discriminant(&me.event)),
+            _ => None,
+        };
+        EventKind(std::mem::discriminant(event), mouse_kind)
+    }
+}
+
+#[cfg(test)]
+mod hook_channels_tests {
+    use super::*;
+
+    fn a_move(x: i32) -> MouseEvent {
+        MouseEvent::for_test(MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x, y: 0 }), delta: None }))
+    }
+
+    fn a_key(pressed: KeyPress) -> KeyboardEvent {
+        KeyboardEvent::for_test(pressed, Some(KeyboardKey::A))
+    }
+
+    fn received(channels: &HookChannels) -> Vec<InputEvent> {
+        std::iter::from_fn(|| channels.try_recv().ok()).collect()
+    }
+
+    #[test]
+    fn unbounded_by_default() {
+        let channels = HookChannels::new();
+        for x in 0..1000 {
+            assert!(channels.send_mouse_event(a_move(x)).is_ok());
+        }
+        assert_eq!(channels.drain(), 1000);
+        assert_eq!(channels.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_last_events() {
+        let channels = HookChannels::new();
+        channels.configure(Some(2), OverflowPolicy::DropOldest);
+        for x in 0..5 {
+            assert!(channels.send_mouse_event(a_move(x)).is_ok());
         }
+        assert_eq!(received(&channels), vec![InputEvent::Mouse(a_move(3)), InputEvent::Mouse(a_move(4))]);
+        assert_eq!(channels.overflowed(), 3);
+        channels.configure(Some(2), OverflowPolicy::DropOldest);
+        assert_eq!(channels.overflowed(), 0);
+    }
+
+    #[test]
+    fn bounded_queue_is_allocated_up_front() {
+        let channels = HookChannels::new();
+        channels.configure(Some(64), OverflowPolicy::DropOldest);
+        let allocated = channels.lock().events.capacity();
+        assert!(allocated >= 64);
+        for x in 0..1000 {
+            assert!(channels.send_mouse_event(a_move(x)).is_ok());
+        }
+        assert_eq!(channels.lock().events.capacity(), allocated);
+    }
+
+    #[test]
+    fn bounded_subscription_is_allocated_up_front() {
+        let channels = HookChannels::new();
+        channels.configure(Some(64), OverflowPolicy::DropOldest);
+        let subscription = channels.subscribe(None);
+        let allocated = subscription.lock().events.capacity();
+        assert!(allocated >= 64);
+        for x in 0..1000 {
+            assert!(channels.send_mouse_event(a_move(x)).is_ok());
+        }
+        assert_eq!(subscription.lock().events.capacity(), allocated);
+        assert_eq!(subscription.lock().events.len(), 64);
+    }
+
+    #[test]
+    fn release_frees_the_queue() {
+        let channels = HookChannels::new();
+        channels.configure(Some(64), OverflowPolicy::DropOldest);
+        assert!(channels.send_reset_event().is_ok());
+        channels.release();
+        assert_eq!(channels.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(channels.lock().events.capacity(), 0);
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_first_events() {
+        let channels = HookChannels::new();
+        channels.configure(Some(2), OverflowPolicy::DropNewest);
+        assert!(channels.send_mouse_event(a_move(0)).is_ok());
+        assert!(channels.send_mouse_event(a_move(1)).is_ok());
+        assert!(channels.send_mouse_event(a_move(2)).is_err());
+        assert_eq!(received(&channels), vec![InputEvent::Mouse(a_move(0)), InputEvent::Mouse(a_move(1))]);
+        assert_eq!(channels.overflowed(), 1);
     }
 
-    pub fn send_keyboard_event(&self, ke: KeyboardEvent) -> Result<(), std::sync::mpsc::SendError<InputEvent>> {
-        self.keyboard_sender.lock().unwrap().send(InputEvent::Keyboard(ke))
+    #[test]
+    fn keep_latest_only_keeps_one_event_per_kind() {
+        use crate::event::IsSystemKeyPress::Normal;
+        let channels = HookChannels::new();
+        channels.configure(None, OverflowPolicy::KeepLatestOnly);
+        assert!(channels.send_mouse_event(a_move(0)).is_ok());
+        assert!(channels.send_keyboard_event(a_key(KeyPress::Down(Normal))).is_ok());
+        assert!(channels.send_mouse_event(a_move(1)).is_ok());
+        assert!(channels.send_keyboard_event(a_key(KeyPress::Up(Normal))).is_ok());
+        assert!(channels.send_reset_event().is_ok());
+        assert_eq!(received(&channels), vec![
+            InputEvent::Mouse(a_move(1)),
+            InputEvent::Keyboard(a_key(KeyPress::Up(Normal))),
+            InputEvent::Reset,
+        ]);
+        assert_eq!(channels.replaced(), 2);
+        assert_eq!(channels.overflowed(), 0);
     }
 
-    pub fn send_mouse_event(&self, me: MouseEvent) -> Result<(), std::sync::mpsc::SendError<InputEvent>>  {
-        self.mouse_sender.lock().unwrap().send(InputEvent::Mouse(me))
+    #[test]
+    fn cancel_unblocks_receivers_of_the_same_generation() {
+        let channels = std::sync::Arc::new(HookChannels::new());
+        let generation = channels.renew_cancellation();
+
+        let receiver = channels.clone();
+        let blocked = std::thread::spawn(move || receiver.recv());
+        // A stale handle does not cancel anything
+        channels.cancel(generation.wrapping_sub(1));
+        assert!(!channels.is_cancelled(generation));
+
+        channels.cancel(generation);
+        assert_eq!(blocked.join().unwrap(), Err(RecvError));
+        assert!(channels.is_cancelled(generation));
+        assert_eq!(channels.recv_timeout(Duration::from_secs(1)), Err(RecvTimeoutError::Disconnected));
+
+        let generation = channels.renew_cancellation();
+        assert!(!channels.is_cancelled(generation));
+        assert_eq!(channels.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Timeout));
     }
 
-    pub fn recv(&self) -> Result<InputEvent, std::sync::mpsc::RecvError> {
-        self.receiver.lock().unwrap().recv()
+    #[test]
+    fn registered_waker_is_woken_once() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountingWaker(AtomicUsize);
+        impl std::task::Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let channels = HookChannels::new();
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        channels.register_waker(&Waker::from(counter.clone()));
+        assert!(channels.send_reset_event().is_ok());
+        assert!(channels.send_reset_event().is_ok());
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+
+        let generation = channels.renew_cancellation();
+        channels.register_waker(&Waker::from(counter.clone()));
+        channels.cancel(generation);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
     }
 
-    pub fn try_recv(&self) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
-        self.receiver.lock().unwrap().try_recv()
+    #[test]
+    fn every_subscriber_gets_every_event() {
+        let channels = HookChannels::new();
+        channels.configure(Some(1), OverflowPolicy::DropNewest);
+        assert!(channels.send_mouse_event(a_move(0)).is_ok());
+
+        let first = channels.subscribe(None);
+        let second = channels.subscribe(None);
+        assert!(channels.send_mouse_event(a_move(1)).is_err());
+        // The main queue is full, but the subscribers get their copy
+        assert_eq!(first.try_recv(), Ok(InputEvent::Mouse(a_move(1))));
+        assert_eq!(second.try_recv(), Ok(InputEvent::Mouse(a_move(1))));
+
+        drop(first);
+        assert!(channels.send_mouse_event(a_move(2)).is_err());
+        assert_eq!(second.try_recv(), Ok(InputEvent::Mouse(a_move(2))));
+        assert_eq!(channels.lock().subscribers.len(), 1);
+        assert_eq!(received(&channels), vec![InputEvent::Mouse(a_move(0))]);
     }
 
-    pub fn drain(&self) {
-        let guarded = self.receiver.lock().unwrap();
-        let r: &Receiver<InputEvent> = &*guarded;
-        while let Ok(_) = r.try_recv() {}
+    #[test]
+    fn copied_subscription_continues_from_the_same_point() {
+        let channels = HookChannels::new();
+        let original = channels.subscribe(None);
+        assert!(channels.send_reset_event().is_ok());
+
+        let copy = channels.subscribe(Some(&original));
+        assert!(channels.send_mouse_event(a_move(0)).is_ok());
+        assert_eq!(original.try_recv(), Ok(InputEvent::Reset));
+        assert_eq!(copy.try_recv(), Ok(InputEvent::Reset));
+        assert_eq!(copy.try_recv(), Ok(InputEvent::Mouse(a_move(0))));
+        assert_eq!(copy.try_recv(), Err(TryRecvError::Empty));
+
+        // The queued events are still delivered after the hook is gone
+        channels.release();
+        assert_eq!(original.recv(), Ok(InputEvent::Mouse(a_move(0))));
+        assert_eq!(original.recv(), Err(RecvError));
+        assert_eq!(copy.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(channels.subscribe(Some(&copy)).recv_timeout(Duration::from_secs(1)), Err(RecvTimeoutError::Disconnected));
+        assert!(channels.lock().subscribers.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn recv_timeout_times_out_on_empty_queue() {
+        let channels = HookChannels::new();
+        assert_eq!(channels.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Timeout));
+        assert!(channels.send_reset_event().is_ok());
+        assert_eq!(channels.recv_timeout(Duration::from_millis(10)), Ok(InputEvent::Reset));
+    }
+}
@@ -0,0 +1,191 @@
+use winapi::shared::minwindef::{DWORD, FALSE, HMODULE, LPVOID};
+use winapi::shared::ntdef::{HANDLE, NULL};
+use winapi::shared::windef::{HDESK, HWND, HWINEVENTHOOK};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::processthreadsapi::{GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId, OpenProcess, OpenProcessToken};
+use winapi::um::securitybaseapi::{GetSidSubAuthority, GetSidSubAuthorityCount, GetTokenInformation};
+use winapi::um::winnt::{TokenIntegrityLevel, PROCESS_QUERY_LIMITED_INFORMATION, TOKEN_MANDATORY_LABEL, TOKEN_QUERY};
+use winapi::um::winuser::{
+    CloseDesktop, GetThreadDesktop, OpenInputDesktop, SetThreadDesktop, DESKTOP_HOOKCONTROL, DESKTOP_READOBJECTS, DESKTOP_CREATEWINDOW,
+    GetForegroundWindow, GetWindowThreadProcessId, SetWinEventHook, UnhookWinEvent, WINEVENTPROC,
+    GetProcessWindowStation, GetUserObjectInformationW, USEROBJECTFLAGS, UOI_FLAGS, WSF_VISIBLE,
+    EVENT_SYSTEM_DESKTOPSWITCH, EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT,
+};
+
+use crate::hook::inner::GLOBAL_SETTINGS;
+use crate::hook::inner::low_level::{desktop_switch_procedure, foreground_procedure, set_foreground_elevated};
+
+/// The desktop receiving the user input, assigned to the hook thread, see [crate::hook::HookBuilder::on_input_desktop].
+/// Dropping it assigns the thread back to its previous desktop, as the desktop in use by a thread can't be closed.
+pub struct InputDesktop {
+    desktop: HDESK,
+    previous: HDESK,
+}
+
+impl InputDesktop {
+    /// Assigns the calling thread to the input desktop, if it was requested. It must be called before the thread installs
+    /// any hook or creates any window. Returns the `GetLastError` code if the desktop could not be opened or assigned.
+    pub fn attach() -> Result<Option<InputDesktop>, DWORD> {
+        if !GLOBAL_SETTINGS.is_input_desktop_requested() {
+            return Ok(None)
+        }
+        unsafe {
+            let previous = GetThreadDesktop(GetCurrentThreadId());
+            let desktop = OpenInputDesktop(0, FALSE, DESKTOP_HOOKCONTROL | DESKTOP_READOBJECTS | DESKTOP_CREATEWINDOW);
+            if desktop == NULL as HDESK {
+                let error_code = GetLastError();
+                log_error!("OpenInputDesktop failed, error code {}", error_code);
+                return Err(error_code)
+            }
+            if SetThreadDesktop(desktop) == FALSE {
+                let error_code = GetLastError();
+                log_error!("SetThreadDesktop failed for the input desktop, error code {}", error_code);
+                CloseDesktop(desktop);
+                return Err(error_code)
+            }
+            log_info!("Assigned the thread {} to the input desktop", GetCurrentThreadId());
+            Ok(Some(InputDesktop { desktop, previous }))
+        }
+    }
+}
+
+impl Drop for InputDesktop {
+    fn drop(&mut self) {
+        // The hooks and windows are gone by now, otherwise the thread could not switch the desktop, and the handle would leak
+        unsafe {
+            if self.previous != NULL as HDESK && SetThreadDesktop(self.previous) != FALSE {
+                CloseDesktop(self.desktop);
+            }
+        }
+    }
+}
+
+/// Returns false if the process runs on a window station that can't receive the user input (e.g. the one of a service,
+/// or of a scheduled task that runs whether the user is logged on or not), where the hooks would install but never get any event.
+/// When it can't be told, the window station is assumed to be interactive.
+pub fn is_interactive_window_station() -> bool {
+    unsafe {
+        let station = GetProcessWindowStation();
+        if station.is_null() {
+            return true
+        }
+        let mut flags: USEROBJECTFLAGS = std::mem::zeroed();
+        let size = std::mem::size_of::<USEROBJECTFLAGS>() as DWORD;
+        if GetUserObjectInformationW(station as HANDLE, UOI_FLAGS as i32, &mut flags as *mut _ as LPVOID, size, NULL as *mut DWORD) == FALSE {
+            log_warn!("GetUserObjectInformationW failed for the window station, error code {}", GetLastError());
+            return true
+        }
+        flags.dwFlags & WSF_VISIBLE != 0
+    }
+}
+
+/// The WinEvent hooks reporting that the low-level hooks may have missed some input, see [crate::hook::HookBuilder::emit_reset_on_focus_loss]:
+/// the desktop switches, and the focus moving to or from the window of a process with a higher integrity level.
+/// The out-of-context WinEvents are delivered through the message loop of the thread that installed them,
+/// so they live next to the low-level hooks, instead of in a thread on their own. Dropping it removes them, on the same thread.
+pub struct FocusWatch {
+    win_event_hooks: Vec<HWINEVENTHOOK>,
+}
+
+impl FocusWatch {
+    pub fn new() -> FocusWatch {
+        let procedures: [(DWORD, WINEVENTPROC); 2] = [
+            (EVENT_SYSTEM_DESKTOPSWITCH, Some(desktop_switch_procedure)),
+            (EVENT_SYSTEM_FOREGROUND, Some(foreground_procedure)),
+        ];
+        let win_event_hooks = procedures.into_iter().filter_map(|(event, procedure)| {
+            let win_event_hook = unsafe {
+                SetWinEventHook(
+                    event,
+                    event,
+                    NULL as HMODULE,
+                    procedure,
+                    NULL as DWORD,  // All processes...
+                    NULL as DWORD,  // ...and all threads
+                    WINEVENT_OUTOFCONTEXT,
+                )
+            };
+            if win_event_hook.is_null() {
+                log_warn!("SetWinEventHook failed for the event {:#x}, error code {}", event, unsafe { GetLastError() });
+            }
+            (!win_event_hook.is_null()).then_some(win_event_hook)
+        }).collect();
+        // The reset is sent on the change, so the window focused at the start is the reference
+        set_foreground_elevated(is_higher_integrity_window(unsafe { GetForegroundWindow() }));
+        FocusWatch { win_event_hooks }
+    }
+}
+
+impl Drop for FocusWatch {
+    fn drop(&mut self) {
+        for win_event_hook in self.win_event_hooks.drain(..) {
+            unsafe { UnhookWinEvent(win_event_hook); }
+        }
+    }
+}
+
+/// True if the window belongs to a process running at a higher integrity level than this one (e.g. "Run as administrator"),
+/// whose input the low-level hooks of this process may not see. When it can't be told, the window is assumed to be higher.
+pub fn is_higher_integrity_window(window: HWND) -> bool {
+    if window.is_null() {
+        return false
+    }
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(window, &mut pid) };
+    if pid == 0 || pid == unsafe { GetCurrentProcessId() } {
+        return false
+    }
+    let own = unsafe { integrity_level(GetCurrentProcess()) };
+    let theirs = unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+        if process.is_null() {
+            None
+        } else {
+            let level = integrity_level(process);
+            CloseHandle(process);
+            level
+        }
+    };
+    match (own, theirs) {
+        (Some(own), Some(theirs)) => theirs > own,
+        // The token of the elevated process can't be opened by the non-elevated one
+        _ => true,
+    }
+}
+
+/// Returns the mandatory integrity level RID (e.g. `SECURITY_MANDATORY_MEDIUM_RID`) of the process.
+unsafe fn integrity_level(process: HANDLE) -> Option<DWORD> {
+    let mut token = NULL as HANDLE;
+    if OpenProcessToken(process, TOKEN_QUERY, &mut token) == FALSE {
+        return None
+    }
+    let mut size = 0;
+    GetTokenInformation(token, TokenIntegrityLevel, NULL as LPVOID, 0, &mut size);
+    // Aligned for the pointer in the TOKEN_MANDATORY_LABEL
+    let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+    let queried = GetTokenInformation(token, TokenIntegrityLevel, buffer.as_mut_ptr() as LPVOID, size, &mut size);
+    CloseHandle(token);
+    if queried == FALSE || buffer.is_empty() {
+        return None
+    }
+    let sid = (*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL)).Label.Sid;
+    let count = *GetSidSubAuthorityCount(sid);
+    if count == 0 {
+        return None
+    }
+    Some(*GetSidSubAuthority(sid, (count - 1) as DWORD))
+}
+
+#[cfg(test)]
+mod desktop_tests {
+    use winapi::shared::windef::HWND;
+    use winapi::shared::ntdef::NULL;
+
+    use super::is_higher_integrity_window;
+
+    #[test]
+    fn no_window_is_not_higher() {
+        assert!(!is_higher_integrity_window(NULL as HWND));
+    }
+}
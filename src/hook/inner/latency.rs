@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use winapi::um::profileapi::{QueryPerformanceCounter, QueryPerformanceFrequency};
+use winapi::um::winnt::LARGE_INTEGER;
+
+use crate::hook::LatencyStats;
+
+/// The time spent by the hook procedures on the events, see [crate::hook::HookBuilder::with_latency_stats].
+/// It is measured in the performance counter ticks, which are cheap to read, and converted only when the stats are requested.
+/// Lock-free, as it is updated inside the hook procedures.
+pub struct HookLatency {
+    enabled: AtomicBool,
+    events: AtomicU64,
+    total_ticks: AtomicU64,
+    max_ticks: AtomicU64,
+}
+
+impl HookLatency {
+    pub const fn new() -> HookLatency {
+        HookLatency {
+            enabled: AtomicBool::new(false),
+            events: AtomicU64::new(0),
+            total_ticks: AtomicU64::new(0),
+            max_ticks: AtomicU64::new(0),
+        }
+    }
+
+    /// Enables or disables the measurement and forgets the measured events.
+    pub fn configure(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        self.events.store(0, Ordering::Relaxed);
+        self.total_ticks.store(0, Ordering::Relaxed);
+        self.max_ticks.store(0, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the start of the measurement of one event, `None` if it is not measured.
+    pub fn start(&self) -> Option<u64> {
+        self.is_enabled().then(performance_counter)
+    }
+
+    /// Completes the measurement started with [HookLatency::start].
+    pub fn record(&self, started: Option<u64>) {
+        if let Some(started) = started {
+            self.add(performance_counter().saturating_sub(started));
+        }
+    }
+
+    fn add(&self, ticks: u64) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        self.total_ticks.fetch_add(ticks, Ordering::Relaxed);
+        self.max_ticks.fetch_max(ticks, Ordering::Relaxed);
+    }
+
+    /// The stats of the events measured so far. The fields are read one by one, so an event measured meanwhile may be counted only in some of them.
+    pub fn stats(&self) -> LatencyStats {
+        let frequency = performance_frequency();
+        let events = self.events.load(Ordering::Relaxed);
+        let total_ticks = self.total_ticks.load(Ordering::Relaxed);
+        LatencyStats {
+            events,
+            average: total_ticks.checked_div(events).map_or(Duration::ZERO, |ticks| ticks_to_duration(ticks, frequency)),
+            max: ticks_to_duration(self.max_ticks.load(Ordering::Relaxed), frequency),
+        }
+    }
+}
+
+fn performance_counter() -> u64 {
+    unsafe {
+        let mut counter: LARGE_INTEGER = std::mem::zeroed();
+        // It can't fail since Windows XP
+        QueryPerformanceCounter(&mut counter);
+        *counter.QuadPart() as u64
+    }
+}
+
+fn performance_frequency() -> u64 {
+    unsafe {
+        let mut frequency: LARGE_INTEGER = std::mem::zeroed();
+        QueryPerformanceFrequency(&mut frequency);
+        *frequency.QuadPart() as u64
+    }
+}
+
+fn ticks_to_duration(ticks: u64, frequency: u64) -> Duration {
+    if frequency == 0 {
+        return Duration::ZERO
+    }
+    Duration::from_nanos((ticks as u128 * 1_000_000_000 / frequency as u128) as u64)
+}
+
+#[cfg(test)]
+mod latency_tests {
+    use std::time::Duration;
+
+    use super::{HookLatency, ticks_to_duration};
+
+    #[test]
+    fn nothing_is_measured_when_disabled() {
+        let latency = HookLatency::new();
+        let started = latency.start();
+        assert_eq!(started, None);
+        latency.record(started);
+        assert_eq!(latency.stats().events, 0);
+    }
+
+    #[test]
+    fn average_and_max_are_tracked() {
+        let latency = HookLatency::new();
+        latency.configure(true);
+        latency.add(10);
+        latency.add(30);
+        latency.add(20);
+        let stats = latency.stats();
+        assert_eq!(stats.events, 3);
+        assert!(stats.max >= stats.average);
+
+        let started = latency.start();
+        assert!(started.is_some());
+        latency.record(started);
+        assert_eq!(latency.stats().events, 4);
+
+        latency.configure(true);
+        assert_eq!(latency.stats().events, 0);
+        assert_eq!(latency.stats().max, Duration::ZERO);
+    }
+
+    #[test]
+    fn ticks_are_converted_with_the_frequency() {
+        assert_eq!(ticks_to_duration(10_000, 10_000_000), Duration::from_millis(1));
+        assert_eq!(ticks_to_duration(3, 0), Duration::ZERO);
+    }
+}
@@ -1,15 +1,20 @@
 
 use crate::event::*;
-use crate::hook::inner::GLOBAL_CHANNEL;
+use crate::hook::inner::{GLOBAL_CHANNEL, GLOBAL_SETTINGS, GLOBAL_PRESSED_BUTTONS, GLOBAL_PRESSED_MODIFIERS, GLOBAL_PRESSED_KEYS, GLOBAL_PROBE, GLOBAL_LATENCY, GLOBAL_UNKNOWN_MESSAGES, GLOBAL_NEXT_HOOKS, HookKind, next_seq};
 
-use std::ptr::null_mut;
+use crate::hook::inner::desktop::is_higher_integrity_window;
 
-use winapi::{shared::{minwindef::*, windef::*}, um::winuser::{CallNextHookEx, KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT, HC_ACTION}};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use winapi::{shared::{minwindef::*, windef::*, ntdef::LONG, basetsd::ULONG_PTR}, um::winuser::{
+    KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT, MOUSEHOOKSTRUCTEX, HC_ACTION, EVENT_SYSTEM_DESKTOPSWITCH, EVENT_SYSTEM_FOREGROUND,
+    LLKHF_EXTENDED, LLKHF_ALTDOWN, LLKHF_UP, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP, GetMessageTime, GetMessageExtraInfo, GetSystemMetrics, SM_SWAPBUTTON,
+}};
 
 // In the case of normal compilation, just call CallNextHookEx
 #[cfg(not(test))]
 unsafe fn call_next_hook(hhk: HHOOK, n_code: INT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    CallNextHookEx(hhk, n_code, w_param, l_param)
+    winapi::um::winuser::CallNextHookEx(hhk, n_code, w_param, l_param)
 }
 
 // In the case of tests, use home-made mock for CallNextHookEx
@@ -25,8 +30,8 @@ static mut CALL_NEXT_HOOK_CALLS: Lazy<(Sender<(usize, INT, WPARAM, LPARAM)>, Rec
 static mut CALL_NEXT_HOOK_RETURN: Mutex<LRESULT> = Mutex::new(0 as LRESULT);
 #[cfg(test)]
 unsafe fn call_next_hook(hhk: HHOOK, n_code: INT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    assert!(CALL_NEXT_HOOK_CALLS.0.send((hhk as usize, n_code, w_param, l_param)).is_ok());
-    let rv = *(CALL_NEXT_HOOK_RETURN.lock().unwrap());
+    assert!((&*std::ptr::addr_of!(CALL_NEXT_HOOK_CALLS)).0.send((hhk as usize, n_code, w_param, l_param)).is_ok());
+    let rv = *((*std::ptr::addr_of!(CALL_NEXT_HOOK_RETURN)).lock().unwrap());
     rv
 }
 
@@ -40,17 +45,83 @@ pub unsafe extern "system" fn keyboard_procedure(
     // must pass the message to the CallNextHookEx function
     // without further processing and should return the value returned by CallNextHookEx.
     if code != HC_ACTION {
-        // hhk - This parameter (the 1st one) is ignored, according to MSDN
+        // hhk - MSDN says it is ignored, but still recommends passing the handle of this hook
         // args... - The subsequent parameters are simply forwarded
-        return call_next_hook(null_mut() as HHOOK, code, wm_key_code, win_hook_struct);
+        return call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Keyboard), code, wm_key_code, win_hook_struct);
     }
 
     let kbd_hook_struct: *mut KBDLLHOOKSTRUCT = win_hook_struct as *mut _;        
-    let keyboard_event = KeyboardEvent::new(wm_key_code, kbd_hook_struct);
+    // The probes of Hook::verify are swallowed, so that they don't reach the other hooks and applications
+    if !kbd_hook_struct.is_null() && GLOBAL_PROBE.observe(HookKind::Keyboard, (*kbd_hook_struct).dwExtraInfo) {
+        return 1;
+    }
+    let mut keyboard_event = KeyboardEvent::new(wm_key_code, kbd_hook_struct);
+    // Copied right away, the structure is not valid after the procedure returns
+    if !kbd_hook_struct.is_null() && GLOBAL_SETTINGS.is_raw_data_requested() {
+        keyboard_event.raw = Some(RawKeyboardData::new(wm_key_code, &*kbd_hook_struct));
+    }
+    // Nonzero return value without calling the next hook swallows the event, see HookBuilder::with_blocking
+    if dispatch_keyboard_event(keyboard_event) {
+        return 1;
+    }
 
-    let _ignore_error = GLOBAL_CHANNEL.send_keyboard_event(keyboard_event).is_err();
+    call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Keyboard), code, wm_key_code, win_hook_struct)
+}
 
-    call_next_hook(null_mut() as HHOOK, code, wm_key_code, win_hook_struct)
+/// Completes the keyboard event with the tracked state and queues it, unless it is dropped.
+/// Returns true if the event should be blocked. The time it takes is what [crate::Hook::latency_stats] reports.
+fn dispatch_keyboard_event(mut keyboard_event: KeyboardEvent) -> bool {
+    // Nothing is tracked either, the state is reset by Hook::resume
+    if GLOBAL_SETTINGS.is_paused() {
+        return false
+    }
+    let started = GLOBAL_LATENCY.start();
+    // Modifiers are tracked regardless of the filtering, like the mouse buttons
+    keyboard_event.modifiers = GLOBAL_PRESSED_MODIFIERS.update(&keyboard_event);
+    keyboard_event.held_for = GLOBAL_PRESSED_KEYS.held_for(&keyboard_event);
+    keyboard_event.chatter = GLOBAL_SETTINGS.chatter_threshold().is_some_and(|threshold| GLOBAL_PRESSED_KEYS.is_chatter(&keyboard_event, threshold));
+    keyboard_event.is_repeat = GLOBAL_PRESSED_KEYS.update(&keyboard_event);
+    GLOBAL_UNKNOWN_MESSAGES.observe(&InputEvent::Keyboard(keyboard_event));
+
+    let is_dropped = GLOBAL_SETTINGS.is_dropped(&InputEvent::Keyboard(keyboard_event));
+    if !is_dropped {
+        keyboard_event.seq = next_seq();
+    }
+    // Decided before the event is queued, so that it carries the decision
+    let is_blocked = GLOBAL_SETTINGS.is_blocked(&InputEvent::Keyboard(keyboard_event));
+    if !is_dropped {
+        keyboard_event.blocked = is_blocked;
+        // The error means the queue is full, the channel counts it for Hook::overflowed_events. There is nothing more to do here.
+        let _ignore_error = GLOBAL_CHANNEL.send_keyboard_event(keyboard_event).is_err();
+    }
+    GLOBAL_LATENCY.record(started);
+    is_blocked
+}
+
+/// Same as [dispatch_keyboard_event], for the mouse.
+fn dispatch_mouse_event(mut mouse_event: MouseEvent) -> bool {
+    if GLOBAL_SETTINGS.is_paused() {
+        return false
+    }
+    let started = GLOBAL_LATENCY.start();
+    // The hooks get the logical buttons, after the swap
+    if GLOBAL_SETTINGS.is_physical_buttons_requested() {
+        swap_primary_button(&mut mouse_event);
+    }
+    // Buttons are tracked regardless of the filtering, so the state is right even if e.g. the releases are not delivered.
+    GLOBAL_PRESSED_BUTTONS.update(&mouse_event);
+    GLOBAL_UNKNOWN_MESSAGES.observe(&InputEvent::Mouse(mouse_event));
+    let is_dropped = GLOBAL_SETTINGS.is_dropped(&InputEvent::Mouse(mouse_event));
+    if !is_dropped {
+        mouse_event.seq = next_seq();
+    }
+    let is_blocked = GLOBAL_SETTINGS.is_blocked(&InputEvent::Mouse(mouse_event));
+    if !is_dropped {
+        mouse_event.blocked = is_blocked;
+        let _ignore_error = GLOBAL_CHANNEL.send_mouse_event(mouse_event).is_err();
+    }
+    GLOBAL_LATENCY.record(started);
+    is_blocked
 }
 
 #[cfg(test)]
@@ -61,12 +132,13 @@ mod keyboard_procedure_tests {
             minwindef::{WPARAM, LPARAM, UINT, INT, DWORD, LRESULT},
             basetsd::ULONG_PTR,
             ntdef::NULL},
-        um::winuser::{WM_KEYDOWN, HC_ACTION, WM_INPUT, WM_SYSKEYDOWN, WM_KEYUP, WM_SYSKEYUP}};
+        um::winuser::{WM_KEYDOWN, HC_ACTION, WM_INPUT, WM_SYSKEYDOWN, WM_KEYUP, WM_SYSKEYUP,
+            KBDLLHOOKSTRUCT, LLKHF_INJECTED, LLKHF_LOWER_IL_INJECTED, VK_ESCAPE}};
 
-    use crate::event::{InputEvent, KeyPress, KeyboardEvent};
+    use crate::event::{InputEvent, KeyPress, KeyboardEvent, RawKeyboardData};
 
     use super::{keyboard_procedure, CALL_NEXT_HOOK_CALLS, CALL_NEXT_HOOK_RETURN};
-    use super::GLOBAL_CHANNEL;
+    use super::{GLOBAL_CHANNEL, GLOBAL_SETTINGS};
 
     use quickcheck::*;
 
@@ -85,7 +157,7 @@ mod keyboard_procedure_tests {
     }
 
     unsafe fn assert_call_next_hook_equals(expected:  Result<(usize, i32, usize, isize), std::sync::mpsc::TryRecvError>) {
-        let actual = CALL_NEXT_HOOK_CALLS.1.try_recv();
+        let actual = (&*std::ptr::addr_of!(CALL_NEXT_HOOK_CALLS)).1.try_recv();
         assert_eq!(expected, actual);
     }
 
@@ -99,12 +171,16 @@ mod keyboard_procedure_tests {
     }
 
     unsafe fn assert_current_input_event_equals(r: Result<InputEvent, std::sync::mpsc::TryRecvError>) {
-        let ie = GLOBAL_CHANNEL.try_recv();
+        // The sequence number depends on the events captured by the other tests
+        let ie = GLOBAL_CHANNEL.try_recv().map(|ie| match ie {
+            InputEvent::Keyboard(ke) => InputEvent::Keyboard(KeyboardEvent { seq: 0, ..ke }),
+            other => other,
+        });
         assert_eq!(r, ie);
     }
 
     unsafe fn set_call_next_hook_return_value(rv: LPARAM) {
-        *(CALL_NEXT_HOOK_RETURN.lock().unwrap()) = rv;
+        *((*std::ptr::addr_of!(CALL_NEXT_HOOK_RETURN)).lock().unwrap()) = rv;
     }
 
     quickcheck! {
@@ -131,11 +207,151 @@ mod keyboard_procedure_tests {
         let l_param = NULL as LPARAM;
         keyboard_procedure(HC_ACTION, w_param as usize, l_param);
         assert_call_next_hook_called_once((NULL as usize, HC_ACTION, w_param, l_param));
-        assert_one_input_event_present(InputEvent::Keyboard(KeyboardEvent{
-            pressed: press,
-            key: None,
-            is_injected: None,
-        }));
+        assert_one_input_event_present(InputEvent::Keyboard(KeyboardEvent::for_test(press, None)));
+    }
+
+    unsafe fn run_kbd_ll_hook_struct(flags: DWORD) -> LPARAM {
+        let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+        kbd.vkCode = VK_ESCAPE as DWORD;
+        kbd.flags = flags;
+        let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+        keyboard_procedure(HC_ACTION, WM_KEYDOWN as WPARAM, l_param);
+        assert_call_next_hook_called_once((NULL as usize, HC_ACTION, WM_KEYDOWN as WPARAM, l_param));
+        l_param
+    }
+
+    #[test]
+    fn injected_events_are_dropped_when_requested() {
+        unsafe {
+            GLOBAL_SETTINGS.set_drop_injected(true);
+            run_kbd_ll_hook_struct(LLKHF_INJECTED);
+            run_kbd_ll_hook_struct(LLKHF_LOWER_IL_INJECTED);
+            assert_there_are_no_more_input_events();
+
+            run_kbd_ll_hook_struct(0);
+            assert!(GLOBAL_CHANNEL.try_recv().is_ok());
+            assert_there_are_no_more_input_events();
+
+            GLOBAL_SETTINGS.set_drop_injected(false);
+            run_kbd_ll_hook_struct(LLKHF_INJECTED);
+            assert!(GLOBAL_CHANNEL.try_recv().is_ok());
+            assert_there_are_no_more_input_events();
+        }
+    }
+
+    #[test]
+    fn extra_info_is_reported() {
+        unsafe {
+            let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+            kbd.dwExtraInfo = 0x5EED;
+            let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+            keyboard_procedure(HC_ACTION, WM_KEYUP as WPARAM, l_param);
+            assert_call_next_hook_called_once((NULL as usize, HC_ACTION, WM_KEYUP as WPARAM, l_param));
+            match GLOBAL_CHANNEL.try_recv() {
+                Ok(InputEvent::Keyboard(ke)) => assert_eq!(ke.extra_info, 0x5EED),
+                other => panic!("Expected keyboard event, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn raw_data_is_copied_on_request() {
+        unsafe {
+            let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+            kbd.vkCode = VK_ESCAPE as DWORD;
+            kbd.scanCode = 0x01;
+            kbd.flags = LLKHF_INJECTED;
+            let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+            GLOBAL_SETTINGS.set_raw_data(true);
+            keyboard_procedure(HC_ACTION, WM_KEYDOWN as WPARAM, l_param);
+            GLOBAL_SETTINGS.set_raw_data(false);
+            assert_call_next_hook_called_once((NULL as usize, HC_ACTION, WM_KEYDOWN as WPARAM, l_param));
+            match GLOBAL_CHANNEL.try_recv() {
+                Ok(InputEvent::Keyboard(ke)) => assert_eq!(ke.raw, Some(RawKeyboardData {
+                    message: WM_KEYDOWN as usize,
+                    vk_code: VK_ESCAPE as u32,
+                    scan_code: 0x01,
+                    flags: LLKHF_INJECTED,
+                    time: 0,
+                    extra_info: 0,
+                })),
+                other => panic!("Expected keyboard event, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn probe_is_swallowed() {
+        unsafe {
+            use super::super::probe::PROBE_SIGNATURE;
+            use super::{GLOBAL_PROBE, HookKind};
+            GLOBAL_PROBE.reset();
+            let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+            kbd.dwExtraInfo = PROBE_SIGNATURE;
+            let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+            assert_eq!(keyboard_procedure(HC_ACTION, WM_KEYDOWN as WPARAM, l_param), 1);
+            assert_call_next_hook_equals(Err(std::sync::mpsc::TryRecvError::Empty));
+            assert_there_are_no_more_input_events();
+            assert!(GLOBAL_PROBE.is_seen(HookKind::Keyboard));
+        }
+    }
+
+    #[test]
+    fn blocked_key_is_delivered_but_not_passed_on() {
+        unsafe {
+            GLOBAL_SETTINGS.set_blocking(Some(Box::new(|_| true)));
+            let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+            kbd.vkCode = VK_ESCAPE as DWORD;
+            let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+            let result = keyboard_procedure(HC_ACTION, WM_KEYDOWN as WPARAM, l_param);
+            GLOBAL_SETTINGS.set_blocking(None);
+
+            assert_eq!(result, 1);
+            assert_call_next_hook_equals(Err(std::sync::mpsc::TryRecvError::Empty));
+            assert!(GLOBAL_CHANNEL.try_recv().is_ok());
+            assert_there_are_no_more_input_events();
+        }
+    }
+
+    #[test]
+    fn unrecognized_vk_code_is_preserved() {
+        unsafe {
+            use crate::event::KeyboardKey;
+            let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+            // Unassigned virtual-key code
+            kbd.vkCode = 0xE8;
+            let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+            keyboard_procedure(HC_ACTION, WM_KEYUP as WPARAM, l_param);
+            assert_call_next_hook_called_once((NULL as usize, HC_ACTION, WM_KEYUP as WPARAM, l_param));
+            match GLOBAL_CHANNEL.try_recv() {
+                Ok(InputEvent::Keyboard(ke)) => {
+                    assert_eq!(ke.key, Some(KeyboardKey::Other(0xE8)));
+                    assert_eq!(ke.pressed, KeyPress::Up(crate::event::IsSystemKeyPress::Normal));
+                },
+                other => panic!("Expected keyboard event, got {:?}", other),
+            }
+            assert_there_are_no_more_input_events();
+        }
+    }
+
+    #[test]
+    fn generic_modifier_is_resolved_to_its_side() {
+        unsafe {
+            use crate::event::KeyboardKey;
+            use winapi::um::winuser::{LLKHF_EXTENDED, VK_CONTROL};
+            let mut kbd: KBDLLHOOKSTRUCT = std::mem::zeroed();
+            kbd.vkCode = VK_CONTROL as DWORD;
+            kbd.scanCode = 0x1D;
+            kbd.flags = LLKHF_EXTENDED;
+            let l_param = &mut kbd as *mut KBDLLHOOKSTRUCT as LPARAM;
+            keyboard_procedure(HC_ACTION, WM_KEYDOWN as WPARAM, l_param);
+            assert_call_next_hook_called_once((NULL as usize, HC_ACTION, WM_KEYDOWN as WPARAM, l_param));
+            match GLOBAL_CHANNEL.try_recv() {
+                Ok(InputEvent::Keyboard(ke)) => assert_eq!(ke.key, Some(KeyboardKey::RightControl)),
+                other => panic!("Expected keyboard event, got {:?}", other),
+            }
+            assert_there_are_no_more_input_events();
+        }
     }
 
     #[test]
@@ -158,15 +374,354 @@ pub unsafe extern "system" fn mouse_procedure(
     // If code is less than zero, then the hook procedure
     // must pass the message to the CallNextHookEx function
     // without further processing and should return the value returned by CallNextHookEx.
-    if code < 0 {
+    // The same goes for HC_NOREMOVE (peeked, not removed input), if the user does not want it twice,
+    // and for the other codes, which are not documented to carry any input.
+    if GLOBAL_SETTINGS.is_skipped_mouse_code(code) {
+        return call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Mouse), code, wm_mouse_param, win_hook_struct);
+    }
+
+    let mice_hook_struct: *const MSLLHOOKSTRUCT = win_hook_struct as *mut _;
+    if !mice_hook_struct.is_null() && GLOBAL_PROBE.observe(HookKind::Mouse, (*mice_hook_struct).dwExtraInfo) {
+        return 1;
+    }
+    let mut mouse_event = MouseEvent::new(wm_mouse_param, mice_hook_struct);
+    if !mice_hook_struct.is_null() && GLOBAL_SETTINGS.is_raw_data_requested() {
+        mouse_event.raw = Some(RawMouseData::new(wm_mouse_param, &*mice_hook_struct));
+    }
+    if dispatch_mouse_event(mouse_event) {
+        return 1;
+    }
+
+    call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Mouse), code, wm_mouse_param, win_hook_struct)
+}
+
+#[cfg(test)]
+mod mouse_procedure_tests {
+    use winapi::shared::{minwindef::{UINT, WPARAM, LPARAM}, ntdef::NULL};
+    use winapi::um::winuser::{HC_ACTION, MSLLHOOKSTRUCT, WM_LBUTTONDOWN, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOUSEHWHEEL, WM_KEYDOWN};
+
+    use crate::event::*;
+
+    use super::{mouse_procedure, CALL_NEXT_HOOK_CALLS, GLOBAL_CHANNEL};
+
+    unsafe fn run_mouse_procedure(wm_mouse_param: UINT, msll: &mut MSLLHOOKSTRUCT) -> MouseEventType {
+        let w_param = wm_mouse_param as WPARAM;
+        let l_param = msll as *mut MSLLHOOKSTRUCT as LPARAM;
+        mouse_procedure(HC_ACTION, w_param, l_param);
+        assert_eq!((&*std::ptr::addr_of!(CALL_NEXT_HOOK_CALLS)).1.try_recv(), Ok((NULL as usize, HC_ACTION, w_param, l_param)));
+        match GLOBAL_CHANNEL.try_recv() {
+            Ok(InputEvent::Mouse(me)) => me.event,
+            other => panic!("Expected mouse event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mouse_messages_are_parsed() {
         unsafe {
-            return CallNextHookEx(null_mut() as HHOOK, code, wm_mouse_param, win_hook_struct);
+            let mut msll: MSLLHOOKSTRUCT = std::mem::zeroed();
+            msll.pt.x = 10;
+            msll.pt.y = 20;
+            assert_eq!(run_mouse_procedure(WM_MOUSEMOVE, &mut msll), MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x: 10, y: 20 }), delta: None }));
+            assert_eq!(run_mouse_procedure(WM_LBUTTONDOWN, &mut msll), MouseEventType::Press(MousePressEvent{
+                pressed: MouseButtonPress::Down,
+                button: MouseButton::Left(MouseClick::SingleClick),
+            }));
+            msll.mouseData = 120u32 << 16;
+            assert_eq!(run_mouse_procedure(WM_MOUSEWHEEL, &mut msll), MouseEventType::Wheel(MouseWheelEvent{
+                wheel: MouseWheel::Vertical,
+                direction: Some(MouseWheelDirection::Forward),
+                delta: Some(120),
+            }));
+            msll.mouseData = (-30i16 as u16 as u32) << 16;
+            assert_eq!(run_mouse_procedure(WM_MOUSEWHEEL, &mut msll), MouseEventType::Wheel(MouseWheelEvent{
+                wheel: MouseWheel::Vertical,
+                direction: Some(MouseWheelDirection::Backward),
+                delta: Some(-30),
+            }));
+            // The horizontal wheel has its own message, it is not merged with the vertical one
+            assert_eq!(run_mouse_procedure(WM_MOUSEHWHEEL, &mut msll), MouseEventType::Wheel(MouseWheelEvent{
+                wheel: MouseWheel::Horizontal,
+                direction: Some(MouseWheelDirection::Backward),
+                delta: Some(-30),
+            }));
+            // Keyboard messages can't come through the mouse hook, but they are reported rather than dropped
+            assert_eq!(run_mouse_procedure(WM_KEYDOWN, &mut msll), MouseEventType::Other(WM_KEYDOWN as usize));
         }
     }
 
-    let mice_hook_struct: *const MSLLHOOKSTRUCT = win_hook_struct as *mut _;
-    let mouse_event = MouseEvent::new(wm_mouse_param, mice_hook_struct);
-    let _ignore_error = GLOBAL_CHANNEL.send_mouse_event(mouse_event).is_err();
+    #[test]
+    fn non_action_codes_are_only_passed_on() {
+        use winapi::um::winuser::{HC_GETNEXT, HC_SKIP, HC_SYSMODALON};
+        unsafe {
+            let mut msll: MSLLHOOKSTRUCT = std::mem::zeroed();
+            let w_param = WM_LBUTTONDOWN as WPARAM;
+            let l_param = &mut msll as *mut MSLLHOOKSTRUCT as LPARAM;
+            for code in [-1, HC_GETNEXT, HC_SKIP, HC_SYSMODALON] {
+                mouse_procedure(code, w_param, l_param);
+                assert_eq!((&*std::ptr::addr_of!(CALL_NEXT_HOOK_CALLS)).1.try_recv(), Ok((NULL as usize, code, w_param, l_param)));
+                assert!(GLOBAL_CHANNEL.try_recv().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn x_buttons_are_distinguished() {
+        unsafe {
+            use winapi::um::winuser::{WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2};
+            let mut msll: MSLLHOOKSTRUCT = std::mem::zeroed();
+            msll.mouseData = (XBUTTON1 as u32) << 16;
+            assert_eq!(run_mouse_procedure(WM_XBUTTONDOWN, &mut msll), MouseEventType::Press(MousePressEvent{
+                pressed: MouseButtonPress::Down,
+                button: MouseButton::X1(MouseClick::SingleClick),
+            }));
+            msll.mouseData = (XBUTTON2 as u32) << 16;
+            assert_eq!(run_mouse_procedure(WM_XBUTTONUP, &mut msll), MouseEventType::Press(MousePressEvent{
+                pressed: MouseButtonPress::Up,
+                button: MouseButton::X2(MouseClick::SingleClick),
+            }));
+        }
+    }
+
+    #[test]
+    fn only_the_primary_buttons_are_swapped() {
+        use winapi::um::winuser::{GetSystemMetrics, SM_SWAPBUTTON};
+        use crate::event::MouseEvent;
+        let press = |button| MouseEvent::for_test(MouseEventType::Press(MousePressEvent { pressed: MouseButtonPress::Down, button }));
+        let swapped = unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0;
+        let mut left = press(MouseButton::Left(MouseClick::SingleClick));
+        super::swap_primary_button(&mut left);
+        let expected = if swapped { MouseButton::Right(MouseClick::SingleClick) } else { MouseButton::Left(MouseClick::SingleClick) };
+        assert_eq!(left, press(expected));
+
+        let mut middle = press(MouseButton::Middle(MouseClick::SingleClick));
+        super::swap_primary_button(&mut middle);
+        assert_eq!(middle, press(MouseButton::Middle(MouseClick::SingleClick)));
+    }
+}
+
+/// The keyboard procedure of the thread hook (`WH_KEYBOARD`), see [crate::hook::HookBuilder::scoped_to_thread].
+/// It is called in the context of the hooked thread, when the thread retrieves a keystroke message.
+/// The key data is packed in the parameters, it is translated to the structure of the low-level hook,
+/// so that both hooks deliver the same events.
+pub unsafe extern "system" fn thread_keyboard_procedure(
+    code: INT,
+    virtual_key: WPARAM,
+    key_data: LPARAM,
+) -> LRESULT {
+    // The message is only peeked with HC_NOREMOVE, it comes again when it is removed
+    if code != HC_ACTION {
+        return call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Keyboard), code, virtual_key, key_data);
+    }
+
+    let (kbd_hook_struct, wm_key_code) = thread_key_to_low_level(virtual_key, key_data);
+    let mut keyboard_event = KeyboardEvent::new(wm_key_code, &kbd_hook_struct);
+    // The thread hook is not told if the key was injected
+    keyboard_event.is_injected = None;
+    // Nonzero return value discards the message
+    if dispatch_keyboard_event(keyboard_event) {
+        return 1;
+    }
+
+    call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Keyboard), code, virtual_key, key_data)
+}
 
-    CallNextHookEx(null_mut() as HHOOK, code, wm_mouse_param, win_hook_struct)
+/// Translates the parameters of the `WH_KEYBOARD` procedure to the ones of the `WH_KEYBOARD_LL` procedure.
+fn thread_key_to_low_level(virtual_key: WPARAM, key_data: LPARAM) -> (KBDLLHOOKSTRUCT, WPARAM) {
+    // Bits 16-23 scan code, 24 extended key, 29 ALT held down, 31 key released
+    let key_data = key_data as u32;
+    let is_extended = key_data & (1 << 24) != 0;
+    let is_alt_down = key_data & (1 << 29) != 0;
+    let is_up = key_data & (1 << 31) != 0;
+
+    let mut flags = 0;
+    if is_extended { flags |= LLKHF_EXTENDED; }
+    if is_alt_down { flags |= LLKHF_ALTDOWN; }
+    if is_up { flags |= LLKHF_UP; }
+    let kbd_hook_struct = KBDLLHOOKSTRUCT {
+        vkCode: virtual_key as DWORD,
+        scanCode: (key_data >> 16) & 0xFF,
+        flags,
+        // The procedure is called while the thread retrieves the message, so it's the time of the message
+        time: unsafe { GetMessageTime() } as DWORD,
+        dwExtraInfo: unsafe { GetMessageExtraInfo() } as ULONG_PTR,
+    };
+    let wm_key_code = match (is_up, is_alt_down) {
+        (false, false) => WM_KEYDOWN,
+        (false, true) => WM_SYSKEYDOWN,
+        (true, false) => WM_KEYUP,
+        (true, true) => WM_SYSKEYUP,
+    };
+    (kbd_hook_struct, wm_key_code as WPARAM)
+}
+
+/// The mouse procedure of the thread hook (`WH_MOUSE`), see [thread_keyboard_procedure].
+pub unsafe extern "system" fn thread_mouse_procedure(
+    code: INT,
+    wm_mouse_param: WPARAM,
+    win_hook_struct: LPARAM,
+) -> LRESULT {
+    if GLOBAL_SETTINGS.is_skipped_mouse_code(code) || win_hook_struct == 0 {
+        return call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Mouse), code, wm_mouse_param, win_hook_struct);
+    }
+
+    let mouse_hook_struct = &*(win_hook_struct as *const MOUSEHOOKSTRUCTEX);
+    let msll_hook_struct = MSLLHOOKSTRUCT {
+        pt: mouse_hook_struct.parent.pt,
+        mouseData: mouse_hook_struct.mouseData,
+        flags: 0,
+        time: GetMessageTime() as DWORD,
+        dwExtraInfo: mouse_hook_struct.parent.dwExtraInfo,
+    };
+    let mut mouse_event = MouseEvent::new(wm_mouse_param, &msll_hook_struct);
+    mouse_event.is_injected = None;
+    if dispatch_mouse_event(mouse_event) {
+        return 1;
+    }
+
+    call_next_hook(GLOBAL_NEXT_HOOKS.get(HookKind::Mouse), code, wm_mouse_param, win_hook_struct)
+}
+
+#[cfg(test)]
+mod thread_procedure_tests {
+    use winapi::shared::{minwindef::{WPARAM, LPARAM}, ntdef::NULL};
+    use winapi::um::winuser::{HC_ACTION, MOUSEHOOKSTRUCTEX, WM_KEYDOWN, WM_SYSKEYUP, WM_RBUTTONUP, LLKHF_EXTENDED, LLKHF_ALTDOWN, LLKHF_UP};
+
+    use crate::event::*;
+
+    use super::{thread_key_to_low_level, thread_mouse_procedure, CALL_NEXT_HOOK_CALLS, GLOBAL_CHANNEL};
+
+    #[test]
+    fn key_data_is_translated() {
+        let (kbd, wm_key_code) = thread_key_to_low_level(0x41, 0x001E_0001);
+        assert_eq!((kbd.vkCode, kbd.scanCode, kbd.flags), (0x41, 0x1E, 0));
+        assert_eq!(wm_key_code, WM_KEYDOWN as WPARAM);
+
+        // Released extended key while ALT is held down
+        let (kbd, wm_key_code) = thread_key_to_low_level(0x2E, 0xE153_0001u32 as LPARAM);
+        assert_eq!((kbd.vkCode, kbd.scanCode, kbd.flags), (0x2E, 0x53, LLKHF_EXTENDED | LLKHF_ALTDOWN | LLKHF_UP));
+        assert_eq!(wm_key_code, WM_SYSKEYUP as WPARAM);
+    }
+
+    #[test]
+    fn mouse_hook_struct_is_translated() {
+        unsafe {
+            let mut mouse: MOUSEHOOKSTRUCTEX = std::mem::zeroed();
+            mouse.parent.pt.x = 3;
+            mouse.parent.pt.y = 4;
+            let w_param = WM_RBUTTONUP as WPARAM;
+            let l_param = &mut mouse as *mut MOUSEHOOKSTRUCTEX as LPARAM;
+            thread_mouse_procedure(HC_ACTION, w_param, l_param);
+            assert_eq!((&*std::ptr::addr_of!(CALL_NEXT_HOOK_CALLS)).1.try_recv(), Ok((NULL as usize, HC_ACTION, w_param, l_param)));
+            match GLOBAL_CHANNEL.try_recv() {
+                Ok(InputEvent::Mouse(me)) => {
+                    assert_eq!(me.event, MouseEventType::Press(MousePressEvent{
+                        pressed: MouseButtonPress::Up,
+                        button: MouseButton::Right(MouseClick::SingleClick),
+                    }));
+                    assert_eq!(me.is_injected, None);
+                },
+                other => panic!("Expected mouse event, got {:?}", other),
+            }
+        }
+    }
+}
+
+/// Swaps the left and the right button of the press, if the user swapped the primary and secondary buttons in the system settings.
+/// Translates the logical button to the physical one, and the other way around.
+pub(super) fn swap_primary_button(mouse_event: &mut MouseEvent) {
+    if let MouseEventType::Press(press) = &mut mouse_event.event {
+        let swapped = match press.button {
+            MouseButton::Left(click) => MouseButton::Right(click),
+            MouseButton::Right(click) => MouseButton::Left(click),
+            _ => return,
+        };
+        // Read for every press, so that the change of the setting applies right away
+        if unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0 {
+            press.button = swapped;
+        }
+    }
+}
+
+/// Whether the focused window belongs to a process of a higher integrity level, see [foreground_procedure].
+static FOREGROUND_ELEVATED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_foreground_elevated(elevated: bool) {
+    FOREGROUND_ELEVATED.store(elevated, Ordering::Relaxed);
+}
+
+/// Forgets the tracked keys and buttons and tells the client, as the hooks may have missed their releases.
+fn reset_tracked_state() {
+    GLOBAL_PRESSED_BUTTONS.reset();
+    GLOBAL_PRESSED_MODIFIERS.reset();
+    GLOBAL_PRESSED_KEYS.reset();
+    let _ignore_error = GLOBAL_CHANNEL.send_reset_event().is_err();
+}
+
+pub unsafe extern "system" fn desktop_switch_procedure(
+    _win_event_hook: HWINEVENTHOOK,
+    event: DWORD,
+    _hwnd: HWND,
+    _id_object: LONG,
+    _id_child: LONG,
+    _id_event_thread: DWORD,
+    _event_time: DWORD,
+) {
+    // The hook is registered only for EVENT_SYSTEM_DESKTOPSWITCH, but be defensive about it anyway.
+    if event != EVENT_SYSTEM_DESKTOPSWITCH {
+        return;
+    }
+
+    // While the other desktop is active (UAC prompt, lock screen, Ctrl+Alt+Del) the low-level hooks are blind,
+    // so any key or button that was held down may be released without us knowing about it.
+    reset_tracked_state();
+}
+
+/// The WinEvent procedure for `EVENT_SYSTEM_FOREGROUND`. While the focused window belongs to a process of a higher integrity level,
+/// the User Interface Privilege Isolation may keep its input from the hooks, just like the other desktop does.
+/// So the reset is sent when the focus moves to such window and back, not on the other focus changes.
+pub unsafe extern "system" fn foreground_procedure(
+    _win_event_hook: HWINEVENTHOOK,
+    event: DWORD,
+    hwnd: HWND,
+    _id_object: LONG,
+    _id_child: LONG,
+    _id_event_thread: DWORD,
+    _event_time: DWORD,
+) {
+    if event != EVENT_SYSTEM_FOREGROUND {
+        return;
+    }
+    let elevated = is_higher_integrity_window(hwnd);
+    if FOREGROUND_ELEVATED.swap(elevated, Ordering::Relaxed) != elevated {
+        reset_tracked_state();
+    }
+}
+
+#[cfg(test)]
+mod desktop_switch_procedure_tests {
+    use winapi::{
+        shared::{ntdef::NULL, windef::{HWINEVENTHOOK, HWND}},
+        um::winuser::{EVENT_SYSTEM_DESKTOPSWITCH, EVENT_SYSTEM_FOREGROUND}};
+
+    use crate::event::InputEvent;
+
+    use super::desktop_switch_procedure;
+    use super::GLOBAL_CHANNEL;
+
+    #[test]
+    fn desktop_switch_emits_reset() {
+        unsafe {
+            desktop_switch_procedure(NULL as HWINEVENTHOOK, EVENT_SYSTEM_DESKTOPSWITCH, NULL as HWND, 0, 0, 0, 0);
+        }
+        assert_eq!(GLOBAL_CHANNEL.try_recv(), Ok(InputEvent::Reset));
+        assert_eq!(GLOBAL_CHANNEL.try_recv(), Err(std::sync::mpsc::TryRecvError::Empty));
+    }
+
+    #[test]
+    fn other_win_event_is_ignored() {
+        unsafe {
+            desktop_switch_procedure(NULL as HWINEVENTHOOK, EVENT_SYSTEM_FOREGROUND, NULL as HWND, 0, 0, 0, 0);
+        }
+        assert_eq!(GLOBAL_CHANNEL.try_recv(), Err(std::sync::mpsc::TryRecvError::Empty));
+    }
 }
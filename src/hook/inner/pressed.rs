@@ -0,0 +1,318 @@
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::event::*;
+
+/// Mouse buttons currently held down, updated by the mouse hook procedure.
+/// It has to be lock-free, because it is updated inside the hook procedure.
+pub struct PressedButtons {
+    bits: AtomicU8,
+}
+
+impl PressedButtons {
+    pub const fn new() -> PressedButtons {
+        PressedButtons {
+            bits: AtomicU8::new(0),
+        }
+    }
+
+    /// Tracks the button presses and releases, all other mouse events are ignored.
+    pub fn update(&self, event: &MouseEvent) {
+        if let MouseEventType::Press(press) = event.event {
+            if let Some(button) = MouseButtons::from_button(press.button) {
+                match press.pressed {
+                    MouseButtonPress::Down => { self.bits.fetch_or(button.bits(), Ordering::Relaxed); },
+                    MouseButtonPress::Up => { self.bits.fetch_and(!button.bits(), Ordering::Relaxed); },
+                    MouseButtonPress::Other(_) => {},
+                }
+            }
+        }
+    }
+
+    /// Forgets all pressed buttons, used when the releases could have been missed.
+    pub fn reset(&self) {
+        self.bits.store(0, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> MouseButtons {
+        MouseButtons::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+/// Keys currently held down (by the virtual-key code), updated by the keyboard hook procedure to detect the auto-repeat
+/// and to measure how long the keys are held. Lock-free for the same reason as [PressedButtons].
+pub struct PressedKeys {
+    bits: [AtomicU64; 4],
+    /// The OS timestamp of the first key down of each key, with [TIME_KNOWN] set if the key down had the timestamp
+    down_times: [AtomicU64; 256],
+}
+
+/// Distinguishes the stored timestamp from the key down without one
+const TIME_KNOWN: u64 = 1 << 32;
+
+impl PressedKeys {
+    pub const fn new() -> PressedKeys {
+        // Every element is a new atomic, the constant is only the initializer
+        #[allow(clippy::declare_interior_mutable_const)]
+        const UNKNOWN: AtomicU64 = AtomicU64::new(0);
+        PressedKeys {
+            bits: [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)],
+            down_times: [UNKNOWN; 256],
+        }
+    }
+
+    /// Returns how long the key was held, if the event is the key up of the key tracked as down.
+    /// It has to be called before the [PressedKeys::update] with the same event, which forgets the key.
+    pub fn held_for(&self, event: &KeyboardEvent) -> Option<Duration> {
+        let vk = match (event.pressed, event.key.and_then(|key| key.virtual_key_code())) {
+            (KeyPress::Up(_), Some(vk)) if vk < 256 => vk as usize,
+            _ => return None,
+        };
+        if self.bits[vk / 64].load(Ordering::Relaxed) & (1u64 << (vk % 64)) == 0 {
+            return None
+        }
+        let down_time = self.down_times[vk].load(Ordering::Relaxed);
+        match (down_time & TIME_KNOWN != 0, event.time) {
+            (true, Some(time)) => Some(Duration::from_millis(time.wrapping_sub(down_time as u32) as u64)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the event is the key down (not the repeated one) that came at most `threshold` milliseconds after the previous press of the key,
+    /// see [crate::hook::HookBuilder::with_chatter_detection]. It has to be called before the [PressedKeys::update] with the same event,
+    /// which replaces the time of the previous press.
+    pub fn is_chatter(&self, event: &KeyboardEvent, threshold: u32) -> bool {
+        let (vk, time) = match (event.pressed, event.key.and_then(|key| key.virtual_key_code()), event.time) {
+            (KeyPress::Down(_), Some(vk), Some(time)) if vk < 256 => (vk as usize, time),
+            _ => return false,
+        };
+        if self.bits[vk / 64].load(Ordering::Relaxed) & (1u64 << (vk % 64)) != 0 {
+            return false
+        }
+        let down_time = self.down_times[vk].load(Ordering::Relaxed);
+        down_time & TIME_KNOWN != 0 && time.wrapping_sub(down_time as u32) <= threshold
+    }
+
+    /// Tracks the key presses and releases, and returns true if the event is the repeated key down.
+    pub fn update(&self, event: &KeyboardEvent) -> bool {
+        let vk = match event.key.and_then(|key| key.virtual_key_code()) {
+            Some(vk) if vk < 256 => vk as usize,
+            _ => return false,
+        };
+        let (word, bit) = (&self.bits[vk / 64], 1u64 << (vk % 64));
+        match event.pressed {
+            KeyPress::Down(_) => {
+                let is_repeat = word.fetch_or(bit, Ordering::Relaxed) & bit != 0;
+                if !is_repeat {
+                    let down_time = event.time.map_or(0, |time| time as u64 | TIME_KNOWN);
+                    self.down_times[vk].store(down_time, Ordering::Relaxed);
+                }
+                is_repeat
+            },
+            KeyPress::Up(_) => { word.fetch_and(!bit, Ordering::Relaxed); false },
+            KeyPress::Other(_) => false,
+        }
+    }
+
+    /// Forgets all pressed keys, used when the releases could have been missed.
+    pub fn reset(&self) {
+        for word in &self.bits {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the keys held down, ordered by the virtual-key code. The words are read one by one,
+    /// so a key pressed or released meanwhile may or may not be included.
+    pub fn get(&self) -> Vec<KeyboardKey> {
+        self.bits.iter().enumerate().flat_map(|(index, word)| {
+            let bits = word.load(Ordering::Relaxed);
+            (0..64).filter(move |bit| bits & (1u64 << bit) != 0).map(move |bit| KeyboardKey::from((index * 64 + bit) as u32))
+        }).collect()
+    }
+}
+
+/// Modifier keys currently held down, updated by the keyboard hook procedure.
+/// Lock-free for the same reason as [PressedButtons].
+pub struct PressedModifiers {
+    bits: AtomicU8,
+}
+
+impl PressedModifiers {
+    pub const fn new() -> PressedModifiers {
+        PressedModifiers {
+            bits: AtomicU8::new(0),
+        }
+    }
+
+    /// Tracks the modifier presses and releases, and returns the modifiers held down after the event.
+    pub fn update(&self, event: &KeyboardEvent) -> Modifiers {
+        let modifier = match event.key.and_then(Modifiers::from_key) {
+            Some(modifier) => modifier,
+            None => return self.get(),
+        };
+        let bits = match event.pressed {
+            KeyPress::Down(_) => self.bits.fetch_or(modifier.bits(), Ordering::Relaxed) | modifier.bits(),
+            // The release without the press just leaves the state as it is
+            KeyPress::Up(_) => self.bits.fetch_and(!modifier.bits(), Ordering::Relaxed) & !modifier.bits(),
+            KeyPress::Other(_) => return self.get(),
+        };
+        Modifiers::from_bits(bits)
+    }
+
+    /// Forgets all pressed modifiers, used when the releases could have been missed.
+    pub fn reset(&self) {
+        self.bits.store(0, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Modifiers {
+        Modifiers::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod pressed_buttons_tests {
+    use super::*;
+
+    fn press(button: MouseButton, pressed: MouseButtonPress) -> MouseEvent {
+        MouseEvent::for_test(MouseEventType::Press(MousePressEvent { pressed, button }))
+    }
+
+    #[test]
+    fn chord_is_tracked() {
+        use MouseClick::*;
+        let pressed = PressedButtons::new();
+        assert!(pressed.get().is_empty());
+
+        pressed.update(&press(MouseButton::Left(SingleClick), MouseButtonPress::Down));
+        pressed.update(&press(MouseButton::Right(DoubleClick), MouseButtonPress::Down));
+        assert_eq!(pressed.get(), MouseButtons::LEFT | MouseButtons::RIGHT);
+
+        pressed.update(&press(MouseButton::UnkownX(SingleClick), MouseButtonPress::Down));
+        pressed.update(&press(MouseButton::Left(SingleClick), MouseButtonPress::Up));
+        assert_eq!(pressed.get(), MouseButtons::RIGHT);
+
+        pressed.reset();
+        assert!(pressed.get().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod pressed_modifiers_tests {
+    use super::*;
+
+    fn key(key: KeyboardKey, pressed: KeyPress) -> KeyboardEvent {
+        KeyboardEvent::for_test(pressed, Some(key))
+    }
+
+    #[test]
+    fn sides_are_tracked_separately() {
+        use IsSystemKeyPress::*;
+        let pressed = PressedModifiers::new();
+        assert_eq!(pressed.update(&key(KeyboardKey::LeftControl, KeyPress::Down(Normal))), Modifiers::LEFT_CONTROL);
+        assert_eq!(pressed.update(&key(KeyboardKey::RightShift, KeyPress::Down(Normal))), Modifiers::LEFT_CONTROL | Modifiers::RIGHT_SHIFT);
+        assert_eq!(pressed.update(&key(KeyboardKey::A, KeyPress::Down(Normal))), Modifiers::LEFT_CONTROL | Modifiers::RIGHT_SHIFT);
+        // Auto-repeat of the held modifier does not change anything
+        assert_eq!(pressed.update(&key(KeyboardKey::LeftControl, KeyPress::Down(Normal))), Modifiers::LEFT_CONTROL | Modifiers::RIGHT_SHIFT);
+
+        assert_eq!(pressed.update(&key(KeyboardKey::RightControl, KeyPress::Up(Normal))), Modifiers::LEFT_CONTROL | Modifiers::RIGHT_SHIFT);
+        assert_eq!(pressed.update(&key(KeyboardKey::LeftControl, KeyPress::Up(Normal))), Modifiers::RIGHT_SHIFT);
+        assert_eq!(pressed.update(&key(KeyboardKey::RightAlt, KeyPress::Down(System))), Modifiers::RIGHT_SHIFT | Modifiers::RIGHT_ALT);
+
+        pressed.reset();
+        assert!(pressed.get().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod pressed_keys_tests {
+    use super::*;
+
+    fn key(key: KeyboardKey, pressed: KeyPress) -> KeyboardEvent {
+        KeyboardEvent::for_test(pressed, Some(key))
+    }
+
+    #[test]
+    fn repeated_down_is_detected() {
+        use IsSystemKeyPress::*;
+        let pressed = PressedKeys::new();
+        assert!(!pressed.update(&key(KeyboardKey::A, KeyPress::Down(Normal))));
+        assert!(pressed.update(&key(KeyboardKey::A, KeyPress::Down(Normal))));
+        // The other key, even with the virtual-key code in the other word
+        assert!(!pressed.update(&key(KeyboardKey::Escape, KeyPress::Down(Normal))));
+        assert!(pressed.update(&key(KeyboardKey::A, KeyPress::Down(System))));
+
+        assert!(!pressed.update(&key(KeyboardKey::A, KeyPress::Up(Normal))));
+        assert!(!pressed.update(&key(KeyboardKey::A, KeyPress::Down(Normal))));
+        // The release without the press is just ignored
+        assert!(!pressed.update(&key(KeyboardKey::B, KeyPress::Up(Normal))));
+
+        pressed.reset();
+        assert!(!pressed.update(&key(KeyboardKey::A, KeyPress::Down(Normal))));
+        assert!(!pressed.update(&key(KeyboardKey::Escape, KeyPress::Down(Normal))));
+    }
+
+    #[test]
+    fn held_keys_are_listed() {
+        use IsSystemKeyPress::*;
+        let pressed = PressedKeys::new();
+        assert!(pressed.get().is_empty());
+        for held in [KeyboardKey::RightControl, KeyboardKey::A, KeyboardKey::Escape, KeyboardKey::B] {
+            pressed.update(&key(held, KeyPress::Down(Normal)));
+        }
+        pressed.update(&key(KeyboardKey::B, KeyPress::Up(Normal)));
+        assert_eq!(pressed.get(), vec![KeyboardKey::Escape, KeyboardKey::A, KeyboardKey::RightControl]);
+        pressed.reset();
+        assert!(pressed.get().is_empty());
+    }
+
+    #[test]
+    fn hold_is_measured_from_the_first_down() {
+        use IsSystemKeyPress::*;
+        let at = |pressed, time| KeyboardEvent { time: Some(time), ..key(KeyboardKey::A, pressed) };
+        let pressed = PressedKeys::new();
+        // The key was already held when the tracking started
+        assert_eq!(pressed.held_for(&at(KeyPress::Up(Normal), 500)), None);
+
+        for (event, held_for) in [(at(KeyPress::Down(Normal), 1000), None), (at(KeyPress::Down(Normal), 1500), None), (at(KeyPress::Up(Normal), 1750), Some(750))] {
+            assert_eq!(pressed.held_for(&event), held_for.map(Duration::from_millis));
+            pressed.update(&event);
+        }
+        assert_eq!(pressed.held_for(&at(KeyPress::Up(Normal), 2000)), None);
+
+        // The timestamps wrap around
+        pressed.update(&at(KeyPress::Down(Normal), u32::MAX - 9));
+        assert_eq!(pressed.held_for(&at(KeyPress::Up(Normal), 10)), Some(Duration::from_millis(20)));
+
+        // Without the timestamp of the key down
+        pressed.update(&key(KeyboardKey::A, KeyPress::Down(Normal)));
+        assert_eq!(pressed.held_for(&at(KeyPress::Up(Normal), 10)), None);
+    }
+
+    #[test]
+    fn quick_press_after_the_previous_one_is_chatter() {
+        use IsSystemKeyPress::*;
+        let at = |pressed, time| KeyboardEvent { time: Some(time), ..key(KeyboardKey::A, pressed) };
+        let pressed = PressedKeys::new();
+        let mut chatters = Vec::new();
+        for event in [
+            at(KeyPress::Down(Normal), 1000),
+            at(KeyPress::Up(Normal), 1003),
+            at(KeyPress::Down(Normal), 1008),
+            // The auto-repeat is not chatter
+            at(KeyPress::Down(Normal), 1009),
+            at(KeyPress::Up(Normal), 1100),
+            at(KeyPress::Down(Normal), 1200),
+            at(KeyPress::Up(Normal), 1201),
+        ] {
+            chatters.push(pressed.is_chatter(&event, 10));
+            pressed.update(&event);
+        }
+        assert_eq!(chatters, vec![false, false, true, false, false, false, false]);
+
+        // Without the timestamp nothing is known
+        assert!(!pressed.is_chatter(&key(KeyboardKey::A, KeyPress::Down(Normal)), 10));
+        // The threshold itself is still chatter
+        assert!(!pressed.is_chatter(&at(KeyPress::Down(Normal), 1205), 4));
+        assert!(pressed.is_chatter(&at(KeyPress::Down(Normal), 1205), 5));
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::minwindef::WORD;
+use winapi::um::winuser::{INPUT, INPUT_KEYBOARD, INPUT_MOUSE, KEYEVENTF_KEYUP, MOUSEEVENTF_MOVE};
+
+use crate::hook::inner::HookKind;
+
+/// Marks the events injected by [crate::hook::Hook::verify] in the `dwExtraInfo`, so that the hook procedures recognize them.
+pub const PROBE_SIGNATURE: ULONG_PTR = 0x5749_4C4C;
+
+/// The virtual key code of the keyboard probe, one of the codes left unassigned by Windows.
+const PROBE_KEY: WORD = 0x97;
+
+/// Tracks which hook procedures have seen the probe events, see [crate::hook::Hook::verify].
+/// It has to be lock-free, because it is updated inside the hook procedures.
+pub struct Probe {
+    keyboard_seen: AtomicBool,
+    mouse_seen: AtomicBool,
+}
+
+impl Probe {
+    pub const fn new() -> Probe {
+        Probe {
+            keyboard_seen: AtomicBool::new(false),
+            mouse_seen: AtomicBool::new(false),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.keyboard_seen.store(false, Ordering::Relaxed);
+        self.mouse_seen.store(false, Ordering::Relaxed);
+    }
+
+    /// Called by the hook procedures for every event, returns true if the event is a probe (and should be swallowed).
+    pub fn observe(&self, kind: HookKind, extra_info: ULONG_PTR) -> bool {
+        if extra_info != PROBE_SIGNATURE {
+            return false
+        }
+        self.seen(kind).store(true, Ordering::Relaxed);
+        true
+    }
+
+    pub fn is_seen(&self, kind: HookKind) -> bool {
+        self.seen(kind).load(Ordering::Relaxed)
+    }
+
+    fn seen(&self, kind: HookKind) -> &AtomicBool {
+        match kind {
+            HookKind::Keyboard => &self.keyboard_seen,
+            HookKind::Mouse => &self.mouse_seen,
+        }
+    }
+
+    /// Returns the inputs to be sent with `SendInput` to probe the hook of the given `kind`.
+    /// The keyboard probe is a press and release of an unassigned key, the mouse probe is a move by zero pixels.
+    pub fn inputs(kind: HookKind) -> Vec<INPUT> {
+        unsafe {
+            match kind {
+                HookKind::Keyboard => [0, KEYEVENTF_KEYUP].iter().map(|&flags| {
+                    let mut input: INPUT = std::mem::zeroed();
+                    input.type_ = INPUT_KEYBOARD;
+                    let ki = input.u.ki_mut();
+                    ki.wVk = PROBE_KEY;
+                    ki.dwFlags = flags;
+                    ki.dwExtraInfo = PROBE_SIGNATURE;
+                    input
+                }).collect(),
+                HookKind::Mouse => {
+                    let mut input: INPUT = std::mem::zeroed();
+                    input.type_ = INPUT_MOUSE;
+                    let mi = input.u.mi_mut();
+                    mi.dwFlags = MOUSEEVENTF_MOVE;
+                    mi.dwExtraInfo = PROBE_SIGNATURE;
+                    vec![input]
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod probe_tests {
+    use super::*;
+
+    #[test]
+    fn only_signed_events_are_probes() {
+        let probe = Probe::new();
+        assert!(!probe.observe(HookKind::Keyboard, 0));
+        assert!(!probe.is_seen(HookKind::Keyboard));
+
+        assert!(probe.observe(HookKind::Keyboard, PROBE_SIGNATURE));
+        assert!(probe.is_seen(HookKind::Keyboard));
+        assert!(!probe.is_seen(HookKind::Mouse));
+
+        probe.reset();
+        assert!(!probe.is_seen(HookKind::Keyboard));
+    }
+
+    #[test]
+    fn probe_inputs_are_signed() {
+        for kind in [HookKind::Keyboard, HookKind::Mouse] {
+            for input in Probe::inputs(kind) {
+                let extra_info = unsafe {
+                    match kind {
+                        HookKind::Keyboard => input.u.ki().dwExtraInfo,
+                        HookKind::Mouse => input.u.mi().dwExtraInfo,
+                    }
+                };
+                assert_eq!(extra_info, PROBE_SIGNATURE);
+            }
+        }
+    }
+}
@@ -1,10 +1,16 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use winapi::shared::minwindef::DWORD;
 use winapi::shared::windef::HHOOK;
 use winapi::shared::ntdef::NULL;
 
+use crate::hook::inner::HookKind;
+
 pub struct RawHook {
-    pub raw_handle: HHOOK,
+    raw_handle: HHOOK,
     pub thread_id: DWORD,
+    /// `GetLastError` code reported when the hook thread's message loop failed.
+    pub loop_error: Option<DWORD>,
 }
 
 impl RawHook {
@@ -12,9 +18,144 @@ impl RawHook {
         RawHook {
             raw_handle: NULL as HHOOK,
             thread_id: NULL as DWORD,
+            loop_error: None,
+        }
+    }
+
+    /// Returns the raw hook as if the thread `thread_id` installed the `handle`, without installing anything,
+    /// so that the cleanup of the [crate::hook::inner::InnerHook] can be tested with the fake or NULL handles.
+    #[cfg(test)]
+    pub fn with_handle(handle: HHOOK, thread_id: DWORD) -> RawHook {
+        RawHook {
+            raw_handle: handle,
+            thread_id,
+            loop_error: None,
         }
     }
+
+    /// Stores the handle returned by `SetWindowsHookExW`. Setting NULL is the same as [RawHook::take].
+    pub fn set(&mut self, handle: HHOOK) {
+        self.raw_handle = handle;
+    }
+
+    /// Returns the handle, or `None` if the hook is not installed.
+    pub fn handle(&self) -> Option<HHOOK> {
+        if self.raw_handle.is_null() {
+            None
+        } else {
+            Some(self.raw_handle)
+        }
+    }
+
+    /// Returns the handle (if any) and forgets it, so it is not unhooked twice.
+    pub fn take(&mut self) -> Option<HHOOK> {
+        let handle = self.handle();
+        self.raw_handle = NULL as HHOOK;
+        handle
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.handle().is_some()
+    }
 }
 
+// The HHOOK is an opaque handle, not a pointer to the memory of this process: any thread can pass it to UnhookWindowsHookEx.
+// It is only read and written under the mutex of the InnerHook.
 unsafe impl Send for RawHook {}
 unsafe impl Sync for RawHook {}
+
+/// The handles of the installed hooks, passed by the hook procedures to `CallNextHookEx`.
+/// The procedures are free functions and must not lock anything, so the handles are published here, next to the [RawHook]s.
+pub struct NextHooks {
+    keyboard: AtomicUsize,
+    mouse: AtomicUsize,
+}
+
+impl NextHooks {
+    pub const fn new() -> NextHooks {
+        NextHooks {
+            keyboard: AtomicUsize::new(0),
+            mouse: AtomicUsize::new(0),
+        }
+    }
+
+    fn slot(&self, kind: HookKind) -> &AtomicUsize {
+        match kind {
+            HookKind::Keyboard => &self.keyboard,
+            HookKind::Mouse => &self.mouse,
+        }
+    }
+
+    /// Publishes the handle of the just installed hook.
+    pub fn set(&self, kind: HookKind, handle: HHOOK) {
+        self.slot(kind).store(handle as usize, Ordering::Release);
+    }
+
+    /// Forgets the handle about to be unhooked. The newer handle of the same kind (e.g. reinstalled meanwhile) is kept.
+    pub fn forget(&self, handle: HHOOK) {
+        for slot in [&self.keyboard, &self.mouse] {
+            let _ignore_error = slot.compare_exchange(handle as usize, 0, Ordering::AcqRel, Ordering::Relaxed);
+        }
+    }
+
+    /// The handle for the `CallNextHookEx`, NULL if the hook is not installed (which `CallNextHookEx` tolerates).
+    pub fn get(&self, kind: HookKind) -> HHOOK {
+        self.slot(kind).load(Ordering::Acquire) as HHOOK
+    }
+}
+
+#[cfg(test)]
+mod raw_hook_tests {
+    use super::*;
+
+    #[test]
+    fn null_handle_is_not_installed() {
+        let mut raw = RawHook::new();
+        assert!(!raw.is_installed());
+        assert_eq!(raw.handle(), None);
+
+        raw.set(NULL as HHOOK);
+        assert!(!raw.is_installed());
+        assert_eq!(raw.take(), None);
+    }
+
+    #[test]
+    fn non_null_handle_is_installed_until_taken() {
+        let mut raw = RawHook::new();
+        let handle = 0x1234 as HHOOK;
+        raw.set(handle);
+        assert!(raw.is_installed());
+        assert_eq!(raw.handle(), Some(handle));
+
+        assert_eq!(raw.take(), Some(handle));
+        assert!(!raw.is_installed());
+        assert_eq!(raw.handle(), None);
+    }
+
+    #[test]
+    fn handle_is_set_without_installing() {
+        let mut raw = RawHook::with_handle(0x1234 as HHOOK, 42);
+        assert!(raw.is_installed());
+        assert_eq!(raw.thread_id, 42);
+        assert_eq!(raw.take(), Some(0x1234 as HHOOK));
+        assert!(!RawHook::with_handle(NULL as HHOOK, 42).is_installed());
+    }
+
+    #[test]
+    fn next_hooks_keep_the_newer_handle() {
+        let next_hooks = NextHooks::new();
+        assert_eq!(next_hooks.get(HookKind::Keyboard), NULL as HHOOK);
+
+        let (old, new, mouse) = (0x10 as HHOOK, 0x20 as HHOOK, 0x30 as HHOOK);
+        next_hooks.set(HookKind::Keyboard, old);
+        next_hooks.set(HookKind::Mouse, mouse);
+        next_hooks.set(HookKind::Keyboard, new);
+        next_hooks.forget(old);
+        assert_eq!(next_hooks.get(HookKind::Keyboard), new);
+        assert_eq!(next_hooks.get(HookKind::Mouse), mouse);
+
+        next_hooks.forget(new);
+        assert_eq!(next_hooks.get(HookKind::Keyboard), NULL as HHOOK);
+        assert_eq!(next_hooks.get(HookKind::Mouse), mouse);
+    }
+}
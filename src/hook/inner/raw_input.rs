@@ -0,0 +1,390 @@
+use crate::event::*;
+use crate::hook::HookError;
+use crate::hook::inner::{GLOBAL_CHANNEL, GLOBAL_SETTINGS, GLOBAL_PRESSED_BUTTONS, GLOBAL_PRESSED_MODIFIERS, GLOBAL_PRESSED_KEYS, GLOBAL_PROBE, GLOBAL_UNKNOWN_MESSAGES, HookKind, apply_thread_priority, next_seq};
+use crate::hook::inner::ready::LoopReady;
+use crate::hook::inner::desktop::{InputDesktop, FocusWatch};
+use crate::hook::inner::low_level::swap_primary_button;
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::hidusage::{HID_USAGE_PAGE_GENERIC, HID_USAGE_GENERIC_KEYBOARD, HID_USAGE_GENERIC_MOUSE};
+use winapi::shared::minwindef::*;
+use winapi::shared::ntdef::NULL;
+use winapi::shared::windef::*;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::*;
+
+/// Not defined by winapi 0.3
+const RI_MOUSE_HWHEEL: USHORT = 0x0800;
+
+/// The background thread with a message-only window, receiving `WM_INPUT` for the registered devices.
+pub struct RawInputThread {
+    kinds: Vec<HookKind>,
+    thread_id: DWORD,
+    loop_error: Arc<Mutex<Option<DWORD>>>,
+    thread_handle: Option<JoinHandle<()>>,
+    ready: Arc<LoopReady>,
+}
+
+impl Drop for RawInputThread {
+    fn drop(&mut self) {
+        // The thread unregisters the devices and destroys the window on its own, once the message loop ends.
+        if 0 == unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, NULL as WPARAM, NULL as LPARAM) } {
+            // Joining could block forever, unless the message loop already failed and the thread is finishing anyway.
+            if self.loop_error().is_none() {
+                return;
+            }
+        }
+        if let Some(jh) = self.thread_handle.take() {
+            let _ignore_error = jh.join();
+        }
+    }
+}
+
+impl RawInputThread {
+    /// Registers the devices of the given kinds for the raw input in the new thread,
+    /// or returns [HookError::WinApiError] if the window could not be created or the devices registered.
+    pub fn new(kinds: &[HookKind], watch_desktop_switch: bool) -> Result<RawInputThread, HookError> {
+        let kinds = kinds.to_vec();
+        let registered_kinds = kinds.clone();
+
+        let loop_error = Arc::new(Mutex::new(None));
+        let set_loop_error = loop_error.clone();
+        let ready = LoopReady::new();
+        let set_ready = ready.clone();
+
+        // Used to notify the owner with the thread id, or the error code
+        let is_started = Arc::new((Mutex::new(None::<Result<DWORD, DWORD>>), Condvar::new()));
+        let set_started = is_started.clone();
+        let notify = move |result: Result<DWORD, DWORD>| {
+            let (start_lock, start_cvar) = &*set_started;
+            *start_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(result);
+            start_cvar.notify_one();
+        };
+
+        let thread_handle = std::thread::Builder::new().name(String::from("willhook-raw-input")).spawn(move || {
+            apply_thread_priority();
+            // See InnerHook::new, the window receives the input of its desktop only
+            let _input_desktop = match InputDesktop::attach() {
+                Ok(input_desktop) => input_desktop,
+                Err(error_code) => return notify(Err(error_code)),
+            };
+            let hwnd = match unsafe { create_window() } {
+                Ok(hwnd) => hwnd,
+                Err(error_code) => {
+                    log_error!("Creating the raw input window failed, error code {}", error_code);
+                    return notify(Err(error_code))
+                },
+            };
+
+            let mut devices: Vec<RAWINPUTDEVICE> = kinds.iter().map(|kind| RAWINPUTDEVICE {
+                usUsagePage: HID_USAGE_PAGE_GENERIC,
+                usUsage: match kind {
+                    HookKind::Keyboard => HID_USAGE_GENERIC_KEYBOARD,
+                    HookKind::Mouse => HID_USAGE_GENERIC_MOUSE,
+                },
+                // Receive the input regardless of the focus, like the low-level hooks do
+                dwFlags: RIDEV_INPUTSINK,
+                hwndTarget: hwnd,
+            }).collect();
+            let registered = unsafe {
+                RegisterRawInputDevices(devices.as_ptr(), devices.len() as UINT, std::mem::size_of::<RAWINPUTDEVICE>() as UINT)
+            };
+            if registered == FALSE {
+                let error_code = unsafe { GetLastError() };
+                log_error!("RegisterRawInputDevices failed for the {:?} devices, error code {}", kinds, error_code);
+                unsafe { DestroyWindow(hwnd); }
+                return notify(Err(error_code));
+            }
+
+            // See InnerHook::new, the WinEvents are delivered through this thread's message loop as well
+            let _focus_watch = watch_desktop_switch.then(FocusWatch::new);
+
+            log_info!("Registered the {:?} devices for the raw input", kinds);
+            notify(Ok(unsafe { GetCurrentThreadId() }));
+
+            // Unlike the low-level hooks, WM_INPUT is posted to the window, so the messages have to be dispatched.
+            let mut msg = std::mem::MaybeUninit::<MSG>::uninit();
+            loop {
+                match unsafe { GetMessageW(msg.as_mut_ptr(), NULL as HWND, NULL as UINT, NULL as UINT) } {
+                    0 => break,
+                    -1 => {
+                        let error_code = unsafe { GetLastError() };
+                        log_error!("GetMessageW failed in the raw input thread, error code {}", error_code);
+                        *set_loop_error.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(error_code);
+                        break;
+                    },
+                    _ => {
+                        set_ready.set();
+                        unsafe { DispatchMessageW(msg.as_ptr()); }
+                    },
+                }
+            }
+
+            unsafe {
+                for device in devices.iter_mut() {
+                    device.dwFlags = RIDEV_REMOVE;
+                    device.hwndTarget = NULL as HWND;
+                }
+                RegisterRawInputDevices(devices.as_ptr(), devices.len() as UINT, std::mem::size_of::<RAWINPUTDEVICE>() as UINT);
+                DestroyWindow(hwnd);
+            }
+        }).map_err(|_| HookError::ThreadSpawnFailed)?;
+
+        let started = {
+            let (start_lock, start_cvar) = &*is_started;
+            let mut started = start_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            while started.is_none() {
+                started = start_cvar.wait(started).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            started.take()
+        };
+        match started {
+            Some(Ok(thread_id)) => Ok(RawInputThread {
+                kinds: registered_kinds,
+                thread_id,
+                loop_error,
+                thread_handle: Some(thread_handle),
+                ready,
+            }),
+            failed => {
+                // The thread is already finishing, so joining won't block.
+                let _ignore_error = thread_handle.join();
+                Err(HookError::WinApiError(match failed { Some(Err(error_code)) => error_code, _ => 0 }))
+            },
+        }
+    }
+
+    pub fn is_kind_registered(&self, kind: HookKind) -> bool {
+        self.kinds.contains(&kind) && self.loop_error().is_none()
+    }
+
+    /// Returns the id of the thread and its message loop, see [crate::Hook::wait_ready].
+    pub fn message_loop(&self) -> (DWORD, Arc<LoopReady>) {
+        (self.thread_id, self.ready.clone())
+    }
+
+    pub fn loop_error(&self) -> Option<HookError> {
+        self.loop_error.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).map(HookError::MessageLoopFailed)
+    }
+}
+
+/// Creates the message-only window receiving the raw input, registering its class on the first use.
+unsafe fn create_window() -> Result<HWND, DWORD> {
+    let class_name: Vec<u16> = "willhook_raw_input\0".encode_utf16().collect();
+    let instance = GetModuleHandleW(std::ptr::null());
+    let mut class: WNDCLASSEXW = std::mem::zeroed();
+    class.cbSize = std::mem::size_of::<WNDCLASSEXW>() as UINT;
+    class.lpfnWndProc = Some(window_procedure);
+    class.hInstance = instance;
+    class.lpszClassName = class_name.as_ptr();
+    // The class stays registered after the first hook is dropped
+    if RegisterClassExW(&class) == 0 && GetLastError() != 1410 /* ERROR_CLASS_ALREADY_EXISTS */ {
+        return Err(GetLastError());
+    }
+
+    let hwnd = CreateWindowExW(
+        0,
+        class_name.as_ptr(),
+        std::ptr::null(),
+        0,
+        0, 0, 0, 0,
+        HWND_MESSAGE,
+        NULL as HMENU,
+        instance,
+        NULL,
+    );
+    if hwnd == NULL as HWND {
+        Err(GetLastError())
+    } else {
+        Ok(hwnd)
+    }
+}
+
+unsafe extern "system" fn window_procedure(hwnd: HWND, msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    if msg == WM_INPUT {
+        handle_raw_input(l_param as HRAWINPUT);
+    }
+    // DefWindowProc has to be called for WM_INPUT as well, so that the system can clean up
+    DefWindowProcW(hwnd, msg, w_param, l_param)
+}
+
+unsafe fn handle_raw_input(handle: HRAWINPUT) {
+    let mut input: RAWINPUT = std::mem::zeroed();
+    let mut size = std::mem::size_of::<RAWINPUT>() as UINT;
+    let read = GetRawInputData(handle, RID_INPUT, &mut input as *mut RAWINPUT as LPVOID, &mut size, std::mem::size_of::<RAWINPUTHEADER>() as UINT);
+    if read == UINT::MAX || read == 0 {
+        return;
+    }
+
+    // The input injected with SendInput does not come from any device
+    let is_injected = if input.header.hDevice.is_null() { IsEventInjected::Injected } else { IsEventInjected::NotInjected };
+    let time = GetMessageTime() as u32;
+    let extra_info = GetMessageExtraInfo() as ULONG_PTR;
+
+    match input.header.dwType {
+        RIM_TYPEKEYBOARD => {
+            // The probes of Hook::verify can't be swallowed here, raw input is only a copy of the input, but at least they are not delivered
+            if GLOBAL_PROBE.observe(HookKind::Keyboard, extra_info) || GLOBAL_SETTINGS.is_paused() {
+                return;
+            }
+            let mut keyboard_event = keyboard_event(input.data.keyboard(), is_injected, time);
+            keyboard_event.extra_info = extra_info;
+            keyboard_event.modifiers = GLOBAL_PRESSED_MODIFIERS.update(&keyboard_event);
+            keyboard_event.held_for = GLOBAL_PRESSED_KEYS.held_for(&keyboard_event);
+            keyboard_event.chatter = GLOBAL_SETTINGS.chatter_threshold().is_some_and(|threshold| GLOBAL_PRESSED_KEYS.is_chatter(&keyboard_event, threshold));
+            keyboard_event.is_repeat = GLOBAL_PRESSED_KEYS.update(&keyboard_event);
+            GLOBAL_UNKNOWN_MESSAGES.observe(&InputEvent::Keyboard(keyboard_event));
+            if !GLOBAL_SETTINGS.is_dropped(&InputEvent::Keyboard(keyboard_event)) {
+                keyboard_event.seq = next_seq();
+                let _ignore_error = GLOBAL_CHANNEL.send_keyboard_event(keyboard_event).is_err();
+            }
+        },
+        RIM_TYPEMOUSE => {
+            if GLOBAL_PROBE.observe(HookKind::Mouse, extra_info) || GLOBAL_SETTINGS.is_paused() {
+                return;
+            }
+            let mut cursor: POINT = std::mem::zeroed();
+            let point = if GetCursorPos(&mut cursor) == FALSE { None } else { Some(Point::from(cursor)) };
+            for mut mouse_event in mouse_events(input.data.mouse(), point, is_injected, time) {
+                mouse_event.extra_info = extra_info;
+                // Raw input reports the buttons of the device, before the swap
+                if !GLOBAL_SETTINGS.is_physical_buttons_requested() {
+                    swap_primary_button(&mut mouse_event);
+                }
+                GLOBAL_PRESSED_BUTTONS.update(&mouse_event);
+                if !GLOBAL_SETTINGS.is_dropped(&InputEvent::Mouse(mouse_event)) {
+                    mouse_event.seq = next_seq();
+                    let _ignore_error = GLOBAL_CHANNEL.send_mouse_event(mouse_event).is_err();
+                }
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Translates the raw keyboard input to the event, the same as the low-level hook would report.
+fn keyboard_event(raw: &RAWKEYBOARD, is_injected: IsEventInjected, time: u32) -> KeyboardEvent {
+    let physical_key = PhysicalKey { scan_code: raw.MakeCode, extended: raw.Flags as DWORD & RI_KEY_E0 != 0 };
+    KeyboardEvent {
+        pressed: KeyPress::from(raw.Message as WPARAM),
+        // Raw input always reports the modifiers without the side
+        key: Some(KeyboardKey::resolve_sided(raw.VKey as DWORD, physical_key)),
+        is_injected: Some(is_injected),
+        time: Some(time),
+        physical_key: Some(physical_key),
+        modifiers: Modifiers::empty(),
+        is_repeat: false,
+        held_for: None,
+        chatter: false,
+        seq: 0,
+        foreground_pid: None,
+        layout: None,
+        extra_info: 0,
+        blocked: false,
+        raw: None,
+    }
+}
+
+/// Translates the raw mouse input to the events, as one raw input may carry the move, several button transitions and the wheel.
+/// The `point` is the cursor position, as the raw input carries only the relative motion of the device.
+fn mouse_events(raw: &RAWMOUSE, point: Option<Point>, is_injected: IsEventInjected, time: u32) -> Vec<MouseEvent> {
+    use MouseButton::*;
+    use MouseClick::SingleClick;
+    const BUTTONS: [(USHORT, MouseButton, MouseButtonPress); 10] = [
+        (RI_MOUSE_LEFT_BUTTON_DOWN, Left(SingleClick), MouseButtonPress::Down),
+        (RI_MOUSE_LEFT_BUTTON_UP, Left(SingleClick), MouseButtonPress::Up),
+        (RI_MOUSE_RIGHT_BUTTON_DOWN, Right(SingleClick), MouseButtonPress::Down),
+        (RI_MOUSE_RIGHT_BUTTON_UP, Right(SingleClick), MouseButtonPress::Up),
+        (RI_MOUSE_MIDDLE_BUTTON_DOWN, Middle(SingleClick), MouseButtonPress::Down),
+        (RI_MOUSE_MIDDLE_BUTTON_UP, Middle(SingleClick), MouseButtonPress::Up),
+        (RI_MOUSE_BUTTON_4_DOWN, X1(SingleClick), MouseButtonPress::Down),
+        (RI_MOUSE_BUTTON_4_UP, X1(SingleClick), MouseButtonPress::Up),
+        (RI_MOUSE_BUTTON_5_DOWN, X2(SingleClick), MouseButtonPress::Down),
+        (RI_MOUSE_BUTTON_5_UP, X2(SingleClick), MouseButtonPress::Up),
+    ];
+
+    let mut events = Vec::new();
+    let mut push = |event| events.push(MouseEvent { event, is_injected: Some(is_injected), time: Some(time), seq: 0, foreground_pid: None, extra_info: 0, blocked: false, raw: None });
+
+    if raw.usFlags & MOUSE_MOVE_ABSOLUTE != 0 || raw.lLastX != 0 || raw.lLastY != 0 {
+        push(MouseEventType::Move(MouseMoveEvent { point, delta: None }));
+    }
+    for (flag, button, pressed) in BUTTONS {
+        if raw.usButtonFlags & flag != 0 {
+            push(MouseEventType::Press(MousePressEvent { pressed, button }));
+        }
+    }
+    for (flag, wheel) in [(RI_MOUSE_WHEEL, MouseWheel::Vertical), (RI_MOUSE_HWHEEL, MouseWheel::Horizontal)] {
+        if raw.usButtonFlags & flag != 0 {
+            let delta = raw.usButtonData as i16;
+            let direction = match delta {
+                _ if delta > 0 => MouseWheelDirection::Forward,
+                _ if delta < 0 => MouseWheelDirection::Backward,
+                _ => MouseWheelDirection::Unknown(raw.usButtonData as u32),
+            };
+            push(MouseEventType::Wheel(MouseWheelEvent { wheel, direction: Some(direction), delta: Some(delta) }));
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod raw_input_tests {
+    use super::*;
+
+    fn raw_key(vkey: i32, make_code: USHORT, flags: DWORD, message: UINT) -> RAWKEYBOARD {
+        RAWKEYBOARD { MakeCode: make_code, Flags: flags as USHORT, Reserved: 0, VKey: vkey as USHORT, Message: message, ExtraInformation: 0 }
+    }
+
+    #[test]
+    fn modifiers_get_their_side() {
+        use IsEventInjected::NotInjected;
+        let key = |raw: RAWKEYBOARD| keyboard_event(&raw, NotInjected, 0).key;
+        assert_eq!(key(raw_key(VK_SHIFT, 0x2A, RI_KEY_MAKE, WM_KEYDOWN)), Some(KeyboardKey::LeftShift));
+        assert_eq!(key(raw_key(VK_SHIFT, 0x36, RI_KEY_MAKE, WM_KEYDOWN)), Some(KeyboardKey::RightShift));
+        assert_eq!(key(raw_key(VK_CONTROL, 0x1D, RI_KEY_MAKE, WM_KEYDOWN)), Some(KeyboardKey::LeftControl));
+        assert_eq!(key(raw_key(VK_CONTROL, 0x1D, RI_KEY_E0, WM_KEYDOWN)), Some(KeyboardKey::RightControl));
+        assert_eq!(key(raw_key(VK_MENU, 0x38, RI_KEY_E0 | RI_KEY_BREAK, WM_SYSKEYUP)), Some(KeyboardKey::RightAlt));
+    }
+
+    #[test]
+    fn keyboard_event_from_raw_input() {
+        let event = keyboard_event(&raw_key(VK_ESCAPE, 0x01, RI_KEY_BREAK, WM_KEYUP), IsEventInjected::Injected, 42);
+        assert_eq!(event, KeyboardEvent {
+            is_injected: Some(IsEventInjected::Injected),
+            time: Some(42),
+            physical_key: Some(PhysicalKey { scan_code: 0x01, extended: false }),
+            ..KeyboardEvent::for_test(KeyPress::Up(IsSystemKeyPress::Normal), Some(KeyboardKey::Escape))
+        });
+    }
+
+    #[test]
+    fn one_raw_mouse_input_may_carry_several_events() {
+        let mut raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        raw.lLastX = 3;
+        raw.usButtonFlags = RI_MOUSE_LEFT_BUTTON_UP | RI_MOUSE_BUTTON_5_DOWN | RI_MOUSE_WHEEL;
+        raw.usButtonData = -120i16 as USHORT;
+        let point = Some(Point { x: 10, y: 20 });
+        let events: Vec<MouseEventType> = mouse_events(&raw, point, IsEventInjected::NotInjected, 0)
+            .into_iter()
+            .map(|me| me.event)
+            .collect();
+        assert_eq!(events, vec![
+            MouseEventType::Move(MouseMoveEvent { point, delta: None }),
+            MouseEventType::Press(MousePressEvent { pressed: MouseButtonPress::Up, button: MouseButton::Left(MouseClick::SingleClick) }),
+            MouseEventType::Press(MousePressEvent { pressed: MouseButtonPress::Down, button: MouseButton::X2(MouseClick::SingleClick) }),
+            MouseEventType::Wheel(MouseWheelEvent { wheel: MouseWheel::Vertical, direction: Some(MouseWheelDirection::Backward), delta: Some(-120) }),
+        ]);
+    }
+
+    #[test]
+    fn no_motion_no_move() {
+        let raw: RAWMOUSE = unsafe { std::mem::zeroed() };
+        assert!(mouse_events(&raw, None, IsEventInjected::NotInjected, 0).is_empty());
+    }
+}
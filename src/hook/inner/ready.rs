@@ -0,0 +1,58 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use winapi::shared::minwindef::{DWORD, LPARAM, WPARAM};
+use winapi::shared::ntdef::NULL;
+use winapi::um::winuser::{PostThreadMessageW, WM_NULL};
+
+/// Set by the message loop of a hook thread once it retrieved its first message, see [crate::hook::Hook::wait_ready].
+/// The hook procedures are called from within the `GetMessageW`, so a loop that got a message is surely live.
+pub struct LoopReady {
+    pumped: Mutex<bool>,
+    available: Condvar,
+}
+
+impl LoopReady {
+    pub fn new() -> Arc<LoopReady> {
+        Arc::new(LoopReady { pumped: Mutex::new(false), available: Condvar::new() })
+    }
+
+    /// Called by the message loop for every message it retrieved.
+    pub fn set(&self) {
+        let mut pumped = self.lock();
+        if !*pumped {
+            *pumped = true;
+            self.available.notify_all();
+        }
+    }
+
+    /// Waits until the loop of the `thread_id` retrieved a message or the `deadline` passed, returns true in the former case.
+    /// The loop that got no message yet is nudged with the `WM_NULL`, which it ignores.
+    pub fn wait(&self, thread_id: DWORD, deadline: Option<Instant>) -> bool {
+        let mut pumped = self.lock();
+        if *pumped {
+            return true
+        }
+        if 0 == unsafe { PostThreadMessageW(thread_id, WM_NULL, NULL as WPARAM, NULL as LPARAM) } {
+            // The thread has no message queue, so it is not looping
+            return false
+        }
+        while !*pumped {
+            pumped = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false
+                    }
+                    self.available.wait_timeout(pumped, deadline - now).unwrap_or_else(|poisoned| poisoned.into_inner()).0
+                },
+                None => self.available.wait(pumped).unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+        true
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, bool> {
+        self.pumped.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
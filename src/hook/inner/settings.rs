@@ -0,0 +1,373 @@
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU32, Ordering};
+use std::time::Duration;
+
+use winapi::shared::minwindef::INT;
+use winapi::um::winuser::{HC_ACTION, HC_NOREMOVE};
+use winapi::um::winbase::{THREAD_PRIORITY_ABOVE_NORMAL, THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_NORMAL, THREAD_PRIORITY_TIME_CRITICAL};
+
+use crate::event::*;
+use crate::hook::{Edges, EventFilter, ThreadPriority};
+
+/// Marks the disabled [crate::hook::HookBuilder::with_chatter_detection]
+const NO_CHATTER_DETECTION: u32 = u32::MAX;
+
+/// Options consulted by the hook procedures for every event.
+/// The hook procedures must return quickly, so all the options are atomics - no locking is involved.
+/// The only exception are the user's predicates, which are written only when the hook is built or dropped, so reading them never waits.
+pub struct HookSettings {
+    drop_injected: AtomicBool,
+    skip_no_remove: AtomicBool,
+    raw_data: AtomicBool,
+    thread_priority: AtomicI32,
+    input_desktop: AtomicBool,
+    physical_buttons: AtomicBool,
+    key_edges: AtomicU8,
+    button_edges: AtomicU8,
+    keyboard_enabled: AtomicBool,
+    mouse_moves_enabled: AtomicBool,
+    mouse_buttons_enabled: AtomicBool,
+    mouse_wheels_enabled: AtomicBool,
+    paused: AtomicBool,
+    /// In milliseconds, [NO_CHATTER_DETECTION] if disabled
+    chatter_threshold: AtomicU32,
+    filter: RwLock<Option<EventFilter>>,
+    blocking: RwLock<Option<EventFilter>>,
+}
+
+impl HookSettings {
+    pub const fn new() -> HookSettings {
+        HookSettings {
+            drop_injected: AtomicBool::new(false),
+            skip_no_remove: AtomicBool::new(false),
+            raw_data: AtomicBool::new(false),
+            thread_priority: AtomicI32::new(THREAD_PRIORITY_NORMAL as i32),
+            input_desktop: AtomicBool::new(false),
+            physical_buttons: AtomicBool::new(false),
+            key_edges: AtomicU8::new(Edges::Both as u8),
+            button_edges: AtomicU8::new(Edges::Both as u8),
+            keyboard_enabled: AtomicBool::new(true),
+            mouse_moves_enabled: AtomicBool::new(true),
+            mouse_buttons_enabled: AtomicBool::new(true),
+            mouse_wheels_enabled: AtomicBool::new(true),
+            paused: AtomicBool::new(false),
+            chatter_threshold: AtomicU32::new(NO_CHATTER_DETECTION),
+            filter: RwLock::new(None),
+            blocking: RwLock::new(None),
+        }
+    }
+
+    pub fn set_drop_injected(&self, drop_injected: bool) {
+        self.drop_injected.store(drop_injected, Ordering::Relaxed);
+    }
+
+    pub fn set_skip_no_remove(&self, skip_no_remove: bool) {
+        self.skip_no_remove.store(skip_no_remove, Ordering::Relaxed);
+    }
+
+    pub fn set_raw_data(&self, raw_data: bool) {
+        self.raw_data.store(raw_data, Ordering::Relaxed);
+    }
+
+    /// True if the hook procedures should copy their parameters into the events, see [crate::hook::HookBuilder::with_raw].
+    pub fn is_raw_data_requested(&self) -> bool {
+        self.raw_data.load(Ordering::Relaxed)
+    }
+
+    pub fn set_thread_priority(&self, priority: ThreadPriority) {
+        let priority = match priority {
+            ThreadPriority::Normal => THREAD_PRIORITY_NORMAL,
+            ThreadPriority::AboveNormal => THREAD_PRIORITY_ABOVE_NORMAL,
+            ThreadPriority::Highest => THREAD_PRIORITY_HIGHEST,
+            ThreadPriority::TimeCritical => THREAD_PRIORITY_TIME_CRITICAL,
+        };
+        self.thread_priority.store(priority as i32, Ordering::Relaxed);
+    }
+
+    /// The `SetThreadPriority` value for the hook threads, see [crate::hook::HookBuilder::with_thread_priority].
+    pub fn thread_priority(&self) -> i32 {
+        self.thread_priority.load(Ordering::Relaxed)
+    }
+
+    pub fn set_input_desktop(&self, input_desktop: bool) {
+        self.input_desktop.store(input_desktop, Ordering::Relaxed);
+    }
+
+    /// True if the hook threads should switch to the input desktop, see [crate::hook::HookBuilder::on_input_desktop].
+    pub fn is_input_desktop_requested(&self) -> bool {
+        self.input_desktop.load(Ordering::Relaxed)
+    }
+
+    pub fn set_physical_buttons(&self, physical_buttons: bool) {
+        self.physical_buttons.store(physical_buttons, Ordering::Relaxed);
+    }
+
+    /// True if the mouse buttons should be reported as they are on the device, see [crate::hook::HookBuilder::physical_mouse_buttons].
+    pub fn is_physical_buttons_requested(&self) -> bool {
+        self.physical_buttons.load(Ordering::Relaxed)
+    }
+
+    /// Decides if the mouse hook procedure called with the `code` should just pass the event to the next hook.
+    /// Only the `HC_ACTION` and `HC_NOREMOVE` codes carry the input: the negative codes must not be processed at all,
+    /// and the other ones are not documented for the mouse hooks. The `HC_NOREMOVE` ones are skipped on request.
+    pub fn is_skipped_mouse_code(&self, code: INT) -> bool {
+        match code {
+            HC_ACTION => false,
+            HC_NOREMOVE => self.skip_no_remove.load(Ordering::Relaxed),
+            _ => true,
+        }
+    }
+
+    pub fn set_key_edges(&self, edges: Edges) {
+        self.key_edges.store(edges as u8, Ordering::Relaxed);
+    }
+
+    pub fn set_button_edges(&self, edges: Edges) {
+        self.button_edges.store(edges as u8, Ordering::Relaxed);
+    }
+
+    pub fn set_keyboard_enabled(&self, enabled: bool) {
+        self.keyboard_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_mouse_moves_enabled(&self, enabled: bool) {
+        self.mouse_moves_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_mouse_buttons_enabled(&self, enabled: bool) {
+        self.mouse_buttons_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_mouse_wheels_enabled(&self, enabled: bool) {
+        self.mouse_wheels_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// While paused the hook procedures pass the events on untouched, see [crate::Hook::pause].
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_chatter_threshold(&self, threshold: Option<Duration>) {
+        // The longer thresholds make no sense, the keys can't be pressed that slowly
+        let threshold = threshold.map_or(NO_CHATTER_DETECTION, |threshold| threshold.as_millis().min(NO_CHATTER_DETECTION as u128 - 1) as u32);
+        self.chatter_threshold.store(threshold, Ordering::Relaxed);
+    }
+
+    /// The threshold of [crate::hook::HookBuilder::with_chatter_detection] in milliseconds, `None` if it is not requested.
+    pub fn chatter_threshold(&self) -> Option<u32> {
+        let threshold = self.chatter_threshold.load(Ordering::Relaxed);
+        (threshold != NO_CHATTER_DETECTION).then_some(threshold)
+    }
+
+    pub fn set_filter(&self, filter: Option<EventFilter>) {
+        *self.filter.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = filter;
+    }
+
+    pub fn set_blocking(&self, blocking: Option<EventFilter>) {
+        *self.blocking.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = blocking;
+    }
+
+    /// Decides if the event should not be passed to the other hooks and applications, see [crate::hook::HookBuilder::with_blocking].
+    pub fn is_blocked(&self, event: &InputEvent) -> bool {
+        let blocking = self.blocking.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        matches!(&*blocking, Some(blocks) if blocks(event))
+    }
+
+    /// Enables all the event kinds and resumes, so that each new hook starts with everything delivered.
+    pub fn enable_all(&self) {
+        self.set_paused(false);
+        self.set_keyboard_enabled(true);
+        self.set_mouse_moves_enabled(true);
+        self.set_mouse_buttons_enabled(true);
+        self.set_mouse_wheels_enabled(true);
+    }
+
+    /// Decides if the event should not be sent to the client.
+    pub fn is_dropped(&self, event: &InputEvent) -> bool {
+        !self.is_kind_enabled(event)
+            || (self.drop_injected.load(Ordering::Relaxed) && event.is_injected())
+            || self.is_unwanted_edge(event)
+            || self.is_filtered_out(event)
+    }
+
+    fn is_filtered_out(&self, event: &InputEvent) -> bool {
+        let filter = self.filter.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        matches!(&*filter, Some(accepts) if !accepts(event))
+    }
+
+    fn is_kind_enabled(&self, event: &InputEvent) -> bool {
+        let enabled = match event {
+            InputEvent::Keyboard(_) => &self.keyboard_enabled,
+            InputEvent::Mouse(me) => match me.event {
+                MouseEventType::Move(_) => &self.mouse_moves_enabled,
+                MouseEventType::Press(_) => &self.mouse_buttons_enabled,
+                MouseEventType::Wheel(_) => &self.mouse_wheels_enabled,
+                MouseEventType::Other(_) => return true,
+            },
+            _ => return true,
+        };
+        enabled.load(Ordering::Relaxed)
+    }
+
+    fn is_unwanted_edge(&self, event: &InputEvent) -> bool {
+        // true => down, false => up, None => not an edge at all (never dropped)
+        let (edges, is_down) = match event {
+            InputEvent::Keyboard(ke) => (&self.key_edges, match ke.pressed {
+                KeyPress::Down(_) => Some(true),
+                KeyPress::Up(_) => Some(false),
+                KeyPress::Other(_) => None,
+            }),
+            InputEvent::Mouse(MouseEvent{ event: MouseEventType::Press(press), .. }) => (&self.button_edges, match press.pressed {
+                MouseButtonPress::Down => Some(true),
+                MouseButtonPress::Up => Some(false),
+                MouseButtonPress::Other(_) => None,
+            }),
+            _ => return false,
+        };
+        let edges = edges.load(Ordering::Relaxed);
+        match is_down {
+            Some(true) => edges == Edges::UpOnly as u8,
+            Some(false) => edges == Edges::DownOnly as u8,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod hook_settings_tests {
+    use super::*;
+
+    fn key(pressed: KeyPress) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent {
+            is_injected: Some(IsEventInjected::NotInjected),
+            ..KeyboardEvent::for_test(pressed, Some(KeyboardKey::A))
+        })
+    }
+
+    fn button(pressed: MouseButtonPress) -> InputEvent {
+        InputEvent::Mouse(MouseEvent {
+            is_injected: Some(IsEventInjected::NotInjected),
+            ..MouseEvent::for_test(MouseEventType::Press(MousePressEvent { pressed, button: MouseButton::Left(MouseClick::SingleClick) }))
+        })
+    }
+
+    #[test]
+    fn all_edges_are_delivered_by_default() {
+        let settings = HookSettings::new();
+        assert!(!settings.is_dropped(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+        assert!(!settings.is_dropped(&key(KeyPress::Up(IsSystemKeyPress::Normal))));
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Down)));
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Up)));
+    }
+
+    #[test]
+    fn key_and_button_edges_are_filtered_separately() {
+        let settings = HookSettings::new();
+        settings.set_key_edges(Edges::DownOnly);
+        settings.set_button_edges(Edges::UpOnly);
+
+        assert!(!settings.is_dropped(&key(KeyPress::Down(IsSystemKeyPress::System))));
+        assert!(settings.is_dropped(&key(KeyPress::Up(IsSystemKeyPress::System))));
+        assert!(!settings.is_dropped(&key(KeyPress::Other(0))));
+        assert!(settings.is_dropped(&button(MouseButtonPress::Down)));
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Up)));
+        assert!(!settings.is_dropped(&InputEvent::Reset));
+    }
+
+    #[test]
+    fn injected_events_are_dropped_on_request() {
+        let settings = HookSettings::new();
+        let injected = InputEvent::Mouse(MouseEvent {
+            is_injected: Some(IsEventInjected::Injected),
+            ..MouseEvent::for_test(MouseEventType::Move(MouseMoveEvent{ point: None, delta: None }))
+        });
+        assert!(injected.is_injected());
+        assert!(!key(KeyPress::Down(IsSystemKeyPress::Normal)).is_injected());
+        assert!(!InputEvent::Reset.is_injected());
+        assert!(!settings.is_dropped(&injected));
+
+        settings.set_drop_injected(true);
+        assert!(settings.is_dropped(&injected));
+        assert!(!settings.is_dropped(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+    }
+
+    #[test]
+    fn filter_drops_rejected_events() {
+        let settings = HookSettings::new();
+        settings.set_filter(Some(Box::new(|event| matches!(event, InputEvent::Keyboard(_)))));
+        assert!(!settings.is_dropped(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+        assert!(settings.is_dropped(&button(MouseButtonPress::Down)));
+
+        settings.set_filter(None);
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Down)));
+    }
+
+    #[test]
+    fn nothing_is_blocked_by_default() {
+        let settings = HookSettings::new();
+        assert!(!settings.is_blocked(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+
+        settings.set_blocking(Some(Box::new(|event| matches!(event, InputEvent::Mouse(_)))));
+        assert!(!settings.is_blocked(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+        assert!(settings.is_blocked(&button(MouseButtonPress::Down)));
+        // Blocking does not affect the delivery
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Down)));
+    }
+
+    #[test]
+    fn no_remove_code_is_skipped_on_request() {
+        use winapi::um::winuser::{HC_GETNEXT, HC_SKIP};
+        let settings = HookSettings::new();
+        assert!(settings.is_skipped_mouse_code(-1));
+        assert!(!settings.is_skipped_mouse_code(HC_ACTION));
+        assert!(!settings.is_skipped_mouse_code(HC_NOREMOVE));
+        // Not documented for the mouse hooks, they don't carry the input
+        assert!(settings.is_skipped_mouse_code(HC_GETNEXT));
+        assert!(settings.is_skipped_mouse_code(HC_SKIP));
+        assert!(settings.is_skipped_mouse_code(42));
+
+        settings.set_skip_no_remove(true);
+        assert!(settings.is_skipped_mouse_code(-1));
+        assert!(!settings.is_skipped_mouse_code(HC_ACTION));
+        assert!(settings.is_skipped_mouse_code(HC_NOREMOVE));
+    }
+
+    #[test]
+    fn disabled_kinds_are_dropped() {
+        let settings = HookSettings::new();
+        settings.set_keyboard_enabled(false);
+        settings.set_mouse_moves_enabled(false);
+        assert!(settings.is_dropped(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Down)));
+        let mouse_move = InputEvent::Mouse(MouseEvent::for_test(MouseEventType::Move(MouseMoveEvent{ point: None, delta: None })));
+        assert!(settings.is_dropped(&mouse_move));
+
+        settings.set_mouse_buttons_enabled(false);
+        assert!(settings.is_dropped(&button(MouseButtonPress::Down)));
+
+        settings.set_paused(true);
+        settings.enable_all();
+        assert!(!settings.is_paused());
+        assert!(!settings.is_dropped(&key(KeyPress::Down(IsSystemKeyPress::Normal))));
+        assert!(!settings.is_dropped(&button(MouseButtonPress::Down)));
+        assert!(!settings.is_dropped(&mouse_move));
+    }
+
+    #[test]
+    fn chatter_threshold_is_in_milliseconds() {
+        let settings = HookSettings::new();
+        assert_eq!(settings.chatter_threshold(), None);
+        settings.set_chatter_threshold(Some(Duration::from_micros(15_900)));
+        assert_eq!(settings.chatter_threshold(), Some(15));
+        settings.set_chatter_threshold(Some(Duration::ZERO));
+        assert_eq!(settings.chatter_threshold(), Some(0));
+        settings.set_chatter_threshold(Some(Duration::MAX));
+        assert_eq!(settings.chatter_threshold(), Some(u32::MAX - 1));
+        settings.set_chatter_threshold(None);
+        assert_eq!(settings.chatter_threshold(), None);
+    }
+}
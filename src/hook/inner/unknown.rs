@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use crate::event::*;
+
+/// How many different unknown messages are counted, the ones seen after them are not.
+const SLOTS: usize = 16;
+
+/// The messages the hook procedures did not recognize, see [crate::hook::HookBuilder::count_unknown_messages].
+/// Each message claims a slot the first time it is seen. Lock-free, as it is updated inside the hook procedures.
+pub struct UnknownMessages {
+    enabled: AtomicBool,
+    /// The message + 1, so that 0 marks the free slot
+    messages: [AtomicUsize; SLOTS],
+    counts: [AtomicU64; SLOTS],
+}
+
+impl UnknownMessages {
+    pub const fn new() -> UnknownMessages {
+        // Every element is a new atomic, the constants are only the initializers
+        #[allow(clippy::declare_interior_mutable_const)]
+        const FREE: AtomicUsize = AtomicUsize::new(0);
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        UnknownMessages {
+            enabled: AtomicBool::new(false),
+            messages: [FREE; SLOTS],
+            counts: [ZERO; SLOTS],
+        }
+    }
+
+    /// Enables or disables the counting and forgets the messages counted so far.
+    pub fn configure(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        for (message, count) in self.messages.iter().zip(&self.counts) {
+            message.store(0, Ordering::Relaxed);
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Counts the message of the event, if the hook procedure did not recognize it.
+    pub fn observe(&self, event: &InputEvent) {
+        if !self.is_enabled() {
+            return
+        }
+        match event {
+            InputEvent::Keyboard(KeyboardEvent { pressed: KeyPress::Other(message), .. }) => self.count(*message),
+            InputEvent::Mouse(MouseEvent { event: MouseEventType::Other(message), .. }) => self.count(*message),
+            _ => (),
+        }
+    }
+
+    fn count(&self, message: usize) {
+        let key = message.wrapping_add(1);
+        for (slot, count) in self.messages.iter().zip(&self.counts) {
+            match slot.compare_exchange(0, key, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    // Logged only the first time, the hook procedures must return quickly
+                    log_warn!("The hook received the unknown message {:#x}", message);
+                },
+                Err(claimed) if claimed == key => (),
+                Err(_) => continue,
+            }
+            count.fetch_add(1, Ordering::Relaxed);
+            return
+        }
+    }
+
+    /// The messages counted so far with their counts, in the order they were first seen.
+    pub fn counts(&self) -> Vec<(usize, u64)> {
+        self.messages.iter().zip(&self.counts)
+            .map(|(message, count)| (message.load(Ordering::Relaxed), count.load(Ordering::Relaxed)))
+            .take_while(|(key, _)| *key != 0)
+            .map(|(key, count)| (key.wrapping_sub(1), count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod unknown_messages_tests {
+    use super::*;
+
+    fn key(pressed: KeyPress) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent::for_test(pressed, None))
+    }
+
+    #[test]
+    fn nothing_is_counted_when_disabled() {
+        let unknown = UnknownMessages::new();
+        unknown.observe(&key(KeyPress::Other(0x1234)));
+        assert!(unknown.counts().is_empty());
+    }
+
+    #[test]
+    fn unknown_messages_are_counted_separately() {
+        let unknown = UnknownMessages::new();
+        unknown.configure(true);
+        unknown.observe(&key(KeyPress::Other(0x1234)));
+        unknown.observe(&key(KeyPress::Down(IsSystemKeyPress::Normal)));
+        unknown.observe(&key(KeyPress::Other(0)));
+        unknown.observe(&key(KeyPress::Other(0x1234)));
+        unknown.observe(&InputEvent::Reset);
+        assert_eq!(unknown.counts(), vec![(0x1234, 2), (0, 1)]);
+
+        // The messages beyond the slots are not counted
+        for message in 1..=SLOTS {
+            unknown.observe(&key(KeyPress::Other(message)));
+        }
+        assert_eq!(unknown.counts().len(), SLOTS);
+
+        unknown.configure(true);
+        assert!(unknown.counts().is_empty());
+    }
+}
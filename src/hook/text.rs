@@ -0,0 +1,133 @@
+use crate::event::*;
+
+use crate::event::details::to_unicode;
+
+use winapi::shared::minwindef::BYTE;
+use winapi::um::winuser::{GetKeyState, VK_CAPITAL, VK_CONTROL, VK_MENU, VK_SHIFT};
+
+/// Accumulates the typed text from the keyboard events, see [crate::hook::HookBuilder::accumulate_text].
+/// It runs on the consumer thread, so the dead keys are composed in that thread's keyboard state,
+/// without disturbing the application the user is typing into.
+pub(super) struct TextAccumulator {
+    buffer: String,
+    shift: bool,
+    control: bool,
+    alt: bool,
+    caps_lock: bool,
+}
+
+impl TextAccumulator {
+    pub fn new() -> TextAccumulator {
+        TextAccumulator {
+            buffer: String::new(),
+            shift: false,
+            control: false,
+            alt: false,
+            // The toggle state is the lowest bit
+            caps_lock: unsafe { GetKeyState(VK_CAPITAL) } & 1 != 0,
+        }
+    }
+
+    /// Feeds the event, returns the finished word if the event typed a whitespace.
+    /// The `layout` is the raw `HKL` value used for the translation.
+    pub fn feed(&mut self, event: &InputEvent, layout: usize) -> Option<String> {
+        let ke = match event {
+            InputEvent::Keyboard(ke) => ke,
+            InputEvent::Reset => {
+                // Modifiers might have been released on the other desktop, and the text is probably not continued
+                *self = TextAccumulator::new();
+                return None
+            },
+            _ => return None,
+        };
+        let key = ke.key?;
+        let is_down = match ke.pressed {
+            KeyPress::Down(_) => true,
+            KeyPress::Up(_) => false,
+            KeyPress::Other(_) => return None,
+        };
+
+        match key {
+            KeyboardKey::LeftShift | KeyboardKey::RightShift => self.shift = is_down,
+            KeyboardKey::LeftControl | KeyboardKey::RightControl => self.control = is_down,
+            KeyboardKey::LeftAlt | KeyboardKey::RightAlt => self.alt = is_down,
+            KeyboardKey::CapsLock if is_down => self.caps_lock = !self.caps_lock,
+            KeyboardKey::BackSpace if is_down => { self.buffer.pop(); },
+            _ if is_down => return self.type_key(key, layout),
+            _ => (),
+        }
+        None
+    }
+
+    /// Returns the text typed so far (if any) and starts over.
+    pub fn take(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.buffer))
+        }
+    }
+
+    fn type_key(&mut self, key: KeyboardKey, layout: usize) -> Option<String> {
+        let mut word = None;
+        for c in self.translate(key, layout) {
+            if c.is_whitespace() {
+                word = word.or(self.take());
+            } else if !c.is_control() {
+                self.buffer.push(c);
+            }
+        }
+        word
+    }
+
+    fn translate(&self, key: KeyboardKey, layout: usize) -> Vec<char> {
+        let vk = match key.virtual_key_code() {
+            Some(vk) => vk,
+            None => return Vec::new(),
+        };
+
+        let mut key_state: [BYTE; 256] = [0; 256];
+        let pressed = |on: bool| if on { 0x80 } else { 0 };
+        key_state[VK_SHIFT as usize] = pressed(self.shift);
+        key_state[VK_CONTROL as usize] = pressed(self.control);
+        key_state[VK_MENU as usize] = pressed(self.alt);
+        key_state[VK_CAPITAL as usize] = if self.caps_lock { 1 } else { 0 };
+
+        // The dead key stays in this thread's keyboard state, it will be composed with the next key
+        to_unicode(vk, &key_state, layout, 0)
+    }
+}
+
+#[cfg(test)]
+mod text_accumulator_tests {
+    use super::*;
+
+    fn key(key: KeyboardKey, pressed: KeyPress) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent::for_test(pressed, Some(key)))
+    }
+
+    #[test]
+    fn backspace_removes_last_character() {
+        let mut text = TextAccumulator::new();
+        text.buffer.push_str("ab");
+        assert_eq!(text.feed(&key(KeyboardKey::BackSpace, KeyPress::Down(IsSystemKeyPress::Normal)), 0), None);
+        assert_eq!(text.take(), Some(String::from("a")));
+        assert_eq!(text.take(), None);
+    }
+
+    #[test]
+    fn modifiers_are_tracked_and_reset() {
+        let mut text = TextAccumulator::new();
+        text.feed(&key(KeyboardKey::LeftShift, KeyPress::Down(IsSystemKeyPress::Normal)), 0);
+        text.feed(&key(KeyboardKey::RightControl, KeyPress::Down(IsSystemKeyPress::Normal)), 0);
+        assert!(text.shift && text.control && !text.alt);
+
+        text.feed(&key(KeyboardKey::LeftShift, KeyPress::Up(IsSystemKeyPress::Normal)), 0);
+        assert!(!text.shift && text.control);
+
+        text.buffer.push('x');
+        text.feed(&InputEvent::Reset, 0);
+        assert!(!text.control);
+        assert_eq!(text.take(), None);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::hook::inner::GLOBAL_HOOK;
+use crate::hook::{probe_hooks, HookError};
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How long the watchdog waits for its probes, see [crate::hook::Hook::verify].
+const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Periodically checks that the low-level hooks are still called and reinstalls them if they are not,
+/// see [crate::hook::HookBuilder::with_auto_reinstall]. Stopped and joined when dropped.
+pub(super) struct Watchdog {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub fn new(interval: Duration, watch_desktop_switch: bool, shared_thread: bool) -> Result<Watchdog, HookError> {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let stop_requested = stop.clone();
+        let thread_handle = std::thread::Builder::new().name(String::from("willhook-watchdog")).spawn(move || {
+            let (stopped, wakeup) = &*stop_requested;
+            loop {
+                // Waiting on the condition variable, so that dropping the hook does not wait for the whole interval
+                let guard = stopped.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let (guard, _) = wakeup.wait_timeout_while(guard, interval, |stopped| !*stopped).unwrap_or_else(|poisoned| poisoned.into_inner());
+                if *guard {
+                    break
+                }
+                drop(guard);
+
+                let installed = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).is_installed();
+                // The probes can't be injected e.g. while the secure desktop is shown, try again later
+                if installed && probe_hooks(PROBE_TIMEOUT) != Some(false) {
+                    continue
+                }
+                log_warn!("The hooks stopped working, reinstalling them");
+                let mut global_hooks = GLOBAL_HOOK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                // If it fails, then nothing is installed, but the kinds to install are kept and the next check tries again
+                if let Err(error) = global_hooks.reinstall_hooks(watch_desktop_switch, shared_thread) {
+                    log_error!("Reinstalling the hooks failed: {}", error);
+                }
+            }
+        }).map_err(|_| HookError::ThreadSpawnFailed)?;
+        Ok(Watchdog { stop, thread_handle: Some(thread_handle) })
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        let (stopped, wakeup) = &*self.stop;
+        *stopped.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        wakeup.notify_one();
+        if let Some(thread_handle) = self.thread_handle.take() {
+            let _ = thread_handle.join();
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Watching for the key combinations, like Ctrl+Shift+K, on top of the keyboard events of the [crate::Hook].
+//!
+//! The combinations are recognized on the thread that receives the events, not in the hook procedures,
+//! so the callbacks may take their time. A [Hotkey] fires once per press of the combination:
+//! holding it down does not fire it again with every auto-repeat of the key.
+//!
+//! ```rust
+//! # fn main() {
+//! use willhook::hotkey::{Hotkey, HotkeyWatcher};
+//! let hook = willhook::keyboard_hook().unwrap();
+//! let (sender, triggered) = std::sync::mpsc::channel();
+//! let mut watcher = HotkeyWatcher::new()
+//!     .on("Ctrl+Shift+K".parse().unwrap(), move |hotkey: &Hotkey| { let _ = sender.send(*hotkey); });
+//! while let Ok(event) = hook.try_recv() {
+//!     watcher.feed(&event);
+//! }
+//! assert!(triggered.try_recv().is_err());
+//! # }
+//! ```
+//!
+//! The [HotkeyWatcher] is an [EventSink] as well, so it can be handed over to [crate::HookBuilder::with_sink].
+
+use crate::event::*;
+use crate::sink::EventSink;
+
+use std::str::FromStr;
+
+/// The modifiers of the combination, without telling the left and the right key apart
+const MODIFIER_GROUPS: [(Modifiers, &str); 4] = [
+    (Modifiers::from_bits(Modifiers::LEFT_CONTROL.bits() | Modifiers::RIGHT_CONTROL.bits()), "Ctrl"),
+    (Modifiers::from_bits(Modifiers::LEFT_SHIFT.bits() | Modifiers::RIGHT_SHIFT.bits()), "Shift"),
+    (Modifiers::from_bits(Modifiers::LEFT_ALT.bits() | Modifiers::RIGHT_ALT.bits()), "Alt"),
+    (Modifiers::from_bits(Modifiers::LEFT_WINDOWS.bits() | Modifiers::RIGHT_WINDOWS.bits()), "Win"),
+];
+
+/// A combination of the modifier keys and the trigger key, e.g. Ctrl+Shift+K.
+///
+/// It is satisfied by the key down of the trigger key, while exactly the given modifiers are held: Ctrl+K does not fire
+/// on Ctrl+Shift+K. Either of the left and right modifier keys counts, e.g. [Modifiers::LEFT_CONTROL] stands for both controls.
+/// The modifiers are the ones tracked by the hook, see [Modifiers].
+///
+/// ```rust
+/// # use willhook::event::*;
+/// # use willhook::hotkey::Hotkey;
+/// let hotkey = Hotkey::new(Modifiers::LEFT_CONTROL | Modifiers::LEFT_SHIFT, KeyboardKey::K);
+/// assert_eq!(hotkey, "ctrl+shift+k".parse().unwrap());
+/// assert_eq!(hotkey.to_string(), "Ctrl+Shift+K");
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Hotkey {
+    modifiers: Modifiers,
+    key: KeyboardKey,
+    /// The combination fired and the trigger key was not released yet
+    held: bool,
+}
+
+impl Hotkey {
+    pub fn new(modifiers: Modifiers, key: KeyboardKey) -> Hotkey {
+        // Both sides of the modifier, so that the equal combinations compare equal
+        let modifiers = MODIFIER_GROUPS.iter()
+            .filter(|(group, _)| modifiers.intersects(*group))
+            .fold(Modifiers::empty(), |all, (group, _)| all | *group);
+        Hotkey { modifiers, key, held: false }
+    }
+
+    /// The modifiers of the combination, with both the left and the right flag of each.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// The key that completes the combination.
+    pub fn key(&self) -> KeyboardKey {
+        self.key
+    }
+
+    /// Feeds the event, returns true if it completed the combination.
+    /// The auto-repeat of the held combination does not fire it again, the trigger key has to be released first.
+    /// The [InputEvent::Reset] forgets the held combination as well, as the release may have been missed.
+    pub fn triggered(&mut self, event: &InputEvent) -> bool {
+        let ke = match event {
+            InputEvent::Keyboard(ke) if ke.key == Some(self.key) => ke,
+            InputEvent::Reset => {
+                self.held = false;
+                return false
+            },
+            _ => return false,
+        };
+        match ke.pressed {
+            KeyPress::Down(_) if !self.held && self.are_modifiers_held(ke) => {
+                self.held = true;
+                true
+            },
+            KeyPress::Up(_) => {
+                self.held = false;
+                false
+            },
+            _ => false,
+        }
+    }
+
+    fn are_modifiers_held(&self, ke: &KeyboardEvent) -> bool {
+        // The trigger key itself may be a modifier, e.g. Ctrl+LeftShift, its own press does not count
+        let own = Modifiers::from_key(self.key).unwrap_or(Modifiers::empty());
+        let held = Modifiers::from_bits(ke.modifiers.bits() & !own.bits());
+        MODIFIER_GROUPS.iter().all(|(group, _)| held.intersects(*group) == self.modifiers.intersects(*group))
+    }
+}
+
+// The combinations are compared, not whether they are held at the moment
+impl PartialEq for Hotkey {
+    fn eq(&self, other: &Hotkey) -> bool {
+        (self.modifiers, self.key) == (other.modifiers, other.key)
+    }
+}
+
+impl Eq for Hotkey {}
+
+impl std::hash::Hash for Hotkey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.modifiers, self.key).hash(state)
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    /// Displays e.g. `Ctrl+Shift+K`, the modifiers in the order Ctrl, Shift, Alt, Win.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (group, name) in MODIFIER_GROUPS.iter() {
+            if self.modifiers.intersects(*group) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = ParseNameError;
+
+    /// Parses the modifiers and the key joined with `+` (case insensitive), e.g. `"Ctrl+Shift+K"`.
+    /// The modifiers are `Ctrl` (or `Control`), `Shift`, `Alt` and `Win` (or `Windows`), the key is parsed as [KeyboardKey].
+    fn from_str(s: &str) -> Result<Hotkey, ParseNameError> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().ok_or_else(|| ParseNameError::new(s))?.parse::<KeyboardKey>()?;
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            let group = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => MODIFIER_GROUPS[0].0,
+                "shift" => MODIFIER_GROUPS[1].0,
+                "alt" => MODIFIER_GROUPS[2].0,
+                "win" | "windows" => MODIFIER_GROUPS[3].0,
+                _ => return Err(ParseNameError::new(part)),
+            };
+            modifiers |= group;
+        }
+        Ok(Hotkey::new(modifiers, key))
+    }
+}
+
+/// Called with the [Hotkey] that fired, see [HotkeyWatcher::on].
+type HotkeyCallback = Box<dyn FnMut(&Hotkey) + Send + 'static>;
+
+/// Runs the callbacks of the registered [Hotkey]s, when they fire in the fed events.
+pub struct HotkeyWatcher {
+    hotkeys: Vec<(Hotkey, HotkeyCallback)>,
+}
+
+impl HotkeyWatcher {
+    pub fn new() -> HotkeyWatcher {
+        HotkeyWatcher { hotkeys: Vec::new() }
+    }
+
+    /// Registers the `callback` to run every time the `hotkey` fires.
+    /// To receive the hotkeys through the channel instead, send them from the callback.
+    pub fn on<F>(mut self, hotkey: Hotkey, callback: F) -> Self
+    where
+        F: FnMut(&Hotkey) + Send + 'static,
+    {
+        self.hotkeys.push((hotkey, Box::new(callback)));
+        self
+    }
+
+    /// Feeds the event to all the hotkeys, runs the callbacks of the ones that fired (in the order they were registered)
+    /// and returns how many of them fired.
+    pub fn feed(&mut self, event: &InputEvent) -> usize {
+        let mut fired = 0;
+        for (hotkey, callback) in self.hotkeys.iter_mut() {
+            if hotkey.triggered(event) {
+                callback(hotkey);
+                fired += 1;
+            }
+        }
+        fired
+    }
+}
+
+impl Default for HotkeyWatcher {
+    fn default() -> HotkeyWatcher {
+        HotkeyWatcher::new()
+    }
+}
+
+impl EventSink for HotkeyWatcher {
+    fn handle(&mut self, event: &InputEvent) {
+        self.feed(event);
+    }
+}
+
+#[cfg(test)]
+mod hotkey_tests {
+    use super::*;
+
+    fn key(key: KeyboardKey, pressed: KeyPress, modifiers: Modifiers) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent { modifiers, ..KeyboardEvent::for_test(pressed, Some(key)) })
+    }
+
+    const DOWN: KeyPress = KeyPress::Down(IsSystemKeyPress::Normal);
+    const UP: KeyPress = KeyPress::Up(IsSystemKeyPress::Normal);
+
+    #[test]
+    fn fires_once_per_press() {
+        let ctrl_shift = Modifiers::LEFT_CONTROL | Modifiers::RIGHT_SHIFT;
+        let mut hotkey: Hotkey = "Ctrl+Shift+K".parse().unwrap();
+        assert!(!hotkey.triggered(&key(KeyboardKey::K, DOWN, Modifiers::LEFT_CONTROL)));
+        assert!(hotkey.triggered(&key(KeyboardKey::K, DOWN, ctrl_shift)));
+        // The auto-repeat
+        assert!(!hotkey.triggered(&key(KeyboardKey::K, DOWN, ctrl_shift)));
+        assert!(!hotkey.triggered(&key(KeyboardKey::K, UP, ctrl_shift)));
+        assert!(hotkey.triggered(&key(KeyboardKey::K, DOWN, ctrl_shift)));
+        assert!(!hotkey.triggered(&InputEvent::Reset));
+        assert!(hotkey.triggered(&key(KeyboardKey::K, DOWN, ctrl_shift)));
+    }
+
+    #[test]
+    fn extra_modifiers_or_other_keys_do_not_fire() {
+        let mut hotkey = Hotkey::new(Modifiers::RIGHT_CONTROL, KeyboardKey::K);
+        assert!(!hotkey.triggered(&key(KeyboardKey::K, DOWN, Modifiers::LEFT_CONTROL | Modifiers::LEFT_ALT)));
+        assert!(!hotkey.triggered(&key(KeyboardKey::J, DOWN, Modifiers::LEFT_CONTROL)));
+        assert!(hotkey.triggered(&key(KeyboardKey::K, DOWN, Modifiers::LEFT_CONTROL)));
+    }
+
+    #[test]
+    fn modifier_can_be_the_trigger() {
+        let mut hotkey: Hotkey = "Ctrl+LeftShift".parse().unwrap();
+        assert!(hotkey.triggered(&key(KeyboardKey::LeftShift, DOWN, Modifiers::LEFT_CONTROL | Modifiers::LEFT_SHIFT)));
+    }
+
+    #[test]
+    fn names_are_parsed_and_displayed() {
+        let hotkey: Hotkey = "win + ALT + f4".parse().unwrap();
+        assert_eq!(hotkey, Hotkey::new(Modifiers::LEFT_ALT | Modifiers::RIGHT_WINDOWS, KeyboardKey::F4));
+        assert_eq!(hotkey.to_string(), "Alt+Win+F4");
+        assert!("Hyper+K".parse::<Hotkey>().is_err());
+        assert!("Ctrl+".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn watcher_runs_the_callbacks() {
+        use std::sync::{Arc, Mutex};
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let on_fired = fired.clone();
+        let mut watcher = HotkeyWatcher::new()
+            .on("Ctrl+K".parse().unwrap(), move |hotkey: &Hotkey| on_fired.lock().unwrap().push(hotkey.key()))
+            .on("K".parse().unwrap(), |_: &Hotkey| ());
+        assert_eq!(watcher.feed(&key(KeyboardKey::K, DOWN, Modifiers::LEFT_CONTROL)), 1);
+        assert_eq!(watcher.feed(&key(KeyboardKey::K, UP, Modifiers::LEFT_CONTROL)), 0);
+        assert_eq!(watcher.feed(&key(KeyboardKey::K, DOWN, Modifiers::empty())), 1);
+        assert_eq!(*fired.lock().unwrap(), vec![KeyboardKey::K]);
+    }
+}
@@ -0,0 +1,64 @@
+//! Selection of the keyboard layout used to translate keys into text.
+//!
+//! On Windows the keyboard layout (`HKL`) is a per-thread setting. Each GUI thread has its own active layout,
+//! and the user switching the layout (e.g. with Win+Space) changes it only for the thread of the foreground window.
+//! A background thread, like the ones running the low-level hooks or the thread consuming the events,
+//! usually keeps the layout that was active when it started.
+//!
+//! That's why by default the layout of the foreground window's thread is used - this is the layout the user is actually typing with.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::NULL;
+use winapi::um::winuser::{GetForegroundWindow, GetKeyboardLayout, GetWindowThreadProcessId};
+
+/// Where to take the keyboard layout from, see the [module](crate::layout) documentation.
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LayoutSource {
+    /// The layout of the thread owning the foreground window.
+    /// Falls back to [LayoutSource::CurrentThread] if there is no foreground window (e.g. during desktop switch).
+    #[default]
+    ForegroundThread,
+    /// The layout of the thread that does the translation.
+    CurrentThread,
+    /// The specific layout, given as the raw `HKL` value (e.g. from `LoadKeyboardLayoutW`).
+    Specific(usize),
+}
+
+impl LayoutSource {
+    /// Returns the raw `HKL` value of the keyboard layout.
+    /// The value is queried at the moment of the call, as the user may switch the layout at any time.
+    pub fn resolve(&self) -> usize {
+        match *self {
+            LayoutSource::ForegroundThread => unsafe {
+                let window = GetForegroundWindow();
+                if window.is_null() {
+                    GetKeyboardLayout(NULL as DWORD) as usize
+                } else {
+                    let thread_id = GetWindowThreadProcessId(window, std::ptr::null_mut());
+                    GetKeyboardLayout(thread_id) as usize
+                }
+            },
+            LayoutSource::CurrentThread => unsafe { GetKeyboardLayout(NULL as DWORD) as usize },
+            LayoutSource::Specific(hkl) => hkl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod layout_source_tests {
+    use super::LayoutSource;
+
+    #[test]
+    fn specific_layout_is_used_as_is() {
+        assert_eq!(LayoutSource::Specific(0x04090409).resolve(), 0x04090409);
+    }
+
+    #[test]
+    fn foreground_thread_is_default() {
+        assert_eq!(LayoutSource::default(), LayoutSource::ForegroundThread);
+    }
+}
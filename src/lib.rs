@@ -14,10 +14,11 @@
 //! 
 //! ### What this crate does NOT provide
 //! 
-//! This crate is intended for "read-only" access to hooks. It does not support injecting input events or altering them.
+//! This crate is intended mostly for "read-only" access to hooks. The events can be swallowed (see [hook::HookBuilder::with_blocking]),
+//! and injected with the "inject" feature (see the `replay` module), but they can't be altered on their way to the other applications.
 //! If you are looking for that kind of functionality, you can give [mki](https://crates.io/crates/mki) a try.
 //! In comparison, the mki crate supports also Linux, but does not cleanup the low-level hooks (by [unhooking them](https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-unhookwindowshookex)) and threads behind them (by [joinging with them](https://doc.rust-lang.org/std/thread/struct.JoinHandle.html#method.join)).
-//! This *may* not be an issue for you. The addition of "injecting" and "altering" input events to [willhook] is a possibility, although it is not top priority.
+//! This *may* not be an issue for you.
 //! 
 //! # Warning: The current state
 //! 
@@ -36,17 +37,13 @@
 //! - document unsafe code before I forget all the quirks :-)
 //! - maybe write more unit tests
 //! - maybe improve the crate partitioning to modules (without breaking the API)
-//! - maybe rework underlying channels, so that they are dropped with the hook (now they are just drained)
-//! - maybe add injecting events
-//! - maybe add blocking events, if even possible
 //! - maybe add manipulating events, if even possible
-//! - maybe add information about time of the event
 //! 
 //! # How it works
 //! 
 //! In short, there are a few handy functions to request a hook: [keyboard_hook], [mouse_hook] and [willhook].
 //! When called they:
-//! - start background thread(s) for each low-level hook, and in that thread(s):
+//! - start one background thread for all the low-level hooks (or one per hook, see [hook::HookBuilder::thread_per_hook]), and in that thread(s):
 //!     - register a mouse and/or keyboard low-level hook(s)
 //!     - start Windows message queue and wait for the message to end execution
 //! - create, if were not created already, the channels for passing events to "client" thread
@@ -55,7 +52,7 @@
 //! When the [hook::Hook] goes out of scope, the underlying resources supporting low-level hooks are dropped:
 //! - each of the underlying low-level hooks is unhooked from the Windows Kernel
 //! - each of the background threads is properly joined
-//! - all pending events are dropped (background channels are drained)
+//! - all pending events are dropped, together with the memory of the background queue
 //! 
 //! When the [hook::Hook] is active (in scope / not dropped). 
 //! Then one can receive recorded [event::InputEvent]s via [hook::Hook::try_recv].
@@ -68,25 +65,156 @@
 //! To enable [serde](https://crates.io/crates/serde) support, add willhook with "serde" feature to your cargo.toml:
 //! 
 //! `willhook = { version = "^0.6.2", features = ["serde"]}`
+//! 
+//! ## Replay of recorded input
+//! 
+//! To replay the recorded [event::InputEvent]s via `SendInput`, add willhook with "inject" feature to your cargo.toml:
+//! 
+//! `willhook = { version = "^0.6.2", features = ["inject"]}`
+//! 
+//! See the `replay` module for details. Together with "serde" feature, it allows to persist the recording and replay it later.
+//! 
+//! ## Async support
+//! 
+//! To receive the events as a [futures](https://crates.io/crates/futures) `Stream`, add willhook with "async" feature to your cargo.toml:
+//! 
+//! `willhook = { version = "^0.6.2", features = ["async"]}`
+//! 
+//! See the `stream` module for details.
+//! 
+//! ## Logging
+//! 
+//! To see what happens in the background threads (hooks installed and removed, WinAPI failures, the full queue),
+//! add willhook with "logging" feature to your cargo.toml, and any [log](https://crates.io/crates/log) logger:
+//! 
+//! `willhook = { version = "^0.6.2", features = ["logging"]}`
+//! 
+//! ## Test utilities
+//! 
+//! To inject the synthetic keys and mouse input in the integration tests, add willhook with "test-util" feature to your dev-dependencies:
+//! 
+//! `willhook = { version = "^0.6.2", features = ["test-util"]}`
+//! 
+//! See the `test_util` module for details. **The tests injecting the input must run single-threaded** (`cargo test -- --test-threads=1`),
+//! as the input injected by one test is captured by the hook of every other test running meanwhile.
+//! 
+//! ## Other platforms
+//! 
+//! The crate does not build for the targets other than Windows, with an error saying so. In a cross-platform workspace
+//! add willhook with "stub-non-windows" feature to your cargo.toml, so that it builds everywhere:
+//! 
+//! `willhook = { version = "^0.6.2", features = ["stub-non-windows"]}`
+//! 
+//! On the other targets only the [event] types and the [keyboard_hook], [mouse_hook] and [willhook()] functions are available,
+//! and the functions always return `None`, which the code has to handle anyway, as the hook may fail to install.
+//! The feature has no effect on Windows.
+
+#[cfg(all(not(windows), not(feature = "stub-non-windows")))]
+compile_error!("willhook only supports Windows. To build a cross-platform workspace, enable the \"stub-non-windows\" feature on the other targets, where no hook is ever returned.");
 
+#[cfg(windows)]
+#[macro_use]
+mod logging;
+
+#[cfg(windows)]
 pub mod hook;
 pub mod event;
+#[cfg(windows)]
+pub mod hotkey;
+#[cfg(windows)]
+pub mod layout;
+#[cfg(windows)]
+pub mod merge;
+#[cfg(windows)]
+pub mod sink;
+#[cfg(windows)]
+pub mod system;
+#[cfg(all(windows, feature = "inject"))]
+pub mod replay;
+#[cfg(all(windows, feature = "async"))]
+pub mod stream;
+#[cfg(all(windows, feature = "test-util"))]
+pub mod test_util;
 
+#[cfg(windows)]
 pub use hook::Hook;
+#[cfg(windows)]
 pub use hook::HookBuilder;
 pub use event::*;
 
+#[cfg(not(windows))]
+mod stub;
+#[cfg(not(windows))]
+pub use stub::*;
+
 /// Return the Keyboard Hook handle. For more details see [Hook] and [HookBuilder]
+/// 
+/// Only one [Hook] can be live in the process: this returns `None` while any hook of this crate is live,
+/// of whichever kind, including one returned by the previous call. The existing hook is not shared, nor disturbed.
+/// See [try_keyboard_hook] for the reason of the failure, [hook::HookError::AlreadyInstalled] in this case.
+#[cfg(windows)]
 pub fn keyboard_hook() -> Option<Hook> {
-    HookBuilder::new().with_keyboard().build()
+    try_keyboard_hook().ok()
 }
 
 /// Return the Mouse Hook handle. For more details see [Hook] and [HookBuilder]
+/// 
+/// Returns `None` while any hook of this crate is live, see [keyboard_hook].
+/// The mouse moves are not delivered, see [HookBuilder::with_mouse_moves].
+#[cfg(windows)]
 pub fn mouse_hook() -> Option<Hook> {
-    HookBuilder::new().with_mouse().build()
+    try_mouse_hook().ok()
 }
 
 /// Return the handle for both mouse and keyboard hook. For more details see [Hook] and [HookBuilder]
+/// 
+/// Returns `None` while any hook of this crate is live, see [keyboard_hook].
+/// The mouse moves are not delivered, see [HookBuilder::with_mouse_moves].
+#[cfg(windows)]
 pub fn willhook() -> Option<Hook> {
-    HookBuilder::new().with_keyboard().with_mouse().build()
-}
\ No newline at end of file
+    try_willhook().ok()
+}
+
+/// Same as [keyboard_hook], but returns the reason why the hook could not be built, see [HookBuilder::try_build].
+/// 
+/// ```rust
+/// # fn main() {
+/// # use willhook::hook::HookError;
+/// let hook = willhook::try_keyboard_hook().unwrap();
+/// assert_eq!(willhook::try_mouse_hook().err(), Some(HookError::AlreadyInstalled));
+/// # }
+/// ```
+#[cfg(windows)]
+pub fn try_keyboard_hook() -> Result<Hook, hook::HookError> {
+    HookBuilder::new().with_keyboard().try_build()
+}
+
+/// Same as [mouse_hook], but returns the reason why the hook could not be built, see [HookBuilder::try_build].
+#[cfg(windows)]
+pub fn try_mouse_hook() -> Result<Hook, hook::HookError> {
+    HookBuilder::new().with_mouse().try_build()
+}
+
+/// Same as [willhook()], but returns the reason why the hook could not be built, see [HookBuilder::try_build].
+#[cfg(windows)]
+pub fn try_willhook() -> Result<Hook, hook::HookError> {
+    HookBuilder::new().with_keyboard().with_mouse().try_build()
+}
+
+/// Reports whether the keyboard and/or mouse low-level hooks of this crate are live in this process.
+/// It does not install anything, it is meant for diagnostics, e.g. to learn why [HookBuilder::build] returned `None`.
+/// 
+/// ```rust
+/// # fn main() {
+/// let hook = willhook::keyboard_hook().unwrap();
+/// let installed = willhook::installed_hooks();
+/// assert!(installed.keyboard);
+/// assert!(!installed.mouse);
+/// drop(hook);
+/// assert!(!willhook::installed_hooks().any());
+/// # }
+/// ```
+#[cfg(windows)]
+pub fn installed_hooks() -> hook::InstalledHooks {
+    hook::installed_hooks()
+}
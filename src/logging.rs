@@ -0,0 +1,34 @@
+//! Internal diagnostics through the `log` crate, available with the "logging" feature.
+//!
+//! Without the feature the macros expand to nothing, the arguments are only type-checked.
+//! The hook procedures must return quickly, so the only things they log are the first overflow of the queue and the first unknown messages.
+
+#[cfg(feature = "logging")]
+macro_rules! log_info {
+    ($($arg:tt)*) => { log::info!($($arg)*) };
+}
+
+#[cfg(feature = "logging")]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { log::warn!($($arg)*) };
+}
+
+#[cfg(feature = "logging")]
+macro_rules! log_error {
+    ($($arg:tt)*) => { log::error!($($arg)*) };
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! log_info {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } };
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } };
+}
+
+#[cfg(not(feature = "logging"))]
+macro_rules! log_error {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } };
+}
@@ -0,0 +1,101 @@
+//! Consuming multiple [Hook]s as a single stream of events.
+//!
+//! Only one [Hook] can be live in the process at a time, so the merged receiver never gets the events from two hooks at once:
+//! it merges the one live hook with the hooks already shut down (see [Hook::shutdown]), which are simply disconnected.
+//! It lets the code written for a list of hooks keep working while the hooks are replaced one by one, e.g. with a different
+//! [crate::HookBuilder::map_events] mapping. For several consumers of the same events see [Hook::subscribe] instead.
+//!
+//! ```rust
+//! # fn main() {
+//! use willhook::merge::merge;
+//! let old = willhook::willhook().unwrap();
+//! old.shutdown();
+//! let hook = willhook::willhook().unwrap();
+//! let merged = merge(&[&old, &hook]);
+//! // The old hook is disconnected, the events come from the live one
+//! assert!(merged.try_recv().is_err());
+//! // The merged receiver only borrows the hooks, dropping it does not drop them
+//! drop(merged);
+//! assert!(hook.is_installed());
+//! # }
+//! ```
+
+use crate::hook::Hook;
+
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// How long the merged receiver waits on a hook, before checking the other hooks.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Receives the events from any of the borrowed hooks, see [merge].
+pub struct MergedReceiver<'a, T> {
+    hooks: Vec<&'a Hook<T>>,
+}
+
+/// Returns the receiver yielding the events from the live one of the `hooks`, the others being shut down (see the [crate::merge] module).
+/// The hooks are only borrowed, so they stay alive (and installed) when the receiver is dropped.
+pub fn merge<'a, T>(hooks: &[&'a Hook<T>]) -> MergedReceiver<'a, T> {
+    MergedReceiver { hooks: hooks.to_vec() }
+}
+
+impl<'a, T> MergedReceiver<'a, T> {
+    /// Tries to receive an event from any of the hooks, checking them in the order they were given.
+    /// Returns `Err(TryRecvError::Disconnected)` only when all the hooks are disconnected (or there are no hooks at all).
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut all_disconnected = true;
+        for hook in &self.hooks {
+            match hook.try_recv() {
+                Ok(event) => return Ok(event),
+                Err(TryRecvError::Empty) => all_disconnected = false,
+                Err(TryRecvError::Disconnected) => (),
+            }
+        }
+        if all_disconnected {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Blocks the current thread until there is an event from any of the hooks, but no longer than the `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            match self.try_recv() {
+                Ok(event) => return Ok(event),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => (),
+            }
+            let remaining = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => POLL_INTERVAL,
+            };
+            if remaining.is_zero() {
+                return Err(RecvTimeoutError::Timeout)
+            }
+            // Wait on the first hook for a moment instead of spinning, the events of the other hooks are checked in the next round
+            match self.hooks[0].recv_timeout(remaining.min(POLL_INTERVAL)) {
+                Ok(event) => return Ok(event),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(remaining.min(POLL_INTERVAL)),
+            }
+        }
+    }
+
+    /// Blocks the current thread until there is an event from any of the hooks.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.recv_timeout(Duration::from_secs(1)) {
+                Ok(event) => return Ok(event),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(RecvError),
+            }
+        }
+    }
+
+    /// The merged hooks, in the order they are checked.
+    pub fn hooks(&self) -> &[&'a Hook<T>] {
+        &self.hooks
+    }
+}
@@ -0,0 +1,565 @@
+//! Replay of the recorded [InputEvent]s, available with the "inject" feature.
+//!
+//! The recording is just a sequence of [InputEvent]s received from the [crate::Hook].
+//! With the "serde" feature the recording can be persisted, e.g. as JSON, and replayed later:
+//!
+//! ```rust,no_run
+//! # fn main() {
+//! use willhook::replay;
+//! # let hook = willhook::willhook().unwrap();
+//! // Record the session
+//! let mut recording = Vec::new();
+//! while let Ok(event) = hook.try_recv() {
+//!     recording.push(event);
+//! }
+//! drop(hook);
+//! // Replay it twice as fast
+//! replay::play(&recording, 2.0);
+//! # }
+//! ```
+//!
+//! The events are injected with `SendInput`, so they go through the OS like any other user input,
+//! and will be seen by the focused window. The delays between events are derived from the OS timestamps
+//! stored in the events (`time` field).
+//!
+//! The mouse moves are recorded as absolute screen positions, and they are replayed as such (not as relative moves).
+//! This way the cursor ends up in the same positions, regardless of the mouse acceleration settings.
+//!
+//! Single events can be injected with [inject], or with the shortcuts [inject_key] and [inject_mouse_move]:
+//!
+//! ```rust,no_run
+//! # fn main() {
+//! use willhook::replay;
+//! use willhook::event::KeyboardKey;
+//! replay::inject_key(KeyboardKey::A, true);
+//! replay::inject_key(KeyboardKey::A, false);
+//! replay::inject_mouse_move(100, 200);
+//! # }
+//! ```
+//!
+//! The events can also be scheduled to be injected at the given instants, by the background thread of the [Injector].
+
+use crate::event::*;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use winapi::ctypes::c_int;
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::minwindef::{DWORD, WORD};
+use winapi::um::winuser::*;
+
+/// Injects the `events` in order, sleeping between them as long as the original user did.
+///
+/// The `speed` scales the delays: 1.0 replays in real time, 2.0 twice as fast, 0.5 twice as slow.
+/// If the `speed` is not a positive number, the events are injected without any delays.
+///
+/// Events that can't be replayed (e.g. [InputEvent::Reset] or mouse move without the position) are skipped.
+/// Returns the number of events that were injected.
+pub fn play(events: &[InputEvent], speed: f32) -> usize {
+    replay(events, speed as f64)
+}
+
+/// Like [play], but for any sequence of the events, owned or borrowed, e.g. read lazily from a file:
+///
+/// ```rust,no_run
+/// # fn main() {
+/// # #[cfg(feature = "serde")] {
+/// use std::io::BufRead;
+/// use willhook::event::InputEvent;
+/// let file = std::io::BufReader::new(std::fs::File::open("recording.jsonl").unwrap());
+/// let events = file.lines()
+///     .map_while(Result::ok)
+///     .filter_map(|line| serde_json::from_str::<InputEvent>(&line).ok());
+/// willhook::replay::replay(events, 1.0);
+/// # }
+/// # }
+/// ```
+///
+/// The delays are derived from the timestamps of the consecutive events, so the events without the timestamp
+/// are injected right after the previous one.
+pub fn replay<I>(events: I, speed: f64) -> usize
+where
+    I: IntoIterator,
+    I::Item: std::borrow::Borrow<InputEvent>,
+{
+    use std::borrow::Borrow;
+    let mut injected = 0;
+    let mut previous_time: Option<u32> = None;
+    for event in events {
+        let event = event.borrow();
+        let time = event.time();
+        if let (Some(previous), Some(current)) = (previous_time, time) {
+            delay(elapsed(previous, current), speed);
+        }
+        if time.is_some() {
+            previous_time = time;
+        }
+
+        if inject(event) {
+            injected += 1;
+        }
+    }
+    injected
+}
+
+/// Injects the single `event` right away. Returns false if the event can't be replayed (see [play]) or `SendInput` failed.
+pub fn inject(event: &InputEvent) -> bool {
+    inject_with_extra_info(event, 0)
+}
+
+/// Same as [inject], with the `dwExtraInfo` of the injected input set to the `extra_info`.
+fn inject_with_extra_info(event: &InputEvent, extra_info: usize) -> bool {
+    let mut input = match to_input(event) {
+        Some(input) => input,
+        None => return false,
+    };
+    unsafe {
+        match input.type_ {
+            INPUT_KEYBOARD => input.u.ki_mut().dwExtraInfo = extra_info as ULONG_PTR,
+            _ => input.u.mi_mut().dwExtraInfo = extra_info as ULONG_PTR,
+        }
+        1 == SendInput(1, &mut input, std::mem::size_of::<INPUT>() as c_int)
+    }
+}
+
+/// Injects the scheduled events at their instants, from its own background thread, e.g. to drive the automated tests
+/// with the precise sequences of the input. The thread stops when the injector is dropped, the events still scheduled are discarded.
+///
+/// The thread sleeps until the next instant, so the precision is the one of the system timer: about 15.6 ms by default,
+/// unless the application raised the resolution with `timeBeginPeriod`. The events scheduled for the same instant are injected
+/// in the order they were scheduled, and so are the events scheduled for the past, right away.
+///
+/// The injected events are captured by the [crate::Hook] like any other input. To tell them apart, the injector can tag them:
+/// the tag is the `dwExtraInfo` of the injected input, reported as the `extra_info` of the captured events.
+///
+/// ```rust,no_run
+/// # fn main() {
+/// use std::time::{Duration, Instant};
+/// use willhook::event::*;
+/// use willhook::replay::Injector;
+/// const TAG: usize = 0x7E57;
+/// let hook = willhook::hook::HookBuilder::new()
+///     .with_keyboard()
+///     // Don't capture what the injector types
+///     .filter(|event| !matches!(event, InputEvent::Keyboard(ke) if ke.extra_info == TAG))
+///     .build()
+///     .unwrap();
+/// let injector = Injector::with_extra_info(TAG).unwrap();
+/// let start = Instant::now();
+/// let key = |pressed| InputEvent::Keyboard(KeyboardEvent {
+///     pressed,
+///     key: Some(KeyboardKey::A),
+///     is_injected: None,
+///     time: None,
+///     physical_key: None,
+///     modifiers: Modifiers::empty(),
+///     is_repeat: false,
+///     held_for: None,
+///     chatter: false,
+///     seq: 0,
+///     foreground_pid: None,
+///     layout: None,
+///     extra_info: 0,
+///     blocked: false,
+///     raw: None,
+/// });
+/// injector.schedule(key(KeyPress::Down(IsSystemKeyPress::Normal)), start + Duration::from_millis(100));
+/// injector.schedule(key(KeyPress::Up(IsSystemKeyPress::Normal)), start + Duration::from_millis(150));
+/// assert!(injector.wait(Duration::from_secs(1)));
+/// assert!(hook.try_recv().is_err());
+/// # }
+/// ```
+pub struct Injector {
+    shared: Arc<(Mutex<Schedule>, Condvar)>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+/// The events waiting for their instants, shared with the thread of the [Injector].
+struct Schedule {
+    /// The number breaks the ties of the same instant, in the order of scheduling
+    events: BTreeMap<(Instant, u64), InputEvent>,
+    next: u64,
+    /// The event taken off the schedule, but not injected yet
+    injecting: bool,
+    stopped: bool,
+}
+
+impl Injector {
+    /// Starts the injector, with the injected input untagged. Fails if its thread can't be spawned.
+    pub fn new() -> std::io::Result<Injector> {
+        Injector::with_extra_info(0)
+    }
+
+    /// Starts the injector, with the `dwExtraInfo` of the injected input set to the `extra_info`. Fails if its thread can't be spawned.
+    pub fn with_extra_info(extra_info: usize) -> std::io::Result<Injector> {
+        let shared = Arc::new((Mutex::new(Schedule { events: BTreeMap::new(), next: 0, injecting: false, stopped: false }), Condvar::new()));
+        let thread_shared = shared.clone();
+        let thread_handle = std::thread::Builder::new().name(String::from("willhook-injector")).spawn(move || {
+            let (schedule, changed) = &*thread_shared;
+            let mut locked = lock(schedule);
+            while !locked.stopped {
+                let due = match locked.events.keys().next() {
+                    Some((at, _)) => *at,
+                    None => {
+                        locked = changed.wait(locked).unwrap_or_else(|poisoned| poisoned.into_inner());
+                        continue
+                    },
+                };
+                let now = Instant::now();
+                if due > now {
+                    // Woken up earlier if an earlier event is scheduled
+                    locked = changed.wait_timeout(locked, due - now).unwrap_or_else(|poisoned| poisoned.into_inner()).0;
+                    continue
+                }
+                if let Some((_, event)) = locked.events.pop_first() {
+                    locked.injecting = true;
+                    drop(locked);
+                    inject_with_extra_info(&event, extra_info);
+                    locked = lock(schedule);
+                    locked.injecting = false;
+                    changed.notify_all();
+                }
+            }
+        })?;
+        Ok(Injector { shared, thread_handle: Some(thread_handle) })
+    }
+
+    /// Schedules the `event` to be injected at the instant `at`. Returns false if the event can't be replayed (see [play]).
+    pub fn schedule(&self, event: InputEvent, at: Instant) -> bool {
+        if to_input(&event).is_none() {
+            return false
+        }
+        let (schedule, changed) = &*self.shared;
+        let mut locked = lock(schedule);
+        let next = locked.next;
+        locked.next = next.wrapping_add(1);
+        locked.events.insert((at, next), event);
+        changed.notify_all();
+        true
+    }
+
+    /// Returns the number of the events waiting for their instants.
+    pub fn pending(&self) -> usize {
+        let locked = lock(&self.shared.0);
+        locked.events.len() + locked.injecting as usize
+    }
+
+    /// Discards all the events waiting for their instants. The injector can be used for the new ones.
+    pub fn cancel(&self) {
+        let (schedule, changed) = &*self.shared;
+        lock(schedule).events.clear();
+        changed.notify_all();
+    }
+
+    /// Waits at most the `timeout` for all the scheduled events to be injected. Returns true if there are none left.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let (schedule, changed) = &*self.shared;
+        let locked = lock(schedule);
+        let (locked, _) = changed
+            .wait_timeout_while(locked, timeout, |schedule| !schedule.events.is_empty() || schedule.injecting)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        locked.events.is_empty() && !locked.injecting
+    }
+}
+
+impl Drop for Injector {
+    fn drop(&mut self) {
+        let (schedule, changed) = &*self.shared;
+        lock(schedule).stopped = true;
+        changed.notify_all();
+        if let Some(thread_handle) = self.thread_handle.take() {
+            let _ = thread_handle.join();
+        }
+    }
+}
+
+fn lock(schedule: &Mutex<Schedule>) -> MutexGuard<'_, Schedule> {
+    schedule.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Injects the press (`down`) or release of the `key`. Returns false if the key has no virtual-key code or `SendInput` failed.
+pub fn inject_key(key: KeyboardKey, down: bool) -> bool {
+    inject(&InputEvent::Keyboard(KeyboardEvent {
+        pressed: if down { KeyPress::Down(IsSystemKeyPress::Normal) } else { KeyPress::Up(IsSystemKeyPress::Normal) },
+        key: Some(key),
+        is_injected: None,
+        time: None,
+        physical_key: None,
+        modifiers: Modifiers::empty(),
+        is_repeat: false,
+        held_for: None,
+        chatter: false,
+        seq: 0,
+        foreground_pid: None,
+        layout: None,
+        extra_info: 0,
+        blocked: false,
+        raw: None,
+    }))
+}
+
+/// Moves the cursor to the absolute screen position (`x`, `y`), in the virtual desktop coordinates like [Point].
+/// Returns false if `SendInput` failed.
+pub fn inject_mouse_move(x: i32, y: i32) -> bool {
+    inject(&InputEvent::Mouse(MouseEvent {
+        event: MouseEventType::Move(MouseMoveEvent{ point: Some(Point{ x, y }), delta: None }),
+        is_injected: None,
+        time: None,
+        seq: 0,
+        foreground_pid: None,
+        extra_info: 0,
+        blocked: false,
+        raw: None,
+    }))
+}
+
+/// The milliseconds from the `previous` to the `current` timestamp. The timestamps wrap around every 49.7 days,
+/// so the "delta" over the half of that is the `current` being earlier than the `previous` (e.g. the events out of order), taken as no delay.
+fn elapsed(previous: u32, current: u32) -> u32 {
+    let elapsed_ms = current.wrapping_sub(previous);
+    if elapsed_ms > i32::MAX as u32 { 0 } else { elapsed_ms }
+}
+
+/// The `elapsed_ms` scaled by the `speed`, `None` if there is nothing to wait for. The delay that can't be represented
+/// (with the speed close to 0) is clamped to the longest one.
+fn scaled_delay(elapsed_ms: u32, speed: f64) -> Option<std::time::Duration> {
+    if speed.is_nan() || speed <= 0.0 || speed.is_infinite() {
+        return None;
+    }
+    Some(std::time::Duration::try_from_secs_f64(elapsed_ms as f64 / 1000.0 / speed).unwrap_or(std::time::Duration::MAX))
+}
+
+fn delay(elapsed_ms: u32, speed: f64) {
+    if let Some(scaled) = scaled_delay(elapsed_ms, speed) {
+        std::thread::sleep(scaled);
+    }
+}
+
+impl InputEvent {
+    /// Builds the `SendInput` structure that injects this event, available with the "inject" feature.
+    /// It bridges the captured events to the code that injects the input on its own, see [to_input] for the details.
+    /// 
+    /// ```rust
+    /// # use willhook::event::*;
+    /// let click = InputEvent::Mouse(MouseEvent {
+    ///     event: MouseEventType::Press(MousePressEvent{ pressed: MouseButtonPress::Down, button: MouseButton::Left(MouseClick::SingleClick) }),
+    ///     is_injected: None,
+    ///     time: None,
+    ///     seq: 0,
+    ///     foreground_pid: None,
+    ///     extra_info: 0,
+    ///     blocked: false,
+    ///     raw: None,
+    /// });
+    /// assert!(click.to_input().is_some());
+    /// assert!(InputEvent::Reset.to_input().is_none());
+    /// ```
+    pub fn to_input(&self) -> Option<INPUT> {
+        to_input(self)
+    }
+}
+
+/// Builds the `SendInput` structure injecting the `event`, with the `KEYEVENTF_*` or `MOUSEEVENTF_*` flags set accordingly.
+/// Returns `None` if the event can't be replayed (see [play]).
+/// 
+/// The keys carry both the virtual-key code and the scan code: the recorded [PhysicalKey] if there is one,
+/// otherwise the one mapped from the virtual-key code. The mouse moves are absolute (`MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE`),
+/// with the position normalized to the 0..=65535 range spanning the whole virtual desktop.
+pub fn to_input(event: &InputEvent) -> Option<INPUT> {
+    match event {
+        InputEvent::Keyboard(ke) => keyboard_input(ke),
+        InputEvent::Mouse(me) => mouse_input(me),
+        _ => None,
+    }
+}
+
+fn keyboard_input(ke: &KeyboardEvent) -> Option<INPUT> {
+    let vk = ke.key?.virtual_key_code()?;
+    let mut flags = match ke.pressed {
+        KeyPress::Down(_) => 0,
+        KeyPress::Up(_) => KEYEVENTF_KEYUP,
+        KeyPress::Other(_) => return None,
+    };
+    // The recorded position of the key is the most faithful, the software may inject the keys without it
+    let (scan_code, extended) = match ke.physical_key {
+        Some(physical) if physical.scan_code != 0 => (physical.scan_code, physical.extended),
+        _ => (unsafe { MapVirtualKeyW(vk, MAPVK_VK_TO_VSC) } as WORD, is_extended_key(vk)),
+    };
+    if extended {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+
+    unsafe {
+        let mut input = INPUT { type_: INPUT_KEYBOARD, u: std::mem::zeroed() };
+        *input.u.ki_mut() = KEYBDINPUT {
+            wVk: vk as WORD,
+            wScan: scan_code,
+            dwFlags: flags,
+            time: 0,
+            dwExtraInfo: 0,
+        };
+        Some(input)
+    }
+}
+
+/// Keys that are reported with the extended flag, see [MSDN](https://learn.microsoft.com/en-us/windows/win32/inputdev/about-keyboard-input#extended-key-flag)
+fn is_extended_key(vk: DWORD) -> bool {
+    matches!(vk as c_int,
+        VK_RMENU | VK_RCONTROL | VK_INSERT | VK_DELETE | VK_HOME | VK_END | VK_PRIOR | VK_NEXT
+        | VK_LEFT | VK_UP | VK_RIGHT | VK_DOWN | VK_NUMLOCK | VK_SNAPSHOT | VK_DIVIDE
+        | VK_LWIN | VK_RWIN | VK_APPS)
+}
+
+fn mouse_input(me: &MouseEvent) -> Option<INPUT> {
+    let (dx, dy, mouse_data, flags) = match me.event {
+        MouseEventType::Move(MouseMoveEvent{ point: Some(point), .. }) => {
+            let (x, y) = normalize_to_virtual_desktop(point);
+            (x, y, 0, MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK)
+        },
+        MouseEventType::Move(MouseMoveEvent{ point: None, .. }) => return None,
+        MouseEventType::Press(press) => {
+            let down = match press.pressed {
+                MouseButtonPress::Down => true,
+                MouseButtonPress::Up => false,
+                MouseButtonPress::Other(_) => return None,
+            };
+            let (flags, mouse_data) = match (press.button, down) {
+                (MouseButton::Left(_), true) => (MOUSEEVENTF_LEFTDOWN, 0),
+                (MouseButton::Left(_), false) => (MOUSEEVENTF_LEFTUP, 0),
+                (MouseButton::Right(_), true) => (MOUSEEVENTF_RIGHTDOWN, 0),
+                (MouseButton::Right(_), false) => (MOUSEEVENTF_RIGHTUP, 0),
+                (MouseButton::Middle(_), true) => (MOUSEEVENTF_MIDDLEDOWN, 0),
+                (MouseButton::Middle(_), false) => (MOUSEEVENTF_MIDDLEUP, 0),
+                (MouseButton::X1(_), true) => (MOUSEEVENTF_XDOWN, XBUTTON1 as DWORD),
+                (MouseButton::X1(_), false) => (MOUSEEVENTF_XUP, XBUTTON1 as DWORD),
+                (MouseButton::X2(_), true) => (MOUSEEVENTF_XDOWN, XBUTTON2 as DWORD),
+                (MouseButton::X2(_), false) => (MOUSEEVENTF_XUP, XBUTTON2 as DWORD),
+                (MouseButton::UnkownX(_), _) | (MouseButton::Other(_), _) => return None,
+            };
+            (0, 0, mouse_data, flags)
+        },
+        MouseEventType::Wheel(wheel) => {
+            let flags = match wheel.wheel {
+                MouseWheel::Vertical => MOUSEEVENTF_WHEEL,
+                MouseWheel::Horizontal => MOUSEEVENTF_HWHEEL,
+                MouseWheel::Unknown(_) => return None,
+            };
+            // The recorded delta keeps the high-resolution scrolling, the direction is enough for the classic wheels
+            let delta = match (wheel.delta, wheel.direction) {
+                (Some(delta), _) if delta != 0 => delta as i32,
+                (_, Some(MouseWheelDirection::Forward)) => WHEEL_DELTA as i32,
+                (_, Some(MouseWheelDirection::Backward)) => -(WHEEL_DELTA as i32),
+                (_, Some(MouseWheelDirection::Unknown(_)) | None) => return None,
+            };
+            (0, 0, delta as DWORD, flags)
+        },
+        MouseEventType::Other(_) => return None,
+    };
+
+    unsafe {
+        let mut input = INPUT { type_: INPUT_MOUSE, u: std::mem::zeroed() };
+        *input.u.mi_mut() = MOUSEINPUT {
+            dx,
+            dy,
+            mouseData: mouse_data,
+            dwFlags: flags,
+            time: 0,
+            dwExtraInfo: 0,
+        };
+        Some(input)
+    }
+}
+
+/// Absolute mouse input is expressed in 0..=65535 range, that spans over the whole virtual desktop (all monitors).
+fn normalize_to_virtual_desktop(point: Point) -> (i32, i32) {
+    let (left, top, width, height) = unsafe {(
+        GetSystemMetrics(SM_XVIRTUALSCREEN),
+        GetSystemMetrics(SM_YVIRTUALSCREEN),
+        GetSystemMetrics(SM_CXVIRTUALSCREEN),
+        GetSystemMetrics(SM_CYVIRTUALSCREEN),
+    )};
+    (normalize(point.x, left, width), normalize(point.y, top, height))
+}
+
+fn normalize(value: i32, origin: i32, extent: i32) -> i32 {
+    if extent <= 1 {
+        return 0;
+    }
+    let relative = (value as i64 - origin as i64).clamp(0, extent as i64 - 1);
+    (relative * 65535 / (extent as i64 - 1)) as i32
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+
+    #[test]
+    fn normalize_spans_whole_range() {
+        assert_eq!(normalize(0, 0, 1920), 0);
+        assert_eq!(normalize(1919, 0, 1920), 65535);
+        assert_eq!(normalize(-1920, -1920, 3840), 0);
+        assert_eq!(normalize(1919, -1920, 3840), 65535);
+        // Out of the virtual desktop
+        assert_eq!(normalize(5000, 0, 1920), 65535);
+        assert_eq!(normalize(-5, 0, 1920), 0);
+        // Invalid metrics
+        assert_eq!(normalize(10, 0, 0), 0);
+    }
+
+    #[test]
+    fn backwards_timestamps_are_not_waited_for() {
+        assert_eq!(elapsed(1000, 1500), 500);
+        assert_eq!(elapsed(u32::MAX - 10, 10), 21);
+        assert_eq!(elapsed(1500, 1000), 0);
+    }
+
+    #[test]
+    fn delay_is_scaled_without_overflow() {
+        use std::time::Duration;
+        assert_eq!(scaled_delay(1000, 2.0), Some(Duration::from_millis(500)));
+        assert_eq!(scaled_delay(1000, 0.0), None);
+        assert_eq!(scaled_delay(1000, f64::NAN), None);
+        assert_eq!(scaled_delay(u32::MAX, f64::MIN_POSITIVE), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn unreplayable_events_are_skipped() {
+        assert!(to_input(&InputEvent::Reset).is_none());
+        assert!(to_input(&InputEvent::Other(0)).is_none());
+        let no_point = InputEvent::Mouse(MouseEvent::for_test(MouseEventType::Move(MouseMoveEvent{ point: None, delta: None })));
+        assert!(to_input(&no_point).is_none());
+    }
+
+    #[test]
+    fn replay_takes_owned_events() {
+        let events = vec![InputEvent::Reset, InputEvent::Text(String::from("hi")), InputEvent::Other(0)];
+        assert_eq!(replay(events.clone(), 1.0), 0);
+        assert_eq!(replay(events.iter(), 1.0), 0);
+    }
+
+    #[test]
+    fn key_release_sets_key_up_flag() {
+        let release = InputEvent::Keyboard(KeyboardEvent::for_test(KeyPress::Up(IsSystemKeyPress::Normal), Some(KeyboardKey::ArrowLeft)));
+        let input = to_input(&release).unwrap();
+        assert_eq!(input.type_, INPUT_KEYBOARD);
+        let ki = unsafe { input.u.ki() };
+        assert_eq!(ki.wVk, VK_LEFT as WORD);
+        assert_eq!(ki.dwFlags, KEYEVENTF_KEYUP | KEYEVENTF_EXTENDEDKEY);
+    }
+
+    #[test]
+    fn recorded_physical_key_is_injected() {
+        let press = InputEvent::Keyboard(KeyboardEvent {
+            // The numpad Enter
+            physical_key: Some(PhysicalKey { scan_code: 0x1C, extended: true }),
+            ..KeyboardEvent::for_test(KeyPress::Down(IsSystemKeyPress::Normal), Some(KeyboardKey::Enter))
+        });
+        let input = press.to_input().unwrap();
+        let ki = unsafe { input.u.ki() };
+        assert_eq!((ki.wVk, ki.wScan), (VK_RETURN as WORD, 0x1C));
+        assert_eq!(ki.dwFlags, KEYEVENTF_EXTENDEDKEY);
+    }
+}
@@ -0,0 +1,266 @@
+//! Handing the events over to a sink running in the background, see [crate::HookBuilder::with_sink].
+//!
+//! The sink is driven by its own thread, not by the hook procedures, so it may do slow things
+//! (like writing to a file or a socket) without delaying the user input.
+//!
+//! ```rust
+//! # fn main() {
+//! use willhook::event::InputEvent;
+//! let hook = willhook::hook::HookBuilder::new()
+//!     .with_keyboard()
+//!     .with_sink(|event: &InputEvent| println!("{:?}", event))
+//!     .build()
+//!     .unwrap();
+//! // The sink stops when the hook is dropped
+//! drop(hook);
+//! # }
+//! ```
+//!
+//! With the "serde" feature, the [Recorder] writes all the keyboard and mouse events to any writer as JSON Lines, ready for [crate::replay].
+
+use crate::event::InputEvent;
+use crate::hook::HookError;
+use crate::hook::inner::InnerHook;
+#[cfg(feature = "serde")]
+use crate::hook::{Hook, HookBuilder};
+
+use std::sync::Arc;
+#[cfg(feature = "serde")]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often the sink thread checks if the hook was dropped.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Receives every event captured by the hook, see [crate::HookBuilder::with_sink].
+///
+/// Any `FnMut(&InputEvent)` closure is a sink as well.
+pub trait EventSink {
+    fn handle(&mut self, event: &InputEvent);
+}
+
+impl<F: FnMut(&InputEvent)> EventSink for F {
+    fn handle(&mut self, event: &InputEvent) {
+        self(event)
+    }
+}
+
+/// Writes the events to a file as newline-delimited JSON, available with the "serde" feature.
+///
+/// Each line is flushed as soon as it is written, so the file is complete up to the last event
+/// even if the program is terminated abruptly. Write errors are ignored, the sink keeps trying with the following events.
+#[cfg(feature = "serde")]
+pub struct FileSink {
+    file: std::io::LineWriter<std::fs::File>,
+}
+
+#[cfg(feature = "serde")]
+impl FileSink {
+    /// Creates the file at the `path`, or truncates it if it exists.
+    pub fn create<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<FileSink> {
+        Ok(FileSink::from(std::fs::File::create(path)?))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<std::fs::File> for FileSink {
+    fn from(file: std::fs::File) -> FileSink {
+        FileSink { file: std::io::LineWriter::new(file) }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl EventSink for FileSink {
+    fn handle(&mut self, event: &InputEvent) {
+        use std::io::Write;
+        if serde_json::to_writer(&mut self.file, event).is_ok() {
+            let _ = self.file.write_all(b"\n");
+        }
+    }
+}
+
+/// Records the keyboard and mouse events as newline-delimited JSON to the writer, available with the "serde" feature.
+///
+/// The recorder owns its [Hook] (so no other hook can be built meanwhile) and writes the events from the sink thread,
+/// see [crate::HookBuilder::with_sink]. Every event is written as it was captured, with its `time` and `seq`,
+/// so the lines can be read back as [InputEvent]s, e.g. to [crate::replay] them.
+///
+/// ```rust
+/// # fn main() {
+/// # #[cfg(feature = "serde")] {
+/// use willhook::sink::Recorder;
+/// let recorder = Recorder::start(Vec::new()).unwrap();
+/// // ...the user types and clicks...
+/// let recorded = recorder.stop().unwrap();
+/// for line in String::from_utf8(recorded).unwrap().lines() {
+///     let event: willhook::InputEvent = serde_json::from_str(line).unwrap();
+///     println!("{:?}", event);
+/// }
+/// # }
+/// # }
+/// ```
+///
+/// The writer is not buffered by the recorder: wrap a file into the `BufWriter` or `LineWriter`, depending on how much
+/// of the recording must survive the abrupt termination. After the first write error nothing more is written,
+/// and the error is returned by [Recorder::stop].
+#[cfg(feature = "serde")]
+pub struct Recorder<W: std::io::Write + Send + 'static> {
+    hook: Hook,
+    output: Arc<Mutex<RecorderOutput<W>>>,
+}
+
+#[cfg(feature = "serde")]
+struct RecorderOutput<W> {
+    /// Taken back by [Recorder::stop]
+    writer: Option<W>,
+    error: Option<std::io::Error>,
+    written: u64,
+}
+
+#[cfg(feature = "serde")]
+impl<W: std::io::Write> RecorderOutput<W> {
+    fn write(&mut self, event: &InputEvent) {
+        if self.error.is_some() {
+            return
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            let mut line = match serde_json::to_vec(event) {
+                Ok(line) => line,
+                Err(error) => {
+                    self.error = Some(error.into());
+                    return
+                },
+            };
+            line.push(b'\n');
+            match writer.write_all(&line) {
+                Ok(()) => self.written += 1,
+                Err(error) => self.error = Some(error),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W: std::io::Write + Send + 'static> Recorder<W> {
+    /// Starts recording the keyboard and mouse events (without the mouse moves, as [crate::willhook]) to the `writer`.
+    /// Fails like [crate::try_willhook], e.g. while another hook is live.
+    pub fn start(writer: W) -> Result<Recorder<W>, HookError> {
+        Recorder::start_with(HookBuilder::new().with_keyboard().with_mouse(), writer)
+    }
+
+    /// Starts recording the events of the hook configured by the `builder`, e.g. with the mouse moves.
+    /// The sink of the `builder`, if any, is replaced by the recorder.
+    pub fn start_with(builder: HookBuilder, writer: W) -> Result<Recorder<W>, HookError> {
+        let output = Arc::new(Mutex::new(RecorderOutput { writer: Some(writer), error: None, written: 0 }));
+        let sink_output = output.clone();
+        let hook = builder
+            .with_sink(move |event: &InputEvent| sink_output.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).write(event))
+            .try_build()?;
+        Ok(Recorder { hook, output })
+    }
+
+    /// Returns the number of the events written so far.
+    pub fn recorded(&self) -> u64 {
+        self.output.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).written
+    }
+
+    /// Stops the hook, waits for the events already captured to be written, flushes the writer and returns it.
+    /// Returns the first error of writing the events or of the flush.
+    pub fn stop(self) -> std::io::Result<W> {
+        // Joins the sink thread once it wrote the events left in the queue, nothing is written after this
+        self.hook.shutdown();
+        let mut output = self.output.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut writer = output.writer.take().expect("the writer is taken only once");
+        if let Some(error) = output.error.take() {
+            return Err(error)
+        }
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// The thread driving the sink, stopped and joined when dropped.
+pub(crate) struct SinkThread {
+    stop: Arc<AtomicBool>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl SinkThread {
+    pub fn new(mut sink: Box<dyn EventSink + Send>) -> Result<SinkThread, HookError> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_requested = stop.clone();
+        let thread_handle = std::thread::Builder::new().name(String::from("willhook-sink")).spawn(move || {
+            while !stop_requested.load(Ordering::Relaxed) {
+                match InnerHook::recv_timeout(STOP_CHECK_INTERVAL) {
+                    Ok(event) => sink.handle(&event),
+                    Err(RecvTimeoutError::Timeout) => (),
+                    // Cancelled receives return immediately, don't spin on them
+                    Err(RecvTimeoutError::Disconnected) => std::thread::sleep(STOP_CHECK_INTERVAL),
+                }
+            }
+            // The hooks are removed before the stop, so the queue holds the last events: hand them over as well
+            while let Ok(event) = InnerHook::try_recv() {
+                sink.handle(&event);
+            }
+        }).map_err(|_| HookError::ThreadSpawnFailed)?;
+        Ok(SinkThread { stop, thread_handle: Some(thread_handle) })
+    }
+}
+
+impl Drop for SinkThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread_handle) = self.thread_handle.take() {
+            // A panicking sink only ends its own thread
+            let _ = thread_handle.join();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod file_sink_tests {
+    use super::*;
+
+    #[test]
+    fn writes_one_json_line_per_event() {
+        let path = std::env::temp_dir().join(format!("willhook_file_sink_{}.jsonl", std::process::id()));
+        {
+            let mut sink = FileSink::create(&path).unwrap();
+            sink.handle(&InputEvent::Reset);
+            sink.handle(&InputEvent::Text(String::from("hi")));
+        }
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let events: Vec<InputEvent> = written.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(events, vec![InputEvent::Reset, InputEvent::Text(String::from("hi"))]);
+    }
+
+    #[test]
+    fn recorder_output_stops_on_the_first_error() {
+        struct Failing;
+        impl std::io::Write for Failing {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("full"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut output = RecorderOutput { writer: Some(Vec::new()), error: None, written: 0 };
+        output.write(&InputEvent::Reset);
+        output.write(&InputEvent::Reset);
+        assert_eq!(output.written, 2);
+        assert_eq!(output.writer.unwrap().split(|b| *b == b'\n').filter(|line| !line.is_empty()).count(), 2);
+
+        let mut output = RecorderOutput { writer: Some(Failing), error: None, written: 0 };
+        output.write(&InputEvent::Reset);
+        output.write(&InputEvent::Reset);
+        assert_eq!(output.written, 0);
+        assert!(output.error.is_some());
+    }
+}
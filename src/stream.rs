@@ -0,0 +1,76 @@
+//! Receiving the events as an asynchronous stream, available with the "async" feature.
+//!
+//! The [HookStream] does not block the executor thread: when there is no event queued, it registers the task's waker,
+//! and the hook procedure wakes the task with the next event.
+//!
+//! ```rust
+//! # fn main() {
+//! use futures::StreamExt;
+//! let hook = willhook::keyboard_hook().unwrap();
+//! let handle = hook.cancellation_handle();
+//! let mut events = hook.into_stream();
+//! std::thread::spawn(move || handle.cancel());
+//! futures::executor::block_on(async {
+//!     // Ends when the hook is cancelled
+//!     while let Some(event) = events.next().await {
+//!         println!("{:?}", event);
+//!     }
+//! });
+//! # }
+//! ```
+
+use crate::hook::Hook;
+use crate::hook::inner::InnerHook;
+
+use futures_core::Stream;
+
+use std::pin::Pin;
+use std::sync::mpsc::TryRecvError;
+use std::task::{Context, Poll};
+
+/// The [Stream] of the events of the [Hook], see [Hook::into_stream].
+///
+/// The stream ends when the hook is cancelled (see [Hook::cancellation_handle]), or when it stopped working (see [Hook::error]).
+/// The failure of the hook does not wake the task on its own, so it is noticed only with the next event or poll.
+/// Dropping the stream drops the hook.
+pub struct HookStream<T> {
+    hook: Hook<T>,
+}
+
+impl<T> HookStream<T> {
+    /// Returns the hook back, e.g. to receive the events synchronously again.
+    pub fn into_inner(self) -> Hook<T> {
+        self.hook
+    }
+}
+
+impl<T> Hook<T> {
+    /// Turns the hook into the asynchronous [Stream] of its events, see the [crate::stream] module.
+    pub fn into_stream(self) -> HookStream<T> {
+        HookStream { hook: self }
+    }
+}
+
+impl<T> HookStream<T> {
+    fn try_next(&self) -> Poll<Option<T>> {
+        match self.hook.try_recv() {
+            Ok(event) => Poll::Ready(Some(event)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) if self.hook.cancellation_handle().is_cancelled() => Poll::Ready(None),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Stream for HookStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Poll::Ready(next) = self.try_next() {
+            return Poll::Ready(next)
+        }
+        InnerHook::register_waker(cx.waker());
+        // The event could have been queued before the waker was registered
+        self.try_next()
+    }
+}
@@ -0,0 +1,45 @@
+//! The stand-ins for the hooks on the targets other than Windows, available with the "stub-non-windows" feature.
+//! The low-level hooks exist only on Windows, so no [Hook] is ever returned: the code capturing the input compiles everywhere,
+//! and handles the `None` at runtime, like the hook that could not be installed. The [crate::event] types are available as usual.
+
+use crate::event::InputEvent;
+
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::time::Duration;
+
+/// The stand-in for the Windows [Hook], it can't be constructed. It has only the receiving methods, to let the code using it compile.
+pub struct Hook<T = InputEvent> {
+    never: Infallible,
+    events: PhantomData<T>,
+}
+
+impl<T> Hook<T> {
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self.never {}
+    }
+
+    pub fn recv(&self) -> Result<T, RecvError> {
+        match self.never {}
+    }
+
+    pub fn recv_timeout(&self, _timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match self.never {}
+    }
+}
+
+/// Always returns `None`, the keyboard hook is available only on Windows.
+pub fn keyboard_hook() -> Option<Hook> {
+    None
+}
+
+/// Always returns `None`, the mouse hook is available only on Windows.
+pub fn mouse_hook() -> Option<Hook> {
+    None
+}
+
+/// Always returns `None`, the hooks are available only on Windows.
+pub fn willhook() -> Option<Hook> {
+    None
+}
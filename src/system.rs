@@ -0,0 +1,74 @@
+//! The system-wide input settings, that affect the timing of the events.
+//!
+//! The values are queried from the OS at the moment of the call, as the user may change them in the Control Panel at any time.
+//!
+//! ```rust
+//! # fn main() {
+//! use willhook::system;
+//! // Treat two clicks as a double-click the same way as Windows does
+//! let double_click = system::double_click_time();
+//! println!("Clicks closer than {:?} make a double-click", double_click);
+//! // The held key is repeated after the delay, at the rate
+//! if let (Some(delay), Some(rate)) = (system::keyboard_repeat_delay(), system::keyboard_repeat_rate()) {
+//!     println!("Keys repeat after {:?}, {} times per second", delay, rate);
+//! }
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use winapi::shared::minwindef::{DWORD, FALSE, UINT};
+use winapi::um::winuser::{GetDoubleClickTime, GetSystemMetrics, SystemParametersInfoW, SM_CXDOUBLECLK, SM_CYDOUBLECLK, SPI_GETKEYBOARDDELAY, SPI_GETKEYBOARDSPEED};
+
+/// The maximum time between the clicks of a double-click, as returned by `GetDoubleClickTime`.
+pub fn double_click_time() -> Duration {
+    Duration::from_millis(unsafe { GetDoubleClickTime() } as u64)
+}
+
+/// The width and height of the rectangle centered on the first click, within which the second click has to be
+/// to make a double-click (`SM_CXDOUBLECLK`, `SM_CYDOUBLECLK`), in pixels.
+pub fn double_click_size() -> (u32, u32) {
+    let (width, height) = unsafe { (GetSystemMetrics(SM_CXDOUBLECLK), GetSystemMetrics(SM_CYDOUBLECLK)) };
+    (width.max(0) as u32, height.max(0) as u32)
+}
+
+/// The delay before the held key starts to repeat (`SPI_GETKEYBOARDDELAY`), between 250 ms and 1 s.
+/// Returns `None` if the OS refused to tell.
+pub fn keyboard_repeat_delay() -> Option<Duration> {
+    // The setting is 0 to 3, for 250 ms to 1 s.
+    // Windows documents the values as approximate, the actual delay may differ slightly depending on the hardware.
+    get_parameter(SPI_GETKEYBOARDDELAY).map(|delay| Duration::from_millis((delay.min(3) as u64 + 1) * 250))
+}
+
+/// The rate of the key repeats (`SPI_GETKEYBOARDSPEED`), in repetitions per second, between about 2.5 and 30.
+/// Returns `None` if the OS refused to tell.
+pub fn keyboard_repeat_rate() -> Option<f32> {
+    // The setting is 0 to 31, mapped linearly to about 2.5 to 30 repetitions per second
+    get_parameter(SPI_GETKEYBOARDSPEED).map(|speed| 2.5 + speed.min(31) as f32 * (27.5 / 31.0))
+}
+
+fn get_parameter(action: UINT) -> Option<DWORD> {
+    let mut value: DWORD = 0;
+    let ok = unsafe { SystemParametersInfoW(action, 0, &mut value as *mut DWORD as *mut _, 0) };
+    if ok == FALSE {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod system_tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_within_documented_ranges() {
+        assert!(double_click_time() > Duration::ZERO);
+        let (width, height) = double_click_size();
+        assert!(width > 0 && height > 0);
+        let delay = keyboard_repeat_delay().unwrap();
+        assert!(delay >= Duration::from_millis(250) && delay <= Duration::from_secs(1));
+        let rate = keyboard_repeat_rate().unwrap();
+        assert!((2.5..=30.0).contains(&rate));
+    }
+}
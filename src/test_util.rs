@@ -0,0 +1,110 @@
+//! Helpers injecting the synthetic input for the integration tests, available with the "test-util" feature.
+//!
+//! **The tests injecting the input must run one at a time:** `cargo test -- --test-threads=1`.
+//! Only one [crate::Hook] can be live in the process, and the input injected by one test is captured by the hook of any other test
+//! running meanwhile. The injected input also goes to the focused window, like the real one, so the tests are best run
+//! from a terminal that can take a few stray key presses.
+//!
+//! The input is injected with `SendInput` (see [crate::replay]), so it goes through the OS and the hooks the same way as the user input,
+//! except that it is marked as injected (see [crate::hook::HookBuilder::physical_only]). The hooks get it asynchronously,
+//! so wait a bit with [settle] before checking what the hook received:
+//!
+//! ```rust,no_run
+//! # fn main() {
+//! use willhook::event::*;
+//! use willhook::test_util;
+//! let hook = willhook::keyboard_hook().unwrap();
+//! test_util::send_key_down(KeyboardKey::A);
+//! test_util::send_key_up(KeyboardKey::A);
+//! test_util::settle();
+//! assert!(matches!(hook.try_recv(), Ok(InputEvent::Keyboard(KeyboardEvent { key: Some(KeyboardKey::A), .. }))));
+//! # }
+//! ```
+//!
+//! Every helper returns false if the input could not be injected, e.g. `SendInput` was blocked by the UIPI.
+
+use crate::event::*;
+use crate::replay::{inject, inject_key, inject_mouse_move};
+
+use std::time::Duration;
+
+use winapi::um::winuser::WHEEL_DELTA;
+
+/// How long [settle] waits for the hooks to get the injected input.
+pub const SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// Waits for the hooks to get the input injected so far. There is no way to know when they got it, so it just sleeps for the [SETTLE_DELAY].
+pub fn settle() {
+    std::thread::sleep(SETTLE_DELAY);
+    std::thread::yield_now();
+}
+
+/// Injects the press of the `key`.
+pub fn send_key_down(key: KeyboardKey) -> bool {
+    inject_key(key, true)
+}
+
+/// Injects the release of the `key`.
+pub fn send_key_up(key: KeyboardKey) -> bool {
+    inject_key(key, false)
+}
+
+/// Injects the press and the release of the `key`.
+pub fn send_key_click(key: KeyboardKey) -> bool {
+    send_key_down(key) & send_key_up(key)
+}
+
+/// Moves the cursor to the absolute screen position (`x`, `y`), see [crate::replay::inject_mouse_move].
+pub fn send_mouse_move(x: i32, y: i32) -> bool {
+    inject_mouse_move(x, y)
+}
+
+/// Injects the press of the mouse `button`, e.g. `MouseButton::Left(MouseClick::SingleClick)` (the click kind is ignored).
+/// Returns false for the buttons that can't be injected, like [MouseButton::UnkownX].
+pub fn send_button_down(button: MouseButton) -> bool {
+    send_button(button, MouseButtonPress::Down)
+}
+
+/// Injects the release of the mouse `button`, see [send_button_down].
+pub fn send_button_up(button: MouseButton) -> bool {
+    send_button(button, MouseButtonPress::Up)
+}
+
+/// Injects the press and the release of the left mouse button.
+pub fn click_left() -> bool {
+    let left = MouseButton::Left(MouseClick::SingleClick);
+    send_button_down(left) & send_button_up(left)
+}
+
+/// Injects the press and the release of the right mouse button.
+pub fn click_right() -> bool {
+    let right = MouseButton::Right(MouseClick::SingleClick);
+    send_button_down(right) & send_button_up(right)
+}
+
+/// Injects one notch (`WHEEL_DELTA`) of the `wheel` rotation in the `direction`.
+pub fn send_wheel(wheel: MouseWheel, direction: MouseWheelDirection) -> bool {
+    let delta = match direction {
+        MouseWheelDirection::Forward => WHEEL_DELTA,
+        MouseWheelDirection::Backward => -WHEEL_DELTA,
+        MouseWheelDirection::Unknown(_) => return false,
+    };
+    send_mouse(MouseEventType::Wheel(MouseWheelEvent { wheel, direction: Some(direction), delta: Some(delta) }))
+}
+
+fn send_button(button: MouseButton, pressed: MouseButtonPress) -> bool {
+    send_mouse(MouseEventType::Press(MousePressEvent { pressed, button }))
+}
+
+fn send_mouse(event: MouseEventType) -> bool {
+    inject(&InputEvent::Mouse(MouseEvent {
+        event,
+        is_injected: None,
+        time: None,
+        seq: 0,
+        foreground_pid: None,
+        extra_info: 0,
+        blocked: false,
+        raw: None,
+    }))
+}
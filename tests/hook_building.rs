@@ -29,6 +29,18 @@ mod hook_building_tests {
         assert!(willhook().is_none());
     }
 
+    #[test]
+    fn try_helpers_report_the_existing_hook() {
+        use willhook::hook::HookError;
+        use willhook::{try_keyboard_hook, try_mouse_hook, try_willhook};
+        let existing = try_willhook().unwrap();
+        assert_eq!(try_keyboard_hook().err(), Some(HookError::AlreadyInstalled));
+        assert_eq!(try_mouse_hook().err(), Some(HookError::AlreadyInstalled));
+        assert_eq!(try_willhook().err(), Some(HookError::AlreadyInstalled));
+        drop(existing);
+        assert!(try_mouse_hook().is_ok());
+    }
+
     #[test]
     fn build_fails_if_different_type_of_hook_exists_1() {
         let _existing = keyboard_hook();
@@ -60,6 +72,18 @@ mod hook_building_tests {
         assert!(keyboard_hook().is_none());
     }
 
+    #[test]
+    fn second_build_does_not_disturb_existing_hook() {
+        use willhook::hook::HookError;
+        let existing = keyboard_hook().unwrap();
+        assert_eq!(HookBuilder::new().with_keyboard().physical_only().try_build().err(), Some(HookError::AlreadyInstalled));
+        assert!(keyboard_hook().is_none());
+        assert!(existing.is_installed());
+        assert_eq!(existing.error(), None);
+        drop(existing);
+        assert!(keyboard_hook().is_some());
+    }
+
     #[test]
     fn building_data_race() {
         // If tests are run in paraller, then they would expose data race sporadically.
@@ -97,4 +121,99 @@ mod hook_building_tests {
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn shutdown_frees_the_hook_and_disconnects() {
+        use std::sync::Arc;
+        use std::time::Duration;
+        use willhook::hook::HookError;
+
+        let existing = Arc::new(keyboard_hook().unwrap());
+        let blocked = {
+            let existing = existing.clone();
+            std::thread::spawn(move || existing.recv().is_err())
+        };
+        std::thread::sleep(Duration::from_millis(50));
+
+        existing.shutdown();
+        assert!(blocked.join().unwrap());
+        assert!(existing.try_recv().is_err());
+        assert!(existing.recv_timeout(Duration::from_secs(1)).is_err());
+        assert!(!existing.is_installed());
+        assert_eq!(existing.error(), Some(HookError::ShutDown));
+
+        // Neither the second shutdown nor the drop removes the hook built in the meantime
+        let next = mouse_hook().unwrap();
+        existing.shutdown();
+        drop(existing);
+        assert!(next.is_installed());
+    }
+
+    #[test]
+    fn handles_report_the_hook_threads() {
+        let both = willhook().unwrap();
+        let handles = both.handles();
+        let (keyboard, mouse) = (handles.keyboard.unwrap(), handles.mouse.unwrap());
+        assert_ne!(keyboard.handle, mouse.handle);
+        // Both hooks share the message loop
+        assert_eq!(keyboard.thread_id, mouse.thread_id);
+        both.shutdown();
+        assert_eq!(both.handles(), Default::default());
+
+        let separate = HookBuilder::new().with_keyboard().with_mouse().thread_per_hook().build().unwrap();
+        let handles = separate.handles();
+        assert_ne!(handles.keyboard.unwrap().thread_id, handles.mouse.unwrap().thread_id);
+        drop(separate);
+
+        let keyboard = keyboard_hook().unwrap();
+        assert!(keyboard.handles().keyboard.is_some());
+        assert!(keyboard.handles().mouse.is_none());
+    }
+
+    #[test]
+    fn hooks_run_at_any_thread_priority() {
+        use willhook::hook::{Backend, ThreadPriority};
+        for priority in [ThreadPriority::AboveNormal, ThreadPriority::Highest, ThreadPriority::TimeCritical, ThreadPriority::Normal] {
+            let hook = HookBuilder::new().with_keyboard().with_mouse().with_thread_priority(priority).build().unwrap();
+            assert!(hook.is_installed());
+            assert!(hook.verify(std::time::Duration::from_secs(1)));
+        }
+        let raw = HookBuilder::new().with_keyboard().backend(Backend::RawInput).with_thread_priority(ThreadPriority::Highest).build().unwrap();
+        assert!(raw.is_installed());
+    }
+
+    #[test]
+    fn wait_ready_confirms_the_message_loops() {
+        use std::time::Duration;
+        let hook = HookBuilder::new().with_keyboard().with_mouse().thread_per_hook().build().unwrap();
+        assert!(hook.wait_ready(Duration::from_secs(1)));
+        // Once running, it stays ready
+        assert!(hook.wait_ready(Duration::ZERO));
+        hook.shutdown();
+        assert!(!hook.wait_ready(Duration::from_millis(100)));
+
+        let raw = HookBuilder::new().with_mouse().backend(willhook::hook::Backend::RawInput).build().unwrap();
+        assert!(raw.wait_ready(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn hooks_run_on_the_input_desktop() {
+        use willhook::hook::Backend;
+        // The tests run on the interactive desktop, so it is the same desktop, but assigned explicitly
+        let hook = HookBuilder::new().with_keyboard().with_mouse().on_input_desktop().build().unwrap();
+        assert!(hook.verify(std::time::Duration::from_secs(1)));
+        drop(hook);
+        let raw = HookBuilder::new().with_keyboard().backend(Backend::RawInput).on_input_desktop().build().unwrap();
+        assert!(raw.is_installed());
+    }
+
+    #[test]
+    fn context_is_returned_by_the_hook() {
+        #[derive(Debug, PartialEq)]
+        enum Purpose { Shortcuts }
+        let hook = HookBuilder::new().with_keyboard().with_context(Purpose::Shortcuts).build().unwrap();
+        assert_eq!(hook.context::<Purpose>(), Some(&Purpose::Shortcuts));
+        assert_eq!(hook.context::<String>(), None);
+        drop(hook);
+        assert_eq!(keyboard_hook().unwrap().context::<Purpose>(), None);
+    }
+}
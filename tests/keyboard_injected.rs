@@ -27,6 +27,48 @@ mod keyboard_hook_tests {
         assert!(h.try_recv().is_err());
     }    
     
+    #[test]
+    fn new_hook_starts_with_an_empty_queue() {
+        use willhook::hook::HookBuilder;
+        use std::time::Duration;
+
+        let h = keyboard_hook().unwrap();
+        Keyboard::A.press();
+        Keyboard::A.release();
+        std::thread::sleep(Duration::from_millis(50));
+        // Not received, left for the next hook
+        drop(h);
+
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+        drop(h);
+
+        let h = HookBuilder::new().with_keyboard().keep_pending_on_start().build().unwrap();
+        assert!(h.try_recv().is_err());
+        Keyboard::A.release();
+        assert_eq!(h.try_recv(), utils::a_key(A, Up(Normal)));
+    }
+
+    #[test]
+    fn hook_rebuilt_while_the_previous_one_is_dropped_receives_events() {
+        let mut h = keyboard_hook().unwrap();
+        for _ in 0..20 {
+            // The previous hook is torn down on another thread, while this one keeps trying to build the next one
+            let dropping = std::thread::spawn(move || drop(h));
+            h = loop {
+                if let Some(next) = keyboard_hook() {
+                    break next
+                }
+            };
+            dropping.join().unwrap();
+
+            assert!(h.is_installed());
+            Keyboard::A.release();
+            assert_eq!(h.try_recv(), utils::a_key(A, Up(Normal)));
+            assert!(h.try_recv().is_err());
+        }
+    }
+
     #[test]
     fn release_one_keyboard_key() {
         Keyboard::B.release();
@@ -61,26 +103,64 @@ mod keyboard_hook_tests {
         Keyboard::D.click();
         Keyboard::LeftAlt.release();
 
-        assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Down(System)));
-        assert_eq!(h.try_recv(), utils::a_key(D, Down(System)));
-        assert_eq!(h.try_recv(), utils::a_key(D, Up(System)));
+        assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
+        assert_eq!(h.try_recv(), utils::a_key(D, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
+        assert_eq!(h.try_recv(), utils::a_key(D, Up(System)).with_modifiers(Modifiers::LEFT_ALT));
         assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Up(Normal)));
         assert!(h.try_recv().is_err());
     }
 
+    #[test]
+    fn key_up_reports_how_long_the_key_was_held() {
+        use std::time::Duration;
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::G.press();
+        std::thread::sleep(Duration::from_millis(200));
+        Keyboard::G.release();
+
+        match (h.try_recv(), h.try_recv()) {
+            (Ok(InputEvent::Keyboard(down)), Ok(InputEvent::Keyboard(up))) => {
+                assert_eq!(down.held_for, None);
+                // The timestamps have the resolution of the system timer
+                assert!(up.held_for.is_some_and(|held_for| held_for >= Duration::from_millis(150)), "{:?}", up.held_for);
+            },
+            other => panic!("{:?}", other),
+        }
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn reset_state_forgets_the_held_modifiers() {
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::LeftShift.press();
+        assert_eq!(h.try_recv(), utils::a_key(LeftShift, Down(Normal)).with_modifiers(Modifiers::LEFT_SHIFT));
+        h.reset_state();
+        Keyboard::F.click();
+        Keyboard::LeftShift.release();
+
+        assert_eq!(h.try_recv(), utils::a_key(F, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(F, Up(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(LeftShift, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
     #[test]
     fn click_one_system_key_receive_interleaving() {
         let h = keyboard_hook().unwrap();
         assert!(h.try_recv().is_err());
 
         Keyboard::LeftAlt.press();
-        assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Down(System)));
+        assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
         assert!(h.try_recv().is_err());
 
         Keyboard::E.press();
         Keyboard::E.release();
-        assert_eq!(h.try_recv(), utils::a_key(E, Down(System)));
-        assert_eq!(h.try_recv(), utils::a_key(E, Up(System)));
+        assert_eq!(h.try_recv(), utils::a_key(E, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
+        assert_eq!(h.try_recv(), utils::a_key(E, Up(System)).with_modifiers(Modifiers::LEFT_ALT));
         assert!(h.try_recv().is_err());
 
         Keyboard::LeftAlt.release();
@@ -98,16 +178,16 @@ mod keyboard_hook_tests {
         assert!(h.try_recv().is_err());
 
         Keyboard::LeftAlt.press();
-        assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Down(System)));
+        assert_eq!(h.try_recv(), utils::a_key(LeftAlt, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
         assert!(h.try_recv().is_err());
 
         Keyboard::H.press();
         Keyboard::I.click();
         Keyboard::H.release();
-        assert_eq!(h.try_recv(), utils::a_key(H, Down(System)));
-        assert_eq!(h.try_recv(), utils::a_key(I, Down(System)));
-        assert_eq!(h.try_recv(), utils::a_key(I, Up(System)));
-        assert_eq!(h.try_recv(), utils::a_key(H, Up(System)));
+        assert_eq!(h.try_recv(), utils::a_key(H, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
+        assert_eq!(h.try_recv(), utils::a_key(I, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
+        assert_eq!(h.try_recv(), utils::a_key(I, Up(System)).with_modifiers(Modifiers::LEFT_ALT));
+        assert_eq!(h.try_recv(), utils::a_key(H, Up(System)).with_modifiers(Modifiers::LEFT_ALT));
         assert!(h.try_recv().is_err());
 
         Keyboard::LeftAlt.release();
@@ -134,7 +214,7 @@ mod keyboard_hook_tests {
             assert!(h1.try_recv().is_err());
 
             Keyboard::LeftAlt.press();
-            assert_eq!(h1.try_recv(), utils::a_key(LeftAlt, Down(System)));
+            assert_eq!(h1.try_recv(), utils::a_key(LeftAlt, Down(System)).with_modifiers(Modifiers::LEFT_ALT));
             assert!(h1.try_recv().is_err());
 
             // These events are received by h1
@@ -148,6 +228,7 @@ mod keyboard_hook_tests {
             assert!(h2.try_recv().is_err());
 
             Keyboard::I.click();
+            // The alt was pressed before h2 was built, so it is not reported as held down
             assert_eq!(h2.try_recv(), utils::a_key(I, Down(System)));
             assert_eq!(h2.try_recv(), utils::a_key(I, Up(System)));
             assert!(h2.try_recv().is_err());
@@ -186,4 +267,536 @@ mod keyboard_hook_tests {
         assert_eq!(h1.try_recv(), utils::a_key(K, Up(Normal)));
         assert!(h1.try_recv().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn wait_for_consumes_events_until_match() {
+        use std::time::Duration;
+        use willhook::hook::WaitError;
+
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::M.click();
+        Keyboard::N.click();
+        Keyboard::O.click();
+
+        let is_n_released = |e: &InputEvent| matches!(e,
+            InputEvent::Keyboard(KeyboardEvent{ key: Some(N), pressed: Up(Normal), .. }));
+        assert!(h.wait_for(is_n_released, Some(Duration::from_secs(1))).is_ok());
+        assert_eq!(h.try_recv(), utils::a_key(O, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(O, Up(Normal)));
+        assert_eq!(h.wait_for(is_n_released, Some(Duration::from_millis(100))), Err(WaitError::Timeout));
+    }
+
+    #[test]
+    fn recv_deadline_waits_until_instant() {
+        use std::time::{Duration, Instant};
+        use std::sync::mpsc::RecvTimeoutError;
+
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert_eq!(h.recv_deadline(deadline), Err(RecvTimeoutError::Timeout));
+        assert!(Instant::now() >= deadline);
+
+        Keyboard::P.click();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        assert!(h.recv_deadline(deadline).is_ok());
+        assert!(h.recv_deadline(deadline).is_ok());
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn physical_only_drops_injected_keys() {
+        let h = hook::HookBuilder::new().with_keyboard().physical_only().build().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::Q.click();
+        utils::fixme::delay_execution();
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn key_edges_down_only_drops_releases() {
+        let h = hook::HookBuilder::new().with_keyboard().key_edges(hook::Edges::DownOnly).build().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::R.click();
+        assert_eq!(h.try_recv(), utils::a_key(R, Down(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn keyboard_downs_and_ups_only() {
+        let h = hook::HookBuilder::new().with_keyboard().keyboard_downs_only().build().unwrap();
+        assert!(h.try_recv().is_err());
+        Keyboard::R.click();
+        assert_eq!(h.try_recv(), utils::a_key(R, Down(Normal)));
+        assert!(h.try_recv().is_err());
+        drop(h);
+
+        let h = hook::HookBuilder::new().with_keyboard().keyboard_ups_only().build().unwrap();
+        assert!(h.try_recv().is_err());
+        Keyboard::R.click();
+        assert_eq!(h.try_recv(), utils::a_key(R, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn filter_queues_only_accepted_keys() {
+        let h = hook::HookBuilder::new()
+            .with_keyboard()
+            .filter(|e| matches!(e, InputEvent::Keyboard(ke) if ke.key == Some(KeyboardKey::T)))
+            .build()
+            .unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::S.click();
+        Keyboard::T.click();
+        assert_eq!(h.try_recv(), utils::a_key(T, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(T, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn latency_is_measured_on_request() {
+        let h = keyboard_hook().unwrap();
+        assert_eq!(h.latency_stats(), None);
+        drop(h);
+
+        let h = hook::HookBuilder::new()
+            .with_keyboard()
+            .with_latency_stats()
+            .filter(|_| { std::thread::sleep(std::time::Duration::from_millis(5)); true })
+            .build()
+            .unwrap();
+        assert_eq!(h.latency_stats().map(|stats| stats.events), Some(0));
+
+        Keyboard::V.click();
+        assert_eq!(h.try_recv(), utils::a_key(V, Down(Normal)));
+        let stats = h.latency_stats().unwrap();
+        assert_eq!(stats.events, 2);
+        assert!(stats.max >= std::time::Duration::from_millis(5));
+        assert!(stats.average <= stats.max);
+    }
+
+    #[test]
+    fn peek_does_not_consume_the_event() {
+        let h = keyboard_hook().unwrap();
+        assert_eq!(h.peek(), None);
+        assert!(!h.has_pending());
+
+        Keyboard::W.click();
+        assert!(h.has_pending());
+        let next = h.peek();
+        assert_eq!(next, h.peek());
+        assert_eq!(next.ok_or(std::sync::mpsc::TryRecvError::Empty), utils::a_key(W, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(W, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(W, Up(Normal)));
+        assert_eq!(h.peek(), None);
+        assert!(!h.has_pending());
+    }
+
+    #[test]
+    fn map_events_delivers_mapped_type() {
+        let h = hook::HookBuilder::new()
+            .with_keyboard()
+            .map_events(|e| match e {
+                InputEvent::Keyboard(KeyboardEvent{ pressed: Up(_), key, .. }) => key,
+                _ => None,
+            })
+            .build()
+            .unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::S.click();
+        Keyboard::T.click();
+        assert_eq!(h.try_recv(), Ok(S));
+        assert_eq!(h.try_recv(), Ok(T));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn foreground_info_is_filled_in_on_request() {
+        use winapi::um::winuser::{GetForegroundWindow, GetWindowThreadProcessId};
+        let h = keyboard_hook().unwrap();
+        Keyboard::W.click();
+        assert!(matches!(h.try_recv(), Ok(InputEvent::Keyboard(ke)) if ke.foreground_pid.is_none()));
+        drop(h);
+
+        let h = hook::HookBuilder::new().with_keyboard().with_foreground_info().build().unwrap();
+        Keyboard::W.click();
+        let expected = unsafe {
+            let mut pid = 0;
+            let window = GetForegroundWindow();
+            if !window.is_null() { GetWindowThreadProcessId(window, &mut pid); }
+            (pid != 0).then_some(pid)
+        };
+        assert!(matches!(h.try_recv(), Ok(InputEvent::Keyboard(ke)) if ke.foreground_pid == expected));
+    }
+
+    #[test]
+    fn keyboard_can_be_muted_at_runtime() {
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        h.set_keyboard_enabled(false);
+        Keyboard::U.click();
+        utils::fixme::delay_execution();
+        assert!(h.try_recv().is_err());
+
+        h.set_keyboard_enabled(true);
+        Keyboard::V.click();
+        assert_eq!(h.try_recv(), utils::a_key(V, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(V, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn flush_discards_queued_events() {
+        let h = keyboard_hook().unwrap();
+        assert_eq!(h.flush(), 0);
+
+        Keyboard::W.click();
+        utils::fixme::delay_execution();
+        assert_eq!(h.flush(), 2);
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn accumulate_text_emits_typed_words() {
+        use std::time::Duration;
+
+        let h = hook::HookBuilder::new().with_keyboard().accumulate_text().build().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::H.click();
+        Keyboard::I.click();
+        Keyboard::X.click();
+        Keyboard::BackSpace.click();
+        Keyboard::Space.click();
+
+        let is_text = |e: &InputEvent| matches!(e, InputEvent::Text(_));
+        assert_eq!(h.wait_for(is_text, Some(Duration::from_secs(1))), Ok(InputEvent::Text(String::from("hi"))));
+        assert_eq!(h.try_recv(), utils::a_key(Space, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn paused_hook_delivers_nothing() {
+        let h = keyboard_hook().unwrap();
+        h.pause();
+        assert!(h.is_paused());
+        Keyboard::Y.click();
+        utils::fixme::delay_execution();
+        assert!(h.try_recv().is_err());
+
+        h.resume();
+        assert!(!h.is_paused());
+        Keyboard::Y.click();
+        utils::fixme::delay_execution();
+        assert_eq!(h.try_recv(), utils::a_key(Y, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(Y, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn hook_on_current_thread_is_driven_by_its_message_loop() {
+        use std::time::{Duration, Instant};
+        use winapi::um::winuser::{PeekMessageW, TranslateMessage, DispatchMessageW, MSG, PM_REMOVE, WM_QUIT};
+
+        let pump = |duration: Duration| {
+            let deadline = Instant::now() + duration;
+            let mut msg: MSG = unsafe { std::mem::zeroed() };
+            while Instant::now() < deadline {
+                while unsafe { PeekMessageW(&mut msg, std::ptr::null_mut(), 0, 0, PM_REMOVE) } != 0 {
+                    assert_ne!(msg.message, WM_QUIT);
+                    unsafe {
+                        TranslateMessage(&msg);
+                        DispatchMessageW(&msg);
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        let h = hook::HookBuilder::new().with_keyboard().install_on_current_thread().build().unwrap();
+        assert!(h.is_installed());
+        Keyboard::Y.click();
+        pump(Duration::from_millis(100));
+        assert_eq!(h.try_recv(), utils::a_key(Y, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(Y, Up(Normal)));
+        assert!(h.try_recv().is_err());
+
+        // Only unhooked, this thread is not told to quit
+        drop(h);
+        pump(Duration::from_millis(50));
+        assert!(keyboard_hook().is_some());
+    }
+
+    #[test]
+    fn batch_is_bounded_by_max_and_timeout() {
+        use std::time::{Duration, Instant};
+
+        let h = keyboard_hook().unwrap();
+        let started = Instant::now();
+        assert!(h.try_recv_batch(8, Duration::from_millis(50)).is_empty());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        Keyboard::Y.click();
+        Keyboard::Z.click();
+        utils::fixme::delay_execution();
+        assert!(h.try_recv_batch(0, Duration::from_secs(1)).is_empty());
+        // Full: the rest is left queued
+        let batch = h.try_recv_batch(3, Duration::from_secs(1));
+        assert_eq!(batch.len(), 3);
+        assert_eq!(Ok(batch[0].clone()), utils::a_key(Y, Down(Normal)));
+        assert_eq!(Ok(batch[2].clone()), utils::a_key(Z, Down(Normal)));
+        // Partial: returned without waiting for the whole timeout
+        let started = Instant::now();
+        let batch = h.try_recv_batch(3, Duration::from_secs(5));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(batch.len(), 1);
+        assert_eq!(Ok(batch[0].clone()), utils::a_key(Z, Up(Normal)));
+    }
+
+    #[test]
+    fn blocked_keys_are_marked() {
+        let h = hook::HookBuilder::new()
+            .with_keyboard()
+            .with_blocking(|event| matches!(event, InputEvent::Keyboard(ke) if ke.key == Some(Y)))
+            .build()
+            .unwrap();
+        Keyboard::Y.click();
+        Keyboard::Z.click();
+        utils::fixme::delay_execution();
+
+        let blocked: Vec<(Option<KeyboardKey>, bool)> = h.try_recv_all().into_iter()
+            .filter_map(|event| match event {
+                InputEvent::Keyboard(ke) => Some((ke.key, ke.blocked)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(blocked, vec![(Some(Y), true), (Some(Y), true), (Some(Z), false), (Some(Z), false)]);
+    }
+
+    #[test]
+    fn sink_takes_over_the_events() {
+        use std::sync::{Arc, Mutex};
+
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = handled.clone();
+        let h = hook::HookBuilder::new()
+            .with_keyboard()
+            .with_sink(move |e: &InputEvent| sink_events.lock().unwrap().push(e.clone()))
+            .build()
+            .unwrap();
+
+        Keyboard::Y.click();
+        utils::fixme::delay_execution();
+        assert!(h.try_recv().is_err());
+        drop(h);
+
+        let handled = handled.lock().unwrap();
+        assert_eq!(handled.len(), 2);
+        assert_eq!(Ok(handled[0].clone()), utils::a_key(Y, Down(Normal)));
+        assert_eq!(Ok(handled[1].clone()), utils::a_key(Y, Up(Normal)));
+    }
+
+    #[test]
+    fn iterators_end_on_empty_queue_and_cancellation() {
+        let h = keyboard_hook().unwrap();
+        assert_eq!(h.try_iter().count(), 0);
+
+        Keyboard::Z.click();
+        utils::fixme::delay_execution();
+        let queued: Vec<InputEvent> = h.try_iter().collect();
+        assert_eq!(queued.len(), 2);
+        assert_eq!(Ok(queued[0].clone()), utils::a_key(Z, Down(Normal)));
+        assert_eq!(Ok(queued[1].clone()), utils::a_key(Z, Up(Normal)));
+
+        Keyboard::Z.click();
+        utils::fixme::delay_execution();
+        h.cancellation_handle().cancel();
+        // The blocking iterator ends right away, the remaining events are still available without blocking
+        assert_eq!(h.iter().count(), 0);
+        assert_eq!(h.try_recv_all().len(), 2);
+        assert!(h.try_recv_all().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recorder_writes_json_lines() {
+        use willhook::sink::Recorder;
+
+        let recorder = Recorder::start(Vec::new()).unwrap();
+        assert!(keyboard_hook().is_none());
+        Keyboard::Y.click();
+        utils::fixme::delay_execution();
+        let recorded = recorder.stop().unwrap();
+        assert!(keyboard_hook().is_some());
+
+        let events: Vec<InputEvent> = String::from_utf8(recorded).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(Ok(events[0].clone()), utils::a_key(Y, Down(Normal)));
+        assert_eq!(Ok(events[1].clone()), utils::a_key(Y, Up(Normal)));
+        match (&events[0], &events[1]) {
+            (InputEvent::Keyboard(down), InputEvent::Keyboard(up)) => {
+                assert!(down.time.is_some());
+                assert!(down.seq < up.seq);
+            },
+            other => panic!("Expected keyboard events, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn recorder_stop_writes_the_queued_events() {
+        use willhook::sink::Recorder;
+
+        // Writes so slowly that most of the events are still queued when the recorder is stopped
+        struct Slow(Vec<u8>);
+        impl std::io::Write for Slow {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                self.0.write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let recorder = Recorder::start(Slow(Vec::new())).unwrap();
+        for _ in 0..5 {
+            Keyboard::Y.click();
+        }
+        utils::fixme::delay_execution();
+        assert!(recorder.recorded() < 10);
+        let recorded = recorder.stop().unwrap();
+
+        let events: Vec<InputEvent> = String::from_utf8(recorded.0).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 10);
+        for pair in events.chunks(2) {
+            assert_eq!(Ok(pair[0].clone()), utils::a_key(Y, Down(Normal)));
+            assert_eq!(Ok(pair[1].clone()), utils::a_key(Y, Up(Normal)));
+        }
+    }
+
+    #[cfg(feature = "inject")]
+    #[test]
+    fn inject_key_is_captured() {
+        let h = keyboard_hook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        assert!(replay::inject_key(U, true));
+        assert!(replay::inject_key(U, false));
+        assert_eq!(h.try_recv(), utils::a_key(U, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(U, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[cfg(feature = "inject")]
+    #[test]
+    fn scheduled_keys_are_injected_in_order_with_the_tag() {
+        use std::time::{Duration, Instant};
+        use willhook::event::*;
+        use willhook::replay::Injector;
+
+        const TAG: usize = 0x7E57;
+        let key = |key, pressed| InputEvent::Keyboard(KeyboardEvent {
+            pressed,
+            key: Some(key),
+            is_injected: None,
+            time: None,
+            physical_key: None,
+            modifiers: Modifiers::empty(),
+            is_repeat: false,
+            held_for: None,
+            chatter: false,
+            seq: 0,
+            foreground_pid: None,
+            layout: None,
+            extra_info: 0,
+            blocked: false,
+            raw: None,
+        });
+
+        let h = keyboard_hook().unwrap();
+        let injector = Injector::with_extra_info(TAG).unwrap();
+        let start = Instant::now();
+        // Scheduled out of order
+        assert!(injector.schedule(key(Y, Up(Normal)), start + Duration::from_millis(60)));
+        assert!(injector.schedule(key(Y, Down(Normal)), start + Duration::from_millis(30)));
+        assert!(!injector.schedule(InputEvent::Reset, start));
+        assert_eq!(injector.pending(), 2);
+        assert!(injector.wait(Duration::from_secs(1)));
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        utils::fixme::delay_execution();
+
+        let received = h.try_recv_all();
+        assert_eq!(received.len(), 2);
+        assert_eq!(Ok(received[0].clone()), utils::a_key(Y, Down(Normal)));
+        assert_eq!(Ok(received[1].clone()), utils::a_key(Y, Up(Normal)));
+        assert!(received.iter().all(|event| matches!(event, InputEvent::Keyboard(ke) if ke.extra_info == TAG)));
+
+        // Neither the canceled events nor the ones pending at the drop are injected
+        injector.schedule(key(Y, Down(Normal)), Instant::now() + Duration::from_millis(100));
+        injector.cancel();
+        assert_eq!(injector.pending(), 0);
+        injector.schedule(key(Y, Down(Normal)), Instant::now() + Duration::from_millis(100));
+        drop(injector);
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_util_keys_are_captured() {
+        use willhook::test_util;
+
+        let h = keyboard_hook().unwrap();
+        assert!(test_util::send_key_down(J));
+        assert!(test_util::send_key_up(J));
+        assert!(test_util::send_key_click(K));
+        test_util::settle();
+        assert_eq!(h.try_recv(), utils::a_key(J, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(J, Up(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(K, Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(K, Up(Normal)));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn keys_typed_into_other_processes_are_dropped() {
+        use willhook::hook::HookBuilder;
+
+        // No process with the id 0 owns a window
+        let h = HookBuilder::new().with_keyboard().only_foreground_process(0).build().unwrap();
+        Keyboard::L.click();
+        utils::fixme::delay_execution();
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    fn quick_second_press_is_marked_as_chatter() {
+        use std::time::Duration;
+        use willhook::hook::HookBuilder;
+
+        let h = HookBuilder::new().with_keyboard().key_edges(hook::Edges::DownOnly).with_chatter_detection(Duration::from_millis(500)).build().unwrap();
+        Keyboard::Number9.click();
+        Keyboard::Number9.click();
+        utils::fixme::delay_execution();
+        let chatters: Vec<bool> = h.try_recv_all().into_iter().map(|event| match event {
+            InputEvent::Keyboard(ke) => ke.chatter,
+            other => panic!("Expected a keyboard event, got {:?}", other),
+        }).collect();
+        assert_eq!(chatters, vec![false, true]);
+    }
+}
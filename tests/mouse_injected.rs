@@ -204,7 +204,7 @@ mod mouse_hook_tests {
         fn move_once() {
             utils::fixme::move_by(10, 10);
 
-            let h = mouse_hook().unwrap();
+            let h = hook::HookBuilder::new().with_mouse().with_mouse_moves().build().unwrap();
             assert!(h.try_recv().is_err());
 
             let (new_x, new_y) = utils::fixme::move_by(10, 10);
@@ -217,7 +217,7 @@ mod mouse_hook_tests {
         fn move_once_generates_mouse_move() {
             utils::fixme::move_by(10, 10);
 
-            let h = mouse_hook().unwrap();
+            let h = hook::HookBuilder::new().with_mouse().with_mouse_moves().build().unwrap();
             assert!(h.try_recv().is_err());
 
             utils::fixme::move_by(10, 10);
@@ -233,7 +233,7 @@ mod mouse_hook_tests {
         fn move_couple_of_times() {
             utils::fixme::move_by(10, 10);
 
-            let h = mouse_hook().unwrap();
+            let h = hook::HookBuilder::new().with_mouse().with_mouse_moves().build().unwrap();
             assert!(h.try_recv().is_err());
 
             let new_pos = vec![
@@ -256,7 +256,7 @@ mod mouse_hook_tests {
         fn move_couple_of_times_generates_mouse_move() {
             utils::fixme::move_by(10, 10);
 
-            let h = mouse_hook().unwrap();
+            let h = hook::HookBuilder::new().with_mouse().with_mouse_moves().build().unwrap();
             assert!(h.try_recv().is_err());
 
             let new_pos = vec![
@@ -275,6 +275,45 @@ mod mouse_hook_tests {
             }
             assert!(h.try_recv().is_err());
         }
+
+        #[test]
+        fn moves_are_not_delivered_by_default() {
+            let h = mouse_hook().unwrap();
+            assert!(h.try_recv().is_err());
+
+            utils::fixme::move_by(10, 10);
+            utils::fixme::click(Mouse::Left);
+            utils::fixme::move_by(-10, -10);
+
+            assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Down));
+            assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Up));
+            assert!(h.try_recv().is_err());
+        }
+
+        #[test]
+        fn move_deltas_follow_the_positions() {
+            use willhook::event::*;
+            utils::fixme::move_by(10, 10);
+
+            let h = hook::HookBuilder::new().with_mouse().with_mouse_moves().with_move_deltas().build().unwrap();
+            assert!(h.try_recv().is_err());
+
+            utils::fixme::move_by(10, 15);
+            utils::fixme::move_by(-10, 10);
+            utils::fixme::move_by(0, -5);
+
+            let moves: Vec<MouseMoveEvent> = h.try_iter().map(|event| match event {
+                InputEvent::Mouse(MouseEvent{ event: MouseEventType::Move(me), .. }) => me,
+                other => panic!("Not a move: {:?}", other),
+            }).collect();
+            assert_eq!(moves.len(), 3);
+            // The first move has nothing to compare to
+            assert_eq!(moves[0].delta, None);
+            for pair in moves.windows(2) {
+                let (previous, next) = (pair[0].point.unwrap(), pair[1].point.unwrap());
+                assert_eq!(pair[1].delta, Some(MoveDelta{ dx: next.x - previous.x, dy: next.y - previous.y }));
+            }
+        }
     }
 
     mod mouse_wheel {
@@ -321,12 +360,43 @@ mod mouse_hook_tests {
         }
     }
 
+    #[cfg(feature = "test-util")]
+    mod test_util {
+        use crate::mouse_hook_tests::*;
+        use willhook::test_util::*;
+
+        #[test]
+        pub fn clicks_moves_and_wheels_are_captured() {
+            let h = mouse_hook().unwrap();
+
+            assert!(click_left());
+            assert!(click_right());
+            assert!(send_button_down(Middle(SingleClick)));
+            assert!(send_button_up(Middle(SingleClick)));
+            assert!(send_mouse_move(10, 20));
+            assert!(send_wheel(MouseWheel::Vertical, MouseWheelDirection::Backward));
+            assert!(!send_wheel(MouseWheel::Vertical, MouseWheelDirection::Unknown(0)));
+            settle();
+
+            assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Down));
+            assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Up));
+            assert_eq!(h.try_recv(), utils::a_button(Right(SingleClick), Down));
+            assert_eq!(h.try_recv(), utils::a_button(Right(SingleClick), Up));
+            assert_eq!(h.try_recv(), utils::a_button(Middle(SingleClick), Down));
+            assert_eq!(h.try_recv(), utils::a_button(Middle(SingleClick), Up));
+            // The absolute position is normalized by SendInput, so it may be off by a pixel
+            assert!(matches!(h.try_recv(), Ok(InputEvent::Mouse(MouseEvent { event: MouseEventType::Move(_), .. }))));
+            assert_eq!(h.try_recv(), utils::a_wheel(MouseWheel::Vertical, MouseWheelDirection::Backward));
+            assert!(h.try_recv().is_err());
+        }
+    }
+
     mod mixed {
         use crate::mouse_hook_tests::*;
 
         #[test]
         pub fn mixed_mouse_inputs() {            
-            let h = mouse_hook().unwrap();
+            let h = hook::HookBuilder::new().with_mouse().with_mouse_moves().build().unwrap();
 
             utils::fixme::vertical_wheel_backward();
             assert_eq!(h.try_recv(), utils::a_wheel(MouseWheel::Vertical, MouseWheelDirection::Backward));
@@ -378,5 +448,21 @@ mod mouse_hook_tests {
             assert_eq!(h.try_recv(), utils::a_wheel(MouseWheel::Vertical, MouseWheelDirection::Backward));
             assert!(h.try_recv().is_err());
         }
+
+        #[test]
+        pub fn wheels_are_captured_regardless_of_focus() {
+            // Put the cursor in the corner of the screen (usually over the desktop or the taskbar, not over the focused window).
+            // Low-level hooks see the wheel before it is routed to any window, so neither the focus nor the window under cursor matters.
+            unsafe { winapi::um::winuser::SetCursorPos(0, 0); }
+
+            let h = mouse_hook().unwrap();
+            assert!(h.try_recv().is_err());
+
+            utils::fixme::vertical_wheel_forward();
+            utils::fixme::horizontal_wheel_backward();
+            assert_eq!(h.try_recv(), utils::a_wheel(MouseWheel::Vertical, MouseWheelDirection::Forward));
+            assert_eq!(h.try_recv(), utils::a_wheel(MouseWheel::Horizontal, MouseWheelDirection::Backward));
+            assert!(h.try_recv().is_err());
+        }
     }
-}
\ No newline at end of file
+}
@@ -24,6 +24,19 @@ mod feature_test_willhook {
         validate_serde(utils::a_key(A, KeyPress::Up(Normal)).unwrap());
     }
 
+    #[test]
+    pub fn serde_key_with_capture_data() {
+        let mut key = match utils::a_key(LeftShift, KeyPress::Down(Normal)).unwrap() {
+            InputEvent::Keyboard(ke) => ke,
+            other => panic!("Expected keyboard event, got {:?}", other),
+        };
+        key.time = Some(1234);
+        key.physical_key = Some(PhysicalKey { scan_code: 0x2A, extended: false });
+        key.modifiers = Modifiers::LEFT_SHIFT | Modifiers::RIGHT_ALT;
+        key.is_repeat = true;
+        validate_serde(InputEvent::Keyboard(key));
+    }
+
     #[test]
     pub fn serde_mouse_button() {            
         validate_serde(utils::a_button(Left(SingleClick), Up).unwrap());
@@ -34,4 +47,42 @@ mod feature_test_willhook {
         let (new_x, new_y) = (100, 200);
         validate_serde(utils::a_move(new_x, new_y).unwrap());
     }
-}
\ No newline at end of file
+}
+#[cfg(feature = "serde")]
+#[cfg(test)]
+mod feature_test_hook_builder {
+    use willhook::hook::{HookBuilder, Edges, OverflowPolicy};
+    use willhook::layout::LayoutSource;
+
+    #[test]
+    pub fn serde_builder_options() {
+        let builder = HookBuilder::new()
+            .with_mouse()
+            .layout(LayoutSource::Specific(0x0409_0409))
+            .button_edges(Edges::UpOnly)
+            .capacity(16)
+            .overflow_policy(OverflowPolicy::KeepLatestOnly)
+            .map_events(|e| Some(format!("{:?}", e)));
+        let serialized = serde_json::to_string(&builder).unwrap();
+        let deserialized = serde_json::from_str::<HookBuilder>(&serialized).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), serialized);
+    }
+
+    #[test]
+    pub fn serde_builder_missing_options_are_defaults() {
+        let deserialized = serde_json::from_str::<HookBuilder>(r#"{"keyboard": true}"#).unwrap();
+        let expected = serde_json::to_string(&HookBuilder::new().with_keyboard()).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), expected);
+    }
+
+    #[test]
+    pub fn serde_builder_keeps_the_foreground_exe_only() {
+        let serialized = serde_json::to_string(&HookBuilder::new().with_keyboard().only_foreground_exe("game.exe")).unwrap();
+        let deserialized = serde_json::from_str::<HookBuilder>(&serialized).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), serialized);
+
+        let serialized = serde_json::to_string(&HookBuilder::new().with_keyboard().only_foreground_process(1234)).unwrap();
+        let expected = serde_json::to_string(&HookBuilder::new().with_keyboard()).unwrap();
+        assert_eq!(serialized, expected);
+    }
+}
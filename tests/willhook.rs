@@ -11,7 +11,7 @@ mod willhook {
 
     #[test]
     pub fn mixed_inputs() {            
-        let h = willhook().unwrap();
+        let h = hook::HookBuilder::new().with_keyboard().with_mouse().with_mouse_moves().build().unwrap();
 
         utils::fixme::vertical_wheel_backward();
         assert_eq!(h.try_recv(), utils::a_wheel(MouseWheel::Vertical, MouseWheelDirection::Backward));
@@ -55,4 +55,218 @@ mod willhook {
         assert_eq!(h.recv(), utils::as_blocking(utils::a_button(Middle(SingleClick), Down)));
         assert_eq!(h.recv(), utils::as_blocking(utils::a_button(Middle(SingleClick), Up)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn interleaved_inputs_keep_os_order() {
+        let h = willhook().unwrap();
+
+        Keyboard::A.press();
+        utils::fixme::click(Mouse::Left);
+        Keyboard::A.release();
+        utils::fixme::vertical_wheel_forward();
+        Keyboard::B.click();
+
+        let events = vec![
+            (h.try_recv(), utils::a_key(A, KeyPress::Down(Normal))),
+            (h.try_recv(), utils::a_button(Left(SingleClick), Down)),
+            (h.try_recv(), utils::a_button(Left(SingleClick), Up)),
+            (h.try_recv(), utils::a_key(A, KeyPress::Up(Normal))),
+            (h.try_recv(), utils::a_wheel(MouseWheel::Vertical, MouseWheelDirection::Forward)),
+            (h.try_recv(), utils::a_key(B, KeyPress::Down(Normal))),
+            (h.try_recv(), utils::a_key(B, KeyPress::Up(Normal))),
+        ];
+        assert!(h.try_recv().is_err());
+
+        let mut previous_time = 0;
+        for (actual, expected) in events {
+            assert_eq!(actual, expected);
+            // Sorting by the OS timestamp must not reorder the stream
+            let time = utils::time_of(actual);
+            assert!(previous_time <= time);
+            previous_time = time;
+        }
+    }
+
+    #[test]
+    pub fn events_are_numbered_across_devices() {
+        let h = willhook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::D.click();
+        utils::fixme::click(Mouse::Left);
+        Keyboard::E.click();
+
+        let seqs: Vec<u64> = h.try_iter().filter_map(|event| event.seq()).collect();
+        assert_eq!(seqs.len(), 6);
+        assert!(seqs.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    }
+
+    #[test]
+    pub fn overflowed_events_leave_a_gap() {
+        let h = hook::HookBuilder::new().with_keyboard().capacity(2).overflow_policy(hook::OverflowPolicy::DropNewest).build().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::G.click();
+        // No room for the H key down and up
+        Keyboard::H.click();
+        let g_up = h.try_iter().filter_map(|event| event.seq()).last().unwrap();
+        Keyboard::J.click();
+
+        let j_down = h.try_recv().ok().and_then(|event| event.seq()).unwrap();
+        assert_eq!(j_down, g_up + 3);
+        assert_eq!(h.overflowed_events(), 2);
+    }
+
+    #[test]
+    pub fn thread_per_hook_captures_both() {
+        let h = hook::HookBuilder::new().with_keyboard().with_mouse().thread_per_hook().build().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::C.click();
+        utils::fixme::click(Mouse::Right);
+
+        assert_eq!(h.try_recv(), utils::a_key(C, KeyPress::Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(C, KeyPress::Up(Normal)));
+        assert_eq!(h.try_recv(), utils::a_button(Right(SingleClick), Down));
+        assert_eq!(h.try_recv(), utils::a_button(Right(SingleClick), Up));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    pub fn removed_mouse_hook_leaves_keyboard_capture() {
+        for thread_per_hook in [true, false] {
+            let builder = hook::HookBuilder::new().with_keyboard().with_mouse();
+            let h = if thread_per_hook { builder.thread_per_hook() } else { builder }.build().unwrap();
+            assert!(h.try_recv().is_err());
+            assert!(h.remove_hook(InputSource::Mouse));
+            assert!(!h.remove_hook(InputSource::Mouse));
+            assert!(!h.remove_hook(InputSource::Keyboard));
+            assert!(h.is_installed());
+            let installed = installed_hooks();
+            assert!(installed.keyboard && !installed.mouse);
+
+            utils::fixme::click(Mouse::Right);
+            Keyboard::C.click();
+            assert_eq!(h.try_recv(), utils::a_key(C, KeyPress::Down(Normal)));
+            assert_eq!(h.try_recv(), utils::a_key(C, KeyPress::Up(Normal)));
+            assert!(h.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    pub fn verify_swallows_the_probes() {
+        use std::time::Duration;
+
+        let h = willhook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        assert!(h.verify(Duration::from_secs(1)));
+        utils::fixme::delay_execution();
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    pub fn events_are_tagged_with_their_source() {
+        let h = willhook().unwrap();
+        assert!(h.try_recv().is_err());
+
+        Keyboard::F.click();
+        utils::fixme::click(Mouse::Left);
+
+        let sources: Vec<_> = h.try_iter().map(|event| event.source()).collect();
+        assert_eq!(sources, vec![Some(InputSource::Keyboard), Some(InputSource::Keyboard), Some(InputSource::Mouse), Some(InputSource::Mouse)]);
+    }
+
+    #[test]
+    pub fn quick_clicks_are_a_double_click() {
+        let h = hook::HookBuilder::new().with_mouse().with_double_click_detection().build().unwrap();
+        assert!(h.try_recv().is_err());
+
+        utils::fixme::click(Mouse::Left);
+        utils::fixme::click(Mouse::Left);
+
+        assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Down));
+        assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Up));
+        assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Down));
+        assert_eq!(h.try_recv(), Ok(InputEvent::MouseDoubleClick(Left(DoubleClick))));
+        assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Up));
+        assert!(h.try_recv().is_err());
+    }
+
+    #[test]
+    pub fn subscribers_get_every_event() {
+        let h = willhook().unwrap();
+        let logger = h.subscribe();
+        let ui = logger.clone();
+
+        Keyboard::G.click();
+        drop(ui);
+        utils::fixme::click(Mouse::Right);
+
+        assert_eq!(h.try_recv(), utils::a_key(G, KeyPress::Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(G, KeyPress::Up(Normal)));
+        assert_eq!(h.try_recv(), utils::a_button(Right(SingleClick), Down));
+        assert_eq!(h.try_recv(), utils::a_button(Right(SingleClick), Up));
+        assert!(h.try_recv().is_err());
+
+        assert_eq!(logger.try_recv(), utils::a_key(G, KeyPress::Down(Normal)));
+        assert_eq!(logger.try_recv(), utils::a_key(G, KeyPress::Up(Normal)));
+        assert_eq!(logger.try_recv(), utils::a_button(Right(SingleClick), Down));
+        assert_eq!(logger.try_recv(), utils::a_button(Right(SingleClick), Up));
+        assert!(logger.try_recv().is_err());
+
+        drop(h);
+        assert!(logger.recv().is_err());
+    }
+
+    #[test]
+    pub fn receiver_does_not_own_the_hook() {
+        let h = willhook().unwrap();
+        let receiver = h.subscribe();
+        let counted = std::thread::spawn(move || receiver.iter().count());
+        drop(h.subscribe());
+        assert!(h.is_installed());
+
+        Keyboard::G.click();
+        drop(h);
+        assert_eq!(counted.join().unwrap(), 2);
+    }
+
+    #[test]
+    pub fn auto_reinstall_runs_unnoticed() {
+        use std::time::{Duration, Instant};
+
+        let h = hook::HookBuilder::new().with_keyboard().with_mouse().with_auto_reinstall(Duration::from_millis(10)).build().unwrap();
+        // Let the watchdog run a few checks, the working hooks are kept and the probes are swallowed
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(h.is_installed());
+        assert!(h.try_recv().is_err());
+
+        Keyboard::E.click();
+        assert_eq!(h.try_recv(), utils::a_key(E, KeyPress::Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(E, KeyPress::Up(Normal)));
+
+        // The watchdog is stopped without waiting for its next check
+        let dropping = Instant::now();
+        drop(h);
+        assert!(dropping.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    pub fn raw_input_backend_captures_both() {
+        let h = hook::HookBuilder::new().with_keyboard().with_mouse().backend(hook::Backend::RawInput).build().unwrap();
+        assert!(h.is_installed());
+        assert!(h.try_recv().is_err());
+
+        Keyboard::D.click();
+        utils::fixme::click(Mouse::Left);
+        // WM_INPUT is posted to the window, it takes a moment to be processed
+        utils::fixme::delay_execution();
+
+        assert_eq!(h.try_recv(), utils::a_key(D, KeyPress::Down(Normal)));
+        assert_eq!(h.try_recv(), utils::a_key(D, KeyPress::Up(Normal)));
+        assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Down));
+        assert_eq!(h.try_recv(), utils::a_button(Left(SingleClick), Up));
+        assert!(h.try_recv().is_err());
+    }
+}
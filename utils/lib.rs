@@ -2,59 +2,142 @@ use willhook::event::*;
 use willhook::event::InputEvent::*;
 use willhook::event::MouseEventType::*;
 
+/// The event expected by the test, to be compared with the actually received one (e.g. via `assert_eq!`).
+/// The data that is known only at the time of capture (like the OS timestamp) is not compared.
+#[derive(Debug)]
+pub struct Expected<E>(pub Result<InputEvent, E>);
+
+impl<E: std::fmt::Debug> Expected<E> {
+    pub fn unwrap(self) -> InputEvent {
+        self.0.unwrap()
+    }
+
+    /// Expects the keyboard event to report the given modifiers held down.
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        if let Ok(Keyboard(ke)) = &mut self.0 {
+            ke.modifiers = modifiers;
+        }
+        self
+    }
+}
+
+impl<E: PartialEq> PartialEq<Expected<E>> for Result<InputEvent, E> {
+    fn eq(&self, other: &Expected<E>) -> bool {
+        self.as_ref().map(|ie| without_capture_data(ie.clone())) == other.0.as_ref().map(|ie| without_capture_data(ie.clone()))
+    }
+}
 
-pub fn as_blocking(ie: Result<InputEvent, std::sync::mpsc::TryRecvError>) -> Result<InputEvent, std::sync::mpsc::RecvError> {
-    ie.map_err(|e| match e {
+/// Clears the data that can't be predicted by the test.
+pub fn without_capture_data(ie: InputEvent) -> InputEvent {
+    match ie {
+        Keyboard(mut ke) => {
+            ke.time = None;
+            ke.foreground_pid = None;
+            ke.extra_info = 0;
+            ke.raw = None;
+            ke.physical_key = None;
+            ke.held_for = None;
+            ke.seq = 0;
+            Keyboard(ke)
+        },
+        Mouse(mut me) => {
+            me.time = None;
+            me.foreground_pid = None;
+            me.extra_info = 0;
+            me.raw = None;
+            me.seq = 0;
+            if let Wheel(ref mut we) = me.event {
+                we.delta = None;
+            }
+            Mouse(me)
+        },
+        other => other,
+    }
+}
+
+pub fn as_blocking(ie: Expected<std::sync::mpsc::TryRecvError>) -> Expected<std::sync::mpsc::RecvError> {
+    Expected(ie.0.map_err(|e| match e {
         std::sync::mpsc::TryRecvError::Empty | std::sync::mpsc::TryRecvError::Disconnected =>
             std::sync::mpsc::RecvError,
-    })
+    }))
 }
 
-pub fn a_key(key: KeyboardKey, press: KeyPress) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
-    Ok(Keyboard(KeyboardEvent {
+pub fn a_key(key: KeyboardKey, press: KeyPress) -> Expected<std::sync::mpsc::TryRecvError> {
+    Expected(Ok(Keyboard(KeyboardEvent {
                     pressed: press,
                     key: Some(key),
-                    is_injected: Some(IsEventInjected::Injected)}))
+                    is_injected: Some(IsEventInjected::Injected),
+                    time: None,
+                    physical_key: None,
+                    modifiers: Modifiers::empty(),
+                    is_repeat: false,
+                    held_for: None,
+                    chatter: false,
+                    seq: 0,
+                    foreground_pid: None,
+                    layout: None,
+                    extra_info: 0, blocked: false, raw: None})))
 }
 
-pub fn a_button(button: MouseButton, press: MouseButtonPress) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
-    Ok(Mouse(MouseEvent {
+pub fn a_button(button: MouseButton, press: MouseButtonPress) -> Expected<std::sync::mpsc::TryRecvError> {
+    Expected(Ok(Mouse(MouseEvent {
                     event: Press(MousePressEvent{
                         pressed: press,
                         button: button,
                     }),
-                    is_injected: Some(IsEventInjected::Injected)}))
+                    is_injected: Some(IsEventInjected::Injected),
+                    time: None,
+                    seq: 0,
+                    foreground_pid: None,
+                    extra_info: 0, blocked: false, raw: None})))
 }
 
-pub fn a_move(an_x: i32, an_y: i32) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
-    Ok(Mouse(MouseEvent {
+pub fn a_move(an_x: i32, an_y: i32) -> Expected<std::sync::mpsc::TryRecvError> {
+    Expected(Ok(Mouse(MouseEvent {
         event: Move(MouseMoveEvent{
             point: Some(Point{x: an_x, y: an_y}),
+            delta: None,
         }),
-        is_injected: Some(IsEventInjected::Injected)}))
+        is_injected: Some(IsEventInjected::Injected),
+        time: None,
+        seq: 0,
+        foreground_pid: None,
+        extra_info: 0, blocked: false, raw: None})))
 }
 
 pub fn is_mouse_move(r: Result<InputEvent, std::sync::mpsc::TryRecvError>) -> bool {
-    if let Ok(ie) = r {
+    if let Ok(ie) = &r {
         if let Mouse(me) = ie {
             if let Move(_) = me.event {
                 return true
             }
         }
         // Assertion to print out the actual value in tests
-        assert_eq!(ie, InputEvent::Other(0));
+        assert_eq!(*ie, InputEvent::Other(0));
     }
     // Assertion to print out that error was returned
     assert!(r.is_ok());
     false
 }
 
-pub fn a_wheel(wheel: MouseWheel, wheel_direction: MouseWheelDirection) -> Result<InputEvent, std::sync::mpsc::TryRecvError> {
-    Ok(Mouse(MouseEvent {
+pub fn a_wheel(wheel: MouseWheel, wheel_direction: MouseWheelDirection) -> Expected<std::sync::mpsc::TryRecvError> {
+    Expected(Ok(Mouse(MouseEvent {
         event: Wheel(MouseWheelEvent {
-                wheel: wheel, direction: Some(wheel_direction),
+                wheel: wheel, direction: Some(wheel_direction), delta: None,
             }),
-        is_injected: Some(IsEventInjected::Injected) }))
+        is_injected: Some(IsEventInjected::Injected),
+        time: None,
+        seq: 0,
+        foreground_pid: None, extra_info: 0, blocked: false, raw: None })))
+}
+
+/// Returns the OS timestamp of the received event, panics if there is none.
+pub fn time_of(r: Result<InputEvent, std::sync::mpsc::TryRecvError>) -> u32 {
+    match r {
+        Ok(Keyboard(KeyboardEvent{ time: Some(t), .. })) => t,
+        Ok(Mouse(MouseEvent{ time: Some(t), .. })) => t,
+        _ => panic!("Expected an event with timestamp, got: {:?}", r),
+    }
 }
 
 // The MKI implementation seems to be buggy at the current version.